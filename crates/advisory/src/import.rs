@@ -0,0 +1,304 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use kellnr_db::error::DbError;
+use kellnr_db::{Advisory, DbProvider};
+use kellnr_settings::Advisories;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+use crate::parse::parse_advisory;
+
+#[derive(Debug, Error)]
+pub enum AdvisoryError {
+    #[error("Failed to read advisory database at {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Advisory database at {0} contains no advisories")]
+    Empty(PathBuf),
+    #[error("Failed to store advisories: {0}")]
+    Db(#[from] DbError),
+}
+
+/// Periodically import the advisory database at `settings.path`. With an
+/// import interval of zero, the advisories are imported once on startup.
+pub fn run_advisory_import(settings: &Advisories, db: Arc<dyn DbProvider>) {
+    let Some(path) = settings.path.clone().map(PathBuf::from) else {
+        return;
+    };
+    let interval = settings.import_interval_seconds;
+    tokio::spawn(async move {
+        loop {
+            match import_advisories(&path, &db).await {
+                Ok(count) => info!("Imported {count} advisories from {}", path.display()),
+                Err(e) => error!("Advisory import failed: {e}"),
+            }
+            if interval == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+/// Import all advisories of a clone of the advisory database or of a
+/// (gzipped) tarball of it, replacing the previously imported advisories.
+///
+/// A webhook is sent for every new advisory that affects a hosted or cached
+/// crate version. No webhooks are sent on the first import into an empty
+/// database, as all advisories are new then.
+pub async fn import_advisories(
+    path: &Path,
+    db: &Arc<dyn DbProvider>,
+) -> Result<usize, AdvisoryError> {
+    let owned_path = path.to_path_buf();
+    let advisories = tokio::task::spawn_blocking(move || read_advisories(&owned_path))
+        .await
+        .map_err(|e| AdvisoryError::Read(path.to_path_buf(), std::io::Error::other(e)))??;
+    if advisories.is_empty() {
+        return Err(AdvisoryError::Empty(path.to_path_buf()));
+    }
+
+    let added = db.replace_advisories(&advisories).await?;
+    if !added.is_empty() && added.len() < advisories.len() {
+        let now = Utc::now();
+        for m in db
+            .get_advisory_matches()
+            .await?
+            .iter()
+            .filter(|m| added.contains(&m.advisory.id))
+        {
+            kellnr_webhooks::notify_advisory(&now, m, db).await;
+        }
+    }
+
+    Ok(advisories.len())
+}
+
+fn read_advisories(path: &Path) -> Result<Vec<Advisory>, AdvisoryError> {
+    let read_error = |e| AdvisoryError::Read(path.to_path_buf(), e);
+    let mut advisories = vec![];
+    if path.is_dir() {
+        read_directory(&path.join("crates"), &mut advisories).map_err(read_error)?;
+    } else {
+        read_tarball(path, &mut advisories).map_err(read_error)?;
+    }
+    Ok(advisories)
+}
+
+/// Read `crates/<crate>/<id>.md` files of a clone of the advisory database.
+fn read_directory(root: &Path, advisories: &mut Vec<Advisory>) -> std::io::Result<()> {
+    for dir in std::fs::read_dir(root)? {
+        let dir = dir?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(&dir)? {
+            let file = file?.path();
+            if file.extension() == Some(OsStr::new("md")) {
+                add_advisory(&file, &std::fs::read_to_string(&file)?, advisories);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the advisories of a tarball, e.g. a GitHub archive of the database.
+/// The tarball may be gzip compressed.
+fn read_tarball(path: &Path, advisories: &mut Vec<Advisory>) -> std::io::Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read> = if file.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        if is_crate_advisory(&entry_path) {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            add_advisory(&entry_path, &content, advisories);
+        }
+    }
+    Ok(())
+}
+
+/// `true` for paths ending in `crates/<crate>/<id>.md`.
+fn is_crate_advisory(path: &Path) -> bool {
+    let components = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    path.extension() == Some(OsStr::new("md"))
+        && components.len() >= 3
+        && components[components.len() - 3] == "crates"
+}
+
+fn add_advisory(path: &Path, content: &str, advisories: &mut Vec<Advisory>) {
+    match parse_advisory(content) {
+        Ok(Some(advisory)) => advisories.push(advisory),
+        Ok(None) => {}
+        Err(e) => warn!("Skipping invalid advisory {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use kellnr_common::webhook::WebhookEvent;
+    use kellnr_db::mock::MockDb;
+    use kellnr_db::{AdvisoryMatch, DbProvider};
+    use mockall::predicate::*;
+
+    use super::*;
+
+    fn advisory_file(id: &str, package: &str) -> String {
+        format!(
+            "```toml\n[advisory]\nid = \"{id}\"\npackage = \"{package}\"\ndate = \"2024-01-01\"\n\n[versions]\npatched = [\">= 1.0.1\"]\n```\n\n# Bug in {package}\n\nDetails.\n"
+        )
+    }
+
+    /// Write a minimal advisory database with two crate advisories and one
+    /// advisory for the Rust toolchain, which is ignored.
+    fn write_database(root: &Path) {
+        for (dir, id, package) in [
+            ("crates/foo", "RUSTSEC-2024-0001", "foo"),
+            ("crates/bar", "RUSTSEC-2024-0002", "bar"),
+            ("rust/std", "RUSTSEC-2024-0003", "std"),
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(
+                root.join(dir).join(format!("{id}.md")),
+                advisory_file(id, package),
+            )
+            .unwrap();
+        }
+        std::fs::write(root.join("crates/foo/README.txt"), "not an advisory").unwrap();
+    }
+
+    fn sorted_ids(advisories: &[Advisory]) -> Vec<&str> {
+        let mut ids = advisories.iter().map(|a| a.id.as_str()).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn read_advisories_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        write_database(dir.path());
+
+        let advisories = read_advisories(dir.path()).unwrap();
+        assert_eq!(
+            vec!["RUSTSEC-2024-0001", "RUSTSEC-2024-0002"],
+            sorted_ids(&advisories)
+        );
+    }
+
+    #[test]
+    fn read_advisories_from_gzipped_tarball() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_dir = dir.path().join("advisory-db-main");
+        write_database(&db_dir);
+        let tarball = dir.path().join("advisory-db.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&tarball).unwrap(),
+            Compression::default(),
+        ));
+        builder.append_dir_all("advisory-db-main", &db_dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let advisories = read_advisories(&tarball).unwrap();
+        assert_eq!(
+            vec!["RUSTSEC-2024-0001", "RUSTSEC-2024-0002"],
+            sorted_ids(&advisories)
+        );
+    }
+
+    #[test]
+    fn crate_advisory_paths() {
+        assert!(is_crate_advisory(Path::new(
+            "advisory-db-main/crates/foo/RUSTSEC-2024-0001.md"
+        )));
+        assert!(is_crate_advisory(Path::new(
+            "crates/foo/RUSTSEC-2024-0001.md"
+        )));
+        assert!(!is_crate_advisory(Path::new(
+            "rust/std/RUSTSEC-2024-0003.md"
+        )));
+        assert!(!is_crate_advisory(Path::new("crates/README.md")));
+    }
+
+    #[tokio::test]
+    async fn import_notifies_about_new_matching_advisories() {
+        let dir = tempfile::tempdir().unwrap();
+        write_database(dir.path());
+
+        let mut db = MockDb::new();
+        db.expect_replace_advisories()
+            .times(1)
+            .returning(|_| Ok(vec!["RUSTSEC-2024-0001".to_string()]));
+        db.expect_get_advisory_matches().times(1).returning(|| {
+            let advisories = read_advisories_for_test();
+            Ok(advisories
+                .into_iter()
+                .map(|advisory| AdvisoryMatch {
+                    advisory,
+                    hosted_versions: vec!["1.0.0".to_string()],
+                    cached_versions: vec![],
+                })
+                .collect())
+        });
+        db.expect_add_webhook_queue()
+            .with(eq(WebhookEvent::AdvisoryAdd), always(), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let db = Arc::new(db) as Arc<dyn DbProvider>;
+
+        assert_eq!(2, import_advisories(dir.path(), &db).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn initial_import_sends_no_webhooks() {
+        let dir = tempfile::tempdir().unwrap();
+        write_database(dir.path());
+
+        let mut db = MockDb::new();
+        db.expect_replace_advisories()
+            .times(1)
+            .returning(|a| Ok(a.iter().map(|a| a.id.clone()).collect()));
+        let db = Arc::new(db) as Arc<dyn DbProvider>;
+
+        assert_eq!(2, import_advisories(dir.path(), &db).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn import_of_empty_database_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("crates")).unwrap();
+        let db = Arc::new(MockDb::new()) as Arc<dyn DbProvider>;
+
+        assert!(matches!(
+            import_advisories(dir.path(), &db).await,
+            Err(AdvisoryError::Empty(_))
+        ));
+    }
+
+    fn read_advisories_for_test() -> Vec<Advisory> {
+        ["RUSTSEC-2024-0001", "RUSTSEC-2024-0002"]
+            .iter()
+            .map(|id| parse_advisory(&advisory_file(id, "foo")).unwrap().unwrap())
+            .collect()
+    }
+}
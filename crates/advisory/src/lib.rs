@@ -0,0 +1,9 @@
+//! Import of security advisories from a local copy of the `RustSec` advisory
+//! database. The database is read from disk only, so the import works on
+//! offline hosts as well.
+
+mod import;
+mod parse;
+
+pub use import::{AdvisoryError, import_advisories, run_advisory_import};
+pub use parse::parse_advisory;
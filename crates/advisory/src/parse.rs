@@ -0,0 +1,140 @@
+use kellnr_db::Advisory;
+use serde::Deserialize;
+
+/// Front matter of an advisory file.
+#[derive(Debug, Deserialize)]
+struct FrontMatter {
+    advisory: AdvisoryMeta,
+    #[serde(default)]
+    versions: Versions,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryMeta {
+    id: String,
+    package: String,
+    date: String,
+    url: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    informational: Option<String>,
+    withdrawn: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Versions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Parse an advisory in the Markdown format of the `RustSec` advisory
+/// database: a fenced TOML block with the metadata, followed by the title as
+/// a heading and the description.
+///
+/// Returns `Ok(None)` for withdrawn advisories.
+pub fn parse_advisory(content: &str) -> Result<Option<Advisory>, String> {
+    let content = content.trim_start();
+    let rest = content
+        .strip_prefix("```toml")
+        .ok_or("advisory does not start with a TOML block")?;
+    let (front_matter, body) = rest
+        .split_once("\n```")
+        .ok_or("TOML block of advisory is not closed")?;
+    let meta = toml::from_str::<FrontMatter>(front_matter).map_err(|e| e.to_string())?;
+    if meta.advisory.withdrawn.is_some() {
+        return Ok(None);
+    }
+
+    let body = body.trim();
+    let (title, description) = match body.strip_prefix("# ") {
+        Some(body) => body.split_once('\n').unwrap_or((body, "")),
+        None => ("", body),
+    };
+
+    Ok(Some(Advisory {
+        id: meta.advisory.id,
+        package: meta.advisory.package.to_lowercase(),
+        title: title.trim().to_string(),
+        description: description.trim().to_string(),
+        date: meta.advisory.date,
+        url: meta.advisory.url,
+        aliases: meta.advisory.aliases,
+        informational: meta.advisory.informational,
+        patched: meta.versions.patched,
+        unaffected: meta.versions.unaffected,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2021-0001"
+package = "Foo"
+date = "2021-01-04"
+url = "https://github.com/example/foo/issues/1"
+categories = ["memory-corruption"]
+aliases = ["CVE-2021-1234", "GHSA-xxxx-yyyy-zzzz"]
+
+[versions]
+patched = [">= 1.2.3"]
+unaffected = ["< 1.0.0"]
+```
+
+# Use after free in `Foo::bar`
+
+The function `Foo::bar` can free memory
+that is still in use.
+"#;
+
+    #[test]
+    fn parse_full_advisory() {
+        let advisory = parse_advisory(ADVISORY).unwrap().unwrap();
+        assert_eq!(
+            Advisory {
+                id: "RUSTSEC-2021-0001".to_string(),
+                package: "foo".to_string(),
+                title: "Use after free in `Foo::bar`".to_string(),
+                description: "The function `Foo::bar` can free memory\nthat is still in use."
+                    .to_string(),
+                date: "2021-01-04".to_string(),
+                url: Some("https://github.com/example/foo/issues/1".to_string()),
+                aliases: vec![
+                    "CVE-2021-1234".to_string(),
+                    "GHSA-xxxx-yyyy-zzzz".to_string()
+                ],
+                informational: None,
+                patched: vec![">= 1.2.3".to_string()],
+                unaffected: vec!["< 1.0.0".to_string()],
+            },
+            advisory
+        );
+    }
+
+    #[test]
+    fn parse_informational_advisory_without_versions() {
+        let content = "```toml\n[advisory]\nid = \"RUSTSEC-2020-0002\"\npackage = \"bar\"\ndate = \"2020-02-02\"\ninformational = \"unmaintained\"\n```\n\n# bar is unmaintained\n";
+        let advisory = parse_advisory(content).unwrap().unwrap();
+        assert_eq!(Some("unmaintained".to_string()), advisory.informational);
+        assert_eq!("bar is unmaintained", advisory.title);
+        assert!(advisory.description.is_empty());
+        assert!(advisory.patched.is_empty());
+    }
+
+    #[test]
+    fn withdrawn_advisories_are_skipped() {
+        let content = "```toml\n[advisory]\nid = \"RUSTSEC-2020-0003\"\npackage = \"bar\"\ndate = \"2020-02-02\"\nwithdrawn = \"2020-03-01\"\n```\n\n# Withdrawn\n";
+        assert_eq!(Ok(None), parse_advisory(content));
+    }
+
+    #[test]
+    fn invalid_advisories_are_rejected() {
+        assert!(parse_advisory("# No front matter").is_err());
+        assert!(parse_advisory("```toml\n[advisory]\nid = \"x\"\n").is_err());
+        assert!(parse_advisory("```toml\n[advisory]\nid = \"x\"\n```\n# Title").is_err());
+    }
+}
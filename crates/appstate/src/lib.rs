@@ -1,9 +1,13 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
 use flume::Sender;
 use kellnr_common::cratesio_prefetch_msg::CratesioPrefetchMsg;
+use kellnr_common::git_index_msg::GitIndexMsg;
+use kellnr_common::proxy_policy::ProxyPolicy;
+use kellnr_common::publish_validator::PublishValidator;
 use kellnr_common::token_cache::TokenCacheManager;
 use kellnr_db::DbProvider;
 use kellnr_db::download_counter::DownloadCounter;
@@ -17,6 +21,9 @@ use reqwest::Client;
 
 pub type AppState = axum::extract::State<AppStateData>;
 
+/// Cache storage of each upstream registry in `settings.proxy.upstreams`, by name.
+pub type UpstreamStorages = HashMap<String, Arc<CratesIoCrateStorage>>;
+
 // Substates
 pub type DbState = axum::extract::State<Arc<dyn DbProvider>>;
 pub type SettingsState = axum::extract::State<Arc<Settings>>;
@@ -24,11 +31,18 @@ pub type SettingsProvState = axum::extract::State<Arc<SettingsProv>>;
 pub type CrateStorageState = axum::extract::State<Arc<KellnrCrateStorage>>;
 pub type CrateIoStorageState = axum::extract::State<Arc<CratesIoCrateStorage>>;
 pub type SigningKeyState = axum::extract::State<Key>;
+pub type UpstreamStoragesState = axum::extract::State<Arc<UpstreamStorages>>;
 pub type CratesIoPrefetchSenderState = axum::extract::State<Sender<CratesioPrefetchMsg>>;
+pub type GitIndexSenderState = axum::extract::State<Option<Sender<GitIndexMsg>>>;
 pub type TokenCacheState = axum::extract::State<Arc<TokenCacheManager>>;
 pub type ToolchainStorageState = axum::extract::State<Option<Arc<ToolchainStorage>>>;
 pub type DownloadCounterState = axum::extract::State<Arc<DownloadCounter>>;
 pub type ProxyClientState = axum::extract::State<Client>;
+pub type ProxyPolicyState = axum::extract::State<Arc<ProxyPolicy>>;
+pub type PublishValidatorsState = axum::extract::State<PublishValidators>;
+
+/// Checks of published crates, built from `settings.publish` on startup
+pub type PublishValidators = Arc<Vec<Box<dyn PublishValidator>>>;
 
 #[derive(Clone, FromRef)]
 pub struct AppStateData {
@@ -42,10 +56,17 @@ pub struct AppStateData {
     pub crate_storage: Arc<KellnrCrateStorage>,
     pub cratesio_storage: Arc<CratesIoCrateStorage>,
     pub cratesio_prefetch_sender: Sender<CratesioPrefetchMsg>,
+    pub upstream_storages: Arc<UpstreamStorages>,
+    /// Notifies the git index task about index changes. `None` if the git
+    /// index is disabled.
+    pub git_index_sender: Option<Sender<GitIndexMsg>>,
     pub token_cache: Arc<TokenCacheManager>,
     pub toolchain_storage: Option<Arc<ToolchainStorage>>,
     pub download_counter: Arc<DownloadCounter>,
     pub proxy_client: Client,
+    /// Policy of the crates.io proxy, built from `settings.proxy` on startup
+    pub proxy_policy: Arc<ProxyPolicy>,
+    pub publish_validators: PublishValidators,
 }
 
 /// Build a defaults-only `SettingsProv`, every leaf reports
@@ -82,9 +103,13 @@ pub fn test_state() -> AppStateData {
         crate_storage,
         cratesio_storage,
         cratesio_prefetch_sender,
+        upstream_storages: Arc::default(),
+        git_index_sender: None,
         token_cache,
         toolchain_storage: None, // Toolchain storage disabled in tests by default
         download_counter,
         proxy_client: kellnr_common::cratesio_downloader::CLIENT.clone(),
+        proxy_policy: Arc::default(),
+        publish_validators: Arc::default(),
     }
 }
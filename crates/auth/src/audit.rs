@@ -0,0 +1,134 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use kellnr_appstate::AppStateData;
+use kellnr_db::{AuditActor, DbProvider, NewAuditEntry};
+use tracing::error;
+
+use crate::maybe_user::MaybeUser;
+
+/// IP address of the client that sent the request.
+///
+/// Taken from the first entry of the `X-Forwarded-For` header if
+/// `registry.trust_proxy_headers` is enabled, otherwise from the peer address
+/// of the connection. `None` if neither is available.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceIp(pub Option<String>);
+
+impl SourceIp {
+    /// Build the actor of an audit log entry for `user`.
+    pub fn actor(self, user: impl Into<String>, token_name: Option<String>) -> AuditActor {
+        AuditActor {
+            user: user.into(),
+            token_name,
+            source_ip: self.0,
+        }
+    }
+
+    fn from_parts(parts: &Parts, trust_proxy_headers: bool) -> Self {
+        if trust_proxy_headers
+            && let Some(ip) = parts
+                .headers
+                .get("X-Forwarded-For")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        {
+            return Self(Some(ip.to_string()));
+        }
+
+        Self(
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string()),
+        )
+    }
+}
+
+impl FromRequestParts<AppStateData> for SourceIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppStateData,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(
+            parts,
+            state.settings.registry.trust_proxy_headers,
+        ))
+    }
+}
+
+impl MaybeUser {
+    /// Build the actor of an audit log entry for this user.
+    pub fn audit_actor(&self, source_ip: SourceIp) -> AuditActor {
+        source_ip.actor(&self.name, self.token_name.clone())
+    }
+}
+
+/// Append `entry` to the audit log. The audited action already happened at
+/// this point, so a failure is logged instead of failing the request.
+pub async fn record(db: &Arc<dyn DbProvider>, entry: NewAuditEntry) {
+    if let Err(err) = db.add_audit_entry(&entry).await {
+        error!("Failed to write audit log entry: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn parts(forwarded_for: Option<&str>, peer: Option<&str>) -> Parts {
+        let mut builder = Request::builder();
+        if let Some(forwarded_for) = forwarded_for {
+            builder = builder.header("X-Forwarded-For", forwarded_for);
+        }
+        let (mut parts, ()) = builder.body(()).unwrap().into_parts();
+        if let Some(peer) = peer {
+            parts
+                .extensions
+                .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        }
+        parts
+    }
+
+    #[test]
+    fn source_ip_from_peer_address() {
+        let parts = parts(None, Some("192.168.1.10:4711"));
+        assert_eq!(
+            SourceIp(Some("192.168.1.10".to_string())),
+            SourceIp::from_parts(&parts, false)
+        );
+    }
+
+    #[test]
+    fn source_ip_ignores_forwarded_for_by_default() {
+        let parts = parts(Some("10.0.0.1"), Some("192.168.1.10:4711"));
+        assert_eq!(
+            SourceIp(Some("192.168.1.10".to_string())),
+            SourceIp::from_parts(&parts, false)
+        );
+    }
+
+    #[test]
+    fn source_ip_from_trusted_forwarded_for() {
+        let parts = parts(Some("10.0.0.1, 172.16.0.1"), Some("192.168.1.10:4711"));
+        assert_eq!(
+            SourceIp(Some("10.0.0.1".to_string())),
+            SourceIp::from_parts(&parts, true)
+        );
+    }
+
+    #[test]
+    fn source_ip_unknown() {
+        let parts = parts(None, None);
+        assert_eq!(SourceIp(None), SourceIp::from_parts(&parts, true));
+    }
+}
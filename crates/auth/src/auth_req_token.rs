@@ -126,9 +126,9 @@ mod test {
     use axum::routing::get;
     use axum::{Router, middleware};
     use kellnr_appstate::AppStateData;
-    use kellnr_db::User;
     use kellnr_db::error::DbError;
     use kellnr_db::mock::MockDb;
+    use kellnr_db::{AuthToken, User};
     use kellnr_settings::Settings;
     use mockall::predicate::*;
     use tower::ServiceExt;
@@ -209,15 +209,18 @@ mod test {
             .expect_get_user_from_token()
             .with(eq("token"))
             .returning(move |_| {
-                Ok(User {
-                    id: 0,
-                    name: "user".to_string(),
-                    pwd: String::new(),
-                    salt: String::new(),
-                    is_admin: false,
-                    is_read_only: false,
-                    created: String::new(),
-                })
+                Ok((
+                    User {
+                        id: 0,
+                        name: "user".to_string(),
+                        pwd: String::new(),
+                        salt: String::new(),
+                        is_admin: false,
+                        is_read_only: false,
+                        created: String::new(),
+                    },
+                    AuthToken::default(),
+                ))
             });
         mock_db
             .expect_get_user_from_token()
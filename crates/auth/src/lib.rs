@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod auth_req_token;
 pub mod maybe_user;
 pub mod oauth2;
@@ -4,6 +4,7 @@ use axum::http::StatusCode;
 use axum::http::request::Parts;
 use axum_extra::extract::PrivateCookieJar;
 use kellnr_appstate::AppStateData;
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_db::SessionInfo;
 use kellnr_settings::constants;
 
@@ -12,24 +13,33 @@ use crate::token;
 #[derive(Debug, Clone)]
 pub struct MaybeUser {
     pub name: String,
+    /// Name of the token the user authenticated with, if any.
+    pub token_name: Option<String>,
     pub is_admin: bool,
     pub is_read_only: bool,
+    /// Restrictions of the token the user authenticated with.
+    /// Session logins are never restricted.
+    pub scopes: TokenScopes,
 }
 
 impl MaybeUser {
     pub fn from_token(token: token::Token) -> Self {
         Self {
             name: token.user,
+            token_name: token.name,
             is_admin: token.is_admin,
             is_read_only: token.is_read_only,
+            scopes: token.scopes,
         }
     }
 
     pub fn from_session(session: SessionInfo) -> Self {
         Self {
             name: session.name,
+            token_name: None,
             is_admin: session.is_admin,
             is_read_only: session.is_read_only,
+            scopes: TokenScopes::default(),
         }
     }
 }
@@ -9,8 +9,9 @@ use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use kellnr_appstate::AppStateData;
 use kellnr_common::token_cache::{CachedTokenData, TokenCacheManager};
-use kellnr_db::DbProvider;
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_db::error::DbError;
+use kellnr_db::{AuthToken, DbProvider};
 use kellnr_settings::Settings;
 use rand::distr::Alphanumeric;
 use rand::{RngExt, rng};
@@ -23,8 +24,13 @@ use utoipa::ToSchema;
 pub struct Token {
     pub value: String,
     pub user: String,
+    /// Name the token was created with. `None` for basic authentication.
+    pub name: Option<String>,
     pub is_admin: bool,
     pub is_read_only: bool,
+    /// Restrictions of the token. Login with user name and password
+    /// (basic authentication) is never restricted.
+    pub scopes: TokenScopes,
 }
 
 // See https://github.com/tokio-rs/axum/discussions/2281
@@ -82,8 +88,10 @@ impl Token {
             return Ok(Token {
                 value: token.to_string(),
                 user: user.name,
+                name: None,
                 is_admin: user.is_admin,
                 is_read_only: user.is_read_only,
+                scopes: TokenScopes::default(),
             });
         }
 
@@ -97,13 +105,15 @@ impl Token {
             return Ok(Token {
                 value: token.to_string(),
                 user: cached.user,
+                name: Some(cached.token_name),
                 is_admin: cached.is_admin,
                 is_read_only: cached.is_read_only,
+                scopes: cached.scopes,
             });
         }
 
         // Cache miss - query DB with retry logic
-        let Ok(user) = get_user_with_retry(
+        let Ok((user, auth_token)) = get_user_with_retry(
             db,
             token,
             settings.registry.token_db_retry_count,
@@ -113,6 +123,11 @@ impl Token {
         else {
             return Err(StatusCode::FORBIDDEN);
         };
+        let scopes = auth_token.scopes;
+
+        // A token limited to some operations or crates must not be usable
+        // for anything else, so it never carries the admin rights of its owner.
+        let is_admin = user.is_admin && !scopes.is_restricted();
 
         // Insert into cache on successful DB lookup
         cache
@@ -120,8 +135,10 @@ impl Token {
                 token.to_string(),
                 CachedTokenData {
                     user: user.name.clone(),
-                    is_admin: user.is_admin,
+                    token_name: auth_token.name.clone(),
+                    is_admin,
                     is_read_only: user.is_read_only,
+                    scopes: scopes.clone(),
                 },
             )
             .await;
@@ -129,8 +146,10 @@ impl Token {
         Ok(Token {
             value: token.to_string(),
             user: user.name,
-            is_admin: user.is_admin,
+            name: Some(auth_token.name),
+            is_admin,
             is_read_only: user.is_read_only,
+            scopes,
         })
     }
 }
@@ -140,12 +159,12 @@ async fn get_user_with_retry(
     token: &str,
     max_retries: u32,
     delay_ms: u64,
-) -> Result<kellnr_db::User, DbError> {
+) -> Result<(kellnr_db::User, AuthToken), DbError> {
     let mut attempts = 0;
 
     loop {
         match db.get_user_from_token(token).await {
-            Ok(user) => return Ok(user),
+            Ok(found) => return Ok(found),
             Err(e) => {
                 // Do not retry on "not found" errors - these are definitive
                 if matches!(e, DbError::TokenNotFound | DbError::UserNotFound(_)) {
@@ -216,12 +235,17 @@ impl FromRequestParts<AppStateData> for OptionToken {
 #[derive(Deserialize, ToSchema)]
 pub struct NewTokenReqData {
     pub name: String,
+    /// Optional restrictions of the new token. Without scopes, the token has
+    /// the full rights of the user.
+    #[serde(default)]
+    pub scopes: TokenScopes,
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::atomic::{AtomicU32, Ordering};
 
+    use kellnr_common::token_scope::TokenOperation;
     use kellnr_db::User;
     use kellnr_db::error::DbError;
     use kellnr_db::mock::MockDb;
@@ -267,13 +291,13 @@ mod tests {
             .expect_get_user_from_token()
             .with(eq("valid_token"))
             .times(1)
-            .returning(|_| Ok(test_user()));
+            .returning(|_| Ok((test_user(), AuthToken::default())));
 
         let db: Arc<dyn DbProvider> = Arc::new(mock_db);
         let result = get_user_with_retry(&db, "valid_token", 3, 10).await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap().name, "test_user");
+        assert_eq!(result.unwrap().0.name, "test_user");
     }
 
     #[tokio::test]
@@ -294,7 +318,7 @@ mod tests {
                         sea_orm::error::ConnAcquireErr::Timeout,
                     )))
                 } else {
-                    Ok(test_user())
+                    Ok((test_user(), AuthToken::default()))
                 }
             });
 
@@ -377,8 +401,10 @@ mod tests {
                 "cached_token".to_string(),
                 CachedTokenData {
                     user: "cached_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: true,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -408,15 +434,18 @@ mod tests {
             .with(eq("new_token"))
             .times(1)
             .returning(|_| {
-                Ok(User {
-                    id: 1,
-                    name: "db_user".to_string(),
-                    pwd: String::new(),
-                    salt: String::new(),
-                    is_admin: false,
-                    is_read_only: true,
-                    created: String::new(),
-                })
+                Ok((
+                    User {
+                        id: 1,
+                        name: "db_user".to_string(),
+                        pwd: String::new(),
+                        salt: String::new(),
+                        is_admin: false,
+                        is_read_only: true,
+                        created: String::new(),
+                    },
+                    AuthToken::default(),
+                ))
             });
 
         let db: Arc<dyn DbProvider> = Arc::new(mock_db);
@@ -495,7 +524,7 @@ mod tests {
             .times(2) // Called twice
             .returning(move |_| {
                 call_count_clone.fetch_add(1, Ordering::SeqCst);
-                Ok(test_user())
+                Ok((test_user(), AuthToken::default()))
             });
 
         let db: Arc<dyn DbProvider> = Arc::new(mock_db);
@@ -522,7 +551,7 @@ mod tests {
             .expect_get_user_from_token()
             .with(eq("lowercase_token"))
             .times(1)
-            .returning(|_| Ok(test_user()));
+            .returning(|_| Ok((test_user(), AuthToken::default())));
 
         let db: Arc<dyn DbProvider> = Arc::new(mock_db);
         let settings = test_settings();
@@ -536,6 +565,44 @@ mod tests {
         assert_eq!(result.unwrap().user, "test_user");
     }
 
+    #[tokio::test]
+    async fn test_scoped_token_drops_admin_rights() {
+        let cache = Arc::new(TokenCacheManager::new(true, 60, 100));
+
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_user_from_token()
+            .with(eq("scoped_token"))
+            .times(1)
+            .returning(|_| {
+                let mut auth_token = AuthToken::default();
+                auth_token.scopes.operations = vec![TokenOperation::Publish];
+                Ok((
+                    User {
+                        is_admin: true,
+                        ..test_user()
+                    },
+                    auth_token,
+                ))
+            });
+
+        let db: Arc<dyn DbProvider> = Arc::new(mock_db);
+        let settings = test_settings();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "scoped_token".parse().unwrap());
+
+        let token = Token::from_header(&headers, &db, &cache, &settings)
+            .await
+            .unwrap();
+        assert!(!token.is_admin);
+        assert_eq!(vec![TokenOperation::Publish], token.scopes.operations);
+
+        // The cached entry must not grant admin rights either
+        let cached = cache.get("scoped_token").await.unwrap();
+        assert!(!cached.is_admin);
+    }
+
     #[tokio::test]
     async fn test_zero_retries_only_attempts_once() {
         // With max_retries = 0, should only attempt once
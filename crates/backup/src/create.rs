@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use kellnr_common::original_name::OriginalName;
+use kellnr_common::version::Version;
+use kellnr_db::{BACKUP_TABLES, CRATESIO_TABLES, DbExport};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{BackupError, BackupSummary, BackupTarget, FORMAT_VERSION, MANIFEST, Manifest};
+
+type Archive = tar::Builder<GzEncoder<File>>;
+
+/// Write a backup of `target` to `archive`.
+///
+/// The database is exported first, in a single transaction. The files are
+/// collected afterwards, based on the exported rows. As kellnr stores files
+/// before it adds them to the database, every exported row has its file,
+/// even if the backup is taken while kellnr is running.
+pub async fn create_backup(
+    archive: &Path,
+    target: &BackupTarget<'_>,
+) -> Result<BackupSummary, BackupError> {
+    // Write to a temporary file, so a failed backup leaves no archive behind
+    let partial = archive.with_extension("partial");
+    let result = write_archive(&partial, target).await;
+    match result {
+        Ok(summary) => {
+            std::fs::rename(&partial, archive)
+                .map_err(|e| BackupError::Archive(archive.to_path_buf(), e))?;
+            Ok(summary)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+async fn write_archive(
+    path: &Path,
+    target: &BackupTarget<'_>,
+) -> Result<BackupSummary, BackupError> {
+    let io_err = |e| BackupError::Archive(path.to_path_buf(), e);
+    let file = File::create(path).map_err(io_err)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut summary = BackupSummary::default();
+
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        kellnr_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Utc::now().to_rfc3339(),
+        includes_cratesio: target.cratesio_storage.is_some(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| BackupError::InvalidArchive(e.to_string()))?;
+    append(&mut archive, MANIFEST, &manifest).map_err(io_err)?;
+
+    let export = target.db.export().await?;
+    let mut files = FileIndex::default();
+    let result = export_tables(&export, &mut archive, &mut files, target, &mut summary).await;
+    export.finish().await?;
+    result.map_err(io_err)?;
+
+    for (name, version) in &files.crates {
+        let data = target
+            .crate_storage
+            .get(
+                &OriginalName::from_unchecked(name.clone()),
+                &Version::from_unchecked_str(version),
+            )
+            .await;
+        if let Some(data) = data {
+            append(
+                &mut archive,
+                &format!("crates/{name}/{version}.crate"),
+                &data,
+            )
+            .map_err(io_err)?;
+            summary.crates += 1;
+        } else {
+            warn!("Crate file of {name} ({version}) not found, skipping it");
+            summary.missing_files += 1;
+        }
+    }
+
+    if let Some(storage) = target.cratesio_storage {
+        for (name, version) in &files.cached_crates {
+            // Only downloaded versions are in the cache, not all versions
+            // of the crates.io index.
+            let data = storage
+                .get(
+                    &OriginalName::from_unchecked(name.clone()),
+                    &Version::from_unchecked_str(version),
+                )
+                .await;
+            if let Some(data) = data {
+                append(
+                    &mut archive,
+                    &format!("cratesio/{name}/{version}.crate"),
+                    &data,
+                )
+                .map_err(io_err)?;
+                summary.cached_crates += 1;
+            }
+        }
+    }
+
+    if let Some(storage) = target.toolchain_storage {
+        for toolchain_path in &files.toolchains {
+            match storage.get(toolchain_path).await {
+                Ok(data) => {
+                    append(&mut archive, &format!("toolchains/{toolchain_path}"), &data)
+                        .map_err(io_err)?;
+                    summary.toolchain_files += 1;
+                }
+                Err(e) => {
+                    warn!("Toolchain archive {toolchain_path} not readable, skipping it: {e}");
+                    summary.missing_files += 1;
+                }
+            }
+        }
+    }
+
+    if target.docs_path.is_dir() {
+        summary.doc_files = append_dir(&mut archive, &target.docs_path, Path::new("docs"))
+            .map_err(|e| BackupError::Archive(target.docs_path.clone(), e))?;
+    }
+
+    archive
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .and_then(|mut file| file.flush())
+        .map_err(io_err)?;
+    Ok(summary)
+}
+
+async fn export_tables(
+    export: &DbExport,
+    archive: &mut Archive,
+    files: &mut FileIndex,
+    target: &BackupTarget<'_>,
+    summary: &mut BackupSummary,
+) -> Result<(), std::io::Error> {
+    for table in BACKUP_TABLES {
+        if target.cratesio_storage.is_none() && CRATESIO_TABLES.contains(table) {
+            continue;
+        }
+        for page in 0.. {
+            let rows = export
+                .rows(table, page)
+                .await
+                .map_err(std::io::Error::other)?;
+            if rows.is_empty() {
+                break;
+            }
+            for row in &rows {
+                files.add(table, row);
+            }
+            summary.rows += rows.len() as u64;
+            let data = serde_json::to_vec(&rows)?;
+            append(archive, &format!("db/{table}/{page:06}.json"), &data)?;
+        }
+    }
+    Ok(())
+}
+
+fn append(archive: &mut Archive, path: &str, data: &[u8]) -> Result<(), std::io::Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default());
+    archive.append_data(&mut header, path, data)
+}
+
+/// Append all files below `dir` and return their number.
+fn append_dir(archive: &mut Archive, dir: &Path, name: &Path) -> Result<u64, std::io::Error> {
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let entry_name = name.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            count += append_dir(archive, &path, &entry_name)?;
+        } else {
+            archive.append_path_with_name(&path, &entry_name)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Files referenced by the exported rows.
+#[derive(Default)]
+struct FileIndex {
+    crate_names: HashMap<i64, String>,
+    crates: Vec<(String, String)>,
+    cached_crate_names: HashMap<i64, String>,
+    cached_crates: Vec<(String, String)>,
+    toolchains: Vec<String>,
+}
+
+impl FileIndex {
+    fn add(&mut self, table: &str, row: &Value) {
+        let int = |key: &str| row[key].as_i64();
+        let string = |key: &str| row[key].as_str().map(ToString::to_string);
+        match table {
+            "krate" => {
+                if let (Some(id), Some(name)) = (int("id"), string("original_name")) {
+                    self.crate_names.insert(id, name);
+                }
+            }
+            "crate_meta" => {
+                if let (Some(name), Some(version)) = (
+                    int("crate_fk").and_then(|id| self.crate_names.get(&id)),
+                    string("version"),
+                ) {
+                    self.crates.push((name.clone(), version));
+                }
+            }
+            "cratesio_crate" => {
+                if let (Some(id), Some(name)) = (int("id"), string("original_name")) {
+                    self.cached_crate_names.insert(id, name);
+                }
+            }
+            "cratesio_meta" => {
+                if let (Some(name), Some(version)) = (
+                    int("crates_io_fk").and_then(|id| self.cached_crate_names.get(&id)),
+                    string("version"),
+                ) {
+                    self.cached_crates.push((name.clone(), version));
+                }
+            }
+            "toolchain_target" | "toolchain_component" => {
+                if let Some(path) = string("storage_path") {
+                    self.toolchains.push(path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
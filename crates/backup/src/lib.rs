@@ -0,0 +1,389 @@
+//! Backup, restore and migration of a kellnr instance. A backup is a single
+//! gzipped tarball with the database content, the crate files, the generated
+//! docs and the toolchain archives. A migration copies the same data directly
+//! into another database and storage backend.
+//!
+//! Layout of the archive:
+//!
+//! - `manifest.json`: format version and origin of the backup
+//! - `db/<table>/<page>.json`: rows of a table as JSON array
+//! - `crates/<name>/<version>.crate`: hosted crates
+//! - `cratesio/<name>/<version>.crate`: cached crates.io crates (optional)
+//! - `toolchains/<path>`: toolchain and component archives
+//! - `docs/<path>`: generated rustdoc
+
+mod create;
+mod migrate;
+mod restore;
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub use create::create_backup;
+use kellnr_db::Database;
+use kellnr_db::error::DbError;
+use kellnr_storage::cratesio_crate_storage::CratesIoCrateStorage;
+use kellnr_storage::kellnr_crate_storage::KellnrCrateStorage;
+use kellnr_storage::storage_error::StorageError;
+use kellnr_storage::toolchain_storage::ToolchainStorage;
+pub use migrate::{MigrationEndpoint, MigrationSummary, migrate, migrate_storage};
+pub use restore::restore_backup;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version of the archive layout, increased on incompatible changes.
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("Failed to access backup archive {0}: {1}")]
+    Archive(PathBuf, std::io::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] DbError),
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("Invalid backup archive: {0}")]
+    InvalidArchive(String),
+    #[error("Backup format {0} is not supported by this version of kellnr")]
+    UnsupportedFormat(u32),
+    #[error("Cannot restore into {0}, the directory is not empty")]
+    TargetNotEmpty(PathBuf),
+    #[error("Failed to read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Failed to write {0}: {1}")]
+    Write(PathBuf, std::io::Error),
+    #[error("Checksum of {key} in storage {storage} differs between source and target")]
+    ChecksumMismatch { storage: String, key: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    kellnr_version: String,
+    created: String,
+    includes_cratesio: bool,
+}
+
+/// Database and storages that are backed up or restored into.
+pub struct BackupTarget<'a> {
+    pub db: &'a Database,
+    pub crate_storage: &'a KellnrCrateStorage,
+    /// `None` leaves out the crates.io proxy cache
+    pub cratesio_storage: Option<&'a CratesIoCrateStorage>,
+    /// `None` leaves out toolchain archives
+    pub toolchain_storage: Option<&'a ToolchainStorage>,
+    pub docs_path: PathBuf,
+}
+
+/// Number of items in a backup or restore.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BackupSummary {
+    pub rows: u64,
+    pub crates: u64,
+    pub cached_crates: u64,
+    pub toolchain_files: u64,
+    pub doc_files: u64,
+    /// Files referenced by the database that were not found in the storage
+    pub missing_files: u64,
+}
+
+impl fmt::Display for BackupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} database rows, {} crates, {} cached crates.io crates, {} toolchain files, {} doc files",
+            self.rows, self.crates, self.cached_crates, self.toolchain_files, self.doc_files
+        )?;
+        if self.missing_files > 0 {
+            write!(f, " ({} missing files)", self.missing_files)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use kellnr_common::normalized_name::NormalizedName;
+    use kellnr_common::original_name::OriginalName;
+    use kellnr_common::publish_metadata::PublishMetadata;
+    use kellnr_common::version::Version;
+    use kellnr_db::{ConString, DbProvider, DocBuildState, SqliteConString};
+    use kellnr_settings::{Registry, Settings};
+    use kellnr_storage::cached_crate_storage::DynStorage;
+    use kellnr_storage::fs_storage::FSStorage;
+
+    use super::*;
+
+    struct Instance {
+        db: Database,
+        crates: KellnrCrateStorage,
+        cratesio: CratesIoCrateStorage,
+        toolchains: ToolchainStorage,
+        settings: Settings,
+    }
+
+    impl Instance {
+        async fn new(dir: &Path) -> Self {
+            let settings = Settings {
+                registry: Registry {
+                    data_dir: dir.to_string_lossy().to_string(),
+                    ..Registry::default()
+                },
+                ..Settings::default()
+            };
+            let storage = |path: String| Box::new(FSStorage::new(&path).unwrap()) as DynStorage;
+            let db = Database::new(&ConString::Sqlite(SqliteConString::from(&settings)), 10)
+                .await
+                .unwrap();
+            Self {
+                db,
+                crates: KellnrCrateStorage::new(&settings, storage(settings.crates_path())),
+                cratesio: CratesIoCrateStorage::new(&settings, storage(settings.crates_io_path())),
+                toolchains: ToolchainStorage::new(storage(settings.toolchain_path())),
+                settings,
+            }
+        }
+
+        fn target(&self, include_cratesio: bool) -> BackupTarget<'_> {
+            BackupTarget {
+                db: &self.db,
+                crate_storage: &self.crates,
+                cratesio_storage: include_cratesio.then_some(&self.cratesio),
+                toolchain_storage: Some(&self.toolchains),
+                docs_path: self.settings.docs_path(),
+            }
+        }
+
+        fn endpoint(&self) -> MigrationEndpoint<'_> {
+            let storage = FSStorage::new(&self.settings.crates_path()).unwrap();
+            MigrationEndpoint {
+                db: &self.db,
+                storages: vec![("crates".to_string(), Box::new(storage) as DynStorage)],
+                docs_path: self.settings.docs_path(),
+            }
+        }
+
+        async fn publish(&self, name: &str, version: &str) {
+            let pm = PublishMetadata {
+                name: name.to_string(),
+                vers: version.to_string(),
+                ..PublishMetadata::default()
+            };
+            self.db
+                .add_crate(&pm, "cksum", &chrono::Utc::now(), "admin")
+                .await
+                .unwrap();
+            self.crates
+                .put(
+                    &OriginalName::try_from(name).unwrap(),
+                    &Version::try_from(version).unwrap(),
+                    Arc::from(format!("{name}-{version}").as_bytes()),
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn backup_and_restore_into_empty_data_dir() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = Instance::new(source_dir.path()).await;
+        source.publish("foo", "1.0.0").await;
+        source.publish("foo", "1.1.0-beta.1").await;
+        source.publish("bar", "0.1.0").await;
+        let docs = source.settings.docs_path().join("foo/1.0.0");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("index.html"), "docs").unwrap();
+
+        let archive = source_dir.path().join("backup.tar.gz");
+        let created = create_backup(&archive, &source.target(false))
+            .await
+            .unwrap();
+        assert_eq!(3, created.crates);
+        assert_eq!(1, created.doc_files);
+        assert_eq!(0, created.missing_files);
+
+        let target_dir = tempfile::TempDir::new().unwrap();
+        let target = Instance::new(target_dir.path()).await;
+        let restored = restore_backup(&archive, &target.target(true))
+            .await
+            .unwrap();
+        assert_eq!(created, restored);
+
+        assert_eq!(
+            source.db.get_crate_summaries().await.unwrap(),
+            target.db.get_crate_summaries().await.unwrap()
+        );
+        let file = target
+            .crates
+            .get(
+                &OriginalName::try_from("foo").unwrap(),
+                &Version::try_from("1.1.0-beta.1").unwrap(),
+            )
+            .await;
+        assert_eq!(Some(Bytes::from("foo-1.1.0-beta.1")), file);
+        assert_eq!(
+            "docs",
+            std::fs::read_to_string(target.settings.docs_path().join("foo/1.0.0/index.html"))
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_contains_finished_doc_builds_only() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = Instance::new(source_dir.path()).await;
+        for name in ["foo", "bar"] {
+            source.publish(name, "1.0.0").await;
+            source
+                .db
+                .add_doc_queue(
+                    &NormalizedName::from_unchecked_str(name),
+                    &Version::try_from("1.0.0").unwrap(),
+                    &source_dir.path().join(name),
+                )
+                .await
+                .unwrap();
+        }
+        let finished = source.db.get_doc_queue().await.unwrap()[0].id;
+        source
+            .db
+            .finish_doc_build(
+                finished,
+                DocBuildState::Failed,
+                std::time::Duration::from_secs(1),
+                "build log",
+            )
+            .await
+            .unwrap();
+        let archive = source_dir.path().join("backup.tar.gz");
+        create_backup(&archive, &source.target(false))
+            .await
+            .unwrap();
+
+        let target_dir = tempfile::TempDir::new().unwrap();
+        let target = Instance::new(target_dir.path()).await;
+        restore_backup(&archive, &target.target(true))
+            .await
+            .unwrap();
+
+        let builds = target.db.get_doc_builds(10).await.unwrap();
+        assert_eq!(1, builds.len());
+        assert_eq!(finished, builds[0].id);
+        assert_eq!(DocBuildState::Failed, builds[0].state);
+        assert_eq!(
+            Some("build log".to_string()),
+            target.db.get_doc_build_log(finished).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn migrate_copies_data_and_skips_existing_objects() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = Instance::new(source_dir.path()).await;
+        source.publish("foo", "1.0.0").await;
+        source.publish("bar", "0.1.0").await;
+        let target_dir = tempfile::TempDir::new().unwrap();
+        let target = Instance::new(target_dir.path()).await;
+        // Left over from an interrupted migration
+        target
+            .crates
+            .put(
+                &OriginalName::try_from("foo").unwrap(),
+                &Version::try_from("1.0.0").unwrap(),
+                Arc::from(b"foo-1.0.0".as_slice()),
+            )
+            .await
+            .unwrap();
+
+        let summary = migrate(&source.endpoint(), &target.endpoint())
+            .await
+            .unwrap();
+
+        assert_eq!(1, summary.objects);
+        assert_eq!(1, summary.skipped_objects);
+        assert_eq!(
+            source.db.get_crate_summaries().await.unwrap(),
+            target.db.get_crate_summaries().await.unwrap()
+        );
+        let file = target
+            .crates
+            .get(
+                &OriginalName::try_from("bar").unwrap(),
+                &Version::try_from("0.1.0").unwrap(),
+            )
+            .await;
+        assert_eq!(Some(Bytes::from("bar-0.1.0")), file);
+    }
+
+    #[tokio::test]
+    async fn migrate_storage_keeps_target_database() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = Instance::new(source_dir.path()).await;
+        source.publish("foo", "1.0.0").await;
+        let target_dir = tempfile::TempDir::new().unwrap();
+        let target = Instance::new(target_dir.path()).await;
+        target.publish("bar", "0.1.0").await;
+
+        let summary = migrate_storage(&source.endpoint(), &target.endpoint())
+            .await
+            .unwrap();
+
+        assert_eq!(1, summary.objects);
+        assert_eq!(0, summary.rows);
+        let crates = target.db.get_crate_summaries().await.unwrap();
+        assert_eq!(1, crates.len());
+        assert_eq!("bar", crates[0].name);
+        let file = target
+            .crates
+            .get(
+                &OriginalName::try_from("foo").unwrap(),
+                &Version::try_from("1.0.0").unwrap(),
+            )
+            .await;
+        assert_eq!(Some(Bytes::from("foo-1.0.0")), file);
+    }
+
+    #[tokio::test]
+    async fn migrate_fails_if_existing_doc_file_differs() {
+        let source_dir = tempfile::TempDir::new().unwrap();
+        let source = Instance::new(source_dir.path()).await;
+        let target_dir = tempfile::TempDir::new().unwrap();
+        let target = Instance::new(target_dir.path()).await;
+        for (instance, content) in [(&source, "docs"), (&target, "DOCS")] {
+            let docs = instance.settings.docs_path().join("foo/1.0.0");
+            std::fs::create_dir_all(&docs).unwrap();
+            std::fs::write(docs.join("index.html"), content).unwrap();
+        }
+
+        let result = migrate(&source.endpoint(), &target.endpoint()).await;
+
+        assert!(matches!(
+            result,
+            Err(BackupError::ChecksumMismatch { storage, .. }) if storage == "docs"
+        ));
+    }
+
+    #[tokio::test]
+    async fn restore_into_instance_with_crates_fails() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let instance = Instance::new(dir.path()).await;
+        instance.publish("foo", "1.0.0").await;
+        let archive = dir.path().join("backup.tar.gz");
+        create_backup(&archive, &instance.target(true))
+            .await
+            .unwrap();
+
+        let result = restore_backup(&archive, &instance.target(true)).await;
+
+        assert!(matches!(
+            result,
+            Err(BackupError::Db(DbError::RestoreTargetNotEmpty("crates")))
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use kellnr_db::{BACKUP_TABLES, Database};
+use kellnr_storage::cached_crate_storage::DynStorage;
+use kellnr_storage::storage::PROBE_PREFIX;
+use tracing::{info, warn};
+
+use crate::BackupError;
+
+/// Database and storages of one side of a migration.
+pub struct MigrationEndpoint<'a> {
+    pub db: &'a Database,
+    /// Storages by name, e.g. `crates` or `toolchains`. Objects are copied
+    /// between the source and target storages of the same name.
+    pub storages: Vec<(String, DynStorage)>,
+    pub docs_path: PathBuf,
+}
+
+/// Number of items copied by a migration.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MigrationSummary {
+    pub rows: u64,
+    pub objects: u64,
+    /// Objects that were already present in the target with the same checksum
+    pub skipped_objects: u64,
+    pub doc_files: u64,
+}
+
+impl fmt::Display for MigrationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} database rows, {} objects ({} already present), {} doc files",
+            self.rows, self.objects, self.skipped_objects, self.doc_files
+        )
+    }
+}
+
+/// Copy all database rows, storage objects and docs from `source` to `target`.
+///
+/// Every object is read back from the target and compared to the source by
+/// its SHA-256 checksum. Objects that already exist in the target with the
+/// same checksum are skipped, so an interrupted migration can be started
+/// again. The database is copied in a single transaction that is committed
+/// last, so it is either fully migrated or left unchanged.
+pub async fn migrate(
+    source: &MigrationEndpoint<'_>,
+    target: &MigrationEndpoint<'_>,
+) -> Result<MigrationSummary, BackupError> {
+    // Fails before anything is copied if the target is in use
+    let import = target.db.import().await?;
+    // Files are stored before their rows are added. Taking the snapshot
+    // first guarantees that the files of all exported rows are copied.
+    let export = source.db.export().await?;
+    let mut summary = migrate_storage(source, target).await?;
+
+    for table in BACKUP_TABLES {
+        for page in 0.. {
+            let rows = export.rows(table, page).await?;
+            if rows.is_empty() {
+                break;
+            }
+            import.insert(table, &rows).await?;
+            summary.rows += rows.len() as u64;
+        }
+    }
+    export.finish().await?;
+    import.commit().await?;
+
+    Ok(summary)
+}
+
+/// Copy only the storage objects and docs from `source` to `target`, e.g. to
+/// move from the filesystem to S3 while keeping the database. The databases
+/// of the endpoints are not used. Objects are verified and skipped like in
+/// [`migrate`].
+pub async fn migrate_storage(
+    source: &MigrationEndpoint<'_>,
+    target: &MigrationEndpoint<'_>,
+) -> Result<MigrationSummary, BackupError> {
+    let mut summary = MigrationSummary::default();
+
+    for (name, source_storage) in &source.storages {
+        let Some((_, target_storage)) = target.storages.iter().find(|(n, _)| n == name) else {
+            warn!("No target storage for {name} configured, skipping it");
+            continue;
+        };
+        copy_objects(name, source_storage, target_storage, &mut summary).await?;
+    }
+
+    if source.docs_path != target.docs_path && source.docs_path.is_dir() {
+        summary.doc_files = copy_dir(&source.docs_path, &target.docs_path)?;
+    }
+
+    Ok(summary)
+}
+
+async fn copy_objects(
+    name: &str,
+    source: &DynStorage,
+    target: &DynStorage,
+    summary: &mut MigrationSummary,
+) -> Result<(), BackupError> {
+    let keys = source.list().await?;
+    info!("Migrating {} objects of storage {name}", keys.len());
+
+    for key in keys.iter().filter(|k| !k.starts_with(PROBE_PREFIX)) {
+        let data = source.get(key).await?;
+        let checksum = sha256::digest(&*data);
+        let mismatch = || BackupError::ChecksumMismatch {
+            storage: name.to_string(),
+            key: key.clone(),
+        };
+
+        if target.exists(key).await? {
+            if sha256::digest(&*target.get(key).await?) != checksum {
+                return Err(mismatch());
+            }
+            summary.skipped_objects += 1;
+            continue;
+        }
+
+        target.put(key, data).await?;
+        if sha256::digest(&*target.get(key).await?) != checksum {
+            return Err(mismatch());
+        }
+        summary.objects += 1;
+    }
+    Ok(())
+}
+
+/// Copy all files below `source` that are missing in `target` and return
+/// their number. Like objects, files that already exist are skipped if their
+/// checksum matches and fail the migration otherwise.
+fn copy_dir(source: &Path, target: &Path) -> Result<u64, BackupError> {
+    let read_err = |e| BackupError::Read(source.to_path_buf(), e);
+    std::fs::create_dir_all(target).map_err(|e| BackupError::Write(target.to_path_buf(), e))?;
+    let mut count = 0;
+    for entry in std::fs::read_dir(source).map_err(read_err)? {
+        let entry = entry.map_err(read_err)?;
+        let metadata = entry.metadata().map_err(read_err)?;
+        let target_path = target.join(entry.file_name());
+        if metadata.is_dir() {
+            count += copy_dir(&entry.path(), &target_path)?;
+            continue;
+        }
+
+        let data = std::fs::read(entry.path()).map_err(|e| BackupError::Read(entry.path(), e))?;
+        let checksum = sha256::digest(&data);
+        let mismatch = || BackupError::ChecksumMismatch {
+            storage: "docs".to_string(),
+            key: target_path.display().to_string(),
+        };
+        let read_target =
+            || std::fs::read(&target_path).map_err(|e| BackupError::Read(target_path.clone(), e));
+
+        if target_path.exists() {
+            if sha256::digest(&read_target()?) != checksum {
+                return Err(mismatch());
+            }
+            continue;
+        }
+
+        std::fs::write(&target_path, &data)
+            .map_err(|e| BackupError::Write(target_path.clone(), e))?;
+        if sha256::digest(&read_target()?) != checksum {
+            return Err(mismatch());
+        }
+        count += 1;
+    }
+    Ok(count)
+}
@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use kellnr_common::original_name::OriginalName;
+use kellnr_common::version::Version;
+use kellnr_db::{CRATESIO_TABLES, DbImport};
+use serde_json::Value;
+
+use crate::{BackupError, BackupSummary, BackupTarget, FORMAT_VERSION, MANIFEST, Manifest};
+
+/// Restore the backup in `archive` into `target`.
+///
+/// The target database must not contain crates or users besides the initial
+/// admin and the docs directory must be empty. The database import is only
+/// committed after all files are restored, so a failed restore leaves the
+/// database unchanged.
+pub async fn restore_backup(
+    archive: &Path,
+    target: &BackupTarget<'_>,
+) -> Result<BackupSummary, BackupError> {
+    let io_err = |e| BackupError::Archive(archive.to_path_buf(), e);
+    let file = File::open(archive).map_err(io_err)?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+    let mut entries = tar.entries().map_err(io_err)?;
+
+    let manifest: Manifest = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry.map_err(io_err)?;
+            if entry.path().map_err(io_err)?.as_ref() != Path::new(MANIFEST) {
+                return Err(BackupError::InvalidArchive(format!(
+                    "{MANIFEST} is not the first entry"
+                )));
+            }
+            serde_json::from_slice(&read(&mut entry).map_err(io_err)?)
+                .map_err(|e| BackupError::InvalidArchive(e.to_string()))?
+        }
+        None => return Err(BackupError::InvalidArchive("archive is empty".to_string())),
+    };
+    if manifest.format != FORMAT_VERSION {
+        return Err(BackupError::UnsupportedFormat(manifest.format));
+    }
+
+    let docs_path = &target.docs_path;
+    if docs_path
+        .read_dir()
+        .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        return Err(BackupError::TargetNotEmpty(docs_path.clone()));
+    }
+
+    let import = target.db.import().await?;
+    let mut summary = BackupSummary::default();
+    for entry in entries {
+        let mut entry = entry.map_err(io_err)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(io_err)?.to_string_lossy().to_string();
+        let data = read(&mut entry).map_err(io_err)?;
+        restore_entry(&import, target, &path, data, &mut summary).await?;
+    }
+    import.commit().await?;
+
+    Ok(summary)
+}
+
+async fn restore_entry(
+    import: &DbImport,
+    target: &BackupTarget<'_>,
+    path: &str,
+    data: Vec<u8>,
+    summary: &mut BackupSummary,
+) -> Result<(), BackupError> {
+    let invalid = || BackupError::InvalidArchive(format!("unexpected entry {path}"));
+
+    if let Some(rest) = path.strip_prefix("db/") {
+        let (table, _) = rest.split_once('/').ok_or_else(invalid)?;
+        if target.cratesio_storage.is_none() && CRATESIO_TABLES.contains(&table) {
+            return Ok(());
+        }
+        let rows: Vec<Value> = serde_json::from_slice(&data)
+            .map_err(|e| BackupError::InvalidArchive(format!("{path}: {e}")))?;
+        import.insert(table, &rows).await?;
+        summary.rows += rows.len() as u64;
+    } else if let Some(rest) = path.strip_prefix("crates/") {
+        let (name, version) = crate_file(rest).ok_or_else(invalid)?;
+        target
+            .crate_storage
+            .put(&name, &version, Arc::from(data))
+            .await?;
+        summary.crates += 1;
+    } else if let Some(rest) = path.strip_prefix("cratesio/") {
+        if let Some(storage) = target.cratesio_storage {
+            let (name, version) = crate_file(rest).ok_or_else(invalid)?;
+            storage.put(&name, &version, Arc::from(data)).await?;
+            summary.cached_crates += 1;
+        }
+    } else if let Some(rest) = path.strip_prefix("toolchains/") {
+        if let Some(storage) = target.toolchain_storage {
+            storage.put_raw(rest, data.into()).await?;
+            summary.toolchain_files += 1;
+        }
+    } else if let Some(rest) = path.strip_prefix("docs/") {
+        let rest = Path::new(rest);
+        // Never write outside of the docs directory
+        if !rest.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(invalid());
+        }
+        let file = target.docs_path.join(rest);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| BackupError::Write(file.clone(), e))?;
+        }
+        std::fs::write(&file, data).map_err(|e| BackupError::Write(file.clone(), e))?;
+        summary.doc_files += 1;
+    } else {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Split `<name>/<version>.crate` into crate name and version.
+fn crate_file(path: &str) -> Option<(OriginalName, Version)> {
+    let (name, file) = path.split_once('/')?;
+    let version = file.strip_suffix(".crate")?;
+    Some((
+        OriginalName::try_from(name).ok()?,
+        Version::try_from(version).ok()?,
+    ))
+}
+
+fn read(entry: &mut impl Read) -> Result<Vec<u8>, std::io::Error> {
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
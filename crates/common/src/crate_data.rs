@@ -13,6 +13,8 @@ pub struct CrateData {
     pub owners: Vec<String>,
     pub max_version: String,
     pub total_downloads: i64,
+    /// Downloads of all versions in the last 30 days
+    pub recent_downloads: i64,
     pub last_updated: String,
     // metadata information from the publishing
     pub homepage: Option<String>,
@@ -30,6 +32,8 @@ pub struct CrateVersionData {
     // additional information about the crate version from kellnr
     pub created: String,
     pub downloads: i64,
+    /// Downloads in the last 30 days
+    pub recent_downloads: i64,
     // metadata information from the publishing
     pub readme: Option<String>,
     pub license: Option<String>,
@@ -1,9 +1,15 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
 use reqwest::{Client, ClientBuilder, StatusCode, Url};
+use serde::Deserialize;
 use tracing::error;
 
+use crate::metrics;
+
 /// Default user-agent sent with requests to crates.io. This is the single source
 /// of truth for the default user-agent across the whole codebase.
 pub const DEFAULT_USER_AGENT: &str = "kellnr.io/kellnr";
@@ -71,6 +77,95 @@ impl From<DownloadCrateError> for StatusCode {
     }
 }
 
+/// Crate data from the crates.io API that is not part of the index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateInfo {
+    pub description: Option<String>,
+    /// License and publish time of each version
+    pub versions: HashMap<String, VersionInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VersionInfo {
+    pub license: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fetch the crate data of `name` from the crates.io API at `api`.
+pub async fn fetch_crate_info(
+    client: &Client,
+    name: &str,
+    api: &Url,
+) -> Result<CrateInfo, DownloadCrateError> {
+    #[derive(Deserialize)]
+    struct Krate {
+        description: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Version {
+        num: String,
+        #[serde(flatten)]
+        info: VersionInfo,
+    }
+    #[derive(Deserialize)]
+    struct CrateResponse {
+        #[serde(rename = "crate")]
+        krate: Krate,
+        #[serde(default)]
+        versions: Vec<Version>,
+    }
+
+    let response = client.get(api.join(name)?).send().await.map_err(|e| {
+        metrics::upstream_error(metrics::CRATES_IO);
+        DownloadCrateError::Unexpected(e)
+    })?;
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => return Err(DownloadCrateError::NotFound),
+        status => {
+            metrics::upstream_error(metrics::CRATES_IO);
+            return Err(DownloadCrateError::NotOk(status));
+        }
+    }
+
+    let response = response
+        .json::<CrateResponse>()
+        .await
+        .map_err(DownloadCrateError::CannotParseResponse)?;
+    Ok(CrateInfo {
+        description: response.krate.description,
+        versions: response
+            .versions
+            .into_iter()
+            .map(|v| (v.num, v.info))
+            .collect(),
+    })
+}
+
+/// Crates.io API data of recently requested crates, to check the license and
+/// age of their versions without asking crates.io for every request.
+static CRATE_INFO_CACHE: std::sync::LazyLock<Cache<String, Arc<CrateInfo>>> =
+    std::sync::LazyLock::new(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(Duration::from_mins(30))
+            .build()
+    });
+
+/// Like [`fetch_crate_info`], but the data of a crate is only fetched again
+/// after 30 minutes.
+pub async fn fetch_crate_info_cached(
+    client: &Client,
+    name: &str,
+    api: &Url,
+) -> Result<Arc<CrateInfo>, Arc<DownloadCrateError>> {
+    CRATE_INFO_CACHE
+        .try_get_with(name.to_lowercase(), async {
+            fetch_crate_info(client, name, api).await.map(Arc::new)
+        })
+        .await
+}
+
 pub async fn download_crate(
     client: &Client,
     name: &str,
@@ -84,9 +179,13 @@ pub async fn download_crate(
         Ok(resp) if resp.status() == 404 => Err(DownloadCrateError::NotFound),
         Ok(resp) if resp.status() == 403 => Err(DownloadCrateError::NotFound), // Map 403 to 404 as
         // crates.io returns 403 for non-existent crates
-        Ok(resp) if resp.status() != 200 => Err(DownloadCrateError::NotOk(resp.status())),
+        Ok(resp) if resp.status() != 200 => {
+            metrics::upstream_error(metrics::CRATES_IO);
+            Err(DownloadCrateError::NotOk(resp.status()))
+        }
         Ok(resp) => Ok(resp),
         Err(e) => {
+            metrics::upstream_error(metrics::CRATES_IO);
             error!("Encountered error... {e}");
             Err(DownloadCrateError::Unexpected(e))
         }
@@ -0,0 +1,22 @@
+use flume::Sender;
+use tracing::warn;
+
+use crate::normalized_name::NormalizedName;
+
+/// Message for the task that keeps the git index in sync with the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitIndexMsg {
+    /// The index entries of the crate changed and have to be written to the
+    /// git index again.
+    Update(NormalizedName),
+}
+
+/// Tell the git index task that the index entries of `name` changed.
+/// Does nothing if the git index is disabled (`sender` is `None`).
+pub fn notify_git_index(sender: Option<&Sender<GitIndexMsg>>, name: &NormalizedName) {
+    if let Some(sender) = sender
+        && let Err(e) = sender.send(GitIndexMsg::Update(name.clone()))
+    {
+        warn!("Failed to notify git index about {name}: {e}");
+    }
+}
@@ -285,17 +285,23 @@ impl<'de> Deserialize<'de> for DependencyKind {
     }
 }
 
+impl DependencyKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+            DependencyKind::Other(s) => s,
+        }
+    }
+}
+
 impl Serialize for DependencyKind {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        match self {
-            DependencyKind::Normal => serializer.serialize_str("normal"),
-            DependencyKind::Build => serializer.serialize_str("build"),
-            DependencyKind::Dev => serializer.serialize_str("dev"),
-            DependencyKind::Other(s) => serializer.serialize_str(s),
-        }
+        serializer.serialize_str(self.as_str())
     }
 }
 
@@ -364,6 +370,16 @@ pub fn metadata_path(index_path: &Path, name: &str) -> PathBuf {
     }
 }
 
+/// Path of the index file of a crate relative to the index root, e.g. `se/rd/serde`.
+/// Always separated by `/`, as used in index URLs and git trees.
+pub fn index_sub_path(name: &str) -> String {
+    metadata_path(Path::new(""), name)
+        .iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -450,6 +466,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn index_sub_path_follows_cargo_layout() {
+        assert_eq!("1/a", index_sub_path("A"));
+        assert_eq!("2/ab", index_sub_path("ab"));
+        assert_eq!("3/a/abc", index_sub_path("abc"));
+        assert_eq!("se/rd/serde", index_sub_path("serde"));
+    }
+
     #[test]
     fn metadata_path_four_or_more_letters() {
         let name = "foo_bAr";
@@ -2,13 +2,20 @@ pub mod crate_data;
 pub mod crate_overview;
 pub mod cratesio_downloader;
 pub mod cratesio_prefetch_msg;
+pub mod git_index_msg;
 pub mod index_metadata;
+pub mod license;
+pub mod metrics;
 pub mod normalized_name;
 pub mod original_name;
 pub mod prefetch;
+pub mod proxy_policy;
 pub mod publish_metadata;
+pub mod publish_policy;
+pub mod publish_validator;
 pub mod search_result;
 pub mod token_cache;
+pub mod token_scope;
 pub mod util;
 pub mod version;
 pub mod webhook;
@@ -0,0 +1,86 @@
+//! Checks of SPDX license expressions against an allowlist.
+
+/// Evaluate an SPDX license expression, e.g. `(MIT OR Apache-2.0) AND
+/// Unicode-3.0`. The legacy `/` separator is read as `OR`. An exception
+/// (`WITH`) is allowed if either the full `license WITH exception` or the
+/// license itself is on the allowlist.
+pub fn license_allowed(expression: &str, allowed: &[String]) -> bool {
+    let spaced = expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " OR ");
+    let tokens = spaced.split_whitespace().collect::<Vec<_>>();
+    let mut parser = LicenseParser {
+        tokens: &tokens,
+        pos: 0,
+        allowed,
+    };
+    parser.or() == Some(true) && parser.pos == tokens.len()
+}
+
+struct LicenseParser<'a> {
+    tokens: &'a [&'a str],
+    pos: usize,
+    allowed: &'a [String],
+}
+
+impl LicenseParser<'_> {
+    fn peek_operator(&self, operator: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case(operator))
+    }
+
+    fn or(&mut self) -> Option<bool> {
+        let mut value = self.and()?;
+        while self.peek_operator("OR") {
+            self.pos += 1;
+            let rhs = self.and()?;
+            value = value || rhs;
+        }
+        Some(value)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut value = self.term()?;
+        while self.peek_operator("AND") {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = value && rhs;
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<bool> {
+        let token = *self.tokens.get(self.pos)?;
+        self.pos += 1;
+        if token == "(" {
+            let value = self.or()?;
+            if self.tokens.get(self.pos) != Some(&")") {
+                return None;
+            }
+            self.pos += 1;
+            return Some(value);
+        }
+        if token == ")"
+            || ["AND", "OR", "WITH"]
+                .iter()
+                .any(|op| token.eq_ignore_ascii_case(op))
+        {
+            return None;
+        }
+
+        if self.peek_operator("WITH") {
+            let exception = *self.tokens.get(self.pos + 1)?;
+            self.pos += 2;
+            return Some(
+                self.is_allowed(&format!("{token} WITH {exception}")) || self.is_allowed(token),
+            );
+        }
+        Some(self.is_allowed(token))
+    }
+
+    fn is_allowed(&self, license: &str) -> bool {
+        self.allowed.iter().any(|a| a.eq_ignore_ascii_case(license))
+    }
+}
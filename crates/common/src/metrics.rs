@@ -0,0 +1,212 @@
+//! Prometheus metrics of Kellnr, exported in the text format at `/metrics`.
+//!
+//! The metrics are process wide, so they can be recorded from every crate
+//! without threading a registry through the application state.
+
+use std::sync::LazyLock;
+
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+
+/// Registry label of crates hosted by Kellnr itself.
+pub const KELLNR: &str = "kellnr";
+/// Registry label of crates proxied from crates.io.
+pub const CRATES_IO: &str = "crates.io";
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<T: Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric names are unique");
+    metric
+}
+
+fn counter(name: &str, help: &str) -> IntCounter {
+    register(IntCounter::new(name, help).expect("valid metric"))
+}
+
+fn counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    register(IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric"))
+}
+
+fn gauge(name: &str, help: &str) -> IntGauge {
+    register(IntGauge::new(name, help).expect("valid metric"))
+}
+
+fn histogram_vec(name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    register(HistogramVec::new(HistogramOpts::new(name, help), labels).expect("valid metric"))
+}
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "kellnr_http_requests_total",
+        "Number of HTTP requests by route and status",
+        &["method", "route", "status"],
+    )
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    histogram_vec(
+        "kellnr_http_request_duration_seconds",
+        "HTTP request latency by route and status",
+        &["method", "route", "status"],
+    )
+});
+
+pub static CRATE_DOWNLOADS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "kellnr_crate_downloads_total",
+        "Number of crate downloads by registry (kellnr, crates.io or upstream name)",
+        &["registry"],
+    )
+});
+
+pub static CRATE_PUBLISHES: LazyLock<IntCounter> = LazyLock::new(|| {
+    counter(
+        "kellnr_crate_publishes_total",
+        "Number of published crate versions",
+    )
+});
+
+pub static DOC_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    gauge(
+        "kellnr_doc_queue_depth",
+        "Number of crate versions waiting for a rustdoc build",
+    )
+});
+
+pub static WEBHOOK_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    gauge(
+        "kellnr_webhook_queue_depth",
+        "Number of webhook deliveries waiting to be sent",
+    )
+});
+
+pub static WEBHOOK_FAILURES: LazyLock<IntCounter> = LazyLock::new(|| {
+    counter(
+        "kellnr_webhook_delivery_failures_total",
+        "Number of failed webhook delivery attempts",
+    )
+});
+
+pub static UPSTREAM_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "kellnr_proxy_upstream_errors_total",
+        "Number of failed requests to proxied registries (crates.io or upstream name)",
+        &["upstream"],
+    )
+});
+
+pub static TOKEN_CACHE_LOOKUPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "kellnr_token_cache_lookups_total",
+        "Number of token cache lookups by result (hit or miss)",
+        &["result"],
+    )
+});
+
+pub static STORAGE_OPERATION_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    histogram_vec(
+        "kellnr_storage_operation_duration_seconds",
+        "Latency of storage operations by backend and operation",
+        &["backend", "operation"],
+    )
+});
+
+pub static DOWNLOAD_PERMITS_IN_USE: LazyLock<IntGauge> = LazyLock::new(|| {
+    gauge(
+        "kellnr_download_permits_in_use",
+        "Number of download concurrency permits currently held",
+    )
+});
+
+/// Count a failed request to a proxied registry.
+pub fn upstream_error(upstream: &str) {
+    UPSTREAM_ERRORS.with_label_values(&[upstream]).inc();
+}
+
+/// Count a crate download from `registry`.
+pub fn crate_download(registry: &str) {
+    CRATE_DOWNLOADS.with_label_values(&[registry]).inc();
+}
+
+/// Count a token cache lookup.
+pub fn token_cache_lookup(hit: bool) {
+    TOKEN_CACHE_LOOKUPS
+        .with_label_values(&[if hit { "hit" } else { "miss" }])
+        .inc();
+}
+
+/// Start timing a storage operation. The duration is recorded when the
+/// returned timer is dropped.
+pub fn storage_timer(backend: &str, operation: &str) -> HistogramTimer {
+    STORAGE_OPERATION_DURATION
+        .with_label_values(&[backend, operation])
+        .start_timer()
+}
+
+/// Increments a gauge while it is alive.
+pub struct GaugeGuard(&'static IntGauge);
+
+impl GaugeGuard {
+    pub fn new(gauge: &'static IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Encode all metrics in the Prometheus text format.
+pub fn gather() -> String {
+    // Register all metrics, so that series without samples yet are exported
+    // with their help text as well.
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_REQUEST_DURATION);
+    LazyLock::force(&CRATE_DOWNLOADS);
+    LazyLock::force(&CRATE_PUBLISHES);
+    LazyLock::force(&DOC_QUEUE_DEPTH);
+    LazyLock::force(&WEBHOOK_QUEUE_DEPTH);
+    LazyLock::force(&WEBHOOK_FAILURES);
+    LazyLock::force(&UPSTREAM_ERRORS);
+    LazyLock::force(&TOKEN_CACHE_LOOKUPS);
+    LazyLock::force(&STORAGE_OPERATION_DURATION);
+    LazyLock::force(&DOWNLOAD_PERMITS_IN_USE);
+
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .expect("metrics can be encoded");
+    String::from_utf8(buffer).expect("metrics are valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gather_exports_recorded_metrics() {
+        crate_download(KELLNR);
+        upstream_error(CRATES_IO);
+        token_cache_lookup(true);
+        {
+            let _guard = GaugeGuard::new(&DOWNLOAD_PERMITS_IN_USE);
+            assert_eq!(1, DOWNLOAD_PERMITS_IN_USE.get());
+        }
+        assert_eq!(0, DOWNLOAD_PERMITS_IN_USE.get());
+
+        let text = gather();
+        assert!(text.contains("kellnr_crate_downloads_total{registry=\"kellnr\"}"));
+        assert!(text.contains("kellnr_proxy_upstream_errors_total{upstream=\"crates.io\"}"));
+        assert!(text.contains("kellnr_token_cache_lookups_total{result=\"hit\"}"));
+        assert!(text.contains("# TYPE kellnr_doc_queue_depth gauge"));
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use semver::VersionReq;
+
+use crate::license::license_allowed;
+use crate::original_name::OriginalName;
+
+/// Policy that decides which crates.io crate versions the proxy serves.
+///
+/// A version is blocked if it matches a deny rule, if its license is not on
+/// the allowlist (when one is configured), or if it was published less than
+/// `min_age` ago.
+#[derive(Debug, Clone, Default)]
+pub struct ProxyPolicy {
+    deny: Vec<DenyRule>,
+    allowed_licenses: Vec<String>,
+    min_age: Option<Duration>,
+}
+
+/// A denied crate, optionally restricted to a version range.
+#[derive(Debug, Clone)]
+struct DenyRule {
+    name: String,
+    versions: Option<VersionReq>,
+}
+
+/// Reason why a crate version is blocked by the [`ProxyPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Blocked {
+    Denied,
+    License(Option<String>),
+    TooNew(DateTime<Utc>),
+}
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Blocked::Denied => write!(f, "denied by proxy policy"),
+            Blocked::License(Some(license)) => write!(f, "license \"{license}\" is not allowed"),
+            Blocked::License(None) => write!(f, "license is unknown"),
+            Blocked::TooNew(published) => write!(f, "published too recently ({published})"),
+        }
+    }
+}
+
+impl ProxyPolicy {
+    /// Create a policy from its settings. Deny rules are crate names or
+    /// `name@<version requirement>`, e.g. `openssl@<0.10.55`.
+    pub fn new(
+        deny: &[String],
+        allowed_licenses: &[String],
+        min_age_days: u64,
+    ) -> Result<Self, String> {
+        let deny = deny
+            .iter()
+            .map(|rule| DenyRule::parse(rule))
+            .collect::<Result<Vec<_>, _>>()?;
+        let min_age = match min_age_days {
+            0 => None,
+            days => Some(
+                i64::try_from(days)
+                    .ok()
+                    .and_then(Duration::try_days)
+                    .ok_or_else(|| format!("Invalid proxy minimum age of {days} days"))?,
+            ),
+        };
+
+        Ok(Self {
+            deny,
+            allowed_licenses: allowed_licenses.to_vec(),
+            min_age,
+        })
+    }
+
+    /// `true` if the policy blocks nothing.
+    pub fn is_empty(&self) -> bool {
+        self.deny.is_empty() && self.allowed_licenses.is_empty() && self.min_age.is_none()
+    }
+
+    /// `true` if the license of a version is needed to check it.
+    pub fn checks_license(&self) -> bool {
+        !self.allowed_licenses.is_empty()
+    }
+
+    /// `true` if the publish time of a version is needed to check it.
+    pub fn checks_age(&self) -> bool {
+        self.min_age.is_some()
+    }
+
+    /// Check a crate version against the deny rules and the minimum age.
+    /// Versions without a known publish time pass the age check.
+    pub fn check_version(
+        &self,
+        name: &str,
+        version: &str,
+        published: Option<DateTime<Utc>>,
+    ) -> Result<(), Blocked> {
+        if self.deny.iter().any(|rule| rule.matches(name, version)) {
+            return Err(Blocked::Denied);
+        }
+        if let (Some(min_age), Some(published)) = (self.min_age, published)
+            && Utc::now() - published < min_age
+        {
+            return Err(Blocked::TooNew(published));
+        }
+        Ok(())
+    }
+
+    /// Check the SPDX license expression of a crate version against the
+    /// allowlist. Versions without a license are blocked if an allowlist is
+    /// configured.
+    pub fn check_license(&self, license: Option<&str>) -> Result<(), Blocked> {
+        if !self.checks_license() {
+            return Ok(());
+        }
+        match license {
+            Some(license) if license_allowed(license, &self.allowed_licenses) => Ok(()),
+            license => Err(Blocked::License(license.map(ToString::to_string))),
+        }
+    }
+}
+
+impl DenyRule {
+    fn parse(rule: &str) -> Result<Self, String> {
+        let (name, versions) = match rule.split_once('@') {
+            Some((name, versions)) => {
+                let versions = VersionReq::parse(versions.trim()).map_err(|e| {
+                    format!("Invalid version requirement in proxy deny rule '{rule}': {e}")
+                })?;
+                (name.trim(), Some(versions))
+            }
+            None => (rule.trim(), None),
+        };
+        OriginalName::try_from(name)
+            .map_err(|e| format!("Invalid crate name in proxy deny rule '{rule}': {e}"))?;
+
+        Ok(Self {
+            name: name.to_lowercase(),
+            versions,
+        })
+    }
+
+    fn matches(&self, name: &str, version: &str) -> bool {
+        if self.name != name.to_lowercase() {
+            return false;
+        }
+        match &self.versions {
+            None => true,
+            // Versions that are not valid semver cannot be matched against a
+            // range, so they are blocked to be on the safe side.
+            Some(req) => semver::Version::parse(version)
+                .ok()
+                .is_none_or(|v| req.matches(&v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(deny: &[&str], licenses: &[&str], min_age_days: u64) -> ProxyPolicy {
+        let to_vec = |v: &[&str]| v.iter().map(ToString::to_string).collect::<Vec<_>>();
+        ProxyPolicy::new(&to_vec(deny), &to_vec(licenses), min_age_days).unwrap()
+    }
+
+    #[test]
+    fn empty_policy_allows_everything() {
+        let p = policy(&[], &[], 0);
+        assert!(p.is_empty());
+        assert_eq!(Ok(()), p.check_version("serde", "1.0.0", Some(Utc::now())));
+        assert_eq!(Ok(()), p.check_license(None));
+    }
+
+    #[test]
+    fn deny_whole_crate() {
+        let p = policy(&["Evil-Crate"], &[], 0);
+        assert_eq!(
+            Err(Blocked::Denied),
+            p.check_version("evil-crate", "1.0.0", None)
+        );
+        assert_eq!(Ok(()), p.check_version("good-crate", "1.0.0", None));
+    }
+
+    #[test]
+    fn deny_version_range() {
+        let p = policy(&["openssl@>=0.10.0, <0.10.55"], &[], 0);
+        assert_eq!(
+            Err(Blocked::Denied),
+            p.check_version("openssl", "0.10.54", None)
+        );
+        assert_eq!(Ok(()), p.check_version("openssl", "0.10.55", None));
+        assert_eq!(Ok(()), p.check_version("openssl", "0.9.0", None));
+    }
+
+    #[test]
+    fn invalid_deny_rules_are_rejected() {
+        let to_vec = |v: &str| vec![v.to_string()];
+        assert!(ProxyPolicy::new(&to_vec("openssl@not-a-range"), &[], 0).is_err());
+        assert!(ProxyPolicy::new(&to_vec("<0.10"), &[], 0).is_err());
+        assert!(ProxyPolicy::new(&to_vec(""), &[], 0).is_err());
+    }
+
+    #[test]
+    fn too_new_versions_are_blocked() {
+        let p = policy(&[], &[], 7);
+        let fresh = Utc::now() - Duration::days(2);
+        let old = Utc::now() - Duration::days(8);
+        assert_eq!(
+            Err(Blocked::TooNew(fresh)),
+            p.check_version("serde", "1.0.0", Some(fresh))
+        );
+        assert_eq!(Ok(()), p.check_version("serde", "1.0.0", Some(old)));
+        assert_eq!(Ok(()), p.check_version("serde", "1.0.0", None));
+    }
+
+    #[test]
+    fn license_allowlist() {
+        let p = policy(&[], &["MIT", "Apache-2.0", "Unicode-3.0"], 0);
+        for allowed in [
+            "MIT",
+            "mit",
+            "MIT OR GPL-3.0",
+            "MIT/Apache-2.0",
+            "(MIT OR Apache-2.0) AND Unicode-3.0",
+            "Apache-2.0 WITH LLVM-exception",
+        ] {
+            assert_eq!(Ok(()), p.check_license(Some(allowed)), "{allowed}");
+        }
+        for blocked in [
+            "GPL-3.0",
+            "MIT AND GPL-3.0",
+            "(MIT OR Apache-2.0) AND GPL-3.0",
+            "MIT OR",
+            "(MIT",
+        ] {
+            assert_eq!(
+                Err(Blocked::License(Some(blocked.to_string()))),
+                p.check_license(Some(blocked)),
+                "{blocked}"
+            );
+        }
+        assert_eq!(Err(Blocked::License(None)), p.check_license(None));
+    }
+}
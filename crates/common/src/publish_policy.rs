@@ -0,0 +1,349 @@
+use std::fmt;
+
+use regex::Regex;
+
+use crate::license::license_allowed;
+use crate::publish_metadata::PublishMetadata;
+
+/// Organisation rules a crate has to satisfy to be published.
+///
+/// The rules on the metadata are checked with [`PublishPolicy::check_metadata`],
+/// the rules on the content of the crate tarball with
+/// [`PublishPolicy::check_contents`]. A policy with default values allows
+/// everything.
+#[derive(Debug, Clone, Default)]
+pub struct PublishPolicy {
+    /// SPDX licenses crates must be available under, empty allows all
+    pub allowed_licenses: Vec<String>,
+    /// Reject dependencies with a `git` source
+    pub deny_git_dependencies: bool,
+    /// Reject dependencies with a `path`
+    pub deny_path_dependencies: bool,
+    /// Pattern the repository URL has to match
+    pub repository_pattern: Option<RepositoryPattern>,
+    /// Prefixes of which crate names have to start with one, empty allows all
+    pub name_prefixes: Vec<String>,
+    /// Max. number of files in the tarball (0 = unlimited)
+    pub max_files: u64,
+    /// Max. unpacked size of the tarball in bytes (0 = unlimited)
+    pub max_unpacked_size: u64,
+}
+
+/// Regular expression that has to match the whole repository URL.
+#[derive(Debug, Clone)]
+pub struct RepositoryPattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl RepositoryPattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|e| format!("Invalid repository pattern '{pattern}': {e}"))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    pub fn is_match(&self, repository: &str) -> bool {
+        self.regex.is_match(repository)
+    }
+}
+
+/// Content of a crate tarball that is checked by the [`PublishPolicy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateContents {
+    pub files: u64,
+    pub unpacked_size: u64,
+    /// Names of the dependencies with a `git` source
+    pub git_dependencies: Vec<String>,
+    /// Names of the dependencies with a `path`
+    pub path_dependencies: Vec<String>,
+}
+
+/// Reason why a crate is rejected by the [`PublishPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejected {
+    License(Option<String>, Vec<String>),
+    Repository(Option<String>, String),
+    NamePrefix(Vec<String>),
+    GitDependency(String),
+    PathDependency(String),
+    TooManyFiles(u64),
+    TooLarge(u64),
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejected::License(Some(license), allowed) => write!(
+                f,
+                "license \"{license}\" is not allowed, allowed licenses: {}",
+                allowed.join(", ")
+            ),
+            Rejected::License(None, allowed) => write!(
+                f,
+                "a license is required, allowed licenses: {}",
+                allowed.join(", ")
+            ),
+            Rejected::Repository(Some(repository), pattern) => write!(
+                f,
+                "repository \"{repository}\" does not match the required pattern \"{pattern}\""
+            ),
+            Rejected::Repository(None, pattern) => write!(
+                f,
+                "a repository matching the pattern \"{pattern}\" is required"
+            ),
+            Rejected::NamePrefix(prefixes) => write!(
+                f,
+                "crate name has to start with one of: {}",
+                prefixes.join(", ")
+            ),
+            Rejected::GitDependency(name) => {
+                write!(f, "git dependencies are not allowed, found \"{name}\"")
+            }
+            Rejected::PathDependency(name) => {
+                write!(f, "path dependencies are not allowed, found \"{name}\"")
+            }
+            Rejected::TooManyFiles(max) => write!(f, "crate contains more than {max} files"),
+            Rejected::TooLarge(max) => {
+                write!(f, "unpacked crate is larger than {max} bytes")
+            }
+        }
+    }
+}
+
+impl PublishPolicy {
+    /// `true` if the tarball has to be read to check the crate.
+    pub fn checks_contents(&self) -> bool {
+        self.deny_git_dependencies
+            || self.deny_path_dependencies
+            || self.max_files > 0
+            || self.max_unpacked_size > 0
+    }
+
+    /// Check the license, repository and name of a crate.
+    pub fn check_metadata(&self, metadata: &PublishMetadata) -> Result<(), Rejected> {
+        if !self.allowed_licenses.is_empty() {
+            match metadata.license.as_deref() {
+                Some(license) if license_allowed(license, &self.allowed_licenses) => {}
+                license => {
+                    return Err(Rejected::License(
+                        license.map(ToString::to_string),
+                        self.allowed_licenses.clone(),
+                    ));
+                }
+            }
+        }
+
+        if let Some(pattern) = &self.repository_pattern {
+            match metadata.repository.as_deref() {
+                Some(repository) if pattern.is_match(repository) => {}
+                repository => {
+                    return Err(Rejected::Repository(
+                        repository.map(ToString::to_string),
+                        pattern.pattern.clone(),
+                    ));
+                }
+            }
+        }
+
+        let name = metadata.name.to_lowercase();
+        if !self.name_prefixes.is_empty()
+            && !self
+                .name_prefixes
+                .iter()
+                .any(|prefix| name.starts_with(&prefix.to_lowercase()))
+        {
+            return Err(Rejected::NamePrefix(self.name_prefixes.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Check the dependencies, number of files and size of a crate tarball.
+    pub fn check_contents(&self, contents: &CrateContents) -> Result<(), Rejected> {
+        if self.deny_git_dependencies
+            && let Some(name) = contents.git_dependencies.first()
+        {
+            return Err(Rejected::GitDependency(name.clone()));
+        }
+        if self.deny_path_dependencies
+            && let Some(name) = contents.path_dependencies.first()
+        {
+            return Err(Rejected::PathDependency(name.clone()));
+        }
+        if self.max_files > 0 && contents.files > self.max_files {
+            return Err(Rejected::TooManyFiles(self.max_files));
+        }
+        if self.max_unpacked_size > 0 && contents.unpacked_size > self.max_unpacked_size {
+            return Err(Rejected::TooLarge(self.max_unpacked_size));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(name: &str, license: Option<&str>, repository: Option<&str>) -> PublishMetadata {
+        PublishMetadata {
+            license: license.map(ToString::to_string),
+            repository: repository.map(ToString::to_string),
+            ..PublishMetadata::minimal(name, "1.0.0")
+        }
+    }
+
+    #[test]
+    fn default_policy_allows_everything() {
+        let policy = PublishPolicy::default();
+        let contents = CrateContents {
+            files: 10_000,
+            unpacked_size: u64::MAX,
+            git_dependencies: vec!["foo".to_string()],
+            path_dependencies: vec!["bar".to_string()],
+        };
+
+        assert!(!policy.checks_contents());
+        assert_eq!(Ok(()), policy.check_metadata(&metadata("foo", None, None)));
+        assert_eq!(Ok(()), policy.check_contents(&contents));
+    }
+
+    #[test]
+    fn license_has_to_be_allowed() {
+        let policy = PublishPolicy {
+            allowed_licenses: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+            ..PublishPolicy::default()
+        };
+
+        assert_eq!(
+            Ok(()),
+            policy.check_metadata(&metadata("foo", Some("MIT OR GPL-3.0"), None))
+        );
+        let rejected = policy
+            .check_metadata(&metadata("foo", Some("GPL-3.0"), None))
+            .unwrap_err();
+        assert_eq!(
+            "license \"GPL-3.0\" is not allowed, allowed licenses: MIT, Apache-2.0",
+            rejected.to_string()
+        );
+        assert!(matches!(
+            policy.check_metadata(&metadata("foo", None, None)),
+            Err(Rejected::License(None, _))
+        ));
+    }
+
+    #[test]
+    fn repository_has_to_match_whole_pattern() {
+        let policy = PublishPolicy {
+            repository_pattern: Some(
+                RepositoryPattern::new(r"https://github\.com/my-org/.+").unwrap(),
+            ),
+            ..PublishPolicy::default()
+        };
+
+        assert_eq!(
+            Ok(()),
+            policy.check_metadata(&metadata(
+                "foo",
+                None,
+                Some("https://github.com/my-org/foo")
+            ))
+        );
+        let rejected = policy
+            .check_metadata(&metadata(
+                "foo",
+                None,
+                Some("https://evil.example.com/?https://github.com/my-org/foo"),
+            ))
+            .unwrap_err();
+        assert_eq!(
+            r#"repository "https://evil.example.com/?https://github.com/my-org/foo" does not match the required pattern "https://github\.com/my-org/.+""#,
+            rejected.to_string()
+        );
+        assert!(matches!(
+            policy.check_metadata(&metadata("foo", None, None)),
+            Err(Rejected::Repository(None, _))
+        ));
+    }
+
+    #[test]
+    fn invalid_repository_pattern_is_an_error() {
+        assert!(RepositoryPattern::new("(unclosed").is_err());
+    }
+
+    #[test]
+    fn name_has_to_start_with_prefix() {
+        let policy = PublishPolicy {
+            name_prefixes: vec!["acme-".to_string(), "Acme_".to_string()],
+            ..PublishPolicy::default()
+        };
+
+        assert_eq!(
+            Ok(()),
+            policy.check_metadata(&metadata("acme-foo", None, None))
+        );
+        assert_eq!(
+            Ok(()),
+            policy.check_metadata(&metadata("ACME_foo", None, None))
+        );
+        assert_eq!(
+            Err(Rejected::NamePrefix(policy.name_prefixes.clone())),
+            policy.check_metadata(&metadata("foo", None, None))
+        );
+    }
+
+    #[test]
+    fn git_and_path_dependencies_are_denied() {
+        let contents = CrateContents {
+            git_dependencies: vec!["from-git".to_string()],
+            path_dependencies: vec!["from-path".to_string()],
+            ..CrateContents::default()
+        };
+        let git = PublishPolicy {
+            deny_git_dependencies: true,
+            ..PublishPolicy::default()
+        };
+        let path = PublishPolicy {
+            deny_path_dependencies: true,
+            ..PublishPolicy::default()
+        };
+
+        assert!(git.checks_contents());
+        assert_eq!(
+            Err(Rejected::GitDependency("from-git".to_string())),
+            git.check_contents(&contents)
+        );
+        assert_eq!(
+            Err(Rejected::PathDependency("from-path".to_string())),
+            path.check_contents(&contents)
+        );
+        assert_eq!(Ok(()), git.check_contents(&CrateContents::default()));
+    }
+
+    #[test]
+    fn files_and_size_are_limited() {
+        let policy = PublishPolicy {
+            max_files: 2,
+            max_unpacked_size: 100,
+            ..PublishPolicy::default()
+        };
+        let contents = |files, unpacked_size| CrateContents {
+            files,
+            unpacked_size,
+            ..CrateContents::default()
+        };
+
+        assert_eq!(Ok(()), policy.check_contents(&contents(2, 100)));
+        assert_eq!(
+            Err(Rejected::TooManyFiles(2)),
+            policy.check_contents(&contents(3, 100))
+        );
+        assert_eq!(
+            Err(Rejected::TooLarge(100)),
+            policy.check_contents(&contents(2, 101))
+        );
+    }
+}
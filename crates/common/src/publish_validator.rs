@@ -0,0 +1,20 @@
+//! Checks that run on a published crate before it is stored.
+
+use async_trait::async_trait;
+
+use crate::publish_metadata::PublishMetadata;
+
+/// A crate that is about to be published.
+pub struct PublishedCrate<'a> {
+    pub metadata: &'a PublishMetadata,
+    pub cratedata: &'a [u8],
+    /// Name of the publishing user
+    pub user: &'a str,
+}
+
+/// A check that runs before a published crate is stored.
+#[async_trait]
+pub trait PublishValidator: Send + Sync {
+    /// Returns the reason if the crate is rejected.
+    async fn validate(&self, krate: &PublishedCrate<'_>) -> Result<(), String>;
+}
@@ -2,11 +2,16 @@ use std::time::Duration;
 
 use moka::future::Cache;
 
+use crate::metrics;
+use crate::token_scope::TokenScopes;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CachedTokenData {
     pub user: String,
+    pub token_name: String,
     pub is_admin: bool,
     pub is_read_only: bool,
+    pub scopes: TokenScopes,
 }
 
 pub struct TokenCacheManager {
@@ -36,11 +41,19 @@ impl TokenCacheManager {
         Self { cache }
     }
 
+    /// Returns the cached data for `token`. A token that expired while it was
+    /// cached is evicted and reported as a cache miss.
     pub async fn get(&self, token: &str) -> Option<CachedTokenData> {
-        match &self.cache {
-            Some(cache) => cache.get(token).await,
-            None => None,
-        }
+        let cache = self.cache.as_ref()?;
+        let data = match cache.get(token).await {
+            Some(data) if data.scopes.is_expired() => {
+                cache.invalidate(token).await;
+                None
+            }
+            data => data,
+        };
+        metrics::token_cache_lookup(data.is_some());
+        data
     }
 
     pub async fn insert(&self, token: String, data: CachedTokenData) {
@@ -78,8 +91,10 @@ mod tests {
 
         let data = CachedTokenData {
             user: "test_user".to_string(),
+            token_name: "test_token".to_string(),
             is_admin: false,
             is_read_only: false,
+            scopes: TokenScopes::default(),
         };
 
         cache.insert("token123".to_string(), data).await;
@@ -93,8 +108,10 @@ mod tests {
 
         let data = CachedTokenData {
             user: "test_user".to_string(),
+            token_name: "test_token".to_string(),
             is_admin: true,
             is_read_only: false,
+            scopes: TokenScopes::default(),
         };
 
         cache.insert("token123".to_string(), data.clone()).await;
@@ -119,8 +136,10 @@ mod tests {
 
         let data = CachedTokenData {
             user: "test_user".to_string(),
+            token_name: "test_token".to_string(),
             is_admin: false,
             is_read_only: true,
+            scopes: TokenScopes::default(),
         };
 
         cache.insert("token1".to_string(), data.clone()).await;
@@ -134,4 +153,23 @@ mod tests {
         // Note: moka's invalidate_all is async internally and may not be immediately visible
         // In production use, entries will be invalidated lazily
     }
+
+    #[tokio::test]
+    async fn test_expired_token_is_a_cache_miss() {
+        let cache = TokenCacheManager::new(true, 60, 100);
+
+        let data = CachedTokenData {
+            user: "test_user".to_string(),
+            token_name: "test_token".to_string(),
+            is_admin: false,
+            is_read_only: false,
+            scopes: TokenScopes {
+                expires_at: Some(chrono::Utc::now() - chrono::Duration::seconds(1)),
+                ..TokenScopes::default()
+            },
+        };
+
+        cache.insert("expired".to_string(), data).await;
+        assert!(cache.get("expired").await.is_none());
+    }
 }
@@ -0,0 +1,269 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Registry operation a scoped API token can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenOperation {
+    /// Publish new crates and new versions of existing crates.
+    Publish,
+    /// Yank and unyank crate versions.
+    Yank,
+    /// Change owners, crate users and crate groups.
+    ChangeOwners,
+    /// Download crates with restricted downloads.
+    Download,
+}
+
+impl From<TokenOperation> for &str {
+    fn from(value: TokenOperation) -> Self {
+        match value {
+            TokenOperation::Publish => "publish",
+            TokenOperation::Yank => "yank",
+            TokenOperation::ChangeOwners => "change-owners",
+            TokenOperation::Download => "download",
+        }
+    }
+}
+
+impl TryFrom<&str> for TokenOperation {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "publish" => Ok(Self::Publish),
+            "yank" => Ok(Self::Yank),
+            "change-owners" => Ok(Self::ChangeOwners),
+            "download" => Ok(Self::Download),
+            a => Err(format!("'{a}' is not a valid token operation")),
+        }
+    }
+}
+
+/// Restrictions attached to an API token.
+///
+/// The default value places no restrictions on the token, which then has the
+/// full rights of its owner. This is what all tokens created before scopes
+/// existed get.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TokenScopes {
+    /// Operations the token may perform. Empty means all operations.
+    #[serde(default)]
+    pub operations: Vec<TokenOperation>,
+    /// Crate name patterns the token is limited to. A pattern is either an
+    /// exact crate name or a name prefix followed by `*`. Empty means all crates.
+    #[serde(default)]
+    pub crates: Vec<String>,
+    /// Point in time after which the token is rejected. `None` never expires.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl TokenScopes {
+    /// `true` if the token is limited to some operations or crates.
+    /// Restricted tokens never carry the admin rights of their owner.
+    pub fn is_restricted(&self) -> bool {
+        !self.operations.is_empty() || !self.crates.is_empty()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|e| e <= Utc::now())
+    }
+
+    /// Check if the token may perform `operation` on the crate `crate_name`.
+    pub fn allows(&self, operation: TokenOperation, crate_name: &str) -> bool {
+        self.allows_operation(operation) && self.allows_crate(crate_name)
+    }
+
+    pub fn allows_operation(&self, operation: TokenOperation) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+
+    /// Crate names are compared case-insensitively and without distinguishing
+    /// `-` from `_`, the same way cargo treats them.
+    pub fn allows_crate(&self, crate_name: &str) -> bool {
+        if self.crates.is_empty() {
+            return true;
+        }
+
+        let name = canonical_crate_name(crate_name);
+        self.crates.iter().any(|pattern| {
+            let pattern = canonical_crate_name(pattern);
+            match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            }
+        })
+    }
+
+    /// Check that all crate patterns are well-formed.
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in &self.crates {
+            if pattern.is_empty() {
+                return Err("Crate pattern must not be empty".to_string());
+            }
+            let name = pattern.strip_suffix('*').unwrap_or(pattern);
+            if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Invalid crate pattern '{pattern}'. Only alphanumeric characters, '-', '_' and a trailing '*' are allowed"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize the operations for storage in a single text column.
+    /// Returns `None` if the token is not limited to any operation.
+    pub fn operations_to_db(&self) -> Option<String> {
+        (!self.operations.is_empty()).then(|| {
+            self.operations
+                .iter()
+                .map(|o| Into::<&str>::into(*o))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    /// Serialize the crate patterns for storage in a single text column.
+    /// Returns `None` if the token is not limited to any crate.
+    pub fn crates_to_db(&self) -> Option<String> {
+        (!self.crates.is_empty()).then(|| self.crates.join(","))
+    }
+
+    /// Parse the operations from the format produced by [`Self::operations_to_db`].
+    /// Unknown operations are an error, as dropping them could turn a
+    /// restricted token into an unrestricted one.
+    pub fn operations_from_db(value: Option<&str>) -> Result<Vec<TokenOperation>, String> {
+        split_db_list(value).map(TokenOperation::try_from).collect()
+    }
+
+    /// Parse the crate patterns from the format produced by [`Self::crates_to_db`].
+    pub fn crates_from_db(value: Option<&str>) -> Vec<String> {
+        split_db_list(value).map(ToString::to_string).collect()
+    }
+}
+
+fn split_db_list(value: Option<&str>) -> impl Iterator<Item = &str> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn canonical_crate_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn default_scopes_allow_everything() {
+        let scopes = TokenScopes::default();
+        assert!(!scopes.is_restricted());
+        assert!(!scopes.is_expired());
+        assert!(scopes.allows(TokenOperation::Publish, "foo"));
+        assert!(scopes.allows(TokenOperation::ChangeOwners, "bar"));
+    }
+
+    #[test]
+    fn operations_restrict_access() {
+        let scopes = TokenScopes {
+            operations: vec![TokenOperation::Publish, TokenOperation::Yank],
+            ..TokenScopes::default()
+        };
+        assert!(scopes.is_restricted());
+        assert!(scopes.allows(TokenOperation::Publish, "foo"));
+        assert!(scopes.allows(TokenOperation::Yank, "foo"));
+        assert!(!scopes.allows(TokenOperation::ChangeOwners, "foo"));
+        assert!(!scopes.allows(TokenOperation::Download, "foo"));
+    }
+
+    #[test]
+    fn crate_patterns_restrict_access() {
+        let scopes = TokenScopes {
+            crates: vec!["exact".to_string(), "my-org-*".to_string()],
+            ..TokenScopes::default()
+        };
+        assert!(scopes.allows_crate("exact"));
+        assert!(scopes.allows_crate("Exact"));
+        assert!(!scopes.allows_crate("exact2"));
+        assert!(scopes.allows_crate("my-org-core"));
+        assert!(scopes.allows_crate("my_org_core"));
+        assert!(!scopes.allows_crate("other"));
+    }
+
+    #[test]
+    fn wildcard_only_pattern_matches_all() {
+        let scopes = TokenScopes {
+            crates: vec!["*".to_string()],
+            ..TokenScopes::default()
+        };
+        assert!(scopes.allows_crate("anything"));
+        assert!(scopes.validate().is_ok());
+    }
+
+    #[test]
+    fn expiry() {
+        let expired = TokenScopes {
+            expires_at: Some(Utc::now() - Duration::minutes(1)),
+            ..TokenScopes::default()
+        };
+        assert!(expired.is_expired());
+
+        let valid = TokenScopes {
+            expires_at: Some(Utc::now() + Duration::days(1)),
+            ..TokenScopes::default()
+        };
+        assert!(!valid.is_expired());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_patterns() {
+        for pattern in ["", "foo*bar", "foo/bar", "foo bar"] {
+            let scopes = TokenScopes {
+                crates: vec![pattern.to_string()],
+                ..TokenScopes::default()
+            };
+            assert!(scopes.validate().is_err(), "{pattern} should be invalid");
+        }
+    }
+
+    #[test]
+    fn db_round_trip() {
+        let scopes = TokenScopes {
+            operations: vec![TokenOperation::Publish, TokenOperation::ChangeOwners],
+            crates: vec!["foo".to_string(), "bar-*".to_string()],
+            expires_at: None,
+        };
+
+        let operations = scopes.operations_to_db();
+        let crates = scopes.crates_to_db();
+        assert_eq!(Some("publish,change-owners".to_string()), operations);
+        assert_eq!(Some("foo,bar-*".to_string()), crates);
+
+        assert_eq!(
+            scopes.operations,
+            TokenScopes::operations_from_db(operations.as_deref()).unwrap()
+        );
+        assert_eq!(
+            scopes.crates,
+            TokenScopes::crates_from_db(crates.as_deref())
+        );
+        assert!(TokenScopes::default().operations_to_db().is_none());
+        assert!(TokenScopes::operations_from_db(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_operation_in_db_is_an_error() {
+        assert!(TokenScopes::operations_from_db(Some("publish,delete-everything")).is_err());
+        assert!(TokenScopes::operations_from_db(Some("delete-everything")).is_err());
+    }
+}
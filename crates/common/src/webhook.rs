@@ -2,35 +2,317 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct Webhook {
     pub id: Option<String>,
-    pub event: WebhookEvent,
+    pub events: Vec<WebhookEvent>,
     pub callback_url: String,
     pub name: Option<String>,
+    /// Glob the crate name of an event has to match, e.g. `acme-*`
+    pub crate_filter: Option<String>,
+    /// Group the crate of an event has to belong to
+    pub group_filter: Option<String>,
+    /// Format of the delivered payload
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// JSON template of the payload for [`WebhookFormat::Template`]
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Start of the current series of failed deliveries
+    #[serde(default)]
+    pub failing_since: Option<DateTime<Utc>>,
+    /// Time the webhook was disabled after failing for too long. Disabled
+    /// webhooks get no events until they are enabled again.
+    #[serde(default)]
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+impl Webhook {
+    /// Whether `event` about the crate `crate_name`, which belongs to
+    /// `crate_groups`, is delivered to this webhook. Events without a crate,
+    /// e.g. a new user, are not delivered if a crate or group filter is set.
+    pub fn accepts(
+        &self,
+        event: WebhookEvent,
+        crate_name: Option<&str>,
+        crate_groups: &[String],
+    ) -> bool {
+        if !self.events.contains(&event) {
+            return false;
+        }
+        if let Some(filter) = &self.crate_filter
+            && !crate_name.is_some_and(|name| glob_matches(filter, name))
+        {
+            return false;
+        }
+        if let Some(group) = &self.group_filter
+            && (crate_name.is_none() || !crate_groups.contains(group))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Case-insensitive match of `name` against `pattern`, where `*` matches any
+/// number of characters and `?` a single character.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` in the pattern and the name position it
+    // was matched at, to backtrack if the rest does not match.
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    p = bp;
+                    n = bn + 1;
+                    backtrack = Some((bp, bn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Secrets the deliveries of a webhook are signed with.
+///
+/// After a rotation, deliveries are signed with the current and the previous
+/// secret until the previous one expires, so receivers can switch to the new
+/// secret without missing deliveries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebhookSecrets {
+    pub secret: Option<String>,
+    pub previous_secret: Option<String>,
+    pub previous_secret_expires: Option<DateTime<Utc>>,
+}
+
+impl WebhookSecrets {
+    /// Secrets to sign a delivery at `now` with, the current secret first.
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<String> {
+        let previous = self
+            .previous_secret
+            .as_ref()
+            .filter(|_| self.previous_secret_expires.is_some_and(|e| e > now));
+        self.secret.iter().chain(previous).cloned().collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WebhookQueue {
+    /// Id of the message, which stays the same for all attempts
     pub id: String,
+    pub webhook_id: String,
     pub callback_url: String,
     pub payload: serde_json::Value,
     pub last_attempt: Option<DateTime<Utc>>,
     pub next_attempt: DateTime<Utc>,
+    /// Secrets to sign the delivery with, empty for unsigned deliveries
+    #[serde(skip)]
+    pub secrets: Vec<String>,
+    pub format: WebhookFormat,
+    pub template: Option<String>,
+    /// Number of failed attempts to deliver the message
+    pub attempts: u32,
+    /// Start of the current series of failed deliveries to the webhook
+    pub failing_since: Option<DateTime<Utc>>,
 }
 
+/// Message that was given up after too many failed attempts. It stays in the
+/// queue, but is not delivered until it is retried.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
-#[allow(clippy::enum_variant_names)]
+pub struct WebhookDeadLetter {
+    pub id: String,
+    pub webhook_id: String,
+    pub payload: serde_json::Value,
+    /// Number of failed attempts to deliver the message
+    pub attempts: u32,
+    pub last_attempt: Option<DateTime<Utc>>,
+    /// Time the message was given up
+    pub dead_since: DateTime<Utc>,
+}
+
+/// Outcome of an attempt to deliver a webhook message, which is recorded
+/// in the delivery log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewWebhookDelivery {
+    pub webhook_id: String,
+    pub message_id: String,
+    pub payload: serde_json::Value,
+    /// Response status, `None` if no response was received
+    pub status: Option<u16>,
+    /// Start of the response body
+    pub response_body: Option<String>,
+    /// Reason why no response was received
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Attempt to deliver a webhook message from the delivery log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    /// Id of the message, which is sent as `webhook-id` header and is the
+    /// same for all attempts of the message
+    pub message_id: String,
+    pub payload: serde_json::Value,
+    /// Number of the attempt to deliver the message, starting at 1
+    pub attempt: u32,
+    /// Response status, `None` if no response was received
+    pub status: Option<u16>,
+    /// Start of the response body
+    pub response_body: Option<String>,
+    /// Reason why no response was received
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub created: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    /// Whether the receiver accepted the message with a 2xx status.
+    pub fn is_success(&self) -> bool {
+        self.status.is_some_and(is_success_status)
+    }
+}
+
+impl NewWebhookDelivery {
+    /// Whether the receiver accepted the message with a 2xx status.
+    pub fn is_success(&self) -> bool {
+        self.status.is_some_and(is_success_status)
+    }
+}
+
+fn is_success_status(status: u16) -> bool {
+    (200..300).contains(&status)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum WebhookEvent {
+    /// First version of a crate published
     #[serde(rename = "crate_add")]
     CrateAdd,
+    /// New version of an existing crate published
     #[serde(rename = "crate_update")]
     CrateUpdate,
     #[serde(rename = "crate_yank")]
     CrateYank,
     #[serde(rename = "crate_unyank")]
     CrateUnyank,
+    /// Crate with all its versions deleted
+    #[serde(rename = "crate_delete")]
+    CrateDelete,
+    #[serde(rename = "version_delete")]
+    VersionDelete,
+    #[serde(rename = "owner_add")]
+    OwnerAdd,
+    #[serde(rename = "owner_remove")]
+    OwnerRemove,
+    /// Crate user or group added or removed, or download restriction changed
+    #[serde(rename = "acl_change")]
+    AclChange,
+    #[serde(rename = "user_add")]
+    UserAdd,
+    #[serde(rename = "user_delete")]
+    UserDelete,
+    #[serde(rename = "docs_build_success")]
+    DocsBuildSuccess,
+    #[serde(rename = "docs_build_failure")]
+    DocsBuildFailure,
+    #[serde(rename = "toolchain_add")]
+    ToolchainAdd,
+    /// Toolchain of a channel, e.g. `stable`, changed
+    #[serde(rename = "toolchain_channel_change")]
+    ToolchainChannelChange,
+    /// Security advisory affects a hosted or cached crate
+    #[serde(rename = "advisory_add")]
+    AdvisoryAdd,
 }
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 16] = [
+        WebhookEvent::CrateAdd,
+        WebhookEvent::CrateUpdate,
+        WebhookEvent::CrateYank,
+        WebhookEvent::CrateUnyank,
+        WebhookEvent::CrateDelete,
+        WebhookEvent::VersionDelete,
+        WebhookEvent::OwnerAdd,
+        WebhookEvent::OwnerRemove,
+        WebhookEvent::AclChange,
+        WebhookEvent::UserAdd,
+        WebhookEvent::UserDelete,
+        WebhookEvent::DocsBuildSuccess,
+        WebhookEvent::DocsBuildFailure,
+        WebhookEvent::ToolchainAdd,
+        WebhookEvent::ToolchainChannelChange,
+        WebhookEvent::AdvisoryAdd,
+    ];
+}
+
+/// Format of the payload sent to a webhook. Events are always queued in the
+/// native JSON format and converted when they are delivered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// Kellnr's own JSON with the event type, timestamp and event data
+    #[default]
+    Json,
+    /// Message for Slack compatible incoming webhooks, e.g. of Slack,
+    /// Mattermost or the Matrix hookshot bridge
+    Slack,
+    /// `MessageCard` for Microsoft Teams connectors
+    Teams,
+    /// Adaptive Card for Microsoft Teams workflows
+    AdaptiveCard,
+    /// User-supplied JSON template with placeholders for the event fields
+    Template,
+}
+
+impl WebhookFormat {
+    pub const ALL: [WebhookFormat; 5] = [
+        WebhookFormat::Json,
+        WebhookFormat::Slack,
+        WebhookFormat::Teams,
+        WebhookFormat::AdaptiveCard,
+        WebhookFormat::Template,
+    ];
+}
+
+impl From<WebhookFormat> for &str {
+    fn from(value: WebhookFormat) -> Self {
+        match value {
+            WebhookFormat::Json => "json",
+            WebhookFormat::Slack => "slack",
+            WebhookFormat::Teams => "teams",
+            WebhookFormat::AdaptiveCard => "adaptive_card",
+            WebhookFormat::Template => "template",
+        }
+    }
+}
+
+impl TryFrom<&str> for WebhookFormat {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|format| Into::<&str>::into(*format) == value)
+            .ok_or_else(|| format!("'{value}' is not a valid webhook format"))
+    }
+}
+
 impl From<WebhookEvent> for &str {
     fn from(value: WebhookEvent) -> Self {
         match value {
@@ -38,18 +320,121 @@ impl From<WebhookEvent> for &str {
             WebhookEvent::CrateUpdate => "crate_update",
             WebhookEvent::CrateYank => "crate_yank",
             WebhookEvent::CrateUnyank => "crate_unyank",
+            WebhookEvent::CrateDelete => "crate_delete",
+            WebhookEvent::VersionDelete => "version_delete",
+            WebhookEvent::OwnerAdd => "owner_add",
+            WebhookEvent::OwnerRemove => "owner_remove",
+            WebhookEvent::AclChange => "acl_change",
+            WebhookEvent::UserAdd => "user_add",
+            WebhookEvent::UserDelete => "user_delete",
+            WebhookEvent::DocsBuildSuccess => "docs_build_success",
+            WebhookEvent::DocsBuildFailure => "docs_build_failure",
+            WebhookEvent::ToolchainAdd => "toolchain_add",
+            WebhookEvent::ToolchainChannelChange => "toolchain_channel_change",
+            WebhookEvent::AdvisoryAdd => "advisory_add",
         }
     }
 }
 impl TryFrom<&str> for WebhookEvent {
     type Error = String;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "crate_add" => Ok(Self::CrateAdd),
-            "crate_update" => Ok(Self::CrateUpdate),
-            "crate_yank" => Ok(Self::CrateYank),
-            "crate_unyank" => Ok(Self::CrateUnyank),
-            a => Err(format!("'{a}' is not a valid webhook event")),
+        Self::ALL
+            .into_iter()
+            .find(|event| Into::<&str>::into(*event) == value)
+            .ok_or_else(|| format!("'{value}' is not a valid webhook event"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use serde_json::json;
+
+    use super::*;
+
+    fn webhook(crate_filter: Option<&str>, group_filter: Option<&str>) -> Webhook {
+        Webhook {
+            events: vec![WebhookEvent::CrateAdd, WebhookEvent::UserAdd],
+            crate_filter: crate_filter.map(ToString::to_string),
+            group_filter: group_filter.map(ToString::to_string),
+            ..Webhook::default()
         }
     }
+
+    #[test]
+    fn events_roundtrip_as_str() {
+        for event in WebhookEvent::ALL {
+            let name: &str = event.into();
+            assert_eq!(Ok(event), WebhookEvent::try_from(name));
+            assert_eq!(json!(name), json!(event));
+        }
+        assert!(WebhookEvent::try_from("crate_publish").is_err());
+    }
+
+    #[test]
+    fn formats_roundtrip_as_str() {
+        for format in WebhookFormat::ALL {
+            let name: &str = format.into();
+            assert_eq!(Ok(format), WebhookFormat::try_from(name));
+            assert_eq!(json!(name), json!(format));
+        }
+        assert!(WebhookFormat::try_from("discord").is_err());
+    }
+
+    #[test]
+    fn webhook_accepts_subscribed_events() {
+        let webhook = webhook(None, None);
+
+        assert!(webhook.accepts(WebhookEvent::CrateAdd, Some("foo"), &[]));
+        assert!(webhook.accepts(WebhookEvent::UserAdd, None, &[]));
+        assert!(!webhook.accepts(WebhookEvent::CrateYank, Some("foo"), &[]));
+    }
+
+    #[test]
+    fn webhook_filters_crate_name() {
+        let webhook = webhook(Some("acme-*"), None);
+
+        assert!(webhook.accepts(WebhookEvent::CrateAdd, Some("acme-core"), &[]));
+        assert!(webhook.accepts(WebhookEvent::CrateAdd, Some("ACME-Core"), &[]));
+        assert!(!webhook.accepts(WebhookEvent::CrateAdd, Some("other"), &[]));
+        assert!(!webhook.accepts(WebhookEvent::UserAdd, None, &[]));
+    }
+
+    #[test]
+    fn webhook_filters_group() {
+        let webhook = webhook(None, Some("team-a"));
+        let groups = ["team-b".to_string(), "team-a".to_string()];
+
+        assert!(webhook.accepts(WebhookEvent::CrateAdd, Some("foo"), &groups));
+        assert!(!webhook.accepts(WebhookEvent::CrateAdd, Some("foo"), &groups[..1]));
+        assert!(!webhook.accepts(WebhookEvent::UserAdd, None, &groups));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("foo", "foo"));
+        assert!(!glob_matches("foo", "foobar"));
+        assert!(glob_matches("foo*", "foobar"));
+        assert!(glob_matches("*bar", "foobar"));
+        assert!(glob_matches("f*o*r", "foobar"));
+        assert!(glob_matches("*-core-*", "acme-core-macros"));
+        assert!(glob_matches("fo?bar", "foobar"));
+        assert!(!glob_matches("fo?bar", "fobar"));
+        assert!(!glob_matches("*a*b", "ba"));
+    }
+
+    #[test]
+    fn previous_secret_is_active_until_it_expires() {
+        let now = Utc::now();
+        let secrets = WebhookSecrets {
+            secret: Some("new".to_string()),
+            previous_secret: Some("old".to_string()),
+            previous_secret_expires: Some(now + TimeDelta::hours(1)),
+        };
+
+        assert_eq!(vec!["new", "old"], secrets.active(now));
+        assert_eq!(vec!["new"], secrets.active(now + TimeDelta::hours(2)));
+        assert!(WebhookSecrets::default().active(now).is_empty());
+    }
 }
@@ -0,0 +1,36 @@
+//! `SeaORM` Entity for imported security advisories
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "advisory")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(column_type = "Text", unique)]
+    pub advisory_id: String,
+    #[sea_orm(column_type = "Text")]
+    pub crate_name: String,
+    #[sea_orm(column_type = "Text")]
+    pub title: String,
+    #[sea_orm(column_type = "Text")]
+    pub description: String,
+    #[sea_orm(column_type = "Text")]
+    pub date: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub url: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub aliases: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub informational: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub patched: String,
+    #[sea_orm(column_type = "Text")]
+    pub unaffected: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
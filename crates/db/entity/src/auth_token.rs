@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "auth_token")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -12,6 +13,12 @@ pub struct Model {
     #[sea_orm(column_type = "Text", unique)]
     pub token: String,
     pub user_fk: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub operations: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub crate_scopes: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub expires_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
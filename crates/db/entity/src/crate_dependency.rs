@@ -0,0 +1,40 @@
+//! `SeaORM` Entity for the reverse dependency index
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "crate_dependency")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub crate_index_fk: i64,
+    /// Normalized name of the crate that is depended on
+    #[sea_orm(column_type = "Text")]
+    pub dependency: String,
+    #[sea_orm(column_type = "Text")]
+    pub req: String,
+    #[sea_orm(column_type = "Text")]
+    pub kind: String,
+    pub optional: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::crate_index::Entity",
+        from = "Column::CrateIndexFk",
+        to = "super::crate_index::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    CrateIndex,
+}
+
+impl Related<super::crate_index::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CrateIndex.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,36 @@
+//! `SeaORM` Entity for per-day download counts of crate versions
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "crate_download_daily")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub crate_meta_fk: i64,
+    /// Day of the downloads (`%Y-%m-%d`, UTC)
+    #[sea_orm(column_type = "Text")]
+    pub day: String,
+    pub downloads: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::crate_meta::Entity",
+        from = "Column::CrateMetaFk",
+        to = "super::crate_meta::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    CrateMeta,
+}
+
+impl Related<super::crate_meta::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CrateMeta.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
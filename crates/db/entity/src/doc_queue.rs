@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "doc_queue")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -13,6 +14,17 @@ pub struct Model {
     pub version: String,
     #[sea_orm(column_type = "Text")]
     pub path: String,
+    #[sea_orm(column_type = "Text")]
+    pub state: String,
+    #[sea_orm(column_type = "Text")]
+    pub queued: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub started: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub finished: Option<String>,
+    pub duration_ms: Option<i64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub log: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,11 +2,15 @@
 
 pub mod prelude;
 
+pub mod advisory;
+pub mod audit_log;
 pub mod auth_token;
 pub mod crate_author;
 pub mod crate_author_to_crate;
 pub mod crate_category;
 pub mod crate_category_to_crate;
+pub mod crate_dependency;
+pub mod crate_download_daily;
 pub mod crate_group;
 pub mod crate_index;
 pub mod crate_keyword;
@@ -29,4 +33,5 @@ pub mod toolchain_component;
 pub mod toolchain_target;
 pub mod user;
 pub mod webhook;
+pub mod webhook_delivery;
 pub mod webhook_queue;
@@ -1,8 +1,9 @@
 //! `SeaORM` Entity for `OAuth2` state (PKCE/CSRF storage during auth flow)
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "oauth2_state")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -1,10 +1,14 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
+pub use super::advisory::Entity as Advisory;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::auth_token::Entity as AuthToken;
 pub use super::crate_author::Entity as CrateAuthor;
 pub use super::crate_author_to_crate::Entity as CrateAuthorToCrate;
 pub use super::crate_category::Entity as CrateCategory;
 pub use super::crate_category_to_crate::Entity as CrateCategoryToCrate;
+pub use super::crate_dependency::Entity as CrateDependency;
+pub use super::crate_download_daily::Entity as CrateDownloadDaily;
 pub use super::crate_group::Entity as CrateGroup;
 pub use super::crate_index::Entity as CrateIndex;
 pub use super::crate_keyword::Entity as CrateKeyword;
@@ -27,4 +31,5 @@ pub use super::toolchain_component::Entity as ToolchainComponent;
 pub use super::toolchain_target::Entity as ToolchainTarget;
 pub use super::user::Entity as User;
 pub use super::webhook::Entity as Webhook;
+pub use super::webhook_delivery::Entity as WebhookDelivery;
 pub use super::webhook_queue::Entity as WebhookQueue;
@@ -1,8 +1,9 @@
 //! `SeaORM` Entity for toolchain target archives
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "toolchain_target")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -2,24 +2,42 @@
 
 use sea_orm::Set;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "webhook")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
-    pub event: String,
+    pub events: String,
     pub callback_url: String,
     pub name: Option<String>,
+    pub crate_filter: Option<String>,
+    pub group_filter: Option<String>,
+    pub format: String,
+    pub template: Option<String>,
+    pub failing_since: Option<DateTimeWithTimeZone>,
+    pub disabled_at: Option<DateTimeWithTimeZone>,
+    pub secret: Option<String>,
+    pub previous_secret: Option<String>,
+    pub previous_secret_expires: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::webhook_delivery::Entity")]
+    WebhookDelivery,
     #[sea_orm(has_many = "super::webhook_queue::Entity")]
     WebhookQueue,
 }
 
+impl Related<super::webhook_delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebhookDelivery.def()
+    }
+}
+
 impl Related<super::webhook_queue::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::WebhookQueue.def()
@@ -2,9 +2,10 @@
 
 use sea_orm::Set;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "webhook_queue")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -13,6 +14,8 @@ pub struct Model {
     pub payload: Json,
     pub last_attempt: Option<DateTimeWithTimeZone>,
     pub next_attempt: DateTimeWithTimeZone,
+    pub attempts: i32,
+    pub dead_since: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -88,6 +88,9 @@ pub enum AuthTokenIden {
     Token,
     #[iden = "user_fk"]
     UserFk,
+    Operations,
+    CrateScopes,
+    ExpiresAt,
 }
 
 #[derive(Iden, Copy, Clone)]
@@ -98,6 +101,12 @@ pub enum DocQueueIden {
     Krate,
     Version,
     Path,
+    State,
+    Queued,
+    Started,
+    Finished,
+    DurationMs,
+    Log,
 }
 #[derive(Iden, Copy, Clone)]
 pub enum CrateAuthorIden {
@@ -297,3 +306,99 @@ pub enum ToolchainComponentIden {
     Hash,
     Size,
 }
+
+#[derive(Iden, Copy, Clone)]
+pub enum AuditLogIden {
+    #[iden = "audit_log"]
+    Table,
+    Id,
+    Created,
+    Actor,
+    TokenName,
+    SourceIp,
+    Action,
+    Target,
+    Version,
+    Detail,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum AdvisoryIden {
+    #[iden = "advisory"]
+    Table,
+    Id,
+    AdvisoryId,
+    CrateName,
+    Title,
+    Description,
+    Date,
+    Url,
+    Aliases,
+    Informational,
+    Patched,
+    Unaffected,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum CrateDependencyIden {
+    #[iden = "crate_dependency"]
+    Table,
+    Id,
+    CrateIndexFk,
+    Dependency,
+    Req,
+    Kind,
+    Optional,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum CrateDownloadDailyIden {
+    #[iden = "crate_download_daily"]
+    Table,
+    Id,
+    CrateMetaFk,
+    Day,
+    Downloads,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum WebhookIden {
+    #[iden = "webhook"]
+    Table,
+    Id,
+    Event,
+    Events,
+    CrateFilter,
+    GroupFilter,
+    Format,
+    Template,
+    FailingSince,
+    DisabledAt,
+    Secret,
+    PreviousSecret,
+    PreviousSecretExpires,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum WebhookQueueIden {
+    #[iden = "webhook_queue"]
+    Table,
+    Attempts,
+    DeadSince,
+}
+
+#[derive(Iden, Copy, Clone)]
+pub enum WebhookDeliveryIden {
+    #[iden = "webhook_delivery"]
+    Table,
+    Id,
+    WebhookFk,
+    MessageId,
+    Payload,
+    Attempt,
+    Status,
+    ResponseBody,
+    Error,
+    DurationMs,
+    Created,
+}
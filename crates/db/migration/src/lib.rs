@@ -6,6 +6,18 @@ mod m20260128_000001_oauth2_identity;
 mod m20260129_000001_database_improvements;
 mod m20260130_000001_toolchain;
 mod m20260406_000001_toolchain_component;
+mod m20261018_000001_token_scopes;
+mod m20261018_000002_audit_log;
+mod m20261018_000003_advisory;
+mod m20261018_000004_crate_search;
+mod m20261018_000005_crate_dependency;
+mod m20261018_000006_crate_download_daily;
+mod m20261018_000007_doc_build_status;
+mod m20261018_000008_webhook_secret;
+mod m20261018_000009_webhook_filter;
+mod m20261018_000010_webhook_delivery;
+mod m20261018_000011_webhook_format;
+mod m20261018_000012_webhook_dead_letter;
 
 pub struct Migrator;
 
@@ -19,6 +31,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20260129_000001_database_improvements::Migration),
             Box::new(m20260130_000001_toolchain::Migration),
             Box::new(m20260406_000001_toolchain_component::Migration),
+            Box::new(m20261018_000001_token_scopes::Migration),
+            Box::new(m20261018_000002_audit_log::Migration),
+            Box::new(m20261018_000003_advisory::Migration),
+            Box::new(m20261018_000004_crate_search::Migration),
+            Box::new(m20261018_000005_crate_dependency::Migration),
+            Box::new(m20261018_000006_crate_download_daily::Migration),
+            Box::new(m20261018_000007_doc_build_status::Migration),
+            Box::new(m20261018_000008_webhook_secret::Migration),
+            Box::new(m20261018_000009_webhook_filter::Migration),
+            Box::new(m20261018_000010_webhook_delivery::Migration),
+            Box::new(m20261018_000011_webhook_format::Migration),
+            Box::new(m20261018_000012_webhook_dead_letter::Migration),
         ]
     }
 }
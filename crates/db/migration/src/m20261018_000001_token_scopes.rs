@@ -0,0 +1,58 @@
+//! Migration for scoped and expiring API tokens
+//!
+//! This migration adds to the `auth_token` table:
+//! - `operations`: comma-separated list of operations the token is limited to
+//! - `crate_scopes`: comma-separated list of crate name patterns the token is limited to
+//! - `expires_at`: timestamp after which the token is rejected
+//!
+//! All columns are nullable. `NULL` means "no restriction", so existing tokens
+//! keep the full rights of their owner.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::AuthTokenIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports a single column per ALTER TABLE statement.
+        for column in [
+            AuthTokenIden::Operations,
+            AuthTokenIden::CrateScopes,
+            AuthTokenIden::ExpiresAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuthTokenIden::Table)
+                        .add_column(ColumnDef::new(column).text())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            AuthTokenIden::ExpiresAt,
+            AuthTokenIden::CrateScopes,
+            AuthTokenIden::Operations,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(AuthTokenIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//! Migration for the audit log
+//!
+//! This migration adds the `audit_log` table. It records who changed what in
+//! the registry (publish, yank, owner and ACL changes, admin promotions, ...).
+//! Rows are only ever inserted, never updated or deleted by Kellnr.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::AuditLogIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLogIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLogIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLogIden::Created).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Actor).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::TokenName).text())
+                    .col(ColumnDef::new(AuditLogIden::SourceIp).text())
+                    .col(ColumnDef::new(AuditLogIden::Action).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Target).text().not_null())
+                    .col(ColumnDef::new(AuditLogIden::Version).text())
+                    .col(ColumnDef::new(AuditLogIden::Detail).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Queries filter by time range, actor and target
+        for (name, column) in [
+            ("idx_audit_log_created", AuditLogIden::Created),
+            ("idx_audit_log_actor", AuditLogIden::Actor),
+            ("idx_audit_log_target", AuditLogIden::Target),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name(name)
+                        .table(AuditLogIden::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLogIden::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+//! Migration for security advisories
+//!
+//! This migration adds the `advisory` table. It holds the advisories imported
+//! from a local copy of the `RustSec` advisory database. The table is replaced
+//! as a whole on every import. Version requirements and aliases are stored as
+//! JSON arrays.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::AdvisoryIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AdvisoryIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AdvisoryIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AdvisoryIden::AdvisoryId)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(AdvisoryIden::CrateName).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Title).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Description).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Date).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Url).text())
+                    .col(ColumnDef::new(AdvisoryIden::Aliases).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Informational).text())
+                    .col(ColumnDef::new(AdvisoryIden::Patched).text().not_null())
+                    .col(ColumnDef::new(AdvisoryIden::Unaffected).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_advisory_crate_name")
+                    .table(AdvisoryIden::Table)
+                    .col(AdvisoryIden::CrateName)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AdvisoryIden::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
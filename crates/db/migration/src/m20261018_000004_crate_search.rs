@@ -0,0 +1,72 @@
+//! Migration for the full-text search index
+//!
+//! This migration adds the `crate_search` table, one search document per
+//! crate built from its name, keywords, categories, description and the
+//! readme of the newest version. On `SQLite` it is a FTS5 virtual table with
+//! the crate id as rowid, on Postgres a table with a weighted `tsvector`
+//! column and a GIN index. Existing crates are indexed by the migration.
+
+use sea_orm::DatabaseBackend;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SQLITE_UP: &str = r"
+CREATE VIRTUAL TABLE IF NOT EXISTS crate_search USING fts5(
+    name, keywords, categories, description, readme,
+    tokenize = 'porter unicode61'
+);
+INSERT INTO crate_search (rowid, name, keywords, categories, description, readme)
+SELECT k.id,
+    k.original_name,
+    COALESCE((SELECT group_concat(ck.keyword, ' ') FROM crate_keyword_to_crate kc
+        JOIN crate_keyword ck ON ck.id = kc.keyword_fk WHERE kc.crate_fk = k.id), ''),
+    COALESCE((SELECT group_concat(c.category, ' ') FROM crate_category_to_crate cc
+        JOIN crate_category c ON c.id = cc.category_fk WHERE cc.crate_fk = k.id), ''),
+    COALESCE(k.description, ''),
+    COALESCE(cm.readme, '')
+FROM krate k
+LEFT JOIN crate_meta cm ON cm.crate_fk = k.id AND cm.version = k.max_version;
+";
+
+const POSTGRES_UP: &str = r"
+CREATE TABLE IF NOT EXISTS crate_search (
+    crate_fk BIGINT PRIMARY KEY REFERENCES krate (id) ON DELETE CASCADE,
+    document TSVECTOR NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_crate_search_document ON crate_search USING GIN (document);
+INSERT INTO crate_search (crate_fk, document)
+SELECT k.id,
+    setweight(to_tsvector('english', k.original_name), 'A')
+    || setweight(to_tsvector('english', COALESCE((SELECT string_agg(ck.keyword, ' ')
+        FROM crate_keyword_to_crate kc JOIN crate_keyword ck ON ck.id = kc.keyword_fk
+        WHERE kc.crate_fk = k.id), '')), 'B')
+    || setweight(to_tsvector('english', COALESCE((SELECT string_agg(c.category, ' ')
+        FROM crate_category_to_crate cc JOIN crate_category c ON c.id = cc.category_fk
+        WHERE cc.crate_fk = k.id), '')), 'B')
+    || setweight(to_tsvector('english', COALESCE(k.description, '')), 'C')
+    || setweight(to_tsvector('english', COALESCE(cm.readme, '')), 'D')
+FROM krate k
+LEFT JOIN crate_meta cm ON cm.crate_fk = k.id AND cm.version = k.max_version;
+";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let sql = match manager.get_database_backend() {
+            DatabaseBackend::Postgres => POSTGRES_UP,
+            _ => SQLITE_UP,
+        };
+        manager.get_connection().execute_unprepared(sql).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE IF EXISTS crate_search")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! Migration for the reverse dependency index
+//!
+//! This migration adds the `crate_dependency` table. It holds one row per
+//! dependency of every hosted crate version on a crate of this registry, so
+//! the dependents of a crate can be found without reading the dependencies
+//! of all versions. Dependencies on other registries are not indexed. The
+//! rows are built from the dependencies stored in the crate index.
+
+use sea_orm::DatabaseBackend;
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{CrateDependencyIden, CrateIndexIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SQLITE_BACKFILL: &str = r"
+INSERT INTO crate_dependency (crate_index_fk, dependency, req, kind, optional)
+SELECT ci.id,
+    LOWER(COALESCE(json_extract(d.value, '$.package'), json_extract(d.value, '$.name'))),
+    json_extract(d.value, '$.req'),
+    COALESCE(json_extract(d.value, '$.kind'), 'normal'),
+    COALESCE(json_extract(d.value, '$.optional'), 0)
+FROM crate_index ci, json_each(ci.deps) d
+WHERE ci.deps IS NOT NULL AND json_extract(d.value, '$.registry') IS NULL;
+";
+
+const POSTGRES_BACKFILL: &str = r"
+INSERT INTO crate_dependency (crate_index_fk, dependency, req, kind, optional)
+SELECT ci.id,
+    LOWER(COALESCE(d->>'package', d->>'name')),
+    d->>'req',
+    COALESCE(d->>'kind', 'normal'),
+    COALESCE((d->>'optional')::BOOLEAN, false)
+FROM crate_index ci, jsonb_array_elements(ci.deps) d
+WHERE jsonb_typeof(ci.deps) = 'array' AND d->>'registry' IS NULL;
+";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CrateDependencyIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CrateDependencyIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CrateDependencyIden::CrateIndexFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CrateDependencyIden::Dependency)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CrateDependencyIden::Req).text().not_null())
+                    .col(ColumnDef::new(CrateDependencyIden::Kind).text().not_null())
+                    .col(
+                        ColumnDef::new(CrateDependencyIden::Optional)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("crate_dependency_crate_index_fk")
+                            .from(
+                                CrateDependencyIden::Table,
+                                CrateDependencyIden::CrateIndexFk,
+                            )
+                            .to(CrateIndexIden::Table, CrateIndexIden::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_crate_dependency_dependency")
+                    .table(CrateDependencyIden::Table)
+                    .col(CrateDependencyIden::Dependency)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_crate_dependency_crate_index_fk")
+                    .table(CrateDependencyIden::Table)
+                    .col(CrateDependencyIden::CrateIndexFk)
+                    .to_owned(),
+            )
+            .await?;
+
+        let backfill = match manager.get_database_backend() {
+            DatabaseBackend::Postgres => POSTGRES_BACKFILL,
+            _ => SQLITE_BACKFILL,
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(backfill)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CrateDependencyIden::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! Migration for per-day download counts
+//!
+//! This migration adds the `crate_download_daily` table. It holds the number
+//! of downloads of a crate version per day (`%Y-%m-%d`, UTC), next to the
+//! cumulative counters in `crate_meta`. Rows older than the configured
+//! retention window are deleted periodically. There is no history for
+//! downloads before this migration.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{CrateDownloadDailyIden, CrateMetaIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CrateDownloadDailyIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CrateDownloadDailyIden::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CrateDownloadDailyIden::CrateMetaFk)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CrateDownloadDailyIden::Day)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CrateDownloadDailyIden::Downloads)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("crate_download_daily_crate_meta_fk")
+                            .from(
+                                CrateDownloadDailyIden::Table,
+                                CrateDownloadDailyIden::CrateMetaFk,
+                            )
+                            .to(CrateMetaIden::Table, CrateMetaIden::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::NoAction),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per version and day, the download counter adds to it
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_crate_download_daily_version_day")
+                    .table(CrateDownloadDailyIden::Table)
+                    .col(CrateDownloadDailyIden::CrateMetaFk)
+                    .col(CrateDownloadDailyIden::Day)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_crate_download_daily_day")
+                    .table(CrateDownloadDailyIden::Table)
+                    .col(CrateDownloadDailyIden::Day)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(CrateDownloadDailyIden::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
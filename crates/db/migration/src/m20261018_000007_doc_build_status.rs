@@ -0,0 +1,89 @@
+//! Migration for doc build status
+//!
+//! This migration adds to the `doc_queue` table:
+//! - `state`: `queued`, `building`, `succeeded` or `failed`
+//! - `queued`: timestamp the build was queued
+//! - `started` / `finished`: timestamps of the build, if it started or finished
+//! - `duration_ms`: duration of the finished build in milliseconds
+//! - `log`: captured cargo output of the build
+//!
+//! Finished builds are kept in the queue, so their status stays visible until
+//! the crate version is queued again.
+
+use chrono::Utc;
+use sea_orm_migration::prelude::*;
+
+use crate::iden::DocQueueIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports a single column per ALTER TABLE statement.
+        let columns = [
+            ColumnDef::new(DocQueueIden::State)
+                .text()
+                .not_null()
+                .default("queued")
+                .to_owned(),
+            ColumnDef::new(DocQueueIden::Queued)
+                .text()
+                .not_null()
+                .default("")
+                .to_owned(),
+            ColumnDef::new(DocQueueIden::Started).text().to_owned(),
+            ColumnDef::new(DocQueueIden::Finished).text().to_owned(),
+            ColumnDef::new(DocQueueIden::DurationMs)
+                .big_integer()
+                .to_owned(),
+            ColumnDef::new(DocQueueIden::Log).text().to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DocQueueIden::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Entries already in the queue count as queued now.
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(DocQueueIden::Table)
+                    .value(DocQueueIden::Queued, now)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            DocQueueIden::Log,
+            DocQueueIden::DurationMs,
+            DocQueueIden::Finished,
+            DocQueueIden::Started,
+            DocQueueIden::Queued,
+            DocQueueIden::State,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DocQueueIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
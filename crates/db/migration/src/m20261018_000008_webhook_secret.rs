@@ -0,0 +1,63 @@
+//! Migration for signed webhook deliveries
+//!
+//! This migration adds to the `webhook` table:
+//! - `secret`: secret the deliveries are signed with
+//! - `previous_secret`: secret before the last rotation, deliveries are also
+//!   signed with it until `previous_secret_expires`
+//!
+//! Webhooks registered before have no secret and their deliveries stay
+//! unsigned until a secret is set.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::WebhookIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports a single column per ALTER TABLE statement.
+        let columns = [
+            ColumnDef::new(WebhookIden::Secret).text().to_owned(),
+            ColumnDef::new(WebhookIden::PreviousSecret)
+                .text()
+                .to_owned(),
+            ColumnDef::new(WebhookIden::PreviousSecretExpires)
+                .timestamp_with_time_zone()
+                .to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            WebhookIden::PreviousSecretExpires,
+            WebhookIden::PreviousSecret,
+            WebhookIden::Secret,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
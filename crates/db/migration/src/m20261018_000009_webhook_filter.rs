@@ -0,0 +1,66 @@
+//! Migration for webhooks with several events and filters
+//!
+//! This migration changes the `webhook` table:
+//! - `event` is renamed to `events` and holds a comma-separated list of
+//!   events. Existing webhooks keep their single event.
+//! - `crate_filter`: glob the crate name of an event has to match
+//! - `group_filter`: group the crate of an event has to belong to
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::WebhookIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookIden::Table)
+                    .rename_column(WebhookIden::Event, WebhookIden::Events)
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite only supports a single column per ALTER TABLE statement.
+        for column in [WebhookIden::CrateFilter, WebhookIden::GroupFilter] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .add_column(ColumnDef::new(column).text())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [WebhookIden::GroupFilter, WebhookIden::CrateFilter] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookIden::Table)
+                    .rename_column(WebhookIden::Events, WebhookIden::Event)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
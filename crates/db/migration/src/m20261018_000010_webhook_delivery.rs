@@ -0,0 +1,112 @@
+//! Migration for the webhook delivery log
+//!
+//! This migration adds the `webhook_delivery` table. Every attempt to deliver
+//! a webhook message is recorded with its payload, the response and the
+//! latency, so failed deliveries can be inspected and sent again. Attempts
+//! of the same message share the `message_id`. Rows are removed with their
+//! webhook and after the configured retention period.
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{WebhookDeliveryIden, WebhookIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveryIden::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::WebhookFk)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::MessageId)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Payload)
+                            .json()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Attempt)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveryIden::Status).integer())
+                    .col(ColumnDef::new(WebhookDeliveryIden::ResponseBody).text())
+                    .col(ColumnDef::new(WebhookDeliveryIden::Error).text())
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::DurationMs)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryIden::Created)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_delivery_webhook")
+                            .from(WebhookDeliveryIden::Table, WebhookDeliveryIden::WebhookFk)
+                            .to(WebhookIden::Table, WebhookIden::Id)
+                            .on_update(ForeignKeyAction::NoAction)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Deliveries are listed per webhook, counted per message and
+        // removed by age
+        for (name, columns) in [
+            (
+                "idx_webhook_delivery_webhook",
+                vec![WebhookDeliveryIden::WebhookFk, WebhookDeliveryIden::Created],
+            ),
+            (
+                "idx_webhook_delivery_message",
+                vec![WebhookDeliveryIden::MessageId],
+            ),
+            (
+                "idx_webhook_delivery_created",
+                vec![WebhookDeliveryIden::Created],
+            ),
+        ] {
+            let mut index = Index::create();
+            index
+                .if_not_exists()
+                .name(name)
+                .table(WebhookDeliveryIden::Table);
+            for column in columns {
+                index.col(column);
+            }
+            manager.create_index(index.to_owned()).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookDeliveryIden::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+//! Migration for webhook payload formats
+//!
+//! This migration adds to the `webhook` table:
+//! - `format`: format of the delivered payload, e.g. `json` or `slack`.
+//!   Existing webhooks keep the native `json` format.
+//! - `template`: JSON template of the payload for the `template` format
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::WebhookIden;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports a single column per ALTER TABLE statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookIden::Table)
+                    .add_column(
+                        ColumnDef::new(WebhookIden::Format)
+                            .text()
+                            .not_null()
+                            .default("json"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookIden::Table)
+                    .add_column(ColumnDef::new(WebhookIden::Template).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [WebhookIden::Template, WebhookIden::Format] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+//! Migration for webhook dead letters and disabled webhooks
+//!
+//! This migration adds to the `webhook_queue` table:
+//! - `attempts`: number of failed attempts to deliver the message
+//! - `dead_since`: time the message was given up and moved to the dead
+//!   letters. Dead letters are not delivered until they are retried.
+//!
+//! and to the `webhook` table:
+//! - `failing_since`: start of the current series of failed deliveries
+//! - `disabled_at`: time the webhook was disabled after failing too long
+
+use sea_orm_migration::prelude::*;
+
+use crate::iden::{WebhookIden, WebhookQueueIden};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports a single column per ALTER TABLE statement.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookQueueIden::Table)
+                    .add_column(
+                        ColumnDef::new(WebhookQueueIden::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WebhookQueueIden::Table)
+                    .add_column(
+                        ColumnDef::new(WebhookQueueIden::DeadSince).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [WebhookIden::FailingSince, WebhookIden::DisabledAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .add_column(ColumnDef::new(column).timestamp_with_time_zone())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [WebhookIden::DisabledAt, WebhookIden::FailingSince] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for column in [WebhookQueueIden::DeadSince, WebhookQueueIden::Attempts] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(WebhookQueueIden::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
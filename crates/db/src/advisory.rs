@@ -0,0 +1,161 @@
+use kellnr_entity::advisory;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::DbError;
+
+/// Security advisory imported from the `RustSec` advisory database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Advisory {
+    /// Advisory id, e.g. `RUSTSEC-2023-0001`
+    pub id: String,
+    /// Normalized name of the affected crate
+    pub package: String,
+    pub title: String,
+    pub description: String,
+    /// Date the advisory was reported (`%Y-%m-%d`)
+    pub date: String,
+    pub url: Option<String>,
+    /// Other ids of the advisory, e.g. CVE numbers
+    pub aliases: Vec<String>,
+    /// Kind of an informational advisory, e.g. `unmaintained`
+    pub informational: Option<String>,
+    /// Version requirements of the versions that contain a fix
+    pub patched: Vec<String>,
+    /// Version requirements of the versions that were never affected
+    pub unaffected: Vec<String>,
+}
+
+impl Advisory {
+    /// `true` if the version is neither patched nor unaffected. Versions that
+    /// are not valid semver are never considered affected.
+    pub fn affects(&self, version: &str) -> bool {
+        let Ok(version) = Version::parse(version) else {
+            return false;
+        };
+        !self
+            .patched
+            .iter()
+            .chain(&self.unaffected)
+            .filter_map(|req| VersionReq::parse(req).ok())
+            .any(|req| req.matches(&version))
+    }
+
+    /// The versions out of `versions` that are affected by the advisory.
+    pub fn affected_versions<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        versions
+            .into_iter()
+            .filter(|v| self.affects(v))
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+impl TryFrom<advisory::Model> for Advisory {
+    type Error = DbError;
+
+    fn try_from(m: advisory::Model) -> Result<Self, Self::Error> {
+        let list = |json: &str| {
+            serde_json::from_str::<Vec<String>>(json)
+                .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))
+        };
+        Ok(Self {
+            aliases: list(&m.aliases)?,
+            patched: list(&m.patched)?,
+            unaffected: list(&m.unaffected)?,
+            id: m.advisory_id,
+            package: m.crate_name,
+            title: m.title,
+            description: m.description,
+            date: m.date,
+            url: m.url,
+            informational: m.informational,
+        })
+    }
+}
+
+/// An advisory together with the affected crate versions known to Kellnr.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AdvisoryMatch {
+    pub advisory: Advisory,
+    /// Affected versions of the crate hosted by Kellnr
+    pub hosted_versions: Vec<String>,
+    /// Affected versions of the crate cached from crates.io
+    pub cached_versions: Vec<String>,
+}
+
+/// An advisory of a single crate and the versions of it that are affected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateAdvisory {
+    #[serde(flatten)]
+    pub advisory: Advisory,
+    pub affected_versions: Vec<String>,
+}
+
+impl CrateAdvisory {
+    /// Match the advisories of a crate against its versions. Advisories that
+    /// affect none of the versions are dropped.
+    pub fn for_versions(advisories: Vec<Advisory>, versions: &[&str]) -> Vec<CrateAdvisory> {
+        advisories
+            .into_iter()
+            .filter_map(|advisory| {
+                let affected_versions = advisory.affected_versions(versions.iter().copied());
+                (!affected_versions.is_empty()).then_some(CrateAdvisory {
+                    advisory,
+                    affected_versions,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(patched: &[&str], unaffected: &[&str]) -> Advisory {
+        let to_vec = |v: &[&str]| v.iter().map(ToString::to_string).collect::<Vec<_>>();
+        Advisory {
+            id: "RUSTSEC-2023-0001".to_string(),
+            package: "foo".to_string(),
+            title: "Bad bug".to_string(),
+            description: String::new(),
+            date: "2023-01-01".to_string(),
+            url: None,
+            aliases: vec![],
+            informational: None,
+            patched: to_vec(patched),
+            unaffected: to_vec(unaffected),
+        }
+    }
+
+    #[test]
+    fn affects_versions_that_are_not_patched_or_unaffected() {
+        let a = advisory(&[">= 1.2.3, < 2.0.0", ">= 2.0.1"], &["< 1.0.0"]);
+        assert!(a.affects("1.2.2"));
+        assert!(a.affects("2.0.0"));
+        assert!(!a.affects("1.2.3"));
+        assert!(!a.affects("2.1.0"));
+        assert!(!a.affects("0.9.0"));
+        assert!(!a.affects("not-semver"));
+    }
+
+    #[test]
+    fn advisory_without_patches_affects_all_versions() {
+        let a = advisory(&[], &[]);
+        assert!(a.affects("0.1.0"));
+        assert!(a.affects("9.9.9"));
+    }
+
+    #[test]
+    fn crate_advisories_only_contain_matching_advisories() {
+        let advisories = vec![advisory(&[">= 1.0.1"], &[]), advisory(&[">= 0.1.0"], &[])];
+        let matches = CrateAdvisory::for_versions(advisories, &["1.0.0", "1.0.1"]);
+        assert_eq!(1, matches.len());
+        assert_eq!(vec!["1.0.0".to_string()], matches[0].affected_versions);
+    }
+}
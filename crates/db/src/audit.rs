@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use kellnr_entity::audit_log;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::DbError;
+
+/// Registry mutation recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Publish,
+    Yank,
+    Unyank,
+    DeleteVersion,
+    AddOwner,
+    RemoveOwner,
+    AddCrateUser,
+    RemoveCrateUser,
+    AddCrateGroup,
+    RemoveCrateGroup,
+    ChangeDownloadRestriction,
+    GrantAdmin,
+    RevokeAdmin,
+}
+
+impl From<AuditAction> for &str {
+    fn from(value: AuditAction) -> Self {
+        match value {
+            AuditAction::Publish => "publish",
+            AuditAction::Yank => "yank",
+            AuditAction::Unyank => "unyank",
+            AuditAction::DeleteVersion => "delete_version",
+            AuditAction::AddOwner => "add_owner",
+            AuditAction::RemoveOwner => "remove_owner",
+            AuditAction::AddCrateUser => "add_crate_user",
+            AuditAction::RemoveCrateUser => "remove_crate_user",
+            AuditAction::AddCrateGroup => "add_crate_group",
+            AuditAction::RemoveCrateGroup => "remove_crate_group",
+            AuditAction::ChangeDownloadRestriction => "change_download_restriction",
+            AuditAction::GrantAdmin => "grant_admin",
+            AuditAction::RevokeAdmin => "revoke_admin",
+        }
+    }
+}
+
+impl TryFrom<&str> for AuditAction {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "publish" => Ok(Self::Publish),
+            "yank" => Ok(Self::Yank),
+            "unyank" => Ok(Self::Unyank),
+            "delete_version" => Ok(Self::DeleteVersion),
+            "add_owner" => Ok(Self::AddOwner),
+            "remove_owner" => Ok(Self::RemoveOwner),
+            "add_crate_user" => Ok(Self::AddCrateUser),
+            "remove_crate_user" => Ok(Self::RemoveCrateUser),
+            "add_crate_group" => Ok(Self::AddCrateGroup),
+            "remove_crate_group" => Ok(Self::RemoveCrateGroup),
+            "change_download_restriction" => Ok(Self::ChangeDownloadRestriction),
+            "grant_admin" => Ok(Self::GrantAdmin),
+            "revoke_admin" => Ok(Self::RevokeAdmin),
+            a => Err(format!("'{a}' is not a valid audit action")),
+        }
+    }
+}
+
+/// Who performed an audited action.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditActor {
+    /// Name of the user
+    pub user: String,
+    /// Name of the API token, if the request was authenticated with one
+    pub token_name: Option<String>,
+    /// IP address the request came from, if known
+    pub source_ip: Option<String>,
+}
+
+/// A new audit log entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewAuditEntry {
+    pub actor: AuditActor,
+    pub action: AuditAction,
+    /// Crate or user the action was performed on
+    pub target: String,
+    pub version: Option<String>,
+    /// Additional information, e.g. the owner that was added
+    pub detail: Option<String>,
+}
+
+impl NewAuditEntry {
+    pub fn new(actor: AuditActor, action: AuditAction, target: impl Into<String>) -> Self {
+        Self {
+            actor,
+            action,
+            target: target.into(),
+            version: None,
+            detail: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// A recorded audit log entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Time of the action in UTC (`%Y-%m-%d %H:%M:%S`)
+    pub created: String,
+    pub actor: String,
+    pub token_name: Option<String>,
+    pub source_ip: Option<String>,
+    pub action: AuditAction,
+    pub target: String,
+    pub version: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl TryFrom<audit_log::Model> for AuditEntry {
+    type Error = DbError;
+
+    fn try_from(m: audit_log::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: m.id,
+            created: m.created,
+            actor: m.actor,
+            token_name: m.token_name,
+            source_ip: m.source_ip,
+            action: AuditAction::try_from(m.action.as_str())
+                .map_err(|_| DbError::InvalidAuditAction(m.action.clone()))?,
+            target: m.target,
+            version: m.version,
+            detail: m.detail,
+        })
+    }
+}
+
+/// Filter for audit log queries. Unset fields match all entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    pub target: Option<String>,
+    /// Only entries at or after this point in time
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries before this point in time
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// One page of audit log entries, newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    /// Number of entries matching the filter across all pages
+    pub total: u64,
+}
@@ -1,27 +1,56 @@
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_entity::auth_token;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::database::DB_DATE_FORMAT;
+use crate::error::DbError;
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct AuthToken {
     pub id: i32,
     pub name: String,
     #[serde(skip_serializing)]
     token: String,
+    pub scopes: TokenScopes,
 }
 
 impl AuthToken {
     pub fn new(id: i32, name: String, token: String) -> Self {
-        Self { id, name, token }
+        Self {
+            id,
+            name,
+            token,
+            scopes: TokenScopes::default(),
+        }
     }
 }
 
-impl From<auth_token::Model> for AuthToken {
-    fn from(m: auth_token::Model) -> Self {
-        Self {
+impl TryFrom<auth_token::Model> for AuthToken {
+    type Error = DbError;
+
+    fn try_from(m: auth_token::Model) -> Result<Self, Self::Error> {
+        let scopes = token_scopes(&m)?;
+        Ok(Self {
             id: m.id as i32,
             name: m.name,
             token: m.token,
-        }
+            scopes,
+        })
     }
 }
+
+/// Read the scopes of a token from its database row.
+fn token_scopes(m: &auth_token::Model) -> Result<TokenScopes, DbError> {
+    Ok(TokenScopes {
+        operations: TokenScopes::operations_from_db(m.operations.as_deref())
+            .map_err(DbError::InvalidTokenOperation)?,
+        crates: TokenScopes::crates_from_db(m.crate_scopes.as_deref()),
+        expires_at: m
+            .expires_at
+            .as_deref()
+            .and_then(|e| NaiveDateTime::parse_from_str(e, DB_DATE_FORMAT).ok())
+            .map(|e| Utc.from_utc_datetime(&e)),
+    })
+}
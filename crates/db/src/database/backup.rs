@@ -0,0 +1,267 @@
+//! Export and import of the database content for backups.
+//!
+//! Rows are exported as JSON of the entity models, so a backup does not
+//! depend on the database backend and can be restored into `SQLite` or
+//! Postgres.
+
+use kellnr_entity::{
+    advisory, audit_log, auth_token, crate_author, crate_author_to_crate, crate_category,
+    crate_category_to_crate, crate_dependency, crate_download_daily, crate_group, crate_index,
+    crate_keyword, crate_keyword_to_crate, crate_meta, crate_user, cratesio_crate, cratesio_index,
+    cratesio_meta, doc_queue, group, group_user, krate, oauth2_identity, oauth2_state, owner,
+    session, toolchain, toolchain_component, toolchain_target, user, webhook, webhook_delivery,
+    webhook_queue,
+};
+use sea_orm::sea_query::Query;
+use sea_orm::{
+    AccessMode, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseBackend,
+    DatabaseTransaction, EntityTrait, IdenStatic, IntoActiveModel, IsolationLevel, Iterable,
+    PaginatorTrait, PrimaryKeyToColumn, PrimaryKeyTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{Database, pending_doc_build_states, search};
+use crate::error::DbError;
+use crate::provider::DbResult;
+
+/// Number of rows read or written with a single statement.
+pub const BACKUP_PAGE_SIZE: u64 = 500;
+
+/// Tables of the crates.io proxy cache, which can be left out of a backup.
+pub const CRATESIO_TABLES: &[&str] = &["cratesio_crate", "cratesio_meta", "cratesio_index"];
+
+macro_rules! backup_tables {
+    ($($table:ident),* $(,)?) => {
+        /// Tables in a backup, ordered such that every table comes after the
+        /// tables it references. Sessions and login states are short-lived and
+        /// not part of a backup. The doc queue is, for its build history.
+        pub const BACKUP_TABLES: &[&str] = &[$(stringify!($table)),*];
+
+        async fn export_table_page(
+            con: &DatabaseTransaction,
+            table: &str,
+            page: u64,
+        ) -> DbResult<Vec<Value>> {
+            match table {
+                $(stringify!($table) => export_page::<$table::Entity>(con, page).await,)*
+                _ => Err(DbError::UnknownBackupTable(table.to_string())),
+            }
+        }
+
+        async fn import_table_rows(
+            con: &DatabaseTransaction,
+            table: &str,
+            rows: &[Value],
+        ) -> DbResult<()> {
+            match table {
+                $(stringify!($table) => import_rows::<$table::Entity>(con, rows).await,)*
+                _ => Err(DbError::UnknownBackupTable(table.to_string())),
+            }
+        }
+
+        async fn delete_all_rows(con: &DatabaseTransaction) -> DbResult<()> {
+            session::Entity::delete_many().exec(con).await?;
+            oauth2_state::Entity::delete_many().exec(con).await?;
+            search::clear_search_index(con).await?;
+            // Reverse order, so rows are deleted before the rows they reference
+            let deletes = [$(delete_statement::<$table::Entity>()),*];
+            for statement in deletes.iter().rev() {
+                con.execute(statement).await?;
+            }
+            Ok(())
+        }
+
+        async fn reset_sequences(con: &DatabaseTransaction) -> DbResult<()> {
+            $(reset_sequence::<$table::Entity>(con).await?;)*
+            Ok(())
+        }
+    };
+}
+
+backup_tables!(
+    user,
+    group,
+    group_user,
+    krate,
+    crate_meta,
+    doc_queue,
+    crate_download_daily,
+    crate_index,
+    crate_dependency,
+    crate_user,
+    crate_group,
+    owner,
+    crate_author,
+    crate_author_to_crate,
+    crate_keyword,
+    crate_keyword_to_crate,
+    crate_category,
+    crate_category_to_crate,
+    auth_token,
+    oauth2_identity,
+    audit_log,
+    advisory,
+    webhook,
+    webhook_queue,
+    webhook_delivery,
+    toolchain,
+    toolchain_target,
+    toolchain_component,
+    cratesio_crate,
+    cratesio_meta,
+    cratesio_index,
+);
+
+/// Read-only snapshot of the database. All tables are read in one
+/// transaction, so the exported rows are consistent with each other.
+pub struct DbExport {
+    txn: DatabaseTransaction,
+}
+
+impl DbExport {
+    /// Rows of a page of `table`, ordered by primary key. An empty result
+    /// marks the end of the table.
+    pub async fn rows(&self, table: &str, page: u64) -> DbResult<Vec<Value>> {
+        export_table_page(&self.txn, table, page).await
+    }
+
+    /// End the snapshot.
+    pub async fn finish(self) -> DbResult<()> {
+        Ok(self.txn.rollback().await?)
+    }
+}
+
+/// Restore of a backup into the database. Nothing is visible to other
+/// connections until the import is committed.
+pub struct DbImport {
+    txn: DatabaseTransaction,
+}
+
+impl DbImport {
+    /// Insert exported rows of `table`. Tables have to be imported in the
+    /// order of [`BACKUP_TABLES`].
+    pub async fn insert(&self, table: &str, rows: &[Value]) -> DbResult<()> {
+        import_table_rows(&self.txn, table, rows).await
+    }
+
+    pub async fn commit(self) -> DbResult<()> {
+        // Postgres does not advance sequences for explicitly inserted ids
+        if self.txn.get_database_backend() == DatabaseBackend::Postgres {
+            reset_sequences(&self.txn).await?;
+        }
+        // Pending builds belong to the instance that queued them, their
+        // build directories do not exist here
+        doc_queue::Entity::delete_many()
+            .filter(doc_queue::Column::State.is_in(pending_doc_build_states()))
+            .exec(&self.txn)
+            .await?;
+        // The search index is derived data and not part of a backup
+        search::rebuild_search_index(&self.txn).await?;
+        Ok(self.txn.commit().await?)
+    }
+}
+
+impl Database {
+    /// Start a consistent export of all tables in [`BACKUP_TABLES`].
+    pub async fn export(&self) -> DbResult<DbExport> {
+        let txn = match self.db_con.get_database_backend() {
+            DatabaseBackend::Postgres => {
+                self.db_con
+                    .begin_with_config(
+                        Some(IsolationLevel::RepeatableRead),
+                        Some(AccessMode::ReadOnly),
+                    )
+                    .await?
+            }
+            // A SQLite transaction reads from a single snapshot anyway
+            _ => self.db_con.begin().await?,
+        };
+        Ok(DbExport { txn })
+    }
+
+    /// Start an import into a database that contains no crates and no users
+    /// except the initial admin. All existing rows are removed.
+    pub async fn import(&self) -> DbResult<DbImport> {
+        if krate::Entity::find().count(&self.db_con).await? > 0 {
+            return Err(DbError::RestoreTargetNotEmpty("crates"));
+        }
+        if cratesio_crate::Entity::find().count(&self.db_con).await? > 0 {
+            return Err(DbError::RestoreTargetNotEmpty("cached crates.io crates"));
+        }
+        if toolchain::Entity::find().count(&self.db_con).await? > 0 {
+            return Err(DbError::RestoreTargetNotEmpty("toolchains"));
+        }
+        if user::Entity::find().count(&self.db_con).await? > 1 {
+            return Err(DbError::RestoreTargetNotEmpty("users"));
+        }
+
+        let txn = self.db_con.begin().await?;
+        // Boxed, as the deletes of all tables make the future large
+        Box::pin(delete_all_rows(&txn)).await?;
+        Ok(DbImport { txn })
+    }
+}
+
+async fn export_page<E>(con: &DatabaseTransaction, page: u64) -> DbResult<Vec<Value>>
+where
+    E: EntityTrait,
+    E::Model: Serialize + Sync,
+{
+    let mut query = E::find();
+    for key in <E::PrimaryKey as Iterable>::iter() {
+        query = query.order_by_asc(key.into_column());
+    }
+    query
+        .paginate(con, BACKUP_PAGE_SIZE)
+        .fetch_page(page)
+        .await?
+        .iter()
+        .map(|row| {
+            serde_json::to_value(row).map_err(|e| DbError::FailedToConvertToJson(e.to_string()))
+        })
+        .collect()
+}
+
+async fn import_rows<E>(con: &DatabaseTransaction, rows: &[Value]) -> DbResult<()>
+where
+    E: EntityTrait,
+    E::Model: DeserializeOwned + IntoActiveModel<E::ActiveModel>,
+    E::ActiveModel: ActiveModelTrait<Entity = E> + Send,
+{
+    let models = rows
+        .iter()
+        .map(|row| {
+            serde_json::from_value::<E::Model>(row.clone())
+                .map(IntoActiveModel::into_active_model)
+                .map_err(|e| DbError::FailedToConvertFromJson(e.to_string()))
+        })
+        .collect::<DbResult<Vec<_>>>()?;
+    if !models.is_empty() {
+        E::insert_many(models).exec_without_returning(con).await?;
+    }
+    Ok(())
+}
+
+fn delete_statement<E: EntityTrait>() -> sea_orm::sea_query::DeleteStatement {
+    Query::delete().from_table(E::default()).to_owned()
+}
+
+/// Set the id sequence of the table behind the highest imported id.
+async fn reset_sequence<E: EntityTrait>(con: &DatabaseTransaction) -> DbResult<()> {
+    if !<E::PrimaryKey as PrimaryKeyTrait>::auto_increment() {
+        return Ok(());
+    }
+    let Some(key) = <E::PrimaryKey as Iterable>::iter().next() else {
+        return Ok(());
+    };
+    let table = E::default().table_name();
+    let column = key.into_column().as_str();
+    con.execute_unprepared(&format!(
+        "SELECT setval(pg_get_serial_sequence('\"{table}\"', '{column}'), \
+         COALESCE((SELECT MAX(\"{column}\") FROM \"{table}\"), 0) + 1, false)"
+    ))
+    .await?;
+    Ok(())
+}
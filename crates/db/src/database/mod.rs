@@ -1,11 +1,13 @@
+pub mod backup;
 mod operations;
+mod search;
 pub mod test_utils;
 
 use std::cmp::max;
 use std::collections::BTreeMap;
 use std::path::Path;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use kellnr_common::crate_data::{CrateData, CrateRegistryDep, CrateVersionData};
 use kellnr_common::crate_overview::CrateOverview;
 use kellnr_common::cratesio_prefetch_msg::{CratesioPrefetchMsg, UpdateData};
@@ -14,28 +16,34 @@ use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::prefetch::Prefetch;
 use kellnr_common::publish_metadata::PublishMetadata;
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_common::version::Version;
-use kellnr_common::webhook::{Webhook, WebhookEvent, WebhookQueue};
+use kellnr_common::webhook::{
+    NewWebhookDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookEvent, WebhookFormat,
+    WebhookQueue, WebhookSecrets,
+};
 use kellnr_entity::prelude::*;
 use kellnr_entity::{
-    auth_token, crate_author, crate_author_to_crate, crate_category, crate_category_to_crate,
-    crate_group, crate_index, crate_keyword, crate_keyword_to_crate, crate_meta, crate_user,
-    cratesio_crate, cratesio_index, cratesio_meta, doc_queue, group, group_user, krate,
-    oauth2_identity, oauth2_state, owner, session, toolchain, toolchain_component,
-    toolchain_target, user, webhook, webhook_queue,
-};
-use kellnr_migration::iden::{
-    AuthTokenIden, CrateIden, CrateMetaIden, CratesIoIden, CratesIoMetaIden, GroupIden,
+    advisory, audit_log, auth_token, crate_author, crate_author_to_crate, crate_category,
+    crate_category_to_crate, crate_dependency, crate_download_daily, crate_group, crate_index,
+    crate_keyword, crate_keyword_to_crate, crate_meta, crate_user, cratesio_crate, cratesio_index,
+    cratesio_meta, doc_queue, group, group_user, krate, oauth2_identity, oauth2_state, owner,
+    session, toolchain, toolchain_component, toolchain_target, user, webhook, webhook_delivery,
+    webhook_queue,
 };
+use kellnr_migration::iden::{CrateIden, CrateMetaIden, CratesIoIden, CratesIoMetaIden, GroupIden};
 use sea_orm::entity::prelude::Uuid;
 use sea_orm::prelude::async_trait::async_trait;
 use sea_orm::query::{QueryOrder, QuerySelect, TransactionTrait};
-use sea_orm::sea_query::{Alias, Cond, Expr, Iden, JoinType, LikeExpr, Order, Query, UnionType};
+use sea_orm::sea_query::{
+    Alias, Cond, Expr, Iden, JoinType, LikeExpr, Order, Query, SelectStatement, UnionType,
+};
 use sea_orm::{
     ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, ExprTrait,
-    FromQueryResult, ModelTrait, QueryFilter, RelationTrait, Set,
+    FromQueryResult, ModelTrait, PaginatorTrait, QueryFilter, RelationTrait, Set,
 };
 
+use crate::download_history::{DAY_FORMAT, DailyDownloads, RECENT_DOWNLOADS_DAYS};
 use crate::error::DbError;
 use crate::password::{generate_salt, hash_pwd, hash_token};
 use crate::provider::{
@@ -44,10 +52,12 @@ use crate::provider::{
 };
 use crate::tables::init_database;
 use crate::{
-    AuthToken, ConString, CrateMeta, CrateSummary, DbProvider, DocQueueEntry, Group, User,
+    Advisory, AdvisoryMatch, AuditEntry, AuditFilter, AuditPage, AuthToken, ConString, CrateMeta,
+    CrateSummary, DbProvider, Dependent, DocBuildState, DocQueueEntry, Group, NewAuditEntry,
+    SearchQuery, User,
 };
 
-const DB_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+pub(crate) const DB_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 /// Escape the LIKE wildcards `%` and `_` (and the escape character itself) in a
 /// user-supplied search term so it is matched literally. Callers wrap the result
@@ -60,6 +70,46 @@ fn escape_like_pattern(input: &str) -> String {
         .replace('_', "\\_")
 }
 
+/// Overview of the cached crates.io crates, optionally filtered by name.
+fn cached_crates_query(contains: Option<&str>) -> SelectStatement {
+    let mut query = Query::select();
+    query
+        .expr_as(Expr::col(CratesIoIden::OriginalName), Alias::new("name"))
+        .expr_as(Expr::col(CratesIoIden::MaxVersion), Alias::new("version"))
+        .expr_as(Expr::col(CratesIoIden::LastModified), Alias::new("date"))
+        .expr_as(
+            Expr::col(CratesIoIden::TotalDownloads),
+            Alias::new("total_downloads"),
+        )
+        .expr_as(
+            Expr::col(CratesIoIden::Description),
+            Alias::new("description"),
+        )
+        .expr_as(
+            Expr::col(CratesIoMetaIden::Documentation),
+            Alias::new("documentation"),
+        )
+        .expr_as(Expr::cust("true"), Alias::new("is_cache"))
+        .from(CratesIoMetaIden::Table)
+        .inner_join(
+            CratesIoIden::Table,
+            Expr::col((CratesIoMetaIden::Table, CratesIoMetaIden::CratesIoFk))
+                .equals((CratesIoIden::Table, CratesIoIden::Id)),
+        )
+        .and_where(
+            Expr::col((CratesIoMetaIden::Table, CratesIoMetaIden::Version))
+                .equals((CratesIoIden::Table, CratesIoIden::MaxVersion)),
+        );
+    if let Some(contains) = contains {
+        let pattern = format!("%{}%", escape_like_pattern(contains));
+        query.and_where(
+            Expr::col((CratesIoIden::Table, CratesIoIden::OriginalName))
+                .like(LikeExpr::new(pattern).escape('\\')),
+        );
+    }
+    query
+}
+
 /// Upper bound on the configured session age. Clamping to this keeps cutoff
 /// arithmetic well within `chrono::Duration`/`DateTime` range, avoiding
 /// overflow panics for absurd configuration values.
@@ -79,6 +129,25 @@ fn session_age_to_duration(d: std::time::Duration) -> Option<chrono::Duration> {
     Some(chrono::Duration::from_std(clamped).expect("clamped session age fits chrono::Duration"))
 }
 
+fn pending_doc_build_states() -> [&'static str; 2] {
+    [DocBuildState::Queued.into(), DocBuildState::Building.into()]
+}
+
+/// Delete the finished builds of a crate version from the doc queue.
+async fn delete_finished_doc_builds<C: sea_orm::ConnectionTrait>(
+    con: &C,
+    krate: &NormalizedName,
+    version: &Version,
+) -> DbResult<()> {
+    DocQueue::delete_many()
+        .filter(doc_queue::Column::Krate.eq(krate.to_string()))
+        .filter(doc_queue::Column::Version.eq(version.to_string()))
+        .filter(doc_queue::Column::State.is_not_in(pending_doc_build_states()))
+        .exec(con)
+        .await?;
+    Ok(())
+}
+
 pub struct Database {
     db_con: DatabaseConnection,
     /// Maximum lifetime of a session before it is treated as expired, or
@@ -304,42 +373,7 @@ impl Database {
 
         // UNION with cached crates
         if cache {
-            let mut query2 = Query::select();
-            query2
-                .expr_as(Expr::col(CratesIoIden::OriginalName), Alias::new("name"))
-                .expr_as(Expr::col(CratesIoIden::MaxVersion), Alias::new("version"))
-                .expr_as(Expr::col(CratesIoIden::LastModified), Alias::new("date"))
-                .expr_as(
-                    Expr::col(CratesIoIden::TotalDownloads),
-                    Alias::new("total_downloads"),
-                )
-                .expr_as(
-                    Expr::col(CratesIoIden::Description),
-                    Alias::new("description"),
-                )
-                .expr_as(
-                    Expr::col(CratesIoMetaIden::Documentation),
-                    Alias::new("documentation"),
-                )
-                .expr_as(Expr::cust("true"), Alias::new("is_cache"))
-                .from(CratesIoMetaIden::Table)
-                .inner_join(
-                    CratesIoIden::Table,
-                    Expr::col((CratesIoMetaIden::Table, CratesIoMetaIden::CratesIoFk))
-                        .equals((CratesIoIden::Table, CratesIoIden::Id)),
-                )
-                .and_where(
-                    Expr::col((CratesIoMetaIden::Table, CratesIoMetaIden::Version))
-                        .equals((CratesIoIden::Table, CratesIoIden::MaxVersion)),
-                );
-            if let Some(contains) = contains {
-                let pattern = format!("%{}%", escape_like_pattern(contains));
-                query2.and_where(
-                    Expr::col((CratesIoIden::Table, CratesIoIden::OriginalName))
-                        .like(LikeExpr::new(pattern).escape('\\')),
-                );
-            }
-            query.union(UnionType::All, query2);
+            query.union(UnionType::All, cached_crates_query(contains));
         }
 
         // Ordering and optional limit/offset
@@ -355,6 +389,17 @@ impl Database {
             .map_err(DbError::from)
     }
 
+    /// Cached crates.io crates whose name contains `contains`, ordered by name.
+    async fn search_cached_crates(&self, contains: &str) -> DbResult<Vec<CrateOverview>> {
+        let mut query = cached_crates_query(Some(contains));
+        query.order_by(Alias::new("name"), Order::Asc);
+        let builder = self.db_con.get_database_backend();
+        CrateOverview::find_by_statement(builder.build(&query))
+            .all(&self.db_con)
+            .await
+            .map_err(DbError::from)
+    }
+
     /// Executes a count query `SELECT COUNT(id_column) FROM table` and returns the count as u64.
     async fn count<T>(&self, table: T, id_column: T, error: DbError) -> DbResult<u64>
     where
@@ -402,6 +447,14 @@ impl Database {
             .ok_or(DbError::WebhookNotFound)
     }
 
+    async fn get_webhook_dead_letter_model(&self, id: &str) -> DbResult<webhook_queue::Model> {
+        webhook_queue::Entity::find_by_id(parse_uuid(id)?)
+            .filter(webhook_queue::Column::DeadSince.is_not_null())
+            .one(&self.db_con)
+            .await?
+            .ok_or(DbError::WebhookDeadLetterNotFound)
+    }
+
     async fn get_owner_by_crate_and_user(
         &self,
         crate_name: &str,
@@ -431,19 +484,75 @@ impl Database {
 }
 
 fn webhook_model_to_obj(w: webhook::Model) -> DbResult<Webhook> {
-    let event = w
-        .event
-        .as_str()
-        .try_into()
-        .map_err(|_| DbError::InvalidWebhookEvent(w.event))?;
+    let events = w
+        .events
+        .split(',')
+        .map(WebhookEvent::try_from)
+        .collect::<Result<_, _>>()
+        .map_err(|_| DbError::InvalidWebhookEvent(w.events.clone()))?;
+    let format = WebhookFormat::try_from(w.format.as_str())
+        .map_err(|_| DbError::InvalidWebhookFormat(w.format.clone()))?;
     Ok(Webhook {
         id: Some(w.id.into()),
         name: w.name,
-        event,
+        events,
         callback_url: w.callback_url,
+        crate_filter: w.crate_filter,
+        group_filter: w.group_filter,
+        format,
+        template: w.template,
+        failing_since: w.failing_since.map(Into::into),
+        disabled_at: w.disabled_at.map(Into::into),
+    })
+}
+
+fn webhook_dead_letter_model_to_obj(q: webhook_queue::Model) -> Option<WebhookDeadLetter> {
+    Some(WebhookDeadLetter {
+        dead_since: q.dead_since?.into(),
+        id: q.id.to_string(),
+        webhook_id: q.webhook_fk.to_string(),
+        payload: q.payload,
+        attempts: u32::try_from(q.attempts).unwrap_or_default(),
+        last_attempt: q.last_attempt.map(Into::into),
     })
 }
 
+fn webhook_delivery_model_to_obj(d: webhook_delivery::Model) -> WebhookDelivery {
+    WebhookDelivery {
+        id: d.id.to_string(),
+        webhook_id: d.webhook_fk.to_string(),
+        message_id: d.message_id,
+        payload: d.payload,
+        attempt: u32::try_from(d.attempt).unwrap_or_default(),
+        status: d.status.and_then(|s| u16::try_from(s).ok()),
+        response_body: d.response_body,
+        error: d.error,
+        duration_ms: u64::try_from(d.duration_ms).unwrap_or_default(),
+        created: d.created.into(),
+    }
+}
+
+fn parse_uuid(id: &str) -> DbResult<Uuid> {
+    Uuid::try_from(id).map_err(|_| DbError::InvalidId(id.to_string()))
+}
+
+/// Events of a webhook as stored in the `events` column
+fn webhook_events(events: &[WebhookEvent]) -> String {
+    events
+        .iter()
+        .map(|e| Into::<&str>::into(*e))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn webhook_secrets(w: &webhook::Model) -> WebhookSecrets {
+    WebhookSecrets {
+        secret: w.secret.clone(),
+        previous_secret: w.previous_secret.clone(),
+        previous_secret_expires: w.previous_secret_expires.map(Into::into),
+    }
+}
+
 /// Generates an `add_*` method that links two entities via a join table.
 ///
 /// # Parameters
@@ -493,6 +602,10 @@ impl Database {
 
 #[async_trait]
 impl DbProvider for Database {
+    async fn ping(&self) -> DbResult<()> {
+        Ok(self.db_con.ping().await?)
+    }
+
     async fn get_total_unique_cached_crates(&self) -> DbResult<u64> {
         self.count(
             CratesIoIden::Table,
@@ -545,39 +658,8 @@ impl DbProvider for Database {
         crate_name: &NormalizedName,
         crate_version: &Version,
     ) -> DbResult<()> {
-        let txn = self.db_con.begin().await?;
-
-        krate::Entity::update_many()
-            .col_expr(
-                krate::Column::TotalDownloads,
-                Expr::col(krate::Column::TotalDownloads).add(1),
-            )
-            .filter(krate::Column::Name.eq(crate_name))
-            .exec(&txn)
-            .await?;
-
-        let crate_id = krate::Entity::find()
-            .filter(krate::Column::Name.eq(crate_name))
-            .one(&txn)
-            .await?
-            .ok_or_else(|| DbError::CrateNotFound(crate_name.to_string()))?
-            .id;
-
-        crate_meta::Entity::update_many()
-            .col_expr(
-                crate_meta::Column::Downloads,
-                Expr::col(crate_meta::Column::Downloads).add(1),
-            )
-            .filter(
-                Cond::all()
-                    .add(crate_meta::Column::Version.eq(crate_version))
-                    .add(crate_meta::Column::CrateFk.eq(crate_id)),
-            )
-            .exec(&txn)
-            .await?;
-
-        txn.commit().await?;
-        Ok(())
+        self.increase_download_counter_by(crate_name, crate_version, 1)
+            .await
     }
 
     async fn increase_cached_download_counter(
@@ -657,6 +739,17 @@ impl DbProvider for Database {
             .exec(&txn)
             .await?;
 
+        let crate_meta_id = crate_meta::Entity::find()
+            .filter(crate_meta::Column::Version.eq(crate_version))
+            .filter(crate_meta::Column::CrateFk.eq(crate_id))
+            .one(&txn)
+            .await?
+            .map(|cm| cm.id);
+        if let Some(crate_meta_id) = crate_meta_id {
+            operations::add_daily_downloads(&txn, crate_meta_id, Utc::now().date_naive(), count)
+                .await?;
+        }
+
         txn.commit().await?;
         Ok(())
     }
@@ -1024,6 +1117,17 @@ impl DbProvider for Database {
     }
 
     async fn add_auth_token(&self, name: &str, token: &str, user: &str) -> DbResult<()> {
+        self.add_scoped_auth_token(name, token, user, &TokenScopes::default())
+            .await
+    }
+
+    async fn add_scoped_auth_token(
+        &self,
+        name: &str,
+        token: &str,
+        user: &str,
+        scopes: &TokenScopes,
+    ) -> DbResult<()> {
         let hashed_token = hash_token(token);
         let user = self.get_user_model(user).await?;
 
@@ -1031,6 +1135,11 @@ impl DbProvider for Database {
             name: Set(name.to_owned()),
             token: Set(hashed_token),
             user_fk: Set(user.id),
+            operations: Set(scopes.operations_to_db()),
+            crate_scopes: Set(scopes.crates_to_db()),
+            expires_at: Set(scopes
+                .expires_at
+                .map(|e| e.format(DB_DATE_FORMAT).to_string())),
             ..Default::default()
         };
 
@@ -1039,17 +1148,25 @@ impl DbProvider for Database {
         Ok(())
     }
 
-    async fn get_user_from_token(&self, token: &str) -> DbResult<User> {
+    async fn get_user_from_token(&self, token: &str) -> DbResult<(User, AuthToken)> {
         let token = hash_token(token);
 
-        let u = user::Entity::find()
-            .join(JoinType::InnerJoin, user::Relation::AuthToken.def())
-            .filter(Expr::col((AuthTokenIden::Table, AuthTokenIden::Token)).eq(token))
+        let (at, u) = auth_token::Entity::find()
+            .find_also_related(user::Entity)
+            .filter(auth_token::Column::Token.eq(token))
             .one(&self.db_con)
             .await?
             .ok_or(DbError::TokenNotFound)?;
+        let u = u.ok_or(DbError::TokenNotFound)?;
+
+        // An expired token is treated like a missing one, so callers do not
+        // need to distinguish between the two.
+        let at = AuthToken::try_from(at)?;
+        if at.scopes.is_expired() {
+            return Err(DbError::TokenNotFound);
+        }
 
-        Ok(User::from(u))
+        Ok((User::from(u), at))
     }
 
     async fn get_user(&self, name: &str) -> DbResult<User> {
@@ -1067,7 +1184,7 @@ impl DbProvider for Database {
             .all(&self.db_con)
             .await?;
 
-        Ok(at.into_iter().map(AuthToken::from).collect())
+        at.into_iter().map(AuthToken::try_from).collect()
     }
 
     async fn delete_auth_token(&self, id: i32) -> DbResult<()> {
@@ -1233,15 +1350,22 @@ impl DbProvider for Database {
         version: &Version,
         path: &Path,
     ) -> DbResult<()> {
+        let txn = self.db_con.begin().await?;
+        // Only the latest build of a crate version is kept.
+        delete_finished_doc_builds(&txn, krate, version).await?;
+
         let s = doc_queue::ActiveModel {
             krate: Set(krate.to_string()),
             version: Set(version.to_string()),
             // FIXME: Convert Path to String properly, handle errors
             path: Set(path.to_string_lossy().to_string()),
+            state: Set(<&str>::from(DocBuildState::Queued).to_string()),
+            queued: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
             ..Default::default()
         };
 
-        s.insert(&self.db_con).await?;
+        s.insert(&txn).await?;
+        txn.commit().await?;
         Ok(())
     }
 
@@ -1251,9 +1375,93 @@ impl DbProvider for Database {
     }
 
     async fn get_doc_queue(&self) -> DbResult<Vec<DocQueueEntry>> {
-        let entities = DocQueue::find().all(&self.db_con).await?;
+        let entities = DocQueue::find()
+            .filter(doc_queue::Column::State.is_in(pending_doc_build_states()))
+            .order_by_asc(doc_queue::Column::Id)
+            .all(&self.db_con)
+            .await?;
+
+        entities.into_iter().map(DocQueueEntry::try_from).collect()
+    }
+
+    async fn get_doc_builds(&self, limit: u64) -> DbResult<Vec<DocQueueEntry>> {
+        let entities = DocQueue::find()
+            .order_by_desc(doc_queue::Column::Id)
+            .limit(limit)
+            .all(&self.db_con)
+            .await?;
+
+        entities.into_iter().map(DocQueueEntry::try_from).collect()
+    }
+
+    async fn get_crate_doc_builds(&self, krate: &NormalizedName) -> DbResult<Vec<DocQueueEntry>> {
+        let entities = DocQueue::find()
+            .filter(doc_queue::Column::Krate.eq(krate.to_string()))
+            .order_by_desc(doc_queue::Column::Id)
+            .all(&self.db_con)
+            .await?;
+
+        entities.into_iter().map(DocQueueEntry::try_from).collect()
+    }
+
+    async fn get_doc_build(
+        &self,
+        krate: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<Option<DocQueueEntry>> {
+        DocQueue::find()
+            .filter(doc_queue::Column::Krate.eq(krate.to_string()))
+            .filter(doc_queue::Column::Version.eq(version.to_string()))
+            .order_by_desc(doc_queue::Column::Id)
+            .one(&self.db_con)
+            .await?
+            .map(DocQueueEntry::try_from)
+            .transpose()
+    }
+
+    async fn get_doc_build_log(&self, id: i64) -> DbResult<Option<String>> {
+        let entity = DocQueue::find_by_id(id).one(&self.db_con).await?;
+        Ok(entity.and_then(|e| e.log))
+    }
 
-        Ok(entities.into_iter().map(DocQueueEntry::from).collect())
+    async fn start_doc_build(&self, id: i64) -> DbResult<()> {
+        DocQueue::update_many()
+            .col_expr(
+                doc_queue::Column::State,
+                Expr::value(<&str>::from(DocBuildState::Building)),
+            )
+            .col_expr(
+                doc_queue::Column::Started,
+                Expr::value(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            )
+            .filter(doc_queue::Column::Id.eq(id))
+            .exec(&self.db_con)
+            .await?;
+        Ok(())
+    }
+
+    async fn finish_doc_build(
+        &self,
+        id: i64,
+        state: DocBuildState,
+        duration: std::time::Duration,
+        log: &str,
+    ) -> DbResult<()> {
+        DocQueue::update_many()
+            .col_expr(doc_queue::Column::State, Expr::value(<&str>::from(state)))
+            .col_expr(
+                doc_queue::Column::Finished,
+                Expr::value(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            )
+            .col_expr(
+                doc_queue::Column::DurationMs,
+                Expr::value(duration.as_millis() as i64),
+            )
+            .col_expr(doc_queue::Column::Log, Expr::value(log))
+            .filter(doc_queue::Column::Id.eq(id))
+            .exec(&self.db_con)
+            .await?;
+        Ok(())
     }
 
     async fn delete_crate(&self, krate: &NormalizedName, version: &Version) -> DbResult<()> {
@@ -1279,7 +1487,12 @@ impl DbProvider for Database {
             .one(&txn)
             .await?
             .ok_or_else(|| DbError::CrateIndexNotFound(krate.to_string(), version.to_string()))?;
+        crate_dependency::Entity::delete_many()
+            .filter(crate_dependency::Column::CrateIndexFk.eq(crate_index_version.id))
+            .exec(&txn)
+            .await?;
         crate_index_version.delete(&txn).await?;
+        delete_finished_doc_builds(&txn, krate, version).await?;
 
         // If it was the last entry in the "crate_meta" table, delete the entry
         // in the "crate" table as well
@@ -1315,6 +1528,7 @@ impl DbProvider for Database {
             c.e_tag = Set(etag);
             c.update(&txn).await?;
         }
+        search::update_search_document(&txn, crate_id).await?;
 
         txn.commit().await?;
 
@@ -1366,6 +1580,16 @@ impl DbProvider for Database {
         self.query_crates(Some(contains), None, cache).await
     }
 
+    async fn search_crates(&self, query: &str, cache: bool) -> DbResult<Vec<CrateOverview>> {
+        let query = SearchQuery::parse(query);
+        let mut crates = self.search_hosted_crates(&query).await?;
+        // Cached crates have no keywords, categories or owners to filter by
+        if cache && !query.has_filters() {
+            crates.extend(self.search_cached_crates(&query.text).await?);
+        }
+        Ok(crates)
+    }
+
     async fn get_crate_overview_list(
         &self,
         limit: u64,
@@ -1419,6 +1643,14 @@ impl DbProvider for Database {
             .all(&self.db_con)
             .await?;
 
+        let since = Utc::now().date_naive() - TimeDelta::days(RECENT_DOWNLOADS_DAYS as i64 - 1);
+        let recent_downloads = operations::get_downloads_since(
+            &self.db_con,
+            crate_metas.iter().map(|cm| cm.id).collect(),
+            since,
+        )
+        .await?;
+
         let mut versions = Vec::new();
         for cm in crate_metas {
             let ci = crate_indices
@@ -1457,6 +1689,7 @@ impl DbProvider for Database {
                 version: cm.version,
                 created: cm.created,
                 downloads: cm.downloads,
+                recent_downloads: recent_downloads.get(&cm.id).copied().unwrap_or_default(),
                 readme: cm.readme,
                 license: cm.license,
                 license_file: cm.license_file,
@@ -1478,6 +1711,7 @@ impl DbProvider for Database {
             owners,
             max_version: krate.max_version,
             total_downloads: krate.total_downloads,
+            recent_downloads: recent_downloads.values().sum(),
             last_updated: krate.last_updated,
             homepage: krate.homepage,
             description: krate.description,
@@ -1510,7 +1744,9 @@ impl DbProvider for Database {
             e_tag: Set(String::new()), // Set to empty string, as it can be computed, when the crate index is inserted
             restricted_download: Set(false),
         };
-        Ok(krate.insert(&self.db_con).await?.id)
+        let id = krate.insert(&self.db_con).await?.id;
+        search::update_search_document(&self.db_con, id).await?;
+        Ok(id)
     }
 
     async fn add_crate(
@@ -1574,6 +1810,7 @@ impl DbProvider for Database {
         operations::update_crate_categories(&txn, pub_metadata, crate_id).await?;
         operations::update_crate_keywords(&txn, pub_metadata, crate_id).await?;
         operations::update_crate_authors(&txn, pub_metadata, crate_id).await?;
+        search::update_search_document(&txn, crate_id).await?;
 
         txn.commit().await?;
         Ok(crate_id)
@@ -1830,11 +2067,16 @@ impl DbProvider for Database {
         Ok(())
     }
 
-    async fn register_webhook(&self, webhook: Webhook) -> DbResult<String> {
+    async fn register_webhook(&self, webhook: Webhook, secret: Option<String>) -> DbResult<String> {
         let w = webhook::ActiveModel {
-            event: Set(Into::<&str>::into(webhook.event).to_string()),
+            events: Set(webhook_events(&webhook.events)),
             callback_url: Set(webhook.callback_url),
             name: Set(webhook.name),
+            crate_filter: Set(webhook.crate_filter),
+            group_filter: Set(webhook.group_filter),
+            format: Set(Into::<&str>::into(webhook.format).to_string()),
+            template: Set(webhook.template),
+            secret: Set(secret),
             ..Default::default()
         };
 
@@ -1863,16 +2105,63 @@ impl DbProvider for Database {
             .collect())
     }
 
+    async fn get_webhook_secrets(&self, id: &str) -> DbResult<WebhookSecrets> {
+        let w = self.get_webhook_model(id).await?;
+        Ok(webhook_secrets(&w))
+    }
+
+    async fn set_webhook_secrets(&self, id: &str, secrets: &WebhookSecrets) -> DbResult<()> {
+        let mut w: webhook::ActiveModel = self.get_webhook_model(id).await?.into();
+        w.secret = Set(secrets.secret.clone());
+        w.previous_secret = Set(secrets.previous_secret.clone());
+        w.previous_secret_expires = Set(secrets.previous_secret_expires.map(Into::into));
+        w.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn set_webhook_health(
+        &self,
+        id: &str,
+        failing_since: Option<DateTime<Utc>>,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        let mut w: webhook::ActiveModel = self.get_webhook_model(id).await?.into();
+        w.failing_since = Set(failing_since.map(Into::into));
+        w.disabled_at = Set(disabled_at.map(Into::into));
+        w.update(&self.db_con).await?;
+        Ok(())
+    }
+
     async fn add_webhook_queue(
         &self,
         event: WebhookEvent,
+        crate_name: Option<String>,
+        crate_groups: Option<Vec<String>>,
         payload: serde_json::Value,
     ) -> DbResult<()> {
-        let w = webhook::Entity::find()
-            .filter(webhook::Column::Event.eq(Into::<&str>::into(event)))
+        let webhooks = webhook::Entity::find()
+            .filter(webhook::Column::DisabledAt.is_null())
             .all(&self.db_con)
             .await?;
+        let crate_groups = match (&crate_name, crate_groups) {
+            (_, Some(groups)) => groups,
+            (Some(name), None) if webhooks.iter().any(|w| w.group_filter.is_some()) => self
+                .get_crate_groups(&NormalizedName::from_unchecked(name.to_lowercase()))
+                .await?
+                .into_iter()
+                .map(|g| g.name)
+                .collect(),
+            _ => Vec::new(),
+        };
 
+        let w: Vec<_> = webhooks
+            .into_iter()
+            .filter(|w| {
+                webhook_model_to_obj(w.clone()).is_ok_and(|webhook| {
+                    webhook.accepts(event, crate_name.as_deref(), &crate_groups)
+                })
+            })
+            .collect();
         if w.is_empty() {
             return Ok(());
         }
@@ -1899,17 +2188,27 @@ impl DbProvider for Database {
         let w = webhook_queue::Entity::find()
             .find_with_related(webhook::Entity)
             .filter(webhook_queue::Column::NextAttempt.lte(timestamp))
+            .filter(webhook_queue::Column::DeadSince.is_null())
+            .filter(webhook::Column::DisabledAt.is_null())
+            .order_by_asc(webhook_queue::Column::NextAttempt)
             .all(&self.db_con)
             .await?;
 
         Ok(w.into_iter()
             .filter_map(|w| {
+                let webhook = w.1.first()?;
                 Some(WebhookQueue {
                     id: w.0.id.to_string(),
-                    callback_url: w.1.first()?.callback_url.clone(),
+                    webhook_id: webhook.id.to_string(),
+                    callback_url: webhook.callback_url.clone(),
                     payload: w.0.payload,
                     last_attempt: w.0.last_attempt.map(Into::into),
                     next_attempt: w.0.next_attempt.into(),
+                    secrets: webhook_secrets(webhook).active(timestamp),
+                    format: WebhookFormat::try_from(webhook.format.as_str()).unwrap_or_default(),
+                    template: webhook.template.clone(),
+                    attempts: u32::try_from(w.0.attempts).unwrap_or_default(),
+                    failing_since: webhook.failing_since.map(Into::into),
                 })
             })
             .collect())
@@ -1922,6 +2221,8 @@ impl DbProvider for Database {
         next_attempt: DateTime<Utc>,
     ) -> DbResult<()> {
         let mut w: webhook_queue::ActiveModel = self.get_webhook_queue_model(id).await?.into();
+        let attempts = w.attempts.as_ref() + 1;
+        w.attempts = Set(attempts);
         w.last_attempt = Set(Some(last_attempt.into()));
         w.next_attempt = Set(next_attempt.into());
         w.update(&self.db_con).await?;
@@ -1936,6 +2237,392 @@ impl DbProvider for Database {
         Ok(())
     }
 
+    async fn dead_letter_webhook_queue(
+        &self,
+        id: &str,
+        last_attempt: DateTime<Utc>,
+    ) -> DbResult<()> {
+        let mut w: webhook_queue::ActiveModel = self.get_webhook_queue_model(id).await?.into();
+        let attempts = w.attempts.as_ref() + 1;
+        w.attempts = Set(attempts);
+        w.last_attempt = Set(Some(last_attempt.into()));
+        w.dead_since = Set(Some(Utc::now().into()));
+        w.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_webhook_dead_letters(&self, webhook_id: &str) -> DbResult<Vec<WebhookDeadLetter>> {
+        Ok(webhook_queue::Entity::find()
+            .filter(webhook_queue::Column::WebhookFk.eq(parse_uuid(webhook_id)?))
+            .filter(webhook_queue::Column::DeadSince.is_not_null())
+            .order_by_desc(webhook_queue::Column::DeadSince)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .filter_map(webhook_dead_letter_model_to_obj)
+            .collect())
+    }
+
+    async fn retry_webhook_dead_letter(&self, id: &str) -> DbResult<()> {
+        let mut w: webhook_queue::ActiveModel =
+            self.get_webhook_dead_letter_model(id).await?.into();
+        w.attempts = Set(0);
+        w.last_attempt = Set(None);
+        w.next_attempt = Set(Utc::now().into());
+        w.dead_since = Set(None);
+        w.update(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn delete_webhook_dead_letters_before(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        let result = webhook_queue::Entity::delete_many()
+            .filter(webhook_queue::Column::DeadSince.lt(before))
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    async fn get_webhook_queue_length(&self) -> DbResult<u64> {
+        Ok(webhook_queue::Entity::find()
+            .filter(webhook_queue::Column::DeadSince.is_null())
+            .count(&self.db_con)
+            .await?)
+    }
+
+    async fn add_webhook_delivery(
+        &self,
+        delivery: &NewWebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        let previous_attempts = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::MessageId.eq(&delivery.message_id))
+            .count(&self.db_con)
+            .await?;
+
+        let d = webhook_delivery::ActiveModel {
+            webhook_fk: Set(parse_uuid(&delivery.webhook_id)?),
+            message_id: Set(delivery.message_id.clone()),
+            payload: Set(delivery.payload.clone()),
+            attempt: Set(i32::try_from(previous_attempts + 1).unwrap_or(i32::MAX)),
+            status: Set(delivery.status.map(i32::from)),
+            response_body: Set(delivery.response_body.clone()),
+            error: Set(delivery.error.clone()),
+            duration_ms: Set(i64::try_from(delivery.duration_ms).unwrap_or(i64::MAX)),
+            created: Set(Utc::now().into()),
+            ..Default::default()
+        };
+
+        Ok(webhook_delivery_model_to_obj(d.insert(&self.db_con).await?))
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: u64,
+    ) -> DbResult<Vec<WebhookDelivery>> {
+        Ok(webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::WebhookFk.eq(parse_uuid(webhook_id)?))
+            .order_by_desc(webhook_delivery::Column::Created)
+            .limit(limit)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(webhook_delivery_model_to_obj)
+            .collect())
+    }
+
+    async fn get_webhook_delivery(&self, id: &str) -> DbResult<WebhookDelivery> {
+        webhook_delivery::Entity::find_by_id(parse_uuid(id)?)
+            .one(&self.db_con)
+            .await?
+            .map(webhook_delivery_model_to_obj)
+            .ok_or(DbError::WebhookDeliveryNotFound)
+    }
+
+    async fn delete_webhook_deliveries_before(&self, before: DateTime<Utc>) -> DbResult<u64> {
+        let result = webhook_delivery::Entity::delete_many()
+            .filter(webhook_delivery::Column::Created.lt(before))
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    async fn add_audit_entry(&self, entry: &NewAuditEntry) -> DbResult<()> {
+        let action: &str = entry.action.into();
+        let a = audit_log::ActiveModel {
+            created: Set(Utc::now().format(DB_DATE_FORMAT).to_string()),
+            actor: Set(entry.actor.user.clone()),
+            token_name: Set(entry.actor.token_name.clone()),
+            source_ip: Set(entry.actor.source_ip.clone()),
+            action: Set(action.to_string()),
+            target: Set(entry.target.clone()),
+            version: Set(entry.version.clone()),
+            detail: Set(entry.detail.clone()),
+            ..Default::default()
+        };
+
+        a.insert(&self.db_con).await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<AuditPage> {
+        let mut query = AuditLog::find();
+        if let Some(actor) = &filter.actor {
+            query = query.filter(audit_log::Column::Actor.eq(actor));
+        }
+        if let Some(action) = filter.action {
+            let action: &str = action.into();
+            query = query.filter(audit_log::Column::Action.eq(action));
+        }
+        if let Some(target) = &filter.target {
+            query = query.filter(audit_log::Column::Target.eq(target));
+        }
+        // Timestamps are stored in DB_DATE_FORMAT, which sorts lexicographically
+        if let Some(since) = filter.since {
+            query = query
+                .filter(audit_log::Column::Created.gte(since.format(DB_DATE_FORMAT).to_string()));
+        }
+        if let Some(until) = filter.until {
+            query = query
+                .filter(audit_log::Column::Created.lt(until.format(DB_DATE_FORMAT).to_string()));
+        }
+
+        let total = query.clone().count(&self.db_con).await?;
+        let entries = query
+            .order_by_desc(audit_log::Column::Id)
+            .limit(limit)
+            .offset(offset)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(AuditEntry::try_from)
+            .collect::<DbResult<Vec<_>>>()?;
+
+        Ok(AuditPage { entries, total })
+    }
+
+    async fn replace_advisories(&self, advisories: &[Advisory]) -> DbResult<Vec<String>> {
+        let to_json = |list: &[String]| {
+            serde_json::to_string(list).map_err(|e| DbError::FailedToConvertToJson(e.to_string()))
+        };
+
+        let txn = self.db_con.begin().await?;
+        let existing = advisory::Entity::find()
+            .select_only()
+            .column(advisory::Column::AdvisoryId)
+            .into_tuple::<String>()
+            .all(&txn)
+            .await?;
+
+        advisory::Entity::delete_many().exec(&txn).await?;
+        let mut added = vec![];
+        for a in advisories {
+            advisory::ActiveModel {
+                advisory_id: Set(a.id.clone()),
+                crate_name: Set(a.package.to_lowercase()),
+                title: Set(a.title.clone()),
+                description: Set(a.description.clone()),
+                date: Set(a.date.clone()),
+                url: Set(a.url.clone()),
+                aliases: Set(to_json(&a.aliases)?),
+                informational: Set(a.informational.clone()),
+                patched: Set(to_json(&a.patched)?),
+                unaffected: Set(to_json(&a.unaffected)?),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            if !existing.contains(&a.id) {
+                added.push(a.id.clone());
+            }
+        }
+        txn.commit().await?;
+
+        Ok(added)
+    }
+
+    async fn get_advisories(&self, crate_name: &NormalizedName) -> DbResult<Vec<Advisory>> {
+        advisory::Entity::find()
+            .filter(advisory::Column::CrateName.eq(crate_name.to_string()))
+            .order_by_desc(advisory::Column::Date)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(Advisory::try_from)
+            .collect()
+    }
+
+    async fn get_daily_downloads(
+        &self,
+        crate_name: &NormalizedName,
+        since: NaiveDate,
+    ) -> DbResult<Vec<DailyDownloads>> {
+        #[derive(FromQueryResult)]
+        struct Model {
+            version: String,
+            day: String,
+            downloads: i64,
+        }
+
+        crate_download_daily::Entity::find()
+            .select_only()
+            .column(crate_meta::Column::Version)
+            .column(crate_download_daily::Column::Day)
+            .column(crate_download_daily::Column::Downloads)
+            .join(
+                JoinType::InnerJoin,
+                crate_download_daily::Relation::CrateMeta.def(),
+            )
+            .join(JoinType::InnerJoin, crate_meta::Relation::Krate.def())
+            .filter(krate::Column::Name.eq(crate_name))
+            .filter(crate_download_daily::Column::Day.gte(since.format(DAY_FORMAT).to_string()))
+            .order_by_asc(crate_download_daily::Column::Day)
+            .into_model::<Model>()
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(|m| {
+                Ok(DailyDownloads {
+                    day: NaiveDate::parse_from_str(&m.day, DAY_FORMAT)
+                        .map_err(|_| DbError::InvalidDate(m.day.clone()))?,
+                    version: m.version,
+                    downloads: m.downloads,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_daily_downloads_before(&self, day: NaiveDate) -> DbResult<u64> {
+        let result = crate_download_daily::Entity::delete_many()
+            .filter(crate_download_daily::Column::Day.lt(day.format(DAY_FORMAT).to_string()))
+            .exec(&self.db_con)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    async fn get_dependents(&self, crate_name: &NormalizedName) -> DbResult<Vec<Dependent>> {
+        #[derive(FromQueryResult)]
+        struct Model {
+            original_name: String,
+            max_version: String,
+            vers: String,
+            yanked: bool,
+            req: String,
+            kind: String,
+            optional: bool,
+        }
+
+        let mut dependents = crate_dependency::Entity::find()
+            .select_only()
+            .column(krate::Column::OriginalName)
+            .column(krate::Column::MaxVersion)
+            .column(crate_index::Column::Vers)
+            .column(crate_index::Column::Yanked)
+            .column(crate_dependency::Column::Req)
+            .column(crate_dependency::Column::Kind)
+            .column(crate_dependency::Column::Optional)
+            .join(
+                JoinType::InnerJoin,
+                crate_dependency::Relation::CrateIndex.def(),
+            )
+            .join(JoinType::InnerJoin, crate_index::Relation::Krate.def())
+            .filter(crate_dependency::Column::Dependency.eq(crate_name.to_string()))
+            .into_model::<Model>()
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(|m| Dependent {
+                is_latest: m.vers == m.max_version,
+                name: m.original_name,
+                version: m.vers,
+                req: m.req,
+                kind: m.kind,
+                optional: m.optional,
+                yanked: m.yanked,
+            })
+            .collect::<Vec<_>>();
+
+        dependents.sort_by(|a, b| {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then_with(|| {
+                    Version::from_unchecked_str(&b.version)
+                        .cmp(&Version::from_unchecked_str(&a.version))
+                })
+        });
+        Ok(dependents)
+    }
+
+    async fn get_advisory_matches(&self) -> DbResult<Vec<AdvisoryMatch>> {
+        let advisories = advisory::Entity::find()
+            .order_by_desc(advisory::Column::Date)
+            .all(&self.db_con)
+            .await?
+            .into_iter()
+            .map(Advisory::try_from)
+            .collect::<DbResult<Vec<_>>>()?;
+        let names = advisories
+            .iter()
+            .map(|a| a.package.clone())
+            .collect::<std::collections::BTreeSet<_>>();
+
+        let mut hosted = BTreeMap::<String, Vec<String>>::new();
+        for (name, version) in crate_meta::Entity::find()
+            .select_only()
+            .column(krate::Column::Name)
+            .column(crate_meta::Column::Version)
+            .join(JoinType::InnerJoin, crate_meta::Relation::Krate.def())
+            .filter(krate::Column::Name.is_in(names.clone()))
+            .into_tuple::<(String, String)>()
+            .all(&self.db_con)
+            .await?
+        {
+            hosted.entry(name).or_default().push(version);
+        }
+
+        let mut cached = BTreeMap::<String, Vec<String>>::new();
+        for (name, version) in cratesio_index::Entity::find()
+            .select_only()
+            .column(cratesio_crate::Column::Name)
+            .column(cratesio_index::Column::Vers)
+            .join(
+                JoinType::InnerJoin,
+                cratesio_index::Relation::CratesioCrate.def(),
+            )
+            .filter(cratesio_crate::Column::Name.is_in(names))
+            .into_tuple::<(String, String)>()
+            .all(&self.db_con)
+            .await?
+        {
+            cached.entry(name).or_default().push(version);
+        }
+
+        let affected = |versions: &BTreeMap<String, Vec<String>>, a: &Advisory| {
+            versions.get(&a.package).map_or_else(Vec::new, |v| {
+                a.affected_versions(v.iter().map(String::as_str))
+            })
+        };
+        Ok(advisories
+            .into_iter()
+            .filter_map(|advisory| {
+                let hosted_versions = affected(&hosted, &advisory);
+                let cached_versions = affected(&cached, &advisory);
+                (!hosted_versions.is_empty() || !cached_versions.is_empty()).then_some(
+                    AdvisoryMatch {
+                        advisory,
+                        hosted_versions,
+                        cached_versions,
+                    },
+                )
+            })
+            .collect())
+    }
+
     // OAuth2 identity methods
 
     async fn get_user_by_oauth2_identity(
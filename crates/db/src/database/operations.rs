@@ -6,19 +6,24 @@
 
 use std::collections::HashMap;
 
-use kellnr_common::index_metadata::{IndexDep, IndexMetadata};
+use chrono::NaiveDate;
+
+use kellnr_common::index_metadata::{DependencyKind, IndexDep, IndexMetadata};
 use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::publish_metadata::PublishMetadata;
 use kellnr_entity::{
     auth_token, crate_author, crate_author_to_crate, crate_category, crate_category_to_crate,
-    crate_index, crate_keyword, crate_keyword_to_crate, crate_meta, cratesio_crate, cratesio_index,
-    krate, owner, user,
+    crate_dependency, crate_download_daily, crate_index, crate_keyword, crate_keyword_to_crate,
+    crate_meta, cratesio_crate, cratesio_index, krate, owner, user,
 };
+use sea_orm::sea_query::{Alias, Expr, Func, OnConflict};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, ExprTrait,
+    FromQueryResult, QueryFilter, QuerySelect, Set,
 };
 
 use crate::ConString;
+use crate::download_history::DAY_FORMAT;
 use crate::error::DbError;
 use crate::password::{hash_pwd, hash_token};
 use crate::provider::DbResult;
@@ -164,7 +169,40 @@ pub async fn add_crate_index<C: ConnectionTrait>(
         crate_fk: Set(crate_id),
     };
 
-    ci.insert(db_con).await?;
+    let ci = ci.insert(db_con).await?;
+    add_crate_dependencies(db_con, ci.id, &index_data.deps).await
+}
+
+/// Add the dependencies of a crate version to the reverse dependency index.
+/// Dependencies on crates of other registries are not indexed.
+pub async fn add_crate_dependencies<C: ConnectionTrait>(
+    db_con: &C,
+    crate_index_id: i64,
+    deps: &[IndexDep],
+) -> DbResult<()> {
+    let rows = deps
+        .iter()
+        .filter(|d| d.registry.is_none())
+        .map(|d| crate_dependency::ActiveModel {
+            id: ActiveValue::default(),
+            crate_index_fk: Set(crate_index_id),
+            // The original package name of a renamed dependency
+            dependency: Set(d.package.as_ref().unwrap_or(&d.name).to_lowercase()),
+            req: Set(d.req.clone()),
+            kind: Set(d
+                .kind
+                .as_ref()
+                .map_or(DependencyKind::Normal.as_str(), DependencyKind::as_str)
+                .to_string()),
+            optional: Set(d.optional),
+        })
+        .collect::<Vec<_>>();
+
+    if !rows.is_empty() {
+        crate_dependency::Entity::insert_many(rows)
+            .exec(db_con)
+            .await?;
+    }
     Ok(())
 }
 
@@ -191,6 +229,74 @@ pub async fn add_crate_metadata<C: ConnectionTrait>(
     Ok(())
 }
 
+/// Add `count` downloads of a crate version to its downloads of `day`.
+pub async fn add_daily_downloads<C: ConnectionTrait>(
+    db_con: &C,
+    crate_meta_id: i64,
+    day: NaiveDate,
+    count: u64,
+) -> DbResult<()> {
+    let row = crate_download_daily::ActiveModel {
+        id: ActiveValue::default(),
+        crate_meta_fk: Set(crate_meta_id),
+        day: Set(day.format(DAY_FORMAT).to_string()),
+        downloads: Set(count as i64),
+    };
+    crate_download_daily::Entity::insert(row)
+        .on_conflict(
+            OnConflict::columns([
+                crate_download_daily::Column::CrateMetaFk,
+                crate_download_daily::Column::Day,
+            ])
+            .value(
+                crate_download_daily::Column::Downloads,
+                Expr::col((
+                    crate_download_daily::Entity,
+                    crate_download_daily::Column::Downloads,
+                ))
+                .add(count as i64),
+            )
+            .to_owned(),
+        )
+        .exec_without_returning(db_con)
+        .await?;
+    Ok(())
+}
+
+/// Downloads per crate version since `since`, by `crate_meta` id.
+pub async fn get_downloads_since<C: ConnectionTrait>(
+    db_con: &C,
+    crate_meta_ids: Vec<i64>,
+    since: NaiveDate,
+) -> DbResult<HashMap<i64, i64>> {
+    #[derive(FromQueryResult)]
+    struct Model {
+        crate_meta_fk: i64,
+        downloads: i64,
+    }
+
+    Ok(crate_download_daily::Entity::find()
+        .select_only()
+        .column(crate_download_daily::Column::CrateMetaFk)
+        // Postgres sums BIGINT columns as NUMERIC
+        .column_as(
+            Expr::from(Func::cast_as(
+                Func::sum(Expr::col(crate_download_daily::Column::Downloads)),
+                Alias::new("BIGINT"),
+            )),
+            "downloads",
+        )
+        .filter(crate_download_daily::Column::CrateMetaFk.is_in(crate_meta_ids))
+        .filter(crate_download_daily::Column::Day.gte(since.format(DAY_FORMAT).to_string()))
+        .group_by(crate_download_daily::Column::CrateMetaFk)
+        .into_model::<Model>()
+        .all(db_con)
+        .await?
+        .into_iter()
+        .map(|m| (m.crate_meta_fk, m.downloads))
+        .collect())
+}
+
 pub async fn update_crate_categories<C: ConnectionTrait>(
     db_con: &C,
     pub_metadata: &PublishMetadata,
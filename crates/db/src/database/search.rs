@@ -0,0 +1,241 @@
+//! Full-text search over crates.
+//!
+//! The `crate_search` table holds one search document per crate. On `SQLite`
+//! it is a FTS5 table ranked with `bm25`, on Postgres a weighted `tsvector`
+//! ranked with `ts_rank`. Documents are updated whenever a crate is
+//! published or a version is deleted.
+
+use kellnr_common::crate_overview::CrateOverview;
+use sea_orm::{ConnectionTrait, DatabaseBackend, FromQueryResult, Statement, Value};
+
+use super::{Database, escape_like_pattern};
+use crate::SearchQuery;
+use crate::provider::DbResult;
+
+/// Search document of a crate, selected from `krate k` joined with the
+/// `crate_meta cm` of its newest version.
+const SQLITE_DOCUMENT: &str =
+    "INSERT INTO crate_search (rowid, name, keywords, categories, description, readme)
+SELECT k.id,
+    k.original_name,
+    COALESCE((SELECT group_concat(ck.keyword, ' ') FROM crate_keyword_to_crate kc
+        JOIN crate_keyword ck ON ck.id = kc.keyword_fk WHERE kc.crate_fk = k.id), ''),
+    COALESCE((SELECT group_concat(c.category, ' ') FROM crate_category_to_crate cc
+        JOIN crate_category c ON c.id = cc.category_fk WHERE cc.crate_fk = k.id), ''),
+    COALESCE(k.description, ''),
+    COALESCE(cm.readme, '')
+FROM krate k
+LEFT JOIN crate_meta cm ON cm.crate_fk = k.id AND cm.version = k.max_version";
+
+const POSTGRES_DOCUMENT: &str = "INSERT INTO crate_search (crate_fk, document)
+SELECT k.id,
+    setweight(to_tsvector('english', k.original_name), 'A')
+    || setweight(to_tsvector('english', COALESCE((SELECT string_agg(ck.keyword, ' ')
+        FROM crate_keyword_to_crate kc JOIN crate_keyword ck ON ck.id = kc.keyword_fk
+        WHERE kc.crate_fk = k.id), '')), 'B')
+    || setweight(to_tsvector('english', COALESCE((SELECT string_agg(c.category, ' ')
+        FROM crate_category_to_crate cc JOIN crate_category c ON c.id = cc.category_fk
+        WHERE cc.crate_fk = k.id), '')), 'B')
+    || setweight(to_tsvector('english', COALESCE(k.description, '')), 'C')
+    || setweight(to_tsvector('english', COALESCE(cm.readme, '')), 'D')
+FROM krate k
+LEFT JOIN crate_meta cm ON cm.crate_fk = k.id AND cm.version = k.max_version";
+
+/// Replace the search document of a crate with its current data. Removes
+/// the document if the crate does not exist anymore.
+pub(crate) async fn update_search_document<C: ConnectionTrait>(
+    con: &C,
+    crate_id: i64,
+) -> DbResult<()> {
+    let backend = con.get_database_backend();
+    let (delete, insert) = match backend {
+        DatabaseBackend::Postgres => (
+            "DELETE FROM crate_search WHERE crate_fk = $1".to_string(),
+            format!("{POSTGRES_DOCUMENT} WHERE k.id = $1"),
+        ),
+        _ => (
+            "DELETE FROM crate_search WHERE rowid = ?".to_string(),
+            format!("{SQLITE_DOCUMENT} WHERE k.id = ?"),
+        ),
+    };
+    for sql in [delete, insert] {
+        con.execute_raw(Statement::from_sql_and_values(
+            backend,
+            sql,
+            [crate_id.into()],
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Remove all search documents.
+pub(crate) async fn clear_search_index<C: ConnectionTrait>(con: &C) -> DbResult<()> {
+    con.execute_unprepared("DELETE FROM crate_search").await?;
+    Ok(())
+}
+
+/// Build the search documents of all crates.
+pub(crate) async fn rebuild_search_index<C: ConnectionTrait>(con: &C) -> DbResult<()> {
+    clear_search_index(con).await?;
+    let insert = match con.get_database_backend() {
+        DatabaseBackend::Postgres => POSTGRES_DOCUMENT,
+        _ => SQLITE_DOCUMENT,
+    };
+    con.execute_unprepared(insert).await?;
+    Ok(())
+}
+
+/// SQL with positional parameters in the syntax of the backend.
+struct SqlBuilder {
+    backend: DatabaseBackend,
+    sql: String,
+    values: Vec<Value>,
+}
+
+impl SqlBuilder {
+    fn push(&mut self, sql: &str) {
+        self.sql.push_str(sql);
+    }
+
+    /// Append a placeholder for `value`.
+    fn bind(&mut self, value: impl Into<Value>) {
+        self.values.push(value.into());
+        if self.backend == DatabaseBackend::Postgres {
+            self.sql.push('$');
+            self.sql.push_str(&self.values.len().to_string());
+        } else {
+            self.sql.push('?');
+        }
+    }
+
+    fn build(self) -> Statement {
+        Statement::from_sql_and_values(self.backend, self.sql, self.values)
+    }
+}
+
+impl Database {
+    /// Hosted crates matching `query`, best matches first.
+    pub(super) async fn search_hosted_crates(
+        &self,
+        query: &SearchQuery,
+    ) -> DbResult<Vec<CrateOverview>> {
+        let backend = self.db_con.get_database_backend();
+        let mut sql = SqlBuilder {
+            backend,
+            sql: String::new(),
+            values: Vec::new(),
+        };
+        sql.push(
+            "SELECT k.original_name AS name, k.max_version AS version, \
+             k.last_updated AS date, k.total_downloads AS total_downloads, \
+             k.description AS description, cm.documentation AS documentation, \
+             false AS is_cache \
+             FROM krate k \
+             JOIN crate_meta cm ON cm.crate_fk = k.id AND cm.version = k.max_version",
+        );
+
+        let text = query.text.to_lowercase();
+        let terms = query.terms();
+        if !terms.is_empty() {
+            // Every term has to match, the last word may be incomplete
+            if backend == DatabaseBackend::Postgres {
+                let ts_query = terms
+                    .iter()
+                    .map(|t| format!("{t}:*"))
+                    .collect::<Vec<_>>()
+                    .join(" & ");
+                sql.push(
+                    " LEFT JOIN (SELECT crate_fk, \
+                         -ts_rank(document, to_tsquery('english', ",
+                );
+                sql.bind(ts_query.clone());
+                sql.push(
+                    ")) AS score FROM crate_search \
+                         WHERE document @@ to_tsquery('english', ",
+                );
+                sql.bind(ts_query);
+                sql.push(")) s ON s.crate_fk = k.id");
+            } else {
+                let fts_query = terms
+                    .iter()
+                    .map(|t| format!("\"{t}\"*"))
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                // Weights of name, keywords, categories, description, readme
+                sql.push(
+                    " LEFT JOIN (SELECT rowid AS crate_fk, \
+                         bm25(crate_search, 10.0, 5.0, 3.0, 2.0, 1.0) AS score \
+                         FROM crate_search WHERE crate_search MATCH ",
+                );
+                sql.bind(fts_query);
+                sql.push(") s ON s.crate_fk = k.id");
+            }
+        }
+
+        sql.push(" WHERE 1 = 1");
+        if !text.is_empty() {
+            // Substring matches on the name keep finding crates by a part of
+            // their name, like the plain name search did.
+            sql.push(" AND (k.name LIKE ");
+            sql.bind(format!("%{}%", escape_like_pattern(&text)));
+            sql.push(" ESCAPE '\\'");
+            if !terms.is_empty() {
+                sql.push(" OR s.crate_fk IS NOT NULL");
+            }
+            sql.push(")");
+        }
+        for keyword in &query.keywords {
+            sql.push(
+                " AND k.id IN (SELECT kc.crate_fk FROM crate_keyword_to_crate kc \
+                 JOIN crate_keyword ck ON ck.id = kc.keyword_fk WHERE LOWER(ck.keyword) = ",
+            );
+            sql.bind(keyword.clone());
+            sql.push(")");
+        }
+        for category in &query.categories {
+            sql.push(
+                " AND k.id IN (SELECT cc.crate_fk FROM crate_category_to_crate cc \
+                 JOIN crate_category c ON c.id = cc.category_fk WHERE LOWER(c.category) = ",
+            );
+            sql.bind(category.clone());
+            sql.push(")");
+        }
+        for owner in &query.owners {
+            sql.push(
+                " AND k.id IN (SELECT o.crate_fk FROM owner o \
+                 JOIN \"user\" u ON u.id = o.user_fk WHERE LOWER(u.name) = ",
+            );
+            sql.bind(owner.clone());
+            sql.push(")");
+        }
+        for license in &query.licenses {
+            // Matches a whole license identifier of an SPDX expression like
+            // `(MIT OR Apache-2.0)` or `MIT/Apache-2.0`, but not `MIT-0`. The
+            // parentheses and the legacy `/` separator are replaced by spaces,
+            // so every identifier is surrounded by spaces.
+            sql.push(
+                " AND ' ' || REPLACE(REPLACE(REPLACE(LOWER(COALESCE(cm.license, '')), \
+                 '(', ' '), ')', ' '), '/', ' ') || ' ' LIKE ",
+            );
+            sql.bind(format!("% {} %", escape_like_pattern(license)));
+            sql.push(" ESCAPE '\\'");
+        }
+
+        if text.is_empty() {
+            sql.push(" ORDER BY k.name");
+        } else {
+            sql.push(" ORDER BY CASE WHEN k.name = ");
+            sql.bind(text.clone());
+            sql.push(" THEN 0 ELSE 1 END");
+            if !terms.is_empty() {
+                sql.push(", COALESCE(s.score, 0)");
+            }
+            sql.push(", k.name");
+        }
+
+        Ok(CrateOverview::find_by_statement(sql.build())
+            .all(&self.db_con)
+            .await?)
+    }
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Version of a hosted crate that depends on another hosted crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Dependent {
+    /// Name of the depending crate
+    pub name: String,
+    /// Version of the depending crate
+    pub version: String,
+    /// Version requirement on the dependency, e.g. `^1.2`
+    pub req: String,
+    /// Dependency kind: `normal`, `build` or `dev`
+    pub kind: String,
+    pub optional: bool,
+    pub yanked: bool,
+    /// `true` if this is the newest version of the depending crate
+    pub is_latest: bool,
+}
@@ -1,6 +1,51 @@
 use std::path::PathBuf;
 
 use kellnr_common::normalized_name::NormalizedName;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::DbError;
+
+/// State of a doc build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DocBuildState {
+    Queued,
+    Building,
+    Succeeded,
+    Failed,
+}
+
+impl DocBuildState {
+    /// Whether the build has not finished yet.
+    pub fn is_pending(self) -> bool {
+        matches!(self, Self::Queued | Self::Building)
+    }
+}
+
+impl From<DocBuildState> for &str {
+    fn from(value: DocBuildState) -> Self {
+        match value {
+            DocBuildState::Queued => "queued",
+            DocBuildState::Building => "building",
+            DocBuildState::Succeeded => "succeeded",
+            DocBuildState::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<&str> for DocBuildState {
+    type Error = DbError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "queued" => Ok(Self::Queued),
+            "building" => Ok(Self::Building),
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            s => Err(DbError::InvalidDocBuildState(s.to_string())),
+        }
+    }
+}
 
 #[derive(Eq, PartialEq, Debug)]
 pub struct DocQueueEntry {
@@ -8,15 +53,28 @@ pub struct DocQueueEntry {
     pub normalized_name: NormalizedName,
     pub version: String,
     pub path: PathBuf,
+    pub state: DocBuildState,
+    /// Time the build was queued in UTC (`%Y-%m-%d %H:%M:%S`)
+    pub queued: String,
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub duration_ms: Option<i64>,
 }
 
-impl From<kellnr_entity::doc_queue::Model> for DocQueueEntry {
-    fn from(dqm: kellnr_entity::doc_queue::Model) -> Self {
-        Self {
+impl TryFrom<kellnr_entity::doc_queue::Model> for DocQueueEntry {
+    type Error = DbError;
+
+    fn try_from(dqm: kellnr_entity::doc_queue::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
             id: dqm.id,
             normalized_name: NormalizedName::from_unchecked(dqm.krate),
             version: dqm.version,
             path: PathBuf::from(dqm.path),
-        }
+            state: DocBuildState::try_from(dqm.state.as_str())?,
+            queued: dqm.queued,
+            started: dqm.started,
+            finished: dqm.finished,
+            duration_ms: dqm.duration_ms,
+        })
     }
 }
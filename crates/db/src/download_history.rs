@@ -0,0 +1,16 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Format of the days in the download history
+pub const DAY_FORMAT: &str = "%Y-%m-%d";
+
+/// Number of days, including today, counted as recent downloads
+pub const RECENT_DOWNLOADS_DAYS: u64 = 30;
+
+/// Downloads of a crate version on one day (UTC).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyDownloads {
+    pub version: String,
+    pub day: NaiveDate,
+    pub downloads: i64,
+}
@@ -54,8 +54,26 @@ pub enum DbError {
     MissingCratesIoIndexData(String),
     #[error("Webhook not found")]
     WebhookNotFound,
+    #[error("Webhook delivery not found")]
+    WebhookDeliveryNotFound,
+    #[error("Webhook dead letter not found")]
+    WebhookDeadLetterNotFound,
     #[error("Invalid webhook event {0}")]
     InvalidWebhookEvent(String),
+    #[error("Invalid webhook format {0}")]
+    InvalidWebhookFormat(String),
+    #[error("Invalid date {0}")]
+    InvalidDate(String),
     #[error("Invalid id {0}")]
     InvalidId(String),
+    #[error("Invalid token operation {0}")]
+    InvalidTokenOperation(String),
+    #[error("Invalid audit action {0}")]
+    InvalidAuditAction(String),
+    #[error("Invalid doc build state {0}")]
+    InvalidDocBuildState(String),
+    #[error("Unknown table in backup: {0}")]
+    UnknownBackupTable(String),
+    #[error("Cannot restore into a database that already contains {0}")]
+    RestoreTargetNotEmpty(&'static str),
 }
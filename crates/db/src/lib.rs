@@ -1,30 +1,42 @@
+mod advisory;
+mod audit;
 mod auth_token;
 mod con_string;
 mod crate_meta;
 mod crate_summary;
 mod database;
+mod dependent;
 mod doc_queue_entry;
 pub mod download_counter;
+pub mod download_history;
 pub mod error;
 mod group;
 mod krate;
 pub mod password;
 pub mod provider;
+mod search;
 mod tables;
 mod user;
 
 // Re-exports
+pub use advisory::{Advisory, AdvisoryMatch, CrateAdvisory};
+pub use audit::{AuditAction, AuditActor, AuditEntry, AuditFilter, AuditPage, NewAuditEntry};
 pub use auth_token::AuthToken;
 pub use con_string::{AdminUser, ConString, PgConString, SqliteConString};
 pub use crate_meta::CrateMeta;
 pub use crate_summary::CrateSummary;
-pub use doc_queue_entry::DocQueueEntry;
+pub use dependent::Dependent;
+pub use doc_queue_entry::{DocBuildState, DocQueueEntry};
 pub use group::Group;
 pub use krate::Crate;
 pub use provider::{
     ChannelInfo, DbProvider, OAuth2StateData, SessionInfo, ToolchainComponentInfo,
     ToolchainTargetInfo, ToolchainWithTargets, mock,
 };
+pub use search::SearchQuery;
 pub use user::User;
 
+pub use crate::database::backup::{
+    BACKUP_PAGE_SIZE, BACKUP_TABLES, CRATESIO_TABLES, DbExport, DbImport,
+};
 pub use crate::database::{Database, test_utils};
@@ -1,6 +1,7 @@
 use std::path::Path;
+use std::time::Duration;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use crate_meta::CrateMeta;
 use kellnr_common::crate_data::CrateData;
 use kellnr_common::crate_overview::CrateOverview;
@@ -10,13 +11,21 @@ use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::prefetch::Prefetch;
 use kellnr_common::publish_metadata::PublishMetadata;
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_common::version::Version;
-use kellnr_common::webhook::{Webhook, WebhookEvent, WebhookQueue};
+use kellnr_common::webhook::{
+    NewWebhookDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookEvent, WebhookQueue,
+    WebhookSecrets,
+};
 use sea_orm::prelude::async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::download_history::DailyDownloads;
 use crate::error::DbError;
-use crate::{AuthToken, CrateSummary, DocQueueEntry, Group, User, crate_meta};
+use crate::{
+    Advisory, AdvisoryMatch, AuditFilter, AuditPage, AuthToken, CrateSummary, Dependent,
+    DocBuildState, DocQueueEntry, Group, NewAuditEntry, User, crate_meta,
+};
 
 pub type DbResult<T> = Result<T, DbError>;
 
@@ -118,6 +127,8 @@ pub struct ChannelInfo {
 
 #[async_trait]
 pub trait DbProvider: Send + Sync {
+    /// Check that the database connection is alive.
+    async fn ping(&self) -> DbResult<()>;
     async fn get_last_updated_crate(&self) -> DbResult<Option<(OriginalName, Version)>>;
     async fn authenticate_user(&self, name: &str, pwd: &str) -> DbResult<User>;
     async fn increase_download_counter(
@@ -171,7 +182,17 @@ pub trait DbProvider: Send + Sync {
     async fn get_max_version_from_name(&self, crate_name: &NormalizedName) -> DbResult<Version>;
     async fn update_max_version(&self, crate_id: i64, version: &Version) -> DbResult<()>;
     async fn add_auth_token(&self, name: &str, token: &str, user: &str) -> DbResult<()>;
-    async fn get_user_from_token(&self, token: &str) -> DbResult<User>;
+    /// Add a token that is limited to the given operations, crates and lifetime.
+    async fn add_scoped_auth_token(
+        &self,
+        name: &str,
+        token: &str,
+        user: &str,
+        scopes: &TokenScopes,
+    ) -> DbResult<()>;
+    /// Look up a token and its owner. Expired tokens are treated like missing
+    /// ones and return [`DbError::TokenNotFound`].
+    async fn get_user_from_token(&self, token: &str) -> DbResult<(User, AuthToken)>;
     async fn get_user(&self, name: &str) -> DbResult<User>;
     async fn get_auth_tokens(&self, user_name: &str) -> DbResult<Vec<AuthToken>>;
     async fn delete_auth_token(&self, id: i32) -> DbResult<()>;
@@ -214,7 +235,27 @@ pub trait DbProvider: Send + Sync {
         path: &Path,
     ) -> DbResult<()>;
     async fn delete_doc_queue(&self, id: i64) -> DbResult<()>;
+    /// Builds which have not finished yet, in the order they were queued.
     async fn get_doc_queue(&self) -> DbResult<Vec<DocQueueEntry>>;
+    /// The latest `limit` builds, including finished ones, newest first.
+    async fn get_doc_builds(&self, limit: u64) -> DbResult<Vec<DocQueueEntry>>;
+    /// Builds of all versions of a crate, newest first.
+    async fn get_crate_doc_builds(&self, krate: &NormalizedName) -> DbResult<Vec<DocQueueEntry>>;
+    /// Latest build of a crate version.
+    async fn get_doc_build(
+        &self,
+        krate: &NormalizedName,
+        version: &Version,
+    ) -> DbResult<Option<DocQueueEntry>>;
+    async fn get_doc_build_log(&self, id: i64) -> DbResult<Option<String>>;
+    async fn start_doc_build(&self, id: i64) -> DbResult<()>;
+    async fn finish_doc_build(
+        &self,
+        id: i64,
+        state: DocBuildState,
+        duration: Duration,
+        log: &str,
+    ) -> DbResult<()>;
     async fn delete_crate(&self, krate: &NormalizedName, version: &Version) -> DbResult<()>;
     async fn get_crate_meta_list(&self, crate_name: &NormalizedName) -> DbResult<Vec<CrateMeta>>;
     async fn update_last_updated(&self, id: i64, last_updated: &DateTime<Utc>) -> DbResult<()>;
@@ -223,6 +264,9 @@ pub trait DbProvider: Send + Sync {
         contains: &str,
         cache: bool,
     ) -> DbResult<Vec<CrateOverview>>;
+    /// Ranked full-text search, see [`crate::SearchQuery`] for the syntax.
+    /// Cached crates.io crates are only matched by name.
+    async fn search_crates(&self, query: &str, cache: bool) -> DbResult<Vec<CrateOverview>>;
     async fn get_crate_overview_list(
         &self,
         limit: u64,
@@ -268,23 +312,41 @@ pub trait DbProvider: Send + Sync {
     async fn get_cratesio_index_update_list(&self) -> DbResult<Vec<CratesioPrefetchMsg>>;
     async fn unyank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()>;
     async fn yank_crate(&self, crate_name: &NormalizedName, version: &Version) -> DbResult<()>;
-    async fn register_webhook(&self, webhook: Webhook) -> DbResult<String>;
+    /// Register a webhook, whose deliveries are signed with `secret`.
+    async fn register_webhook(&self, webhook: Webhook, secret: Option<String>) -> DbResult<String>;
     async fn delete_webhook(&self, id: &str) -> DbResult<()>;
     async fn get_webhook(&self, id: &str) -> DbResult<Webhook>;
     async fn get_all_webhooks(&self) -> DbResult<Vec<Webhook>>;
+    async fn get_webhook_secrets(&self, id: &str) -> DbResult<WebhookSecrets>;
+    async fn set_webhook_secrets(&self, id: &str, secrets: &WebhookSecrets) -> DbResult<()>;
+    /// Set the start of the current series of failed deliveries and the time
+    /// the webhook was disabled. `None` for both enables a webhook again.
+    async fn set_webhook_health(
+        &self,
+        id: &str,
+        failing_since: Option<DateTime<Utc>>,
+        disabled_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()>;
     /// Creates a new webhook queue entry for each register webhook
-    /// matching the given event. `Next_attempt` is set to current time,
+    /// accepting the given event about the crate `crate_name`, see
+    /// [`Webhook::accepts`]. Disabled webhooks are skipped. `Next_attempt` is set to current time,
     ///  in order to trigger immediate dispatch.
+    /// `crate_groups` are the groups of the crate. They are looked up if `None`,
+    /// which only works as long as the crate exists.
     async fn add_webhook_queue(
         &self,
         event: WebhookEvent,
+        crate_name: Option<String>,
+        crate_groups: Option<Vec<String>>,
         payload: serde_json::Value,
     ) -> DbResult<()>;
-    /// Extracts webhook queue entries with `next_attempt` at or earlier than provided timestamp.
+    /// Extracts webhook queue entries with `next_attempt` at or earlier than provided timestamp,
+    /// ordered by `next_attempt`. Dead letters and entries of disabled webhooks are left out.
     async fn get_pending_webhook_queue_entries(
         &self,
         timestamp: DateTime<Utc>,
     ) -> DbResult<Vec<WebhookQueue>>;
+    /// Record a failed attempt of a queue entry and schedule the next one.
     async fn update_webhook_queue(
         &self,
         id: &str,
@@ -292,6 +354,74 @@ pub trait DbProvider: Send + Sync {
         next_attempt: DateTime<Utc>,
     ) -> DbResult<()>;
     async fn delete_webhook_queue(&self, id: &str) -> DbResult<()>;
+    /// Record the last failed attempt of a queue entry and move it to the dead letters.
+    async fn dead_letter_webhook_queue(
+        &self,
+        id: &str,
+        last_attempt: DateTime<Utc>,
+    ) -> DbResult<()>;
+    /// Dead letters of a webhook, newest first.
+    async fn get_webhook_dead_letters(&self, webhook_id: &str) -> DbResult<Vec<WebhookDeadLetter>>;
+    /// Queue a dead letter again for immediate delivery, with a new series of attempts.
+    async fn retry_webhook_dead_letter(&self, id: &str) -> DbResult<()>;
+    /// Remove dead letters given up before `before`. Returns the number of removed dead letters.
+    async fn delete_webhook_dead_letters_before(&self, before: DateTime<Utc>) -> DbResult<u64>;
+    /// Number of webhook deliveries in the queue, including retries that are not due yet,
+    /// but without dead letters.
+    async fn get_webhook_queue_length(&self) -> DbResult<u64>;
+    /// Record an attempt to deliver a webhook message. The attempt number
+    /// follows the attempts already recorded for the message.
+    async fn add_webhook_delivery(
+        &self,
+        delivery: &NewWebhookDelivery,
+    ) -> DbResult<WebhookDelivery>;
+    /// Latest `limit` deliveries of a webhook, newest first.
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: u64,
+    ) -> DbResult<Vec<WebhookDelivery>>;
+    async fn get_webhook_delivery(&self, id: &str) -> DbResult<WebhookDelivery>;
+    /// Remove deliveries recorded before `before`. Returns the number of removed deliveries.
+    async fn delete_webhook_deliveries_before(&self, before: DateTime<Utc>) -> DbResult<u64>;
+
+    // Audit log methods
+    /// Append an entry to the audit log. Entries are never changed afterwards.
+    async fn add_audit_entry(&self, entry: &NewAuditEntry) -> DbResult<()>;
+    /// Get one page of the audit log entries matching `filter`, newest first.
+    async fn get_audit_entries(
+        &self,
+        filter: &AuditFilter,
+        limit: u64,
+        offset: u64,
+    ) -> DbResult<AuditPage>;
+
+    // Advisory methods
+    /// Replace all stored advisories with `advisories`. Returns the ids of the
+    /// advisories that were not stored before.
+    async fn replace_advisories(&self, advisories: &[Advisory]) -> DbResult<Vec<String>>;
+    /// Get all advisories of a crate.
+    async fn get_advisories(&self, crate_name: &NormalizedName) -> DbResult<Vec<Advisory>>;
+    /// Match all advisories against the hosted and cached crate versions.
+    /// Only advisories that affect at least one known version are returned.
+    async fn get_advisory_matches(&self) -> DbResult<Vec<AdvisoryMatch>>;
+
+    // Download history methods
+    /// Get the per-day downloads of all versions of a crate since `since`,
+    /// oldest first.
+    async fn get_daily_downloads(
+        &self,
+        crate_name: &NormalizedName,
+        since: NaiveDate,
+    ) -> DbResult<Vec<DailyDownloads>>;
+    /// Delete the per-day downloads of all days before `day`. Returns the
+    /// number of deleted rows.
+    async fn delete_daily_downloads_before(&self, day: NaiveDate) -> DbResult<u64>;
+
+    // Reverse dependency methods
+    /// Get all hosted crate versions that depend on the crate, ordered by
+    /// crate name and newest version first.
+    async fn get_dependents(&self, crate_name: &NormalizedName) -> DbResult<Vec<Dependent>>;
 
     // `OAuth2` identity methods
     /// Look up a user by their `OAuth2` identity (issuer + subject)
@@ -419,6 +549,10 @@ pub mod mock {
         #[async_trait]
         impl DbProvider for Db {
 
+            async fn ping(&self) -> DbResult<()> {
+                unimplemented!()
+            }
+
             async fn get_last_updated_crate(&self) -> DbResult<Option<(OriginalName, Version)>> {
                 unimplemented!()
             }
@@ -557,7 +691,11 @@ pub mod mock {
                 unimplemented!()
             }
 
-            async fn get_user_from_token(&self, _token: &str) -> DbResult<User> {
+            async fn add_scoped_auth_token(&self, _name: &str, _token: &str, _user: &str, _scopes: &TokenScopes) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_user_from_token(&self, _token: &str) -> DbResult<(User, AuthToken)> {
                 unimplemented!()
             }
 
@@ -629,6 +767,30 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn get_doc_builds(&self, limit: u64) -> DbResult<Vec<DocQueueEntry>> {
+                unimplemented!()
+            }
+
+            async fn get_crate_doc_builds(&self, krate: &NormalizedName) -> DbResult<Vec<DocQueueEntry>> {
+                unimplemented!()
+            }
+
+            async fn get_doc_build(&self, krate: &NormalizedName, version: &Version) -> DbResult<Option<DocQueueEntry>> {
+                unimplemented!()
+            }
+
+            async fn get_doc_build_log(&self, id: i64) -> DbResult<Option<String>> {
+                unimplemented!()
+            }
+
+            async fn start_doc_build(&self, id: i64) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn finish_doc_build(&self, id: i64, state: DocBuildState, duration: Duration, log: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+
             async fn delete_crate(&self, krate: &NormalizedName, version: &Version) -> DbResult<()> {
                 unimplemented!()
             }
@@ -649,6 +811,10 @@ pub mod mock {
                 unimplemented!()
             }
 
+            async fn search_crates(&self, query: &str, cache: bool) -> DbResult<Vec<CrateOverview>> {
+                unimplemented!()
+            }
+
             async fn get_crate_overview_list(&self, limit: u64, offset: u64, cache: bool) -> DbResult<Vec<CrateOverview >> {
                 unimplemented!()
             }
@@ -748,7 +914,8 @@ pub mod mock {
 
             async fn register_webhook(
                 &self,
-                webhook: Webhook
+                webhook: Webhook,
+                secret: Option<String>
             ) -> DbResult<String> {
                 unimplemented!()
             }
@@ -761,7 +928,16 @@ pub mod mock {
             async fn get_all_webhooks(&self) -> DbResult<Vec<Webhook>> {
                 unimplemented!()
             }
-            async fn add_webhook_queue(&self, event: WebhookEvent, payload: serde_json::Value) -> DbResult<()> {
+            async fn get_webhook_secrets(&self, id: &str) -> DbResult<WebhookSecrets> {
+                unimplemented!()
+            }
+            async fn set_webhook_secrets(&self, id: &str, secrets: &WebhookSecrets) -> DbResult<()> {
+                unimplemented!()
+            }
+            async fn set_webhook_health(&self, id: &str, failing_since: Option<DateTime<Utc>>, disabled_at: Option<DateTime<Utc>>) -> DbResult<()> {
+                unimplemented!()
+            }
+            async fn add_webhook_queue(&self, event: WebhookEvent, crate_name: Option<String>, crate_groups: Option<Vec<String>>, payload: serde_json::Value) -> DbResult<()> {
                 unimplemented!()
             }
             async fn get_pending_webhook_queue_entries(&self, timestamp: DateTime<Utc>) -> DbResult<Vec<WebhookQueue>> {
@@ -773,6 +949,74 @@ pub mod mock {
             async fn delete_webhook_queue(&self, id: &str) -> DbResult<()> {
                 unimplemented!()
             }
+            async fn dead_letter_webhook_queue(&self, id: &str, last_attempt: DateTime<Utc>) -> DbResult<()> {
+                unimplemented!()
+            }
+            async fn get_webhook_dead_letters(&self, webhook_id: &str) -> DbResult<Vec<WebhookDeadLetter>> {
+                unimplemented!()
+            }
+            async fn retry_webhook_dead_letter(&self, id: &str) -> DbResult<()> {
+                unimplemented!()
+            }
+            async fn delete_webhook_dead_letters_before(&self, before: DateTime<Utc>) -> DbResult<u64> {
+                unimplemented!()
+            }
+            async fn get_webhook_queue_length(&self) -> DbResult<u64> {
+                unimplemented!()
+            }
+            async fn add_webhook_delivery(&self, delivery: &NewWebhookDelivery) -> DbResult<WebhookDelivery> {
+                unimplemented!()
+            }
+            async fn get_webhook_deliveries(&self, webhook_id: &str, limit: u64) -> DbResult<Vec<WebhookDelivery>> {
+                unimplemented!()
+            }
+            async fn get_webhook_delivery(&self, id: &str) -> DbResult<WebhookDelivery> {
+                unimplemented!()
+            }
+            async fn delete_webhook_deliveries_before(&self, before: DateTime<Utc>) -> DbResult<u64> {
+                unimplemented!()
+            }
+
+            async fn add_audit_entry(&self, entry: &NewAuditEntry) -> DbResult<()> {
+                unimplemented!()
+            }
+
+            async fn get_audit_entries(
+                &self,
+                filter: &AuditFilter,
+                limit: u64,
+                offset: u64,
+            ) -> DbResult<AuditPage> {
+                unimplemented!()
+            }
+
+            async fn replace_advisories(&self, advisories: &[Advisory]) -> DbResult<Vec<String>> {
+                unimplemented!()
+            }
+
+            async fn get_advisories(&self, crate_name: &NormalizedName) -> DbResult<Vec<Advisory>> {
+                unimplemented!()
+            }
+
+            async fn get_advisory_matches(&self) -> DbResult<Vec<AdvisoryMatch>> {
+                unimplemented!()
+            }
+
+            async fn get_daily_downloads(
+                &self,
+                crate_name: &NormalizedName,
+                since: NaiveDate,
+            ) -> DbResult<Vec<DailyDownloads>> {
+                unimplemented!()
+            }
+
+            async fn delete_daily_downloads_before(&self, day: NaiveDate) -> DbResult<u64> {
+                unimplemented!()
+            }
+
+            async fn get_dependents(&self, crate_name: &NormalizedName) -> DbResult<Vec<Dependent>> {
+                unimplemented!()
+            }
 
             async fn get_user_by_oauth2_identity(
                 &self,
@@ -0,0 +1,98 @@
+/// Parsed crate search query.
+///
+/// Words of the form `keyword:<value>`, `category:<value>`, `owner:<value>`
+/// or `license:<value>` restrict the results. All other words are searched
+/// in the crate name, keywords, categories, description and readme.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Free text without the filters
+    pub text: String,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub owners: Vec<String>,
+    pub licenses: Vec<String>,
+}
+
+impl SearchQuery {
+    #[must_use]
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut text = Vec::new();
+        for word in query.split_whitespace() {
+            let filter = word.split_once(':').and_then(|(key, value)| {
+                let list = match key.to_lowercase().as_str() {
+                    "keyword" => &mut parsed.keywords,
+                    "category" => &mut parsed.categories,
+                    "owner" => &mut parsed.owners,
+                    "license" => &mut parsed.licenses,
+                    _ => return None,
+                };
+                Some((list, value))
+            });
+            match filter {
+                Some((list, value)) if !value.is_empty() => list.push(value.to_lowercase()),
+                // An empty filter like `keyword:` is ignored
+                Some(_) => {}
+                None => text.push(word),
+            }
+        }
+        parsed.text = text.join(" ");
+        parsed
+    }
+
+    /// `true` if the query contains any filter.
+    #[must_use]
+    pub fn has_filters(&self) -> bool {
+        !(self.keywords.is_empty()
+            && self.categories.is_empty()
+            && self.owners.is_empty()
+            && self.licenses.is_empty())
+    }
+
+    /// Alphanumeric words of the free text, which are matched against the
+    /// full-text index. Punctuation separates words, like in the index.
+    #[must_use]
+    pub fn terms(&self) -> Vec<String> {
+        self.text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_splits_filters_from_text() {
+        let query = SearchQuery::parse("postgres  pool keyword:Async owner:alice license:MIT");
+
+        assert_eq!("postgres pool", query.text);
+        assert_eq!(vec!["async"], query.keywords);
+        assert_eq!(vec!["alice"], query.owners);
+        assert_eq!(vec!["mit"], query.licenses);
+        assert!(query.categories.is_empty());
+        assert!(query.has_filters());
+    }
+
+    #[test]
+    fn parse_keeps_unknown_prefixes_and_ignores_empty_filters() {
+        let query = SearchQuery::parse("foo:bar category:");
+
+        assert_eq!("foo:bar", query.text);
+        assert!(!query.has_filters());
+        assert_eq!(vec!["foo", "bar"], query.terms());
+    }
+
+    #[test]
+    fn terms_split_crate_names_into_words() {
+        let query = SearchQuery::parse("tokio-postgres deadpool_redis");
+
+        assert_eq!(
+            vec!["tokio", "postgres", "deadpool", "redis"],
+            query.terms()
+        );
+    }
+}
@@ -9,16 +9,26 @@ use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::prefetch::Prefetch;
 use kellnr_common::publish_metadata::{PublishMetadata, RegistryDep};
+use kellnr_common::token_scope::{TokenOperation, TokenScopes};
 use kellnr_common::version::Version;
-use kellnr_common::webhook::{Webhook, WebhookEvent};
+use kellnr_common::webhook::{NewWebhookDelivery, Webhook, WebhookEvent, WebhookSecrets};
+use kellnr_db::error::DbError;
 use kellnr_db::password::hash_pwd;
 use kellnr_db::provider::PrefetchState;
 use kellnr_db::test_utils::*;
-use kellnr_db::{DbProvider, DocQueueEntry};
+use kellnr_db::{
+    Advisory, AuditAction, AuditActor, AuditFilter, DbProvider, DocBuildState, DocQueueEntry,
+    NewAuditEntry,
+};
 use kellnr_db_testcontainer::db_test;
 use serde_json::json;
 mod image;
 
+#[db_test]
+async fn ping_succeeds_on_open_connection(test_db: &kellnr_db::Database) {
+    test_db.ping().await.unwrap();
+}
+
 #[db_test]
 async fn get_total_unique_crates_returns_number_of_unique_crates(test_db: &kellnr_db::Database) {
     let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
@@ -765,9 +775,52 @@ async fn get_user_from_token_works(test_db: &kellnr_db::Database) {
         .await
         .unwrap();
 
-    let user = test_db.get_user_from_token("mytoken1").await.unwrap();
+    let (user, token) = test_db.get_user_from_token("mytoken1").await.unwrap();
 
     assert_eq!("admin", user.name);
+    assert_eq!("test1", token.name);
+    assert_eq!(TokenScopes::default(), token.scopes);
+}
+
+#[db_test]
+async fn get_user_from_scoped_token_returns_scopes(test_db: &kellnr_db::Database) {
+    let expires_at = Utc.with_ymd_and_hms(2100, 1, 2, 3, 4, 5).unwrap();
+    let scopes = TokenScopes {
+        operations: vec![TokenOperation::Publish, TokenOperation::Yank],
+        crates: vec!["foo".to_string(), "bar-*".to_string()],
+        expires_at: Some(expires_at),
+    };
+    test_db
+        .add_scoped_auth_token("ci", "scopedtoken", "admin", &scopes)
+        .await
+        .unwrap();
+
+    let (user, token) = test_db.get_user_from_token("scopedtoken").await.unwrap();
+    let tokens = test_db.get_auth_tokens("admin").await.unwrap();
+
+    assert_eq!("admin", user.name);
+    assert_eq!(scopes, token.scopes);
+    assert_eq!(
+        scopes,
+        tokens.iter().find(|t| t.name == "ci").unwrap().scopes
+    );
+}
+
+#[db_test]
+async fn get_user_from_expired_token_fails(test_db: &kellnr_db::Database) {
+    let scopes = TokenScopes {
+        expires_at: Some(Utc::now() - TimeDelta::hours(1)),
+        ..TokenScopes::default()
+    };
+    test_db
+        .add_scoped_auth_token("expired", "expiredtoken", "admin", &scopes)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        test_db.get_user_from_token("expiredtoken").await,
+        Err(DbError::TokenNotFound)
+    ));
 }
 
 #[db_test]
@@ -795,7 +848,7 @@ async fn auth_token_insert_and_read(test_db: &kellnr_db::Database) {
         .add_auth_token("test", "mytoken", "admin")
         .await
         .unwrap();
-    let user = test_db.get_user_from_token("mytoken").await.unwrap();
+    let (user, _) = test_db.get_user_from_token("mytoken").await.unwrap();
 
     assert_eq!("admin", user.name);
 }
@@ -1189,22 +1242,102 @@ async fn add_and_get_doc_queue_entries(test_db: &kellnr_db::Database) {
             id: 1,
             normalized_name: NormalizedName::from_unchecked("my_crate".to_string()),
             version: "1.0.0".to_string(),
-            path: PathBuf::from("/tmp/foo")
+            path: PathBuf::from("/tmp/foo"),
+            state: DocBuildState::Queued,
+            queued: queue_entries[0].queued.clone(),
+            started: None,
+            finished: None,
+            duration_ms: None,
         },
         queue_entries[0]
     );
+    assert!(!queue_entries[0].queued.is_empty());
 
     assert_eq!(
         DocQueueEntry {
             id: 2,
             normalized_name: NormalizedName::from_unchecked("my_crate2".to_string()),
             version: "2.0.0".to_string(),
-            path: PathBuf::from("/tmp/bar")
+            path: PathBuf::from("/tmp/bar"),
+            state: DocBuildState::Queued,
+            queued: queue_entries[1].queued.clone(),
+            started: None,
+            finished: None,
+            duration_ms: None,
         },
         queue_entries[1]
     );
 }
 
+#[db_test]
+async fn doc_builds_record_state_and_log(test_db: &kellnr_db::Database) {
+    let name = NormalizedName::from_unchecked("my_crate".to_string());
+    let version = Version::try_from("1.0.0").unwrap();
+    test_db
+        .add_doc_queue(&name, &version, &PathBuf::from("/tmp/foo"))
+        .await
+        .unwrap();
+    test_db
+        .add_doc_queue(
+            &NormalizedName::from_unchecked("my_crate2".to_string()),
+            &Version::try_from("2.0.0").unwrap(),
+            &PathBuf::from("/tmp/bar"),
+        )
+        .await
+        .unwrap();
+
+    test_db.start_doc_build(1).await.unwrap();
+    let build = test_db
+        .get_doc_build(&name, &version)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(DocBuildState::Building, build.state);
+    assert!(build.started.is_some());
+    // Interrupted builds are still pending
+    assert_eq!(2, test_db.get_doc_queue().await.unwrap().len());
+
+    test_db
+        .finish_doc_build(
+            1,
+            DocBuildState::Failed,
+            std::time::Duration::from_millis(1500),
+            "error: broken",
+        )
+        .await
+        .unwrap();
+
+    let build = test_db
+        .get_doc_build(&name, &version)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(DocBuildState::Failed, build.state);
+    assert!(build.finished.is_some());
+    assert_eq!(Some(1500), build.duration_ms);
+    assert_eq!(
+        Some("error: broken".to_string()),
+        test_db.get_doc_build_log(1).await.unwrap()
+    );
+    let queue = test_db.get_doc_queue().await.unwrap();
+    assert_eq!(1, queue.len());
+    assert_eq!(2, queue[0].id);
+    let builds = test_db.get_doc_builds(10).await.unwrap();
+    assert_eq!(vec![2, 1], builds.iter().map(|b| b.id).collect::<Vec<_>>());
+    assert_eq!(1, test_db.get_doc_builds(1).await.unwrap().len());
+    assert_eq!(1, test_db.get_crate_doc_builds(&name).await.unwrap().len());
+
+    // Queueing the version again replaces the finished build
+    test_db
+        .add_doc_queue(&name, &version, &PathBuf::from("/tmp/baz"))
+        .await
+        .unwrap();
+    let builds = test_db.get_crate_doc_builds(&name).await.unwrap();
+    assert_eq!(1, builds.len());
+    assert_eq!(DocBuildState::Queued, builds[0].state);
+    assert_eq!(None, test_db.get_doc_build_log(1).await.unwrap());
+}
+
 #[db_test]
 async fn delete_doc_queue_entry(test_db: &kellnr_db::Database) {
     test_db
@@ -1230,15 +1363,12 @@ async fn delete_doc_queue_entry(test_db: &kellnr_db::Database) {
 
     let queue_entries = test_db.get_doc_queue().await.unwrap();
     assert_eq!(1, queue_entries.len());
+    assert_eq!(2, queue_entries[0].id);
     assert_eq!(
-        DocQueueEntry {
-            id: 2,
-            normalized_name: NormalizedName::from_unchecked("my_crate2".to_string()),
-            version: "2.0.0".to_string(),
-            path: PathBuf::from("/tmp/bar")
-        },
-        queue_entries[0]
+        NormalizedName::from_unchecked("my_crate2".to_string()),
+        queue_entries[0].normalized_name
     );
+    assert_eq!(PathBuf::from("/tmp/bar"), queue_entries[0].path);
 }
 
 #[db_test]
@@ -1493,6 +1623,295 @@ async fn search_in_crate_name_found_match(test_db: &kellnr_db::Database) {
     assert_eq!(expected, search_results);
 }
 
+async fn add_search_crate(
+    test_db: &kellnr_db::Database,
+    name: &str,
+    description: &str,
+    readme: &str,
+    keywords: &[&str],
+    license: &str,
+) {
+    let pm = PublishMetadata {
+        name: name.to_string(),
+        vers: "1.0.0".to_string(),
+        description: Some(description.to_string()),
+        readme: Some(readme.to_string()),
+        keywords: keywords.iter().map(ToString::to_string).collect(),
+        categories: vec!["database".to_string()],
+        license: Some(license.to_string()),
+        ..PublishMetadata::default()
+    };
+    test_db
+        .add_crate(&pm, "cksum", &Utc::now(), "admin")
+        .await
+        .unwrap();
+}
+
+async fn search_names(test_db: &kellnr_db::Database, query: &str) -> Vec<String> {
+    test_db
+        .search_crates(query, false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.name)
+        .collect()
+}
+
+#[db_test]
+async fn search_crates_ranks_full_text_matches(test_db: &kellnr_db::Database) {
+    add_search_crate(
+        test_db,
+        "deadpool-postgres",
+        "Dead simple async pool for tokio-postgres",
+        "",
+        &["pool", "async"],
+        "MIT OR Apache-2.0",
+    )
+    .await;
+    add_search_crate(
+        test_db,
+        "pg-utils",
+        "Helpers",
+        "Works with postgres connection pools",
+        &[],
+        "MIT",
+    )
+    .await;
+    add_search_crate(
+        test_db,
+        "redis-pool",
+        "Connection pool for redis",
+        "",
+        &["pool"],
+        "Apache-2.0",
+    )
+    .await;
+
+    // Words of the description rank higher than words of the readme
+    assert_eq!(
+        vec!["deadpool-postgres", "pg-utils"],
+        search_names(test_db, "postgres pool").await
+    );
+    // Incomplete words match as prefix
+    assert_eq!(
+        vec!["pg-utils"],
+        search_names(test_db, "postgres conn").await
+    );
+    // An exact name match comes first
+    assert_eq!("redis-pool", search_names(test_db, "redis-pool").await[0]);
+    // Parts of a name are found like with the name search
+    assert_eq!(vec!["pg-utils"], search_names(test_db, "g-ut").await);
+
+    // Deleted crates are removed from the index
+    test_db
+        .delete_crate(
+            &NormalizedName::from_unchecked("pg-utils".to_string()),
+            &Version::try_from("1.0.0").unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["deadpool-postgres"],
+        search_names(test_db, "postgres pool").await
+    );
+}
+
+#[db_test]
+async fn search_crates_applies_filters(test_db: &kellnr_db::Database) {
+    add_search_crate(
+        test_db,
+        "deadpool-postgres",
+        "Pool",
+        "",
+        &["pool", "async"],
+        "MIT OR Apache-2.0",
+    )
+    .await;
+    add_search_crate(test_db, "pg-utils", "Pool helpers", "", &[], "MIT").await;
+    add_search_crate(test_db, "redis-pool", "Pool", "", &["pool"], "Apache-2.0").await;
+    add_search_crate(test_db, "mysql-utils", "Tools", "", &[], "(MIT-0 OR Zlib)").await;
+    add_search_crate(test_db, "sqlite-utils", "Tools", "", &[], "Zlib/MIT").await;
+
+    assert_eq!(
+        vec!["deadpool-postgres", "redis-pool"],
+        search_names(test_db, "keyword:pool").await
+    );
+    assert_eq!(
+        vec!["deadpool-postgres"],
+        search_names(test_db, "keyword:pool keyword:Async").await
+    );
+    assert_eq!(
+        vec!["deadpool-postgres", "redis-pool"],
+        search_names(test_db, "license:apache-2.0").await
+    );
+    assert_eq!(
+        vec!["pg-utils"],
+        search_names(test_db, "helpers license:mit").await
+    );
+    // Only whole license identifiers match, `MIT-0` is not `MIT`
+    assert_eq!(
+        vec!["deadpool-postgres", "pg-utils", "sqlite-utils"],
+        search_names(test_db, "license:mit").await
+    );
+    assert_eq!(
+        vec!["mysql-utils", "sqlite-utils"],
+        search_names(test_db, "license:zlib").await
+    );
+    assert_eq!(
+        vec![
+            "deadpool-postgres",
+            "mysql-utils",
+            "pg-utils",
+            "redis-pool",
+            "sqlite-utils"
+        ],
+        search_names(test_db, "category:database owner:admin").await
+    );
+    assert!(search_names(test_db, "owner:nobody").await.is_empty());
+    assert!(search_names(test_db, "category:network").await.is_empty());
+}
+
+#[db_test]
+async fn daily_downloads_are_counted_per_version(test_db: &kellnr_db::Database) {
+    for vers in ["1.0.0", "2.0.0"] {
+        let pm = PublishMetadata {
+            name: "crate1".to_string(),
+            vers: vers.to_string(),
+            ..PublishMetadata::default()
+        };
+        test_db
+            .add_crate(&pm, "cksum", &Utc::now(), "admin")
+            .await
+            .unwrap();
+    }
+    let name = NormalizedName::from_unchecked("crate1".to_string());
+    let v1 = Version::try_from("1.0.0").unwrap();
+    let v2 = Version::try_from("2.0.0").unwrap();
+    test_db.increase_download_counter(&name, &v1).await.unwrap();
+    test_db
+        .increase_download_counter_by(&name, &v1, 4)
+        .await
+        .unwrap();
+    test_db
+        .increase_download_counter_by(&name, &v2, 2)
+        .await
+        .unwrap();
+
+    let today = Utc::now().date_naive();
+    let mut downloads = test_db.get_daily_downloads(&name, today).await.unwrap();
+    downloads.sort_by(|a, b| a.version.cmp(&b.version));
+    assert_eq!(
+        vec![("1.0.0", today, 5), ("2.0.0", today, 2)],
+        downloads
+            .iter()
+            .map(|d| (d.version.as_str(), d.day, d.downloads))
+            .collect::<Vec<_>>()
+    );
+
+    let crate_data = test_db.get_crate_data(&name).await.unwrap();
+    assert_eq!(7, crate_data.recent_downloads);
+    assert_eq!(2, crate_data.versions[0].recent_downloads);
+    assert_eq!(5, crate_data.versions[1].recent_downloads);
+
+    assert_eq!(
+        0,
+        test_db.delete_daily_downloads_before(today).await.unwrap()
+    );
+    assert_eq!(
+        2,
+        test_db
+            .delete_daily_downloads_before(today + TimeDelta::days(1))
+            .await
+            .unwrap()
+    );
+    assert!(
+        test_db
+            .get_daily_downloads(&name, today)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+fn registry_dep(name: &str, req: &str, kind: &str) -> RegistryDep {
+    RegistryDep {
+        name: name.to_string(),
+        version_req: req.to_string(),
+        features: None,
+        optional: false,
+        default_features: true,
+        target: None,
+        kind: Some(kind.to_string()),
+        registry: None,
+        explicit_name_in_toml: None,
+    }
+}
+
+#[db_test]
+async fn get_dependents_indexes_hosted_dependencies(test_db: &kellnr_db::Database) {
+    let publish = |name: &str, vers: &str, deps: Vec<RegistryDep>| PublishMetadata {
+        name: name.to_string(),
+        vers: vers.to_string(),
+        deps,
+        ..PublishMetadata::default()
+    };
+    let renamed = RegistryDep {
+        explicit_name_in_toml: Some("base_alias".to_string()),
+        ..registry_dep("Base", "^0.9", "dev")
+    };
+    let cratesio = RegistryDep {
+        registry: Some("https://github.com/rust-lang/crates.io-index".to_string()),
+        ..registry_dep("base", "^1", "normal")
+    };
+    for pm in [
+        publish("base", "1.0.0", vec![]),
+        publish("app", "0.1.0", vec![registry_dep("base", "^1.0", "normal")]),
+        publish("app", "0.2.0", vec![registry_dep("base", "^1.0", "normal")]),
+        publish("tool", "1.0.0", vec![renamed, cratesio]),
+    ] {
+        test_db
+            .add_crate(&pm, "cksum", &Utc::now(), "admin")
+            .await
+            .unwrap();
+    }
+
+    let base = NormalizedName::from_unchecked("base".to_string());
+    let dependents = test_db.get_dependents(&base).await.unwrap();
+    assert_eq!(
+        vec![
+            ("app", "0.2.0", "^1.0", "normal", true),
+            ("app", "0.1.0", "^1.0", "normal", false),
+            ("tool", "1.0.0", "^0.9", "dev", true),
+        ],
+        dependents
+            .iter()
+            .map(|d| (
+                d.name.as_str(),
+                d.version.as_str(),
+                d.req.as_str(),
+                d.kind.as_str(),
+                d.is_latest
+            ))
+            .collect::<Vec<_>>()
+    );
+
+    test_db
+        .delete_crate(
+            &NormalizedName::from_unchecked("app".to_string()),
+            &Version::try_from("0.1.0").unwrap(),
+        )
+        .await
+        .unwrap();
+    let versions = test_db
+        .get_dependents(&base)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|d| d.version)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["0.2.0", "1.0.0"], versions);
+}
+
 #[db_test]
 async fn get_crate_overview_list(test_db: &kellnr_db::Database) {
     let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
@@ -1782,6 +2201,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
             owners: vec!["owner1".to_string(), "owner2".to_string()],
             max_version: pm1_v1.vers.clone(),
             total_downloads: 0,
+            recent_downloads: 0,
             last_updated: created_string.clone(),
             homepage: pm1_v1.homepage.clone(),
             description: pm1_v1.description.clone(),
@@ -1793,6 +2213,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                 version: pm1_v1.vers.clone(),
                 created: created_string.clone(),
                 downloads: 0,
+                recent_downloads: 0,
                 documentation: pm1_v1.documentation.clone(),
                 readme: pm1_v1.readme.clone(),
                 license: pm1_v1.license.clone(),
@@ -1830,6 +2251,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
             owners: vec!["owner1".to_string(), "owner2".to_string()],
             max_version: pm1_v2.vers.clone(),
             total_downloads: 0,
+            recent_downloads: 0,
             last_updated: created_string.clone(),
             homepage: pm1_v2.homepage.clone(),
             description: pm1_v2.description.clone(),
@@ -1842,6 +2264,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                     version: pm1_v2.vers.clone(),
                     created: created_string.clone(),
                     downloads: 0,
+                    recent_downloads: 0,
                     readme: pm1_v2.readme.clone(),
                     license: pm1_v2.license.clone(),
                     license_file: pm1_v2.license_file.clone(),
@@ -1861,6 +2284,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                     version: pm1_v1.vers.clone(),
                     created: created_string.clone(),
                     downloads: 0,
+                    recent_downloads: 0,
                     readme: pm1_v1.readme.clone(),
                     license: pm1_v1.license.clone(),
                     license_file: pm1_v1.license_file.clone(),
@@ -1900,6 +2324,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
             owners: vec!["owner2".to_string()],
             max_version: pm2_v1.vers.clone(),
             total_downloads: 0,
+            recent_downloads: 0,
             last_updated: created_string.clone(),
             homepage: pm2_v1.homepage.clone(),
             description: pm2_v1.description.clone(),
@@ -1911,6 +2336,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                 version: pm2_v1.vers.clone(),
                 created: created_string.clone(),
                 downloads: 0,
+                recent_downloads: 0,
                 readme: pm2_v1.readme.clone(),
                 license: pm2_v1.license.clone(),
                 license_file: pm2_v1.license_file.clone(),
@@ -1947,6 +2373,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
             owners: vec!["owner2".to_string()],
             max_version: pm2_v2.vers.clone(),
             total_downloads: 0,
+            recent_downloads: 0,
             last_updated: created_string.clone(),
             homepage: pm2_v2.homepage.clone(),
             description: pm2_v2.description.clone(),
@@ -1959,6 +2386,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                     version: pm2_v2.vers.clone(),
                     created: created_string.clone(),
                     downloads: 0,
+                    recent_downloads: 0,
                     readme: pm2_v2.readme.clone(),
                     license: pm2_v2.license.clone(),
                     license_file: pm2_v2.license_file.clone(),
@@ -1978,6 +2406,7 @@ async fn add_crate_and_get_crate_data(test_db: &kellnr_db::Database) {
                     version: pm2_v1.vers.clone(),
                     created: created_string.clone(),
                     downloads: 0,
+                    recent_downloads: 0,
                     documentation: pm2_v1.documentation.clone(),
                     license: pm2_v1.license.clone(),
                     license_file: pm2_v1.license_file.clone(),
@@ -2689,15 +3118,15 @@ async fn test_delete_crate_rollback(test_db: &kellnr_db::Database) {
 #[db_test]
 async fn test_register_webhook(test_db: &kellnr_db::Database) {
     let webhook = Webhook {
-        id: None,
-        event: WebhookEvent::CrateYank,
+        events: vec![WebhookEvent::CrateYank],
         callback_url: "https://my-other-service:8005".to_string(),
         name: Some("myWebhook".to_string()),
+        ..Webhook::default()
     };
-    let id = test_db.register_webhook(webhook).await.unwrap();
+    let id = test_db.register_webhook(webhook, None).await.unwrap();
     let entry = test_db.get_webhook(&id).await.unwrap();
 
-    assert_eq!(WebhookEvent::CrateYank, entry.event);
+    assert_eq!(vec![WebhookEvent::CrateYank], entry.events);
     assert_eq!(
         "https://my-other-service:8005".to_string(),
         entry.callback_url
@@ -2705,15 +3134,188 @@ async fn test_register_webhook(test_db: &kellnr_db::Database) {
     assert_eq!(Some("myWebhook".to_string()), entry.name);
 }
 
+#[db_test]
+async fn test_webhook_secrets(test_db: &kellnr_db::Database) {
+    let webhook = Webhook {
+        events: vec![WebhookEvent::CrateAdd],
+        callback_url: "https://ci.example.com".to_string(),
+        name: None,
+        ..Webhook::default()
+    };
+    let id = test_db
+        .register_webhook(webhook, Some("whsec_old".to_string()))
+        .await
+        .unwrap();
+    test_db
+        .add_webhook_queue(WebhookEvent::CrateAdd, None, None, json!(0))
+        .await
+        .unwrap();
+
+    let entries = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(vec!["whsec_old".to_string()], entries[0].secrets);
+
+    let expires = Utc::now() + TimeDelta::hours(1);
+    let rotated = WebhookSecrets {
+        secret: Some("whsec_new".to_string()),
+        previous_secret: Some("whsec_old".to_string()),
+        previous_secret_expires: Some(expires),
+    };
+    test_db.set_webhook_secrets(&id, &rotated).await.unwrap();
+
+    let secrets = test_db.get_webhook_secrets(&id).await.unwrap();
+    assert_eq!(rotated.secret, secrets.secret);
+    assert_eq!(rotated.previous_secret, secrets.previous_secret);
+    assert_eq!(
+        Some(expires.timestamp()),
+        secrets.previous_secret_expires.map(|e| e.timestamp())
+    );
+    let entries = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["whsec_new".to_string(), "whsec_old".to_string()],
+        entries[0].secrets
+    );
+}
+
+#[db_test]
+async fn test_add_webhook_queue_filters_webhooks(test_db: &kellnr_db::Database) {
+    test_db.add_group("team").await.unwrap();
+    test_add_crate(
+        test_db,
+        "team-crate",
+        "admin",
+        &Version::try_from("1.0.0").unwrap(),
+        &Utc::now(),
+    )
+    .await
+    .unwrap();
+    test_db
+        .add_crate_group(&NormalizedName::from_unchecked_str("team-crate"), "team")
+        .await
+        .unwrap();
+    let webhooks = [
+        ("all", None, None),
+        ("prefix", Some("team-*"), None),
+        ("other-prefix", Some("other-*"), None),
+        ("group", None, Some("team")),
+        ("other-group", None, Some("other")),
+    ];
+    for (name, crate_filter, group_filter) in webhooks {
+        let webhook = Webhook {
+            events: vec![WebhookEvent::CrateAdd, WebhookEvent::UserAdd],
+            callback_url: format!("https://{name}.example.com"),
+            crate_filter: crate_filter.map(ToString::to_string),
+            group_filter: group_filter.map(ToString::to_string),
+            ..Webhook::default()
+        };
+        test_db.register_webhook(webhook, None).await.unwrap();
+    }
+
+    test_db
+        .add_webhook_queue(
+            WebhookEvent::CrateAdd,
+            Some("team-crate".to_string()),
+            None,
+            json!(0),
+        )
+        .await
+        .unwrap();
+    test_db
+        .add_webhook_queue(WebhookEvent::UserAdd, None, None, json!(1))
+        .await
+        .unwrap();
+    test_db
+        .add_webhook_queue(
+            WebhookEvent::CrateYank,
+            Some("team-crate".to_string()),
+            None,
+            json!(2),
+        )
+        .await
+        .unwrap();
+
+    let entries = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    let mut crate_add: Vec<_> = entries
+        .iter()
+        .filter(|e| e.payload == json!(0))
+        .map(|e| e.callback_url.as_str())
+        .collect();
+    crate_add.sort_unstable();
+    assert_eq!(
+        vec![
+            "https://all.example.com",
+            "https://group.example.com",
+            "https://prefix.example.com"
+        ],
+        crate_add
+    );
+    let user_add: Vec<_> = entries.iter().filter(|e| e.payload == json!(1)).collect();
+    assert_eq!(1, user_add.len());
+    assert_eq!("https://all.example.com", user_add[0].callback_url);
+    // No webhook is subscribed to `crate_yank`
+    assert_eq!(4, entries.len());
+}
+
+#[db_test]
+async fn test_add_webhook_queue_with_groups_of_deleted_crate(test_db: &kellnr_db::Database) {
+    let name = NormalizedName::from_unchecked_str("team-crate");
+    let version = Version::try_from("1.0.0").unwrap();
+    test_db.add_group("team").await.unwrap();
+    test_add_crate(test_db, "team-crate", "admin", &version, &Utc::now())
+        .await
+        .unwrap();
+    test_db.add_crate_group(&name, "team").await.unwrap();
+    let webhook = Webhook {
+        events: vec![WebhookEvent::CrateDelete],
+        callback_url: "https://group.example.com".to_string(),
+        group_filter: Some("team".to_string()),
+        ..Webhook::default()
+    };
+    test_db.register_webhook(webhook, None).await.unwrap();
+
+    let groups = test_db
+        .get_crate_groups(&name)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+    test_db.delete_crate(&name, &version).await.unwrap();
+    test_db
+        .add_webhook_queue(
+            WebhookEvent::CrateDelete,
+            Some("team-crate".to_string()),
+            Some(groups),
+            json!(0),
+        )
+        .await
+        .unwrap();
+
+    let entries = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(1, entries.len());
+    assert_eq!("https://group.example.com", entries[0].callback_url);
+}
+
 #[db_test]
 async fn test_delete_webhook(test_db: &kellnr_db::Database) {
     let webhook = Webhook {
-        id: None,
-        event: WebhookEvent::CrateYank,
+        events: vec![WebhookEvent::CrateYank],
         callback_url: "https://my-other-service:8005".to_string(),
         name: None,
+        ..Webhook::default()
     };
-    let id = test_db.register_webhook(webhook).await.unwrap();
+    let id = test_db.register_webhook(webhook, None).await.unwrap();
 
     let result = test_db.delete_webhook(&id).await;
     assert!(result.is_ok());
@@ -2722,22 +3324,237 @@ async fn test_delete_webhook(test_db: &kellnr_db::Database) {
     assert!(result.is_err());
 }
 
+#[db_test]
+async fn test_webhook_deliveries(test_db: &kellnr_db::Database) {
+    let id = test_db
+        .register_webhook(Webhook::default(), None)
+        .await
+        .unwrap();
+    let failed = NewWebhookDelivery {
+        webhook_id: id.clone(),
+        message_id: "msg_1".to_string(),
+        payload: json!({"type": "crate_add"}),
+        status: Some(500),
+        response_body: Some("Internal Server Error".to_string()),
+        error: None,
+        duration_ms: 120,
+    };
+
+    let first = test_db.add_webhook_delivery(&failed).await.unwrap();
+    let second = test_db
+        .add_webhook_delivery(&NewWebhookDelivery {
+            status: Some(200),
+            response_body: Some(String::new()),
+            ..failed.clone()
+        })
+        .await
+        .unwrap();
+    let other = test_db
+        .add_webhook_delivery(&NewWebhookDelivery {
+            message_id: "msg_2".to_string(),
+            status: None,
+            response_body: None,
+            error: Some("connection refused".to_string()),
+            ..failed.clone()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(1, first.attempt);
+    assert!(!first.is_success());
+    assert_eq!(2, second.attempt);
+    assert!(second.is_success());
+    assert_eq!(1, other.attempt);
+    assert_eq!(Some("connection refused".to_string()), other.error);
+
+    let stored = test_db.get_webhook_delivery(&first.id).await.unwrap();
+    assert_eq!(first, stored);
+    assert_eq!(json!({"type": "crate_add"}), stored.payload);
+    assert_eq!(Some(500), stored.status);
+    assert_eq!(120, stored.duration_ms);
+
+    let deliveries = test_db.get_webhook_deliveries(&id, 100).await.unwrap();
+    assert_eq!(
+        vec![other.id.clone(), second.id.clone(), first.id.clone()],
+        deliveries.iter().map(|d| d.id.clone()).collect::<Vec<_>>()
+    );
+    let deliveries = test_db.get_webhook_deliveries(&id, 1).await.unwrap();
+    assert_eq!(vec![other.clone()], deliveries);
+
+    let removed = test_db
+        .delete_webhook_deliveries_before(other.created)
+        .await
+        .unwrap();
+    assert_eq!(2, removed);
+    assert!(matches!(
+        test_db.get_webhook_delivery(&first.id).await,
+        Err(DbError::WebhookDeliveryNotFound)
+    ));
+
+    // Deliveries are removed with their webhook
+    test_db.delete_webhook(&id).await.unwrap();
+    assert!(matches!(
+        test_db.get_webhook_delivery(&other.id).await,
+        Err(DbError::WebhookDeliveryNotFound)
+    ));
+}
+
+#[db_test]
+async fn test_webhook_dead_letters(test_db: &kellnr_db::Database) {
+    let webhook = Webhook {
+        events: vec![WebhookEvent::CrateAdd],
+        ..Webhook::default()
+    };
+    let id = test_db.register_webhook(webhook, None).await.unwrap();
+    test_db
+        .add_webhook_queue(WebhookEvent::CrateAdd, None, None, json!(0))
+        .await
+        .unwrap();
+    let now = Utc::now();
+    let entry = test_db
+        .get_pending_webhook_queue_entries(now)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(0, entry.attempts);
+
+    test_db
+        .update_webhook_queue(&entry.id, now, now)
+        .await
+        .unwrap();
+    test_db
+        .dead_letter_webhook_queue(&entry.id, now)
+        .await
+        .unwrap();
+
+    // Dead letters are not delivered and do not count as queued
+    assert!(
+        test_db
+            .get_pending_webhook_queue_entries(now)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(0, test_db.get_webhook_queue_length().await.unwrap());
+    let dead_letters = test_db.get_webhook_dead_letters(&id).await.unwrap();
+    assert_eq!(1, dead_letters.len());
+    assert_eq!(entry.id, dead_letters[0].id);
+    assert_eq!(2, dead_letters[0].attempts);
+    assert_eq!(json!(0), dead_letters[0].payload);
+
+    test_db.retry_webhook_dead_letter(&entry.id).await.unwrap();
+    assert!(matches!(
+        test_db.retry_webhook_dead_letter(&entry.id).await,
+        Err(DbError::WebhookDeadLetterNotFound)
+    ));
+    assert!(
+        test_db
+            .get_webhook_dead_letters(&id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let pending = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(1, pending.len());
+    assert_eq!(0, pending[0].attempts);
+    assert_eq!(None, pending[0].last_attempt);
+
+    test_db
+        .dead_letter_webhook_queue(&entry.id, Utc::now())
+        .await
+        .unwrap();
+    let removed = test_db
+        .delete_webhook_dead_letters_before(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(1, removed);
+    assert!(
+        test_db
+            .get_webhook_dead_letters(&id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[db_test]
+async fn test_disabled_webhook(test_db: &kellnr_db::Database) {
+    let webhook = Webhook {
+        events: vec![WebhookEvent::CrateAdd],
+        ..Webhook::default()
+    };
+    let id = test_db.register_webhook(webhook, None).await.unwrap();
+    test_db
+        .add_webhook_queue(WebhookEvent::CrateAdd, None, None, json!(0))
+        .await
+        .unwrap();
+
+    let failing_since = Utc::now() - TimeDelta::hours(1);
+    test_db
+        .set_webhook_health(&id, Some(failing_since), None)
+        .await
+        .unwrap();
+    let pending = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(1, pending.len());
+    assert!(pending[0].failing_since.is_some());
+
+    test_db
+        .set_webhook_health(&id, Some(failing_since), Some(Utc::now()))
+        .await
+        .unwrap();
+    let w = test_db.get_webhook(&id).await.unwrap();
+    assert!(w.failing_since.is_some());
+    assert!(w.disabled_at.is_some());
+
+    // Disabled webhooks get no deliveries and no new events
+    test_db
+        .add_webhook_queue(WebhookEvent::CrateAdd, None, None, json!(1))
+        .await
+        .unwrap();
+    assert!(
+        test_db
+            .get_pending_webhook_queue_entries(Utc::now())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(1, test_db.get_webhook_queue_length().await.unwrap());
+
+    test_db.set_webhook_health(&id, None, None).await.unwrap();
+    let w = test_db.get_webhook(&id).await.unwrap();
+    assert_eq!(None, w.failing_since);
+    assert_eq!(None, w.disabled_at);
+    let pending = test_db
+        .get_pending_webhook_queue_entries(Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(1, pending.len());
+    assert_eq!(json!(0), pending[0].payload);
+}
+
 #[db_test]
 async fn test_get_all_webhooks(test_db: &kellnr_db::Database) {
     let mut ids = vec![];
     for i in 0..10 {
         ids.push(
             test_db
-                .register_webhook(Webhook {
-                    id: None,
-                    event: if i % 2 == 0 {
-                        WebhookEvent::CrateYank
-                    } else {
-                        WebhookEvent::CrateAdd
+                .register_webhook(
+                    Webhook {
+                        events: if i % 2 == 0 {
+                            vec![WebhookEvent::CrateYank]
+                        } else {
+                            vec![WebhookEvent::CrateAdd, WebhookEvent::CrateYank]
+                        },
+                        ..Webhook::default()
                     },
-                    callback_url: String::new(),
-                    name: None,
-                })
+                    None,
+                )
                 .await
                 .unwrap(),
         );
@@ -2754,16 +3571,16 @@ async fn test_get_all_webhooks(test_db: &kellnr_db::Database) {
 #[db_test]
 async fn test_add_webhook_queue(test_db: &kellnr_db::Database) {
     let webhook = Webhook {
-        id: None,
-        event: WebhookEvent::CrateUpdate,
+        events: vec![WebhookEvent::CrateUpdate],
         callback_url: "https://update-service.io".to_string(),
         name: None,
+        ..Webhook::default()
     };
-    let webhook_id = test_db.register_webhook(webhook).await.unwrap();
+    let webhook_id = test_db.register_webhook(webhook, None).await.unwrap();
 
     let payload = json!({"test": "data"});
     let result = test_db
-        .add_webhook_queue(WebhookEvent::CrateUpdate, payload.clone())
+        .add_webhook_queue(WebhookEvent::CrateUpdate, None, None, payload.clone())
         .await;
     assert!(result.is_ok());
 
@@ -2784,17 +3601,17 @@ async fn test_add_webhook_queue(test_db: &kellnr_db::Database) {
 #[db_test]
 async fn test_get_pending_webhook_queue_entries(test_db: &kellnr_db::Database) {
     let webhook = Webhook {
-        id: None,
-        event: WebhookEvent::CrateUpdate,
+        events: vec![WebhookEvent::CrateUpdate],
         callback_url: String::new(),
         name: None,
+        ..Webhook::default()
     };
-    let webhook_id = test_db.register_webhook(webhook).await.unwrap();
+    let webhook_id = test_db.register_webhook(webhook, None).await.unwrap();
 
     let payloads = (0..3).map(|i| json!(i)).collect::<Vec<_>>();
     for payload in &payloads {
         test_db
-            .add_webhook_queue(WebhookEvent::CrateUpdate, payload.clone())
+            .add_webhook_queue(WebhookEvent::CrateUpdate, None, None, payload.clone())
             .await
             .unwrap();
     }
@@ -2829,16 +3646,16 @@ async fn test_get_pending_webhook_queue_entries(test_db: &kellnr_db::Database) {
 #[db_test]
 async fn test_delete_webhook_queue(test_db: &kellnr_db::Database) {
     let webhook = Webhook {
-        id: None,
-        event: WebhookEvent::CrateUpdate,
+        events: vec![WebhookEvent::CrateUpdate],
         callback_url: String::new(),
         name: None,
+        ..Webhook::default()
     };
-    let webhook_id = test_db.register_webhook(webhook).await.unwrap();
+    let webhook_id = test_db.register_webhook(webhook, None).await.unwrap();
 
     let payload = json!(0);
     let result = test_db
-        .add_webhook_queue(WebhookEvent::CrateUpdate, payload.clone())
+        .add_webhook_queue(WebhookEvent::CrateUpdate, None, None, payload.clone())
         .await;
     assert!(result.is_ok());
 
@@ -2855,6 +3672,132 @@ async fn test_delete_webhook_queue(test_db: &kellnr_db::Database) {
     assert!(entries.is_empty());
 }
 
+fn audit_actor(user: &str) -> AuditActor {
+    AuditActor {
+        user: user.to_string(),
+        token_name: Some("ci".to_string()),
+        source_ip: Some("10.0.0.1".to_string()),
+    }
+}
+
+#[db_test]
+async fn add_and_get_audit_entries(test_db: &kellnr_db::Database) {
+    test_db
+        .add_audit_entry(
+            &NewAuditEntry::new(audit_actor("admin"), AuditAction::Publish, "foo")
+                .with_version("1.0.0"),
+        )
+        .await
+        .unwrap();
+    test_db
+        .add_audit_entry(
+            &NewAuditEntry::new(audit_actor("bob"), AuditAction::AddOwner, "foo")
+                .with_detail("alice"),
+        )
+        .await
+        .unwrap();
+    test_db
+        .add_audit_entry(&NewAuditEntry::new(
+            audit_actor("admin"),
+            AuditAction::GrantAdmin,
+            "bob",
+        ))
+        .await
+        .unwrap();
+
+    let page = test_db
+        .get_audit_entries(&AuditFilter::default(), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(3, page.total);
+    // Newest first
+    assert_eq!(AuditAction::GrantAdmin, page.entries[0].action);
+    assert_eq!(AuditAction::AddOwner, page.entries[1].action);
+    assert_eq!(Some("alice".to_string()), page.entries[1].detail);
+    let publish = &page.entries[2];
+    assert_eq!(AuditAction::Publish, publish.action);
+    assert_eq!("admin", publish.actor);
+    assert_eq!(Some("ci".to_string()), publish.token_name);
+    assert_eq!(Some("10.0.0.1".to_string()), publish.source_ip);
+    assert_eq!("foo", publish.target);
+    assert_eq!(Some("1.0.0".to_string()), publish.version);
+}
+
+#[db_test]
+async fn get_audit_entries_filters_and_pages(test_db: &kellnr_db::Database) {
+    for version in ["1.0.0", "1.0.1", "1.0.2"] {
+        test_db
+            .add_audit_entry(
+                &NewAuditEntry::new(audit_actor("admin"), AuditAction::Yank, "foo")
+                    .with_version(version),
+            )
+            .await
+            .unwrap();
+    }
+    test_db
+        .add_audit_entry(&NewAuditEntry::new(
+            audit_actor("bob"),
+            AuditAction::Yank,
+            "bar",
+        ))
+        .await
+        .unwrap();
+
+    let filter = AuditFilter {
+        actor: Some("admin".to_string()),
+        target: Some("foo".to_string()),
+        ..AuditFilter::default()
+    };
+    let page = test_db.get_audit_entries(&filter, 2, 0).await.unwrap();
+    assert_eq!(3, page.total);
+    assert_eq!(2, page.entries.len());
+    assert_eq!(Some("1.0.2".to_string()), page.entries[0].version);
+
+    let page = test_db.get_audit_entries(&filter, 2, 2).await.unwrap();
+    assert_eq!(1, page.entries.len());
+    assert_eq!(Some("1.0.0".to_string()), page.entries[0].version);
+
+    let filter = AuditFilter {
+        action: Some(AuditAction::Publish),
+        ..AuditFilter::default()
+    };
+    assert_eq!(
+        0,
+        test_db
+            .get_audit_entries(&filter, 10, 0)
+            .await
+            .unwrap()
+            .total
+    );
+
+    let filter = AuditFilter {
+        since: Some(Utc::now() + TimeDelta::hours(1)),
+        ..AuditFilter::default()
+    };
+    assert_eq!(
+        0,
+        test_db
+            .get_audit_entries(&filter, 10, 0)
+            .await
+            .unwrap()
+            .total
+    );
+
+    let filter = AuditFilter {
+        since: Some(Utc::now() - TimeDelta::hours(1)),
+        until: Some(Utc::now() + TimeDelta::hours(1)),
+        ..AuditFilter::default()
+    };
+    assert_eq!(
+        4,
+        test_db
+            .get_audit_entries(&filter, 10, 0)
+            .await
+            .unwrap()
+            .total
+    );
+}
+
 #[db_test]
 async fn pubtime_stored_and_serialized_correctly(test_db: &kellnr_db::Database) {
     // Add a crate - pubtime will be set to current time by IndexMetadata::from_reg_meta
@@ -2904,3 +3847,153 @@ async fn pubtime_stored_and_serialized_correctly(test_db: &kellnr_db::Database)
         "Expected pubtime '{formatted}' in JSON: {json_str}"
     );
 }
+
+fn test_advisory(id: &str, package: &str, patched: &[&str]) -> Advisory {
+    Advisory {
+        id: id.to_string(),
+        package: package.to_string(),
+        title: format!("{id} title"),
+        description: "description".to_string(),
+        date: "2024-01-01".to_string(),
+        url: None,
+        aliases: vec!["CVE-2024-0001".to_string()],
+        informational: None,
+        patched: patched.iter().map(ToString::to_string).collect(),
+        unaffected: vec![],
+    }
+}
+
+#[db_test]
+async fn replace_advisories_returns_new_ids_and_matches_versions(test_db: &kellnr_db::Database) {
+    let created = Utc.with_ymd_and_hms(2020, 10, 7, 13, 18, 00).unwrap();
+    for version in ["1.0.0", "2.0.0"] {
+        test_add_crate(
+            test_db,
+            "foo",
+            "admin",
+            &Version::try_from(version).unwrap(),
+            &created,
+        )
+        .await
+        .unwrap();
+    }
+
+    let first = [test_advisory("RUSTSEC-2024-0001", "foo", &[">= 2.0.0"])];
+    let added = test_db.replace_advisories(&first).await.unwrap();
+    assert_eq!(vec!["RUSTSEC-2024-0001".to_string()], added);
+
+    let second = [
+        test_advisory("RUSTSEC-2024-0001", "foo", &[">= 2.0.0"]),
+        test_advisory("RUSTSEC-2024-0002", "foo", &[">= 0.1.0"]),
+        test_advisory("RUSTSEC-2024-0003", "bar", &[]),
+    ];
+    let added = test_db.replace_advisories(&second).await.unwrap();
+    assert_eq!(
+        vec![
+            "RUSTSEC-2024-0002".to_string(),
+            "RUSTSEC-2024-0003".to_string()
+        ],
+        added
+    );
+
+    let advisories = test_db
+        .get_advisories(&NormalizedName::from_unchecked("foo".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(2, advisories.len());
+    assert_eq!(vec!["CVE-2024-0001".to_string()], advisories[0].aliases);
+
+    let matches = test_db.get_advisory_matches().await.unwrap();
+    assert_eq!(1, matches.len());
+    assert_eq!("RUSTSEC-2024-0001", matches[0].advisory.id);
+    assert_eq!(vec!["1.0.0".to_string()], matches[0].hosted_versions);
+    assert!(matches[0].cached_versions.is_empty());
+}
+
+#[db_test]
+async fn export_and_import_restores_all_rows(test_db: &kellnr_db::Database) {
+    test_db
+        .add_user("alice", "pwd", "salt", false, false)
+        .await
+        .unwrap();
+    test_add_crate(
+        test_db,
+        "foo",
+        "alice",
+        &Version::try_from("1.0.0").unwrap(),
+        &Utc::now(),
+    )
+    .await
+    .unwrap();
+    test_add_crate(
+        test_db,
+        "foo",
+        "alice",
+        &Version::try_from("1.1.0").unwrap(),
+        &Utc::now(),
+    )
+    .await
+    .unwrap();
+
+    let export = test_db.export().await.unwrap();
+    let mut tables = vec![];
+    for table in kellnr_db::BACKUP_TABLES {
+        let mut rows = vec![];
+        let mut page = 0;
+        loop {
+            let page_rows = export.rows(table, page).await.unwrap();
+            if page_rows.is_empty() {
+                break;
+            }
+            rows.extend(page_rows);
+            page += 1;
+        }
+        tables.push((*table, rows));
+    }
+    export.finish().await.unwrap();
+
+    // The source contains crates, so it cannot be a restore target
+    assert!(matches!(
+        test_db.import().await,
+        Err(DbError::RestoreTargetNotEmpty("crates"))
+    ));
+
+    // Restore into a fresh SQLite database, independent of the source backend
+    let path = PathBuf::from("/tmp").join(format!(
+        "{}.db",
+        kellnr_common::util::generate_rand_string(8)
+    ));
+    let con_string = kellnr_db::ConString::Sqlite(kellnr_db::SqliteConString {
+        path: path.clone(),
+        salt: "salt".to_string(),
+        admin_pwd: "other".to_string(),
+        admin_token: None,
+        session_age: std::time::Duration::from_hours(1),
+    });
+    let target = kellnr_db::Database::new(&con_string, 10).await.unwrap();
+    let import = target.import().await.unwrap();
+    for (table, rows) in &tables {
+        import.insert(table, rows).await.unwrap();
+    }
+    import.commit().await.unwrap();
+
+    assert_eq!(
+        test_db.get_users().await.unwrap(),
+        target.get_users().await.unwrap()
+    );
+    assert_eq!(2, target.get_total_crate_versions().await.unwrap());
+    let name = NormalizedName::from_unchecked("foo".to_string());
+    assert_eq!(
+        test_db.get_crate_data(&name).await.unwrap(),
+        target.get_crate_data(&name).await.unwrap()
+    );
+    // The search index is rebuilt from the imported rows
+    assert_eq!(1, target.search_crates("foo", false).await.unwrap().len());
+    // New rows get fresh ids after the imported ones
+    target
+        .add_user("bob", "pwd", "salt", false, false)
+        .await
+        .unwrap();
+
+    rm_rf::remove(&path).unwrap();
+}
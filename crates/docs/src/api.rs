@@ -1,12 +1,13 @@
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::response::Redirect;
 use kellnr_appstate::{AppState, DbState, SettingsState};
 use kellnr_auth::token::Token;
 use kellnr_common::original_name::OriginalName;
+use kellnr_common::token_scope::TokenOperation;
 use kellnr_common::version::Version;
 use kellnr_error::api_error::ApiResult;
-use kellnr_registry::kellnr_api::check_ownership;
+use kellnr_registry::kellnr_api::{check_ownership, check_token_scope};
 
 use crate::doc_archive::DocArchive;
 use crate::doc_queue_response::DocQueueResponse;
@@ -14,20 +15,41 @@ use crate::docs_error::DocsError;
 use crate::upload_response::DocUploadResponse;
 use crate::{compute_doc_url, get_latest_version_with_doc};
 
+/// Builds returned if no limit is requested
+const DEFAULT_BUILDS_LIMIT: u64 = 100;
+
+/// Most builds returned at once
+const MAX_BUILDS_LIMIT: u64 = 1000;
+
+#[derive(Default, Debug, Clone, serde::Deserialize, utoipa::IntoParams)]
+pub struct DocQueueParams {
+    /// Max. number of builds to return
+    pub limit: Option<u64>,
+}
+
 /// Get documentation build queue
 ///
-/// Returns the list of crates currently in the documentation build queue.
+/// Returns the latest documentation builds with their state, newest first.
+/// Finished builds stay in the list until the crate version is built again.
 #[utoipa::path(
     get,
     path = "/builds",
     tag = "docs",
+    params(DocQueueParams),
     responses(
         (status = 200, description = "Documentation build queue", body = DocQueueResponse)
     ),
     security(("session_cookie" = []))
 )]
-pub async fn docs_in_queue(State(db): DbState) -> ApiResult<Json<DocQueueResponse>> {
-    let doc = db.get_doc_queue().await?;
+pub async fn docs_in_queue(
+    Query(params): Query<DocQueueParams>,
+    State(db): DbState,
+) -> ApiResult<Json<DocQueueResponse>> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_BUILDS_LIMIT)
+        .min(MAX_BUILDS_LIMIT);
+    let doc = db.get_doc_builds(limit).await?;
     Ok(Json(DocQueueResponse::from(doc)))
 }
 
@@ -110,6 +132,7 @@ pub async fn publish_docs(
     // If not, he is not allowed to push the docs.
     let user = kellnr_auth::maybe_user::MaybeUser::from_token(token);
     check_ownership(&normalized_name, &user, &db).await?;
+    check_token_scope(&normalized_name, TokenOperation::Publish, &user.scopes)?;
 
     let doc_path = settings.docs_path().join(&*package).join(crate_version);
 
@@ -152,31 +175,51 @@ mod tests {
     use kellnr_appstate::AppStateData;
     use kellnr_common::normalized_name::NormalizedName;
     use kellnr_db::mock::MockDb;
-    use kellnr_db::{DbProvider, DocQueueEntry};
+    use kellnr_db::{DbProvider, DocBuildState, DocQueueEntry};
+    use mockall::predicate::eq;
     use tower::ServiceExt;
 
     use super::*;
     use crate::doc_queue_response::DocQueueEntryResponse;
+    use hyper::StatusCode;
+
+    fn entry(id: i64, name: &str, version: &str, state: DocBuildState) -> DocQueueEntry {
+        DocQueueEntry {
+            id,
+            normalized_name: NormalizedName::from_unchecked(name.to_string()),
+            version: version.to_string(),
+            path: PathBuf::default(),
+            state,
+            queued: "2026-10-18 10:00:00".to_string(),
+            started: None,
+            finished: None,
+            duration_ms: None,
+        }
+    }
+
+    fn entry_response(name: &str, version: &str, state: DocBuildState) -> DocQueueEntryResponse {
+        DocQueueEntryResponse {
+            name: name.to_string(),
+            version: version.to_string(),
+            state,
+            queued: "2026-10-18 10:00:00".to_string(),
+            started: None,
+            finished: None,
+            duration_ms: None,
+        }
+    }
 
     #[tokio::test]
     async fn doc_in_queue_returns_queue_entries() {
         let mut db = MockDb::new();
-        db.expect_get_doc_queue().returning(|| {
-            Ok(vec![
-                DocQueueEntry {
-                    id: 0,
-                    normalized_name: NormalizedName::from_unchecked("crate1".to_string()),
-                    version: "0.0.1".to_string(),
-                    path: PathBuf::default(),
-                },
-                DocQueueEntry {
-                    id: 1,
-                    normalized_name: NormalizedName::from_unchecked("crate2".to_string()),
-                    version: "0.0.2".to_string(),
-                    path: PathBuf::default(),
-                },
-            ])
-        });
+        db.expect_get_doc_builds()
+            .with(eq(DEFAULT_BUILDS_LIMIT))
+            .returning(|_| {
+                Ok(vec![
+                    entry(1, "crate2", "0.0.2", DocBuildState::Queued),
+                    entry(0, "crate1", "0.0.1", DocBuildState::Failed),
+                ])
+            });
 
         let kellnr = app(Arc::new(db));
         let r = kellnr
@@ -189,20 +232,33 @@ mod tests {
         assert_eq!(
             DocQueueResponse {
                 queue: vec![
-                    DocQueueEntryResponse {
-                        name: "crate1".to_string(),
-                        version: "0.0.1".to_string()
-                    },
-                    DocQueueEntryResponse {
-                        name: "crate2".to_string(),
-                        version: "0.0.2".to_string()
-                    }
+                    entry_response("crate2", "0.0.2", DocBuildState::Queued),
+                    entry_response("crate1", "0.0.1", DocBuildState::Failed),
                 ]
             },
             actual
         );
     }
 
+    #[tokio::test]
+    async fn doc_in_queue_limits_builds() {
+        let mut db = MockDb::new();
+        db.expect_get_doc_builds()
+            .with(eq(MAX_BUILDS_LIMIT))
+            .returning(|_| Ok(vec![]));
+
+        let r = app(Arc::new(db))
+            .oneshot(
+                Request::get("/queue?limit=100000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
     fn app(db: Arc<dyn DbProvider>) -> Router {
         Router::new()
             .route("/queue", get(docs_in_queue))
@@ -0,0 +1,205 @@
+//! Links from the docs of a crate to the docs of its dependencies.
+//!
+//! Without them, rustdoc leaves types of dependencies unlinked, as only the
+//! crate itself is documented. Dependencies from crates.io link to docs.rs,
+//! dependencies hosted in this Kellnr instance to their docs in Kellnr.
+//! rustdoc only accepts `--extern-html-root-url` as an unstable option, so
+//! unstable options are enabled for the crates of the documented package.
+
+use cargo::core::{PackageId, Workspace};
+use cargo::ops;
+use url::Url;
+
+use crate::compute_doc_root_url;
+use crate::docs_error::DocsError;
+
+const DOCS_RS_URL: &str = "https://docs.rs";
+
+/// Path of the sparse index of hosted crates below the Kellnr URL
+const KELLNR_INDEX_PATH: &str = "/api/v1/crates";
+
+/// Path of the sparse index of the crates.io proxy below the Kellnr URL
+const CRATESIO_PROXY_INDEX_PATH: &str = "/api/v1/cratesio";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DocLinks {
+    /// Crate names of the documented package
+    pub crates: Vec<String>,
+    /// Crate name and docs root URL of each dependency
+    pub urls: Vec<(String, String)>,
+}
+
+impl DocLinks {
+    /// Resolve the dependencies of the package in `workspace` and compute the
+    /// docs URLs of those hosted in crates.io or Kellnr.
+    pub fn from_workspace(
+        workspace: &Workspace<'_>,
+        kellnr_url: Option<&str>,
+    ) -> Result<Self, DocsError> {
+        let package = workspace
+            .current()
+            .map_err(|e| DocsError::CargoError(e.to_string()))?;
+        let (_, resolve) =
+            ops::resolve_ws(workspace, false).map_err(|e| DocsError::CargoError(e.to_string()))?;
+        let kellnr_url = kellnr_url.and_then(|url| Url::parse(url).ok());
+
+        let crates = package
+            .targets()
+            .iter()
+            .filter(|t| t.documented())
+            .map(cargo::core::Target::crate_name)
+            .collect();
+        let mut urls: Vec<_> = resolve
+            .deps(package.package_id())
+            .filter_map(|(id, _)| {
+                let url = dependency_doc_url(id, kellnr_url.as_ref())?;
+                Some((id.name().replace('-', "_"), url))
+            })
+            .collect();
+        urls.sort();
+        urls.dedup_by(|a, b| a.0 == b.0);
+
+        Ok(Self { crates, urls })
+    }
+
+    /// Arguments for rustdoc to link the docs of the dependencies.
+    pub fn rustdoc_args(&self) -> Vec<String> {
+        if self.urls.is_empty() {
+            return Vec::new();
+        }
+        let mut args = vec!["-Zunstable-options".to_string()];
+        for (name, url) in &self.urls {
+            args.push("--extern-html-root-url".to_string());
+            args.push(format!("{name}={url}"));
+        }
+        args
+    }
+}
+
+/// Docs root URL of a dependency, if it is hosted in crates.io or Kellnr.
+fn dependency_doc_url(id: PackageId, kellnr_url: Option<&Url>) -> Option<String> {
+    let source = id.source_id();
+    let docs_rs = || format!("{DOCS_RS_URL}/{}/{}/", id.name(), id.version());
+    if source.is_crates_io() {
+        return Some(docs_rs());
+    }
+    if !source.is_registry() {
+        return None;
+    }
+
+    let kellnr_url = kellnr_url?;
+    let index = source.url().as_str();
+    let index = Url::parse(index.strip_prefix("sparse+").unwrap_or(index)).ok()?;
+    if index.scheme() != kellnr_url.scheme()
+        || index.host_str() != kellnr_url.host_str()
+        || index.port_or_known_default() != kellnr_url.port_or_known_default()
+    {
+        return None;
+    }
+
+    let prefix = kellnr_url.path().trim_end_matches('/');
+    match index.path().strip_prefix(prefix)?.trim_end_matches('/') {
+        KELLNR_INDEX_PATH => Some(compute_doc_root_url(
+            &id.name().to_lowercase(),
+            &id.version(),
+            kellnr_url.as_str().trim_end_matches('/'),
+        )),
+        CRATESIO_PROXY_INDEX_PATH => Some(docs_rs()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cargo::GlobalContext;
+    use cargo::core::SourceId;
+
+    use super::*;
+
+    fn package_id(name: &str, version: &str, index: &str) -> PackageId {
+        let source = SourceId::for_registry(&Url::parse(index).unwrap()).unwrap();
+        PackageId::try_new(name, version, source).unwrap()
+    }
+
+    #[test]
+    fn crates_io_dependency_links_to_docs_rs() {
+        let ctx = GlobalContext::default().unwrap();
+        let source = SourceId::crates_io(&ctx).unwrap();
+        let id = PackageId::try_new("serde", "1.0.200", source).unwrap();
+
+        assert_eq!(
+            Some("https://docs.rs/serde/1.0.200/".to_string()),
+            dependency_doc_url(id, None)
+        );
+    }
+
+    #[test]
+    fn kellnr_dependency_links_to_kellnr_docs() {
+        let kellnr_url = Url::parse("https://kellnr.example.com:443/kellnr").unwrap();
+        let id = package_id(
+            "My-Crate",
+            "1.2.3",
+            "sparse+https://kellnr.example.com/kellnr/api/v1/crates/",
+        );
+
+        assert_eq!(
+            Some("https://kellnr.example.com/kellnr/docs/my-crate/1.2.3/doc/".to_string()),
+            dependency_doc_url(id, Some(&kellnr_url))
+        );
+    }
+
+    #[test]
+    fn kellnr_crates_io_proxy_dependency_links_to_docs_rs() {
+        let kellnr_url = Url::parse("http://localhost:8000").unwrap();
+        let id = package_id(
+            "serde",
+            "1.0.200",
+            "sparse+http://localhost:8000/api/v1/cratesio/",
+        );
+
+        assert_eq!(
+            Some("https://docs.rs/serde/1.0.200/".to_string()),
+            dependency_doc_url(id, Some(&kellnr_url))
+        );
+    }
+
+    #[test]
+    fn dependency_of_other_registry_is_not_linked() {
+        let kellnr_url = Url::parse("http://localhost:8000").unwrap();
+        let other_host = package_id(
+            "foo",
+            "1.0.0",
+            "sparse+http://registry.example.com/api/v1/crates/",
+        );
+        let other_port = package_id(
+            "foo",
+            "1.0.0",
+            "sparse+http://localhost:8001/api/v1/crates/",
+        );
+
+        assert_eq!(None, dependency_doc_url(other_host, Some(&kellnr_url)));
+        assert_eq!(None, dependency_doc_url(other_port, Some(&kellnr_url)));
+        assert_eq!(None, dependency_doc_url(other_host, None));
+    }
+
+    #[test]
+    fn rustdoc_args_enable_unstable_options_for_links() {
+        let links = DocLinks {
+            crates: vec!["my_crate".to_string()],
+            urls: vec![(
+                "serde".to_string(),
+                "https://docs.rs/serde/1.0.200/".to_string(),
+            )],
+        };
+
+        assert_eq!(
+            vec![
+                "-Zunstable-options",
+                "--extern-html-root-url",
+                "serde=https://docs.rs/serde/1.0.200/",
+            ],
+            links.rustdoc_args()
+        );
+        assert!(DocLinks::default().rustdoc_args().is_empty());
+    }
+}
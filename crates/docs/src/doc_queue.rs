@@ -1,30 +1,41 @@
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use cargo::GlobalContext;
 use cargo::core::Workspace;
-use cargo::core::compiler::UserIntent;
-use cargo::core::resolver::CliFeatures;
-use cargo::ops::{self, CompileOptions, DocOptions, OutputFormat};
+use cargo::core::compiler::{BuildConfig, UserIntent};
+use cargo::ops::{self, CompileOptions, DocOptions, FetchOptions, OutputFormat};
+use chrono::Utc;
 use flate2::read::GzDecoder;
 use fs_extra::dir::{CopyOptions, copy};
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::version::Version;
-use kellnr_db::{DbProvider, DocQueueEntry};
+use kellnr_common::webhook::WebhookEvent;
+use kellnr_db::{DbProvider, DocBuildState, DocQueueEntry};
 use kellnr_storage::kellnr_crate_storage::KellnrCrateStorage;
 use tar::Archive;
 use tokio::fs::{create_dir_all, remove_dir_all};
+use tokio::time::Instant;
 use tracing::error;
 
 use crate::compute_doc_url;
+use crate::doc_links::DocLinks;
 use crate::docs_error::DocsError;
+use crate::docs_rs_metadata::DocsRsMetadata;
+use crate::sandbox::{self, BuildLimits, CARGO_HOME_DIR};
+
+/// Max. size of the stored log of a doc build in bytes
+const MAX_LOG_SIZE: usize = 256 * 1024;
 
 pub fn doc_extraction_queue(
     db: Arc<dyn DbProvider>,
     cs: Arc<KellnrCrateStorage>,
     docs_path: PathBuf,
     path_prefix: String,
+    kellnr_url: String,
     cratesio_index: Option<String>,
+    limits: BuildLimits,
 ) {
     tokio::spawn(async move {
         loop {
@@ -34,7 +45,9 @@ pub fn doc_extraction_queue(
                 &cs,
                 &docs_path,
                 &path_prefix,
+                &kellnr_url,
                 cratesio_index.as_deref(),
+                &limits,
             )
             .await
             {
@@ -49,34 +62,90 @@ async fn inner_loop(
     cs: &KellnrCrateStorage,
     docs_path: &Path,
     path_prefix: &str,
+    kellnr_url: &str,
     cratesio_index: Option<&str>,
+    limits: &BuildLimits,
 ) -> Result<(), DocsError> {
+    // Builds which are still marked as building were interrupted by a
+    // restart and are built again.
     let entries = db.get_doc_queue().await?;
 
     for entry in entries {
-        if let Err(e) = extract_docs(&entry, cs, docs_path, cratesio_index).await {
-            error!("Failed to extract docs from crate: {e}");
+        db.start_doc_build(entry.id).await?;
+        let started = Instant::now();
+        let mut log = String::new();
+        let result = extract_docs(
+            &entry,
+            cs,
+            docs_path,
+            kellnr_url,
+            cratesio_index,
+            limits,
+            &mut log,
+        )
+        .await;
+        // The unpacked crate and the build output are discarded after every
+        // build, including failed ones.
+        if let Err(e) = clean_up(&entry.path).await {
+            error!("Failed to delete temporary rustdoc queue folder: {e}");
+        }
+        let version = Version::from_unchecked_str(&entry.version);
+        // The build is recorded before a failed docs link update is returned,
+        // so it does not stay marked as building.
+        let mut link_result = Ok(());
+        let (state, event) = if let Err(e) = result {
+            error!(
+                "Failed to extract docs from crate {} {}: {e}",
+                entry.normalized_name, entry.version
+            );
+            let _ = writeln!(log, "\nerror: {e}");
+            (DocBuildState::Failed, WebhookEvent::DocsBuildFailure)
         } else {
-            if let Err(e) = clean_up(&entry.path).await {
-                error!("Failed to delete temporary rustdoc queue folder: {e}");
-            }
-
-            let version = Version::from_unchecked_str(&entry.version);
             let docs_link = compute_doc_url(&entry.normalized_name, &version, path_prefix);
-            db.update_docs_link(&entry.normalized_name, &version, &docs_link)
-                .await?;
-        }
-        db.delete_doc_queue(entry.id).await?;
+            link_result = db
+                .update_docs_link(&entry.normalized_name, &version, &docs_link)
+                .await;
+            if let Err(e) = &link_result {
+                let _ = writeln!(log, "\nerror: Failed to update docs link: {e}");
+                (DocBuildState::Failed, WebhookEvent::DocsBuildFailure)
+            } else {
+                (DocBuildState::Succeeded, WebhookEvent::DocsBuildSuccess)
+            }
+        };
+        db.finish_doc_build(entry.id, state, started.elapsed(), log_tail(&log))
+            .await?;
+        kellnr_webhooks::notify_crate(
+            event,
+            &Utc::now(),
+            &entry.normalized_name,
+            &version,
+            None,
+            &db,
+        )
+        .await;
+        link_result?;
     }
 
     Ok(())
 }
 
+/// End of the build log, which is limited to [`MAX_LOG_SIZE`] bytes.
+fn log_tail(log: &str) -> &str {
+    let mut start = log.len().saturating_sub(MAX_LOG_SIZE);
+    while !log.is_char_boundary(start) {
+        start += 1;
+    }
+    &log[start..]
+}
+
 async fn extract_docs(
     doc: &DocQueueEntry,
     cs: &KellnrCrateStorage,
     docs_path: &Path,
+    kellnr_url: &str,
     cratesio_index: Option<&str>,
+    limits: &BuildLimits,
+    log: &mut String,
 ) -> Result<(), DocsError> {
     // Unpack crate
 
@@ -96,20 +165,45 @@ async fn extract_docs(
         .path
         .join(format!("{}-{}", doc.normalized_name, doc.version));
     strip_rust_toolchain_files(generated_docs_path).await?;
-    generate_docs(generated_docs_path, cratesio_index)?;
-
-    // Copy the docs directory
-    let from = generated_docs_path.join("target").join("doc");
+    let metadata = DocsRsMetadata::from_crate(generated_docs_path)?;
+    sandbox::build_docs(
+        generated_docs_path,
+        &doc.path,
+        cratesio_index,
+        Some(kellnr_url),
+        limits,
+        log,
+    )
+    .await?;
+
+    // Copy the docs directory. The docs of the default target are served as
+    // the docs of the crate, other targets below their target triple.
     let to = docs_path
         .join(doc.normalized_name.to_string())
         .join(&doc.version);
+    let targets = metadata.map(|m| m.build_targets()).unwrap_or_default();
+    let default_target = targets.first().map(String::as_str);
+    let from = DocsRsMetadata::doc_dir(generated_docs_path, default_target);
     copy_dir(&from, &to).await?;
+    for target in targets.iter().skip(1) {
+        let from = DocsRsMetadata::doc_dir(generated_docs_path, Some(target));
+        copy_dir(&from, &to.join(target)).await?;
+    }
 
     Ok(())
 }
 
+/// Delete the temporary folder of a build. The cargo home of the build is
+/// deleted first, so the downloaded dependencies are never reused even if
+/// the rest of the folder cannot be deleted.
 async fn clean_up(path: &Path) -> Result<(), DocsError> {
-    remove_dir_all(path).await?;
+    for dir in [path.join(CARGO_HOME_DIR), path.to_path_buf()] {
+        match remove_dir_all(&dir).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
     Ok(())
 }
 
@@ -166,39 +260,112 @@ async fn copy_dir(from: &Path, to: &Path) -> Result<(), DocsError> {
 /// cargo's `crates-io` source at it via source replacement so dependency
 /// resolution for `cargo doc` honors `proxy.index` instead of fetching from the
 /// upstream `index.crates.io`. See issue #1185.
-fn build_doc_context(cratesio_index: Option<&str>) -> Result<GlobalContext, DocsError> {
+///
+/// An `offline` context only uses dependencies that were fetched before.
+/// `rustdoc_args` are passed to every rustdoc invocation, followed by the
+/// arguments for the `links` to the docs of dependencies.
+fn build_doc_context(
+    cratesio_index: Option<&str>,
+    offline: bool,
+    rustdoc_args: &[String],
+    links: &DocLinks,
+) -> Result<GlobalContext, DocsError> {
     let mut ctx = GlobalContext::default().map_err(|e| DocsError::CargoError(e.to_string()))?;
 
-    if let Some(index) = cratesio_index {
-        let cli_config = vec![
+    let mut cli_config = match cratesio_index {
+        Some(index) => vec![
             "source.crates-io.replace-with=\"kellnr-proxy\"".to_string(),
             format!("source.kellnr-proxy.registry=\"sparse+{index}\""),
-        ];
-        ctx.configure(0, false, None, false, false, false, &None, &[], &cli_config)
-            .map_err(|e| DocsError::CargoError(e.to_string()))?;
+        ],
+        None => Vec::new(),
+    };
+    let rustdoc_args: Vec<_> = rustdoc_args
+        .iter()
+        .cloned()
+        .chain(links.rustdoc_args())
+        .collect();
+    if !rustdoc_args.is_empty() {
+        let args = toml::Value::Array(rustdoc_args.into_iter().map(toml::Value::String).collect());
+        cli_config.push(format!("build.rustdocflags={args}"));
+    }
+    if !links.urls.is_empty() {
+        // Stable rustdoc accepts unstable options only with RUSTC_BOOTSTRAP,
+        // which is limited to the crates of the documented package.
+        let crates = toml::Value::String(links.crates.join(","));
+        cli_config.push(format!("env.RUSTC_BOOTSTRAP={crates}"));
+    }
+    if offline || !cli_config.is_empty() {
+        ctx.configure(
+            0,
+            false,
+            None,
+            false,
+            false,
+            offline,
+            &None,
+            &[],
+            &cli_config,
+        )
+        .map_err(|e| DocsError::CargoError(e.to_string()))?;
     }
 
     Ok(ctx)
 }
 
-fn generate_docs(
+/// Download all dependencies of the crate, without building anything.
+pub(crate) fn fetch_dependencies(
     crate_path: impl AsRef<Path>,
     cratesio_index: Option<&str>,
 ) -> Result<(), DocsError> {
+    let metadata = DocsRsMetadata::from_crate(crate_path.as_ref())?;
     let manifest_path = crate_path.as_ref().join("Cargo.toml").canonicalize()?;
-    let ctx = build_doc_context(cratesio_index)?;
+    let ctx = build_doc_context(cratesio_index, false, &[], &DocLinks::default())?;
     let workspace =
         Workspace::new(&manifest_path, &ctx).map_err(|e| DocsError::CargoError(e.to_string()))?;
+    let options = FetchOptions {
+        gctx: &ctx,
+        targets: metadata.map(|m| m.build_targets()).unwrap_or_default(),
+    };
+    ops::fetch(&workspace, &options).map_err(|e| DocsError::CargoError(e.to_string()))?;
+    Ok(())
+}
+
+pub(crate) fn generate_docs(
+    crate_path: impl AsRef<Path>,
+    cratesio_index: Option<&str>,
+    kellnr_url: Option<&str>,
+    offline: bool,
+) -> Result<(), DocsError> {
+    let metadata = DocsRsMetadata::from_crate(crate_path.as_ref())?;
+    let rustdoc_args = metadata
+        .as_ref()
+        .map(|m| m.rustdoc_args.as_slice())
+        .unwrap_or_default();
+    let targets = metadata
+        .as_ref()
+        .map(DocsRsMetadata::build_targets)
+        .unwrap_or_default();
+    let manifest_path = crate_path.as_ref().join("Cargo.toml").canonicalize()?;
+    // The links are computed with a separate context, as the rustdoc
+    // arguments of a context cannot be changed once it is used.
+    let links = {
+        let ctx = build_doc_context(cratesio_index, offline, &[], &DocLinks::default())?;
+        let workspace = Workspace::new(&manifest_path, &ctx)
+            .map_err(|e| DocsError::CargoError(e.to_string()))?;
+        DocLinks::from_workspace(&workspace, kellnr_url)?
+    };
+    let ctx = build_doc_context(cratesio_index, offline, rustdoc_args, &links)?;
+    let workspace =
+        Workspace::new(&manifest_path, &ctx).map_err(|e| DocsError::CargoError(e.to_string()))?;
+    let intent = UserIntent::Doc {
+        deps: false,
+        json: false,
+    };
     let compile_opts = CompileOptions {
-        cli_features: CliFeatures::new_all(true),
-        ..CompileOptions::new(
-            &ctx,
-            UserIntent::Doc {
-                deps: false,
-                json: false,
-            },
-        )
-        .map_err(|e| DocsError::CargoError(e.to_string()))?
+        cli_features: DocsRsMetadata::cli_features(metadata.as_ref())?,
+        build_config: BuildConfig::new(&ctx, None, false, &targets, intent)
+            .map_err(|e| DocsError::CargoError(e.to_string()))?,
+        ..CompileOptions::new(&ctx, intent).map_err(|e| DocsError::CargoError(e.to_string()))?
     };
     let options = DocOptions {
         open_result: false,
@@ -220,7 +387,7 @@ mod tests {
 
     #[test]
     fn no_index_override_does_not_inject_proxy_source() {
-        let ctx = build_doc_context(None).unwrap();
+        let ctx = build_doc_context(None, false, &[], &DocLinks::default()).unwrap();
         // Without an override we never define the kellnr-proxy source, so cargo
         // keeps whatever crates.io source the ambient environment provides
         // (the upstream index.crates.io in the kellnr container). Asserting on
@@ -235,7 +402,13 @@ mod tests {
 
     #[test]
     fn index_override_replaces_crates_io_source_with_proxy() {
-        let ctx = build_doc_context(Some("https://rsproxy.cn/index/")).unwrap();
+        let ctx = build_doc_context(
+            Some("https://rsproxy.cn/index/"),
+            false,
+            &[],
+            &DocLinks::default(),
+        )
+        .unwrap();
 
         // The CLI config overrides are well-formed and applied by cargo.
         assert_eq!(
@@ -259,6 +432,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rustdoc_args_are_passed_as_rustdocflags() {
+        let args = vec![
+            "--cfg".to_string(),
+            "docsrs".to_string(),
+            "--html-in-header".to_string(),
+            "\"quoted\" header.html".to_string(),
+        ];
+
+        let ctx = build_doc_context(None, false, &args, &DocLinks::default()).unwrap();
+
+        let flags = ctx.build_config().unwrap().rustdocflags.clone().unwrap();
+        assert_eq!(args, flags.as_slice());
+    }
+
+    #[test]
+    fn doc_links_are_passed_to_rustdoc_of_documented_crates() {
+        let args = vec!["--cfg".to_string(), "docsrs".to_string()];
+        let links = DocLinks {
+            crates: vec!["my_crate".to_string(), "my_bin".to_string()],
+            urls: vec![(
+                "serde".to_string(),
+                "https://docs.rs/serde/1.0.200/".to_string(),
+            )],
+        };
+
+        let ctx = build_doc_context(None, false, &args, &links).unwrap();
+
+        let flags = ctx.build_config().unwrap().rustdocflags.clone().unwrap();
+        assert_eq!(
+            [
+                "--cfg",
+                "docsrs",
+                "-Zunstable-options",
+                "--extern-html-root-url",
+                "serde=https://docs.rs/serde/1.0.200/",
+            ],
+            flags.as_slice()
+        );
+        let env = ctx.env_config().unwrap();
+        assert_eq!(
+            Some("my_crate,my_bin"),
+            env.get("RUSTC_BOOTSTRAP").and_then(|v| v.to_str())
+        );
+    }
+
     #[tokio::test]
     async fn strip_rust_toolchain_files_removes_both_variants() {
         let dir = tempfile::tempdir().unwrap();
@@ -304,4 +523,15 @@ mod tests {
         assert!(cargo_toml.exists());
         assert!(src.exists());
     }
+
+    #[test]
+    fn log_tail_keeps_end_of_log() {
+        let log = format!("{}end", "ä".repeat(MAX_LOG_SIZE));
+
+        let tail = log_tail(&log);
+
+        assert!(tail.len() <= MAX_LOG_SIZE);
+        assert!(tail.ends_with("äend"));
+        assert_eq!("short", log_tail("short"));
+    }
 }
@@ -1,4 +1,4 @@
-use kellnr_db::DocQueueEntry;
+use kellnr_db::{DocBuildState, DocQueueEntry};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -16,6 +16,39 @@ pub struct DocQueueEntryResponse {
     pub(crate) name: String,
     /// Crate version
     pub(crate) version: String,
+    /// State of the build
+    pub(crate) state: DocBuildState,
+    /// Time the build was queued in UTC
+    pub(crate) queued: String,
+    /// Time the build started in UTC
+    pub(crate) started: Option<String>,
+    /// Time the build finished in UTC
+    pub(crate) finished: Option<String>,
+    /// Duration of the finished build in milliseconds
+    pub(crate) duration_ms: Option<i64>,
+}
+
+/// Documentation build with its captured cargo output
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+pub struct DocBuildResponse {
+    #[serde(flatten)]
+    pub build: DocQueueEntryResponse,
+    /// Cargo output of the finished build
+    pub log: Option<String>,
+}
+
+impl From<DocQueueEntry> for DocQueueEntryResponse {
+    fn from(e: DocQueueEntry) -> Self {
+        Self {
+            name: e.normalized_name.to_string(),
+            version: e.version,
+            state: e.state,
+            queued: e.queued,
+            started: e.started,
+            finished: e.finished,
+            duration_ms: e.duration_ms,
+        }
+    }
 }
 
 impl From<Vec<DocQueueEntry>> for DocQueueResponse {
@@ -23,10 +56,7 @@ impl From<Vec<DocQueueEntry>> for DocQueueResponse {
         Self {
             queue: entries
                 .into_iter()
-                .map(|e| DocQueueEntryResponse {
-                    name: e.normalized_name.to_string(),
-                    version: e.version,
-                })
+                .map(DocQueueEntryResponse::from)
                 .collect(),
         }
     }
@@ -48,12 +78,22 @@ mod tests {
                 normalized_name: NormalizedName::from_unchecked("crate1".to_string()),
                 version: "0.0.1".to_string(),
                 path: PathBuf::default(),
+                state: DocBuildState::Queued,
+                queued: "2026-10-18 10:00:00".to_string(),
+                started: None,
+                finished: None,
+                duration_ms: None,
             },
             DocQueueEntry {
                 id: 1,
                 normalized_name: NormalizedName::from_unchecked("crate2".to_string()),
                 version: "0.0.2".to_string(),
                 path: PathBuf::default(),
+                state: DocBuildState::Failed,
+                queued: "2026-10-18 09:00:00".to_string(),
+                started: Some("2026-10-18 09:01:00".to_string()),
+                finished: Some("2026-10-18 09:02:00".to_string()),
+                duration_ms: Some(60_000),
             },
         ];
 
@@ -64,11 +104,21 @@ mod tests {
                 queue: vec![
                     DocQueueEntryResponse {
                         name: "crate1".to_string(),
-                        version: "0.0.1".to_string()
+                        version: "0.0.1".to_string(),
+                        state: DocBuildState::Queued,
+                        queued: "2026-10-18 10:00:00".to_string(),
+                        started: None,
+                        finished: None,
+                        duration_ms: None,
                     },
                     DocQueueEntryResponse {
                         name: "crate2".to_string(),
-                        version: "0.0.2".to_string()
+                        version: "0.0.2".to_string(),
+                        state: DocBuildState::Failed,
+                        queued: "2026-10-18 09:00:00".to_string(),
+                        started: Some("2026-10-18 09:01:00".to_string()),
+                        finished: Some("2026-10-18 09:02:00".to_string()),
+                        duration_ms: Some(60_000),
                     }
                 ]
             },
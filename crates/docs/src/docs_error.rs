@@ -16,6 +16,14 @@ pub enum DocsError {
     CopyError(#[from] fs_extra::error::Error),
     #[error("Cargo error: {0}")]
     CargoError(String),
+    #[error("Doc build did not finish within {0} seconds")]
+    BuildTimeout(u64),
+    #[error("Doc build failed: {0}")]
+    BuildFailed(String),
+    #[error("Failed to sandbox doc build: {0}")]
+    Sandbox(String),
+    #[error("Invalid [package.metadata.docs.rs] section: {0}")]
+    InvalidMetadata(String),
 }
 
 impl From<DocsError> for ApiError {
@@ -37,6 +45,12 @@ impl From<DocsError> for ApiError {
                 &String::default(),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            DocsError::BuildTimeout(_)
+            | DocsError::BuildFailed(_)
+            | DocsError::Sandbox(_)
+            | DocsError::InvalidMetadata(_) => {
+                ApiError::from_err(&e, StatusCode::INTERNAL_SERVER_ERROR)
+            }
         }
     }
 }
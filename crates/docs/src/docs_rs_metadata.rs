@@ -0,0 +1,284 @@
+//! Build options of a crate from the `[package.metadata.docs.rs]` section of
+//! its manifest, as they are used by docs.rs.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use cargo::core::resolver::CliFeatures;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::docs_error::DocsError;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub(crate) struct DocsRsMetadata {
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub default_target: Option<String>,
+    pub targets: Vec<String>,
+    pub rustdoc_args: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Option<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    docs: Option<Docs>,
+}
+
+#[derive(Deserialize)]
+struct Docs {
+    rs: Option<DocsRsMetadata>,
+}
+
+impl DocsRsMetadata {
+    /// Read the section from the `Cargo.toml` in `crate_path`. `None` if the
+    /// crate has no such section.
+    pub fn from_crate(crate_path: &Path) -> Result<Option<Self>, DocsError> {
+        let manifest = std::fs::read_to_string(crate_path.join("Cargo.toml"))?;
+        Self::from_manifest(&manifest)
+    }
+
+    fn from_manifest(manifest: &str) -> Result<Option<Self>, DocsError> {
+        let manifest: Manifest = toml::from_str(manifest)
+            .map_err(|e| DocsError::InvalidMetadata(e.message().to_string()))?;
+        Ok(manifest
+            .package
+            .and_then(|p| p.metadata)
+            .and_then(|m| m.docs)
+            .and_then(|d| d.rs))
+    }
+
+    /// Features to document. Crates without the section are documented with
+    /// all features.
+    pub fn cli_features(metadata: Option<&Self>) -> Result<CliFeatures, DocsError> {
+        let Some(metadata) = metadata else {
+            return Ok(CliFeatures::new_all(true));
+        };
+        CliFeatures::from_command_line(
+            &metadata.features,
+            metadata.all_features,
+            !metadata.no_default_features,
+        )
+        .map_err(|e| DocsError::InvalidMetadata(e.to_string()))
+    }
+
+    /// Targets to document, the default target first. Empty if only the host
+    /// target is documented. Targets whose standard library is not installed
+    /// are skipped, so the crate is still documented for the others.
+    pub fn build_targets(&self) -> Vec<String> {
+        let Some(sysroot) = sysroot() else {
+            return self.requested_targets();
+        };
+        self.requested_targets()
+            .into_iter()
+            .filter(|target| {
+                let installed = sysroot
+                    .join("lib")
+                    .join("rustlib")
+                    .join(target)
+                    .join("lib")
+                    .is_dir();
+                if !installed {
+                    warn!(
+                        "Skipping docs for target {target}, its standard library is not installed"
+                    );
+                }
+                installed
+            })
+            .collect()
+    }
+
+    /// Requested targets without duplicates. Only plain target triples are
+    /// accepted, as cargo reads anything else as the path of a target
+    /// specification file.
+    fn requested_targets(&self) -> Vec<String> {
+        let mut targets = Vec::new();
+        for target in self.default_target.iter().chain(&self.targets) {
+            if !is_target_triple(target) {
+                warn!("Skipping docs for invalid target {target}");
+            } else if !targets.contains(target) {
+                targets.push(target.clone());
+            }
+        }
+        targets
+    }
+
+    /// Directory cargo writes the docs of `target` to.
+    pub fn doc_dir(crate_path: &Path, target: Option<&str>) -> PathBuf {
+        let target_dir = crate_path.join("target");
+        match target {
+            Some(target) => target_dir.join(target).join("doc"),
+            None => target_dir.join("doc"),
+        }
+    }
+}
+
+fn is_target_triple(target: &str) -> bool {
+    !target.is_empty()
+        && !target.contains("..")
+        && !Path::new(target)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Sysroot of the toolchain that builds the docs. `None` if it cannot be
+/// determined, then all targets are passed to cargo.
+fn sysroot() -> Option<&'static Path> {
+    static SYSROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    SYSROOT
+        .get_or_init(|| {
+            let output = std::process::Command::new("rustc")
+                .args(["--print", "sysroot"])
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+        })
+        .as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_without_section() {
+        let manifest = r#"
+            [package]
+            name = "foo"
+            version = "1.0.0"
+
+            [package.metadata.other]
+            key = "value"
+        "#;
+
+        assert_eq!(None, DocsRsMetadata::from_manifest(manifest).unwrap());
+    }
+
+    #[test]
+    fn manifest_with_section() {
+        let manifest = r#"
+            [package]
+            name = "foo"
+            version = "1.0.0"
+
+            [package.metadata.docs.rs]
+            features = ["serde", "tokio"]
+            no-default-features = true
+            targets = ["x86_64-unknown-linux-gnu", "wasm32-unknown-unknown"]
+            rustdoc-args = ["--cfg", "docsrs"]
+            unknown-key = true
+        "#;
+
+        let metadata = DocsRsMetadata::from_manifest(manifest).unwrap().unwrap();
+
+        assert_eq!(
+            DocsRsMetadata {
+                features: vec!["serde".to_string(), "tokio".to_string()],
+                all_features: false,
+                no_default_features: true,
+                default_target: None,
+                targets: vec![
+                    "x86_64-unknown-linux-gnu".to_string(),
+                    "wasm32-unknown-unknown".to_string()
+                ],
+                rustdoc_args: vec!["--cfg".to_string(), "docsrs".to_string()],
+            },
+            metadata
+        );
+    }
+
+    #[test]
+    fn invalid_section_is_an_error() {
+        let manifest = r#"
+            [package.metadata.docs.rs]
+            features = "serde"
+        "#;
+
+        assert!(matches!(
+            DocsRsMetadata::from_manifest(manifest),
+            Err(DocsError::InvalidMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn build_targets_start_with_default_target() {
+        let metadata = DocsRsMetadata {
+            default_target: Some("b".to_string()),
+            targets: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            ..DocsRsMetadata::default()
+        };
+
+        assert_eq!(vec!["b", "a", "c"], metadata.requested_targets());
+        assert!(DocsRsMetadata::default().requested_targets().is_empty());
+    }
+
+    #[test]
+    fn only_target_triples_are_requested() {
+        let metadata = DocsRsMetadata {
+            default_target: Some("../../etc/target.json".to_string()),
+            targets: vec![
+                "x86_64-unknown-linux-gnu".to_string(),
+                "thumbv7em-none-eabihf".to_string(),
+                "custom.json".to_string(),
+                "/tmp/custom".to_string(),
+                "a..b".to_string(),
+                String::new(),
+            ],
+            ..DocsRsMetadata::default()
+        };
+
+        assert_eq!(
+            vec!["x86_64-unknown-linux-gnu", "thumbv7em-none-eabihf"],
+            metadata.requested_targets()
+        );
+    }
+
+    #[test]
+    fn targets_without_standard_library_are_skipped() {
+        let metadata = DocsRsMetadata {
+            targets: vec!["kellnr-unknown-none".to_string()],
+            ..DocsRsMetadata::default()
+        };
+
+        assert!(metadata.build_targets().is_empty());
+    }
+
+    #[test]
+    fn cli_features_from_section() {
+        let all = DocsRsMetadata::cli_features(None).unwrap();
+        assert!(all.all_features);
+
+        let metadata = DocsRsMetadata {
+            features: vec!["serde".to_string()],
+            no_default_features: true,
+            ..DocsRsMetadata::default()
+        };
+        let features = DocsRsMetadata::cli_features(Some(&metadata)).unwrap();
+        assert!(!features.all_features);
+        assert!(!features.uses_default_features);
+        assert_eq!(1, features.features.len());
+
+        let defaults = DocsRsMetadata::cli_features(Some(&DocsRsMetadata::default())).unwrap();
+        assert!(!defaults.all_features);
+        assert!(defaults.uses_default_features);
+    }
+}
@@ -1,8 +1,11 @@
 pub mod api;
 mod doc_archive;
+mod doc_links;
 pub mod doc_queue;
 pub mod doc_queue_response;
 pub mod docs_error;
+mod docs_rs_metadata;
+pub mod sandbox;
 pub mod upload_response;
 
 use std::convert::TryFrom;
@@ -36,8 +39,19 @@ pub fn get_doc_url(
 
 pub fn compute_doc_url(crate_name: &str, crate_version: &Version, path_prefix: &str) -> String {
     let docs_name = crate_name_to_docs_name(crate_name);
+    let doc_root = compute_doc_root_url(crate_name, crate_version, path_prefix);
+    format!("{doc_root}{docs_name}/index.html")
+}
+
+/// URL of the directory that contains the docs of all crates documented for
+/// a crate version, as expected by rustdoc's `--extern-html-root-url`.
+pub fn compute_doc_root_url(
+    crate_name: &str,
+    crate_version: &impl std::fmt::Display,
+    path_prefix: &str,
+) -> String {
     let path_prefix = path_prefix.trim();
-    format!("{path_prefix}/docs/{crate_name}/{crate_version}/doc/{docs_name}/index.html")
+    format!("{path_prefix}/docs/{crate_name}/{crate_version}/doc/")
 }
 
 fn crate_name_to_docs_name(crate_name: &str) -> String {
@@ -125,6 +139,16 @@ mod tests {
         assert_eq!(url, "/kellnr/docs/my-crate/1.0.0/doc/my_crate/index.html");
     }
 
+    #[test]
+    fn compute_doc_root_url_with_absolute_prefix() {
+        let version = Version::try_from("1.0.0").unwrap();
+        let url = compute_doc_root_url("my-crate", &version, "https://kellnr.example.com:443");
+        assert_eq!(
+            url,
+            "https://kellnr.example.com:443/docs/my-crate/1.0.0/doc/"
+        );
+    }
+
     #[test]
     fn compute_doc_url_replaces_hyphen_with_underscore_in_docs_name() {
         let version = Version::try_from("2.0.0-beta1").unwrap();
@@ -0,0 +1,641 @@
+//! Doc builds in separate processes.
+//!
+//! `cargo doc` compiles the published crate and runs its build scripts and
+//! proc macros, so the server never builds docs itself. It starts the kellnr
+//! binary with the hidden `doc-build` command instead, twice per build:
+//!
+//! 1. `doc-build --fetch` downloads the dependencies from the configured
+//!    index. It has network access but runs no code of the crate.
+//! 2. `doc-build` builds the docs offline. On Linux it first moves into new
+//!    user, network and mount namespaces, so the build has no network at
+//!    all. Its filesystem only contains the system directories and the
+//!    toolchain, read-only, the build directories, and a private `/tmp`. The
+//!    data directory of kellnr, with its database, crates, docs and
+//!    configuration, is not visible to the build.
+//!
+//! Both processes run in their own process group, which is killed once the
+//! build timeout is reached, and the memory of every process is limited.
+//! Their output is captured as the log of the build.
+//!
+//! The processes only see an allowlist of the server environment, so the
+//! secrets in the `KELLNR_*` variables stay hidden from build scripts. They
+//! use a cargo home and a home directory of their own, which are deleted with
+//! the build, so a crate cannot tamper with the downloads of later builds.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use kellnr_settings::{DocBuildOptions, Docs};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::doc_queue::{fetch_dependencies, generate_docs};
+use crate::docs_error::DocsError;
+
+/// Max. time the output is still read after the build process exited, as
+/// processes started by the build may keep it open.
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Max. duration of the check if the network of builds can be disabled
+const ISOLATION_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Variables of the server environment passed to the build processes
+const ENV_ALLOWLIST: [&str; 4] = ["PATH", "HOME", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN"];
+
+/// Variables of the server environment that configure the network access,
+/// passed to the process that downloads the dependencies
+const FETCH_ENV_ALLOWLIST: [&str; 10] = [
+    "HTTP_PROXY",
+    "http_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "NO_PROXY",
+    "no_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+];
+
+/// Prefix of the cargo settings of the network access, like
+/// `CARGO_HTTP_CAINFO` or `CARGO_HTTP_PROXY`
+const FETCH_ENV_PREFIX: &str = "CARGO_HTTP_";
+
+/// Directory in the temporary folder of a build that is used as cargo home
+pub(crate) const CARGO_HOME_DIR: &str = "cargo-home";
+
+/// Directory in the temporary folder of a build that is used as home
+const HOME_DIR: &str = "home";
+
+/// Empty directory in the temporary folder of a build, where the isolated
+/// filesystem of the build is mounted
+const ROOT_DIR: &str = "root";
+
+/// System directories that are visible read-only to isolated builds
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const SYSTEM_DIRS: [&str; 7] = ["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc"];
+
+/// Limits of a doc build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildLimits {
+    pub timeout: Option<Duration>,
+    /// Max memory of each process in MB (0 = unlimited)
+    pub max_memory: u64,
+    pub require_network_isolation: bool,
+}
+
+impl From<&Docs> for BuildLimits {
+    fn from(docs: &Docs) -> Self {
+        Self {
+            timeout: (docs.build_timeout_seconds > 0)
+                .then(|| Duration::from_secs(docs.build_timeout_seconds)),
+            max_memory: docs.build_max_memory,
+            require_network_isolation: docs.require_network_isolation,
+        }
+    }
+}
+
+/// Build the docs of the unpacked crate in `crate_path` in child processes.
+/// They use directories in `build_dir` as cargo home and home, which is
+/// deleted after the build. The output of the processes is appended to
+/// `log`, also if the build fails.
+pub async fn build_docs(
+    crate_path: &Path,
+    build_dir: &Path,
+    cratesio_index: Option<&str>,
+    kellnr_url: Option<&str>,
+    limits: &BuildLimits,
+    log: &mut String,
+) -> Result<(), DocsError> {
+    let exe = std::env::current_exe()?;
+    let deadline = limits.timeout.map(|t| (Instant::now() + t, t.as_secs()));
+
+    for fetch in [true, false] {
+        let mut cmd = build_command(&exe, build_dir, fetch).await?;
+        cmd.arg("doc-build")
+            .arg(crate_path)
+            .arg("--max-memory")
+            .arg(limits.max_memory.to_string());
+        if let Some(index) = cratesio_index {
+            cmd.arg("--cratesio-index").arg(index);
+        }
+        if let Some(url) = kellnr_url {
+            cmd.arg("--kellnr-url").arg(url);
+        }
+        if fetch {
+            cmd.arg("--fetch");
+        } else {
+            cmd.arg("--sandbox-root").arg(build_dir.join(ROOT_DIR));
+        }
+        if limits.require_network_isolation {
+            cmd.arg("--require-network-isolation");
+        }
+        run_with_deadline(cmd, deadline, log).await?;
+    }
+    Ok(())
+}
+
+/// Check if doc builds can be isolated, by starting a build process that
+/// only isolates itself in a temporary build directory. Returns the reason
+/// if not.
+pub async fn check_network_isolation() -> Result<(), String> {
+    let build_dir =
+        std::env::temp_dir().join(format!("kellnr-isolation-check-{}", std::process::id()));
+    let result = run_isolation_check(&build_dir).await;
+    if let Err(e) = tokio::fs::remove_dir_all(&build_dir).await {
+        warn!("Failed to delete {}: {e}", build_dir.display());
+    }
+    result
+}
+
+async fn run_isolation_check(build_dir: &Path) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let crate_path = build_dir.join("crate");
+    tokio::fs::create_dir_all(&crate_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut cmd = build_command(&exe, build_dir, false)
+        .await
+        .map_err(|e| e.to_string())?;
+    cmd.arg("doc-build")
+        .arg(crate_path)
+        .arg("--sandbox-root")
+        .arg(build_dir.join(ROOT_DIR))
+        .arg("--check-network-isolation");
+    let deadline = Instant::now() + ISOLATION_CHECK_TIMEOUT;
+    let mut log = String::new();
+    match run_with_deadline(
+        cmd,
+        Some((deadline, ISOLATION_CHECK_TIMEOUT.as_secs())),
+        &mut log,
+    )
+    .await
+    {
+        Ok(()) => Ok(()),
+        Err(DocsError::BuildFailed(_)) if !log.trim().is_empty() => Err(log.trim().to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Command for a build process with a cleared environment, except for the
+/// variables in [`ENV_ALLOWLIST`]. The process that downloads the
+/// dependencies (`fetch`) also gets the proxy and certificate settings. The
+/// cargo home and home are directories in `build_dir`, which are created.
+async fn build_command(
+    program: &Path,
+    build_dir: &Path,
+    fetch: bool,
+) -> Result<Command, DocsError> {
+    let cargo_home = build_dir.join(CARGO_HOME_DIR);
+    let home = build_dir.join(HOME_DIR);
+    for dir in [&cargo_home, &home, &build_dir.join(ROOT_DIR)] {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
+    let mut cmd = Command::new(program);
+    cmd.env_clear();
+    for (key, value) in std::env::vars_os() {
+        if is_passed(&key, fetch) {
+            cmd.env(key, value);
+        }
+    }
+    // Rustup finds its toolchains in the home of the server by default
+    if std::env::var_os("RUSTUP_HOME").is_none()
+        && let Some(rustup_home) = std::env::home_dir().map(|h| h.join(".rustup"))
+        && rustup_home.is_dir()
+    {
+        cmd.env("RUSTUP_HOME", rustup_home);
+    }
+    cmd.env("CARGO_HOME", cargo_home).env("HOME", home);
+    Ok(cmd)
+}
+
+/// Whether a variable of the server environment is passed to a build process.
+fn is_passed(key: &std::ffi::OsStr, fetch: bool) -> bool {
+    let network = FETCH_ENV_ALLOWLIST.iter().any(|allowed| key == *allowed)
+        || key
+            .to_str()
+            .is_some_and(|k| k.starts_with(FETCH_ENV_PREFIX));
+    ENV_ALLOWLIST.iter().any(|allowed| key == *allowed) || (fetch && network)
+}
+
+/// Run `cmd` in its own process group and append its output to `log`. The
+/// whole group is killed if it is still running at the deadline, which is
+/// given with the timeout in seconds.
+async fn run_with_deadline(
+    mut cmd: Command,
+    deadline: Option<(Instant, u64)>,
+    log: &mut String,
+) -> Result<(), DocsError> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let mut child = cmd.spawn()?;
+    let pid = child.id();
+    let stdout = read_output(child.stdout.take());
+    let stderr = read_output(child.stderr.take());
+
+    let status = match deadline {
+        Some((deadline, timeout)) => {
+            if let Ok(status) = tokio::time::timeout_at(deadline, child.wait()).await {
+                Ok(status?)
+            } else {
+                kill_process_group(pid);
+                Err(DocsError::BuildTimeout(timeout))
+            }
+        }
+        None => Ok(child.wait().await?),
+    };
+
+    for output in [stdout, stderr] {
+        if let Ok(Ok(output)) = tokio::time::timeout(OUTPUT_GRACE_PERIOD, output).await {
+            log.push_str(&String::from_utf8_lossy(&output));
+        }
+    }
+
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(DocsError::BuildFailed(status.to_string()))
+    }
+}
+
+/// Read the output of a process in the background until it is closed.
+fn read_output(output: Option<impl AsyncRead + Unpin + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    tokio::spawn(async move {
+        let mut buf = Vec::new();
+        if let Some(mut output) = output {
+            // Output read until an error occurred is kept
+            let _ = output.read_to_end(&mut buf).await;
+        }
+        buf
+    })
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: Option<u32>) {
+    use nix::errno::Errno;
+    use nix::sys::signal::{Signal, killpg};
+    use nix::unistd::Pid;
+
+    let Some(pid) = pid else {
+        return;
+    };
+    match killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+        // All processes of the group have already exited
+        Ok(()) | Err(Errno::ESRCH) => {}
+        Err(e) => warn!("Failed to kill doc build processes: {e}"),
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: Option<u32>) {
+    // The direct child is killed when it is dropped
+}
+
+/// Entry point of the `doc-build` command. Has to run before any other
+/// thread is started, as the network can only be disabled for a process
+/// with a single thread. Returns the exit code.
+pub fn run_doc_build(options: &DocBuildOptions) -> i32 {
+    match doc_build(options) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+fn doc_build(options: &DocBuildOptions) -> Result<(), DocsError> {
+    // The paths are the same in the isolated filesystem
+    let crate_path = options.crate_path.canonicalize()?;
+    if options.check_network_isolation {
+        return isolate(&crate_path, options.sandbox_root.as_deref());
+    }
+    limit_memory(options.max_memory)?;
+    let cratesio_index = options.cratesio_index.as_deref();
+    if options.fetch {
+        return fetch_dependencies(&crate_path, cratesio_index);
+    }
+
+    match isolate(&crate_path, options.sandbox_root.as_deref()) {
+        Ok(()) => {}
+        Err(e) if options.require_network_isolation => return Err(e),
+        Err(e) => eprintln!("warning: building docs without isolation: {e}"),
+    }
+    generate_docs(
+        &crate_path,
+        cratesio_index,
+        options.kellnr_url.as_deref(),
+        true,
+    )
+}
+
+/// Isolate the network and the filesystem of this process. Only the crate,
+/// the cargo home and the home are writable, see [`isolate_filesystem`].
+#[cfg(target_os = "linux")]
+fn isolate(crate_path: &Path, sandbox_root: Option<&Path>) -> Result<(), DocsError> {
+    let sandbox_root = sandbox_root
+        .ok_or_else(|| DocsError::Sandbox("no sandbox root directory given".to_string()))?
+        .canonicalize()?;
+    let mut writable = vec![crate_path.to_path_buf()];
+    for var in ["CARGO_HOME", "HOME"] {
+        let dir =
+            std::env::var_os(var).ok_or_else(|| DocsError::Sandbox(format!("{var} is not set")))?;
+        writable.push(PathBuf::from(dir).canonicalize()?);
+    }
+    // Has to be found before the namespaces are entered
+    let read_only = toolchain_dirs();
+
+    isolate_network()?;
+    isolate_filesystem(&sandbox_root, &read_only, &writable)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn isolate(_crate_path: &Path, _sandbox_root: Option<&Path>) -> Result<(), DocsError> {
+    isolate_network()
+}
+
+/// System directories and directories of the Rust toolchain, which the build
+/// needs to read.
+#[cfg(target_os = "linux")]
+fn toolchain_dirs() -> Vec<PathBuf> {
+    let mut dirs = SYSTEM_DIRS.iter().map(PathBuf::from).collect::<Vec<_>>();
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    if let Some(rustup_home) = std::env::var_os("RUSTUP_HOME") {
+        dirs.push(PathBuf::from(rustup_home));
+    }
+    // The sysroot of a toolchain that is not installed with rustup
+    if let Ok(output) = std::process::Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .stderr(Stdio::null())
+        .output()
+        && output.status.success()
+    {
+        dirs.push(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ));
+    }
+    dirs.retain(|d| d.is_absolute() && d.exists());
+    dirs
+}
+
+/// Replace the root of the filesystem of this process by a new one, which
+/// is mounted at `sandbox_root`. It contains the `read_only` and `writable`
+/// directories at their original paths, `/dev`, `/proc` and an empty `/tmp`.
+/// Has to run in a new mount namespace.
+#[cfg(target_os = "linux")]
+fn isolate_filesystem(
+    sandbox_root: &Path,
+    read_only: &[PathBuf],
+    writable: &[PathBuf],
+) -> Result<(), DocsError> {
+    use nix::mount::{MntFlags, MsFlags, mount, umount2};
+    use nix::unistd::{chdir, pivot_root};
+
+    let failed = |what: &str, e: nix::Error| DocsError::Sandbox(format!("failed to {what}: {e}"));
+    let none = None::<&str>;
+
+    // Mounts must not propagate back to the namespace of the server
+    mount(none, "/", none, MsFlags::MS_REC | MsFlags::MS_PRIVATE, none)
+        .map_err(|e| failed("make mounts private", e))?;
+    for (dir, fs) in [
+        (sandbox_root.to_path_buf(), "tmpfs"),
+        (sandbox_root.join("tmp"), "tmpfs"),
+    ] {
+        std::fs::create_dir_all(&dir)?;
+        mount(Some(fs), &dir, Some(fs), MsFlags::empty(), none)
+            .map_err(|e| failed(&format!("mount {}", dir.display()), e))?;
+    }
+
+    let mut binds = [Path::new("/dev"), Path::new("/proc")]
+        .into_iter()
+        .map(|d| (d, true))
+        .chain(read_only.iter().map(|d| (d.as_path(), false)))
+        .chain(writable.iter().map(|d| (d.as_path(), true)))
+        .collect::<Vec<_>>();
+    // Parents are mounted first, so they do not hide their children
+    binds.sort_by_key(|(dir, _)| dir.components().count());
+    for (dir, writable) in binds {
+        let target = sandbox_root.join(dir.strip_prefix("/").unwrap_or(dir));
+        std::fs::create_dir_all(&target)?;
+        mount(
+            Some(dir),
+            &target,
+            none,
+            MsFlags::MS_BIND | MsFlags::MS_REC,
+            none,
+        )
+        .map_err(|e| failed(&format!("bind {}", dir.display()), e))?;
+        if !writable {
+            mount(none, &target, none, read_only_flags(dir)?, none)
+                .map_err(|e| failed(&format!("remount {} read-only", dir.display()), e))?;
+        }
+    }
+
+    chdir(sandbox_root).map_err(|e| failed("enter sandbox root", e))?;
+    // The old root is stacked below the new one and detached right away
+    pivot_root(".", ".").map_err(|e| failed("change root", e))?;
+    umount2(".", MntFlags::MNT_DETACH).map_err(|e| failed("detach old root", e))?;
+    chdir("/").map_err(|e| failed("enter new root", e))?;
+    Ok(())
+}
+
+/// Flags to remount a bind mount of `dir` read-only. The flags of the
+/// original mount, which a user namespace cannot change, are kept.
+#[cfg(target_os = "linux")]
+fn read_only_flags(dir: &Path) -> Result<nix::mount::MsFlags, DocsError> {
+    use nix::mount::MsFlags;
+    use nix::sys::statvfs::{FsFlags, statvfs};
+
+    let stat = statvfs(dir)
+        .map_err(|e| DocsError::Sandbox(format!("failed to stat {}: {e}", dir.display())))?;
+    let mut flags = MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY;
+    for (fs_flag, ms_flag) in [
+        (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+        (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+        (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+        (FsFlags::ST_NOATIME, MsFlags::MS_NOATIME),
+        (FsFlags::ST_NODIRATIME, MsFlags::MS_NODIRATIME),
+        (FsFlags::ST_RELATIME, MsFlags::MS_RELATIME),
+    ] {
+        if stat.flags().contains(fs_flag) {
+            flags |= ms_flag;
+        }
+    }
+    Ok(flags)
+}
+
+/// Limit the address space of this process and all processes it starts.
+#[cfg(unix)]
+fn limit_memory(max_memory: u64) -> Result<(), DocsError> {
+    use nix::sys::resource::{Resource, setrlimit};
+
+    if max_memory == 0 {
+        return Ok(());
+    }
+    let bytes = max_memory.saturating_mul(1024 * 1024);
+    setrlimit(Resource::RLIMIT_AS, bytes, bytes)
+        .map_err(|e| DocsError::Sandbox(format!("failed to limit memory: {e}")))
+}
+
+#[cfg(not(unix))]
+fn limit_memory(max_memory: u64) -> Result<(), DocsError> {
+    if max_memory > 0 {
+        eprintln!("warning: memory limits of doc builds are only supported on Unix");
+    }
+    Ok(())
+}
+
+/// Move this process into new user, network and mount namespaces. The new
+/// network namespace has no interfaces except a loopback device that is down.
+#[cfg(target_os = "linux")]
+fn isolate_network() -> Result<(), DocsError> {
+    use nix::sched::{CloneFlags, unshare};
+    use nix::unistd::{getgid, getuid};
+
+    let (uid, gid) = (getuid(), getgid());
+    unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNET | CloneFlags::CLONE_NEWNS)
+        .map_err(|e| DocsError::Sandbox(format!("failed to create namespaces: {e}")))?;
+    // Map the own user and group into the namespace, files created by the
+    // build could not be written otherwise.
+    std::fs::write("/proc/self/setgroups", "deny")?;
+    std::fs::write("/proc/self/uid_map", format!("{uid} {uid} 1"))?;
+    std::fs::write("/proc/self/gid_map", format!("{gid} {gid} 1"))?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn isolate_network() -> Result<(), DocsError> {
+    Err(DocsError::Sandbox(
+        "network isolation is only supported on Linux".to_string(),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn run_with_deadline_kills_processes_after_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        // The background process is in the same process group and has to be
+        // killed as well, the marker must never be written.
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("(sleep 2; touch {}) & sleep 10", marker.display()));
+
+        let started = Instant::now();
+        let mut log = String::new();
+        let result = run_with_deadline(
+            cmd,
+            Some((Instant::now() + Duration::from_millis(300), 0)),
+            &mut log,
+        )
+        .await;
+
+        assert!(matches!(result, Err(DocsError::BuildTimeout(0))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn run_with_deadline_logs_output_of_failed_build() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("echo out; echo 'error: broken' >&2; exit 3");
+        let mut log = String::from("previous\n");
+
+        let result = run_with_deadline(cmd, None, &mut log).await;
+
+        let Err(DocsError::BuildFailed(status)) = result else {
+            panic!("expected a failed build, got {result:?}");
+        };
+        assert!(status.contains('3'));
+        assert_eq!("previous\nout\nerror: broken\n", log);
+    }
+
+    #[tokio::test]
+    async fn build_command_only_passes_allowed_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cmd = build_command(Path::new("sh"), dir.path(), false)
+            .await
+            .unwrap();
+        cmd.arg("-c").arg("env");
+        let mut log = String::new();
+
+        run_with_deadline(cmd, None, &mut log).await.unwrap();
+
+        // Set by cargo for every test run
+        assert!(std::env::var_os("CARGO_MANIFEST_DIR").is_some());
+        assert!(!log.contains("CARGO_MANIFEST_DIR="));
+        let cargo_home = dir.path().join(CARGO_HOME_DIR);
+        assert!(log.contains(&format!("CARGO_HOME={}\n", cargo_home.display())));
+        let home = dir.path().join(HOME_DIR);
+        assert!(log.contains(&format!("HOME={}\n", home.display())));
+        if let Some(path) = std::env::var_os("PATH") {
+            assert!(log.contains(&format!("PATH={}\n", path.to_string_lossy())));
+        }
+    }
+
+    #[test]
+    fn network_settings_are_only_passed_to_fetch() {
+        for key in [
+            "HTTPS_PROXY",
+            "no_proxy",
+            "SSL_CERT_FILE",
+            "CARGO_HTTP_CAINFO",
+        ] {
+            assert!(is_passed(key.as_ref(), true), "{key}");
+            assert!(!is_passed(key.as_ref(), false), "{key}");
+        }
+        assert!(is_passed("PATH".as_ref(), false));
+        assert!(!is_passed("KELLNR_REGISTRY__DATA_DIR".as_ref(), true));
+        assert!(!is_passed("CARGO_REGISTRY_TOKEN".as_ref(), true));
+    }
+
+    #[tokio::test]
+    async fn run_with_deadline_logs_output_until_timeout() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo started >&2; sleep 10");
+        let mut log = String::new();
+
+        let result = run_with_deadline(
+            cmd,
+            Some((Instant::now() + Duration::from_millis(300), 1)),
+            &mut log,
+        )
+        .await;
+
+        assert!(matches!(result, Err(DocsError::BuildTimeout(1))));
+        assert_eq!("started\n", log);
+    }
+
+    #[test]
+    fn limits_from_settings() {
+        let docs = Docs {
+            build_timeout_seconds: 0,
+            build_max_memory: 512,
+            ..Docs::default()
+        };
+
+        let limits = BuildLimits::from(&docs);
+
+        assert_eq!(None, limits.timeout);
+        assert_eq!(512, limits.max_memory);
+        assert!(limits.require_network_isolation);
+    }
+}
@@ -5,20 +5,23 @@ use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::HeaderMap;
 use hyper::StatusCode;
-use kellnr_appstate::{CratesIoPrefetchSenderState, DbState, SettingsState};
-use kellnr_common::cratesio_downloader::download_crate;
+use kellnr_appstate::{CratesIoPrefetchSenderState, DbState, ProxyPolicyState, SettingsState};
+use kellnr_common::cratesio_downloader::{
+    CrateInfo, download_crate, fetch_crate_info, fetch_crate_info_cached,
+};
 use kellnr_common::cratesio_prefetch_msg::{CratesioPrefetchMsg, InsertData, UpdateData};
 use kellnr_common::index_metadata::IndexMetadata;
+use kellnr_common::metrics;
 use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::prefetch::Prefetch;
+use kellnr_common::proxy_policy::{Blocked, ProxyPolicy};
 use kellnr_common::version::Version;
 use kellnr_db::DbProvider;
 use kellnr_db::provider::PrefetchState;
 use kellnr_storage::cratesio_crate_storage::CratesIoCrateStorage;
 use moka::future::Cache;
 use reqwest::{Client, Url};
-use serde::Deserialize;
 use tracing::{error, trace, warn};
 
 use super::config_json::ConfigJson;
@@ -80,6 +83,7 @@ pub async fn prefetch_cratesio(
     State(db): DbState,
     State(sender): CratesIoPrefetchSenderState,
     State(settings): SettingsState,
+    State(policy): ProxyPolicyState,
 ) -> Result<Prefetch, StatusCode> {
     internal_prefetch_cratesio(
         name,
@@ -88,6 +92,7 @@ pub async fn prefetch_cratesio(
         &settings.proxy.index,
         &sender,
         &settings.proxy,
+        &policy,
     )
     .await
 }
@@ -117,6 +122,7 @@ pub async fn prefetch_len2_cratesio(
     State(db): DbState,
     State(sender): CratesIoPrefetchSenderState,
     State(settings): SettingsState,
+    State(policy): ProxyPolicyState,
 ) -> Result<Prefetch, StatusCode> {
     internal_prefetch_cratesio(
         name,
@@ -125,6 +131,7 @@ pub async fn prefetch_len2_cratesio(
         &settings.proxy.index,
         &sender,
         &settings.proxy,
+        &policy,
     )
     .await
 }
@@ -157,6 +164,7 @@ async fn internal_prefetch_cratesio(
     index_url: &Url,
     sender: &flume::Sender<CratesioPrefetchMsg>,
     proxy_settings: &kellnr_settings::Proxy,
+    policy: &ProxyPolicy,
 ) -> Result<Prefetch, StatusCode> {
     let if_modified_since = headers
         .get("if-modified-since")
@@ -169,33 +177,147 @@ async fn internal_prefetch_cratesio(
         "Prefetching {name} from crates.io cache: Etag {if_none_match:?} - LM {if_modified_since:?}",
     );
 
+    // With a policy, the client may hold index data that was filtered by an
+    // older policy or not at all. The cached data is always loaded then and
+    // compared to the client's copy after the policy was applied.
+    let (etag, last_modified) = if policy.is_empty() {
+        (if_none_match.clone(), if_modified_since.clone())
+    } else {
+        (None, None)
+    };
     let prefetch_state = db
-        .is_cratesio_cache_up_to_date(
-            &name.to_normalized(),
-            if_none_match.clone(),
-            if_modified_since.clone(),
-        )
+        .is_cratesio_cache_up_to_date(&name.to_normalized(), etag, last_modified)
         .await
         .map_err(|e| {
             error!("Could not check if cache is up to date for {name}. Error {e}",);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    match prefetch_state {
+    let prefetch = match prefetch_state {
         PrefetchState::NeedsUpdate(p) => {
-            background_update(name.clone(), sender, if_modified_since, if_none_match);
+            background_update(
+                name.clone(),
+                sender,
+                if_modified_since.clone(),
+                if_none_match.clone(),
+            );
             trace!("Prefetching {name} from crates.io cache: Needs Update");
-            Ok(p)
+            p
         }
         PrefetchState::UpToDate => {
             background_update(name.clone(), sender, if_modified_since, if_none_match);
             trace!("Prefetching {name} from crates.io cache: Up to Date");
-            Err(StatusCode::NOT_MODIFIED)
+            return Err(StatusCode::NOT_MODIFIED);
         }
         PrefetchState::NotFound => {
-            Ok(fetch_cratesio_prefetch(name, index_url, sender, proxy_settings).await?)
+            fetch_cratesio_prefetch(&name, index_url, sender, proxy_settings).await?
+        }
+    };
+
+    // The cache holds the unfiltered data, so policy changes apply right away
+    let info = policy_crate_info(&name, policy, proxy_settings).await;
+    let prefetch = apply_policy_to_prefetch(policy, prefetch, info.as_deref())?;
+    if is_not_modified(
+        &prefetch,
+        if_none_match.as_deref(),
+        if_modified_since.as_deref(),
+    ) {
+        trace!("Prefetching {name} from crates.io cache: Up to Date");
+        return Err(StatusCode::NOT_MODIFIED);
+    }
+    Ok(prefetch)
+}
+
+/// Whether the client already has the served index data, compared like
+/// [`DbProvider::is_cratesio_cache_up_to_date`] does for the cached data.
+fn is_not_modified(
+    prefetch: &Prefetch,
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+) -> bool {
+    match (if_none_match, if_modified_since) {
+        (Some(etag), Some(last_modified)) => {
+            prefetch.etag == etag && prefetch.last_modified == last_modified
         }
+        (Some(etag), None) => prefetch.etag == etag,
+        (None, Some(last_modified)) => prefetch.last_modified == last_modified,
+        (None, None) => false,
+    }
+}
+
+/// Crates.io API data of a crate, if the policy needs the license or the
+/// publish time of its versions. If it cannot be fetched, every version is
+/// treated as having an unknown license.
+async fn policy_crate_info(
+    name: &str,
+    policy: &ProxyPolicy,
+    proxy_settings: &kellnr_settings::Proxy,
+) -> Option<Arc<CrateInfo>> {
+    if !policy.checks_license() && !policy.checks_age() {
+        return None;
+    }
+    let info = fetch_crate_info_cached(get_client(proxy_settings), name, &proxy_settings.api)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Could not fetch crate info of {name} from crates.io: {e}");
+            Arc::default()
+        });
+    Some(info)
+}
+
+/// Remove the versions the proxy policy blocks from index data that is served
+/// to cargo. If versions are removed, the `ETag` is changed so that cargo
+/// does not keep the filtered data once the policy allows more versions.
+fn apply_policy_to_prefetch(
+    policy: &ProxyPolicy,
+    prefetch: Prefetch,
+    info: Option<&CrateInfo>,
+) -> Result<Prefetch, StatusCode> {
+    if policy.is_empty() {
+        return Ok(prefetch);
+    }
+
+    let data = String::from_utf8_lossy(&prefetch.data);
+    let lines = data
+        .lines()
+        .filter(|line| {
+            serde_json::from_str::<IndexMetadata>(line)
+                .map_or(true, |m| check_policy(policy, &m, info).is_ok())
+        })
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if lines.len() == data.lines().count() {
+        return Ok(prefetch);
+    }
+
+    let mut filtered = lines.join("\n");
+    filtered.push('\n');
+    Ok(Prefetch {
+        data: filtered.into_bytes(),
+        etag: format!("{}-policy-{}", prefetch.etag, lines.len()),
+        last_modified: prefetch.last_modified,
+    })
+}
+
+/// Check an index entry against the proxy policy. The license is only checked
+/// if the crate info from the crates.io API is given.
+fn check_policy(
+    policy: &ProxyPolicy,
+    metadata: &IndexMetadata,
+    info: Option<&CrateInfo>,
+) -> Result<(), Blocked> {
+    let version_info = info.and_then(|i| i.versions.get(&metadata.vers));
+    let published = metadata
+        .pubtime
+        .or_else(|| version_info.map(|v| v.created_at));
+    policy.check_version(&metadata.name, &metadata.vers, published)?;
+    if info.is_some() {
+        policy.check_license(version_info.and_then(|v| v.license.as_deref()))?;
     }
+    Ok(())
 }
 
 fn background_update(
@@ -236,36 +358,13 @@ async fn background_update_thread(
     }
 }
 
-async fn fetch_cratesio_description(
+async fn fetch_cratesio_crate_info(
     name: &str,
     proxy_settings: &kellnr_settings::Proxy,
-) -> Result<Option<String>, StatusCode> {
-    #[derive(Deserialize)]
-    struct Krate {
-        description: Option<String>,
-    }
-    #[derive(Deserialize)]
-    struct MinimalCrate {
-        #[serde(rename = "crate")]
-        krate: Krate,
-    }
-
-    let url = proxy_settings
-        .api
-        .join(name)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let response = get_client(proxy_settings)
-        .get(url)
-        .send()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let desc = response
-        .json::<MinimalCrate>()
+) -> Result<CrateInfo, StatusCode> {
+    fetch_crate_info(get_client(proxy_settings), name, &proxy_settings.api)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(desc.krate.description)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 #[derive(Clone)]
@@ -395,12 +494,17 @@ async fn convert_index_data(
 
     match metadata {
         Ok(m) => {
-            let desc = fetch_cratesio_description(name, proxy_settings)
+            // The proxy policy is applied when the data is served, the
+            // cache keeps all versions.
+            let desc = fetch_cratesio_crate_info(name, proxy_settings)
                 .await
-                .unwrap_or_else(|e| {
-                    error!("Could not fetch description for from crates.io {name}: {e:?}",);
-                    None
-                });
+                .map_or_else(
+                    |e| {
+                        error!("Could not fetch description for from crates.io {name}: {e:?}",);
+                        None
+                    },
+                    |info| info.description,
+                );
 
             MetadataDescription {
                 metadata: m,
@@ -544,6 +648,7 @@ async fn fetch_index_data(
             }
         }
         if i >= max_retries {
+            metrics::upstream_error(metrics::CRATES_IO);
             error!("Could not fetch index from crates.io for {name} after 3 tries",);
             break None;
         }
@@ -581,6 +686,7 @@ async fn fetch_index_data(
                 Some(prefetch_data)
             }
             s => {
+                metrics::upstream_error(metrics::CRATES_IO);
                 error!("Unexpected status code from crates.io for {name}: {s}");
                 None
             }
@@ -591,7 +697,7 @@ async fn fetch_index_data(
 }
 
 async fn fetch_cratesio_prefetch(
-    name: OriginalName,
+    name: &OriginalName,
     index_url: &Url,
     sender: &flume::Sender<CratesioPrefetchMsg>,
     proxy_settings: &kellnr_settings::Proxy,
@@ -634,7 +740,7 @@ async fn fetch_cratesio_prefetch(
                     // which would take a long time.
                     sender
                         .send(CratesioPrefetchMsg::Insert(InsertData {
-                            name,
+                            name: name.clone(),
                             etag,
                             last_modified,
                             data,
@@ -647,12 +753,14 @@ async fn fetch_cratesio_prefetch(
                     Ok(prefetch)
                 }
                 s => {
+                    metrics::upstream_error(metrics::CRATES_IO);
                     error!("Unexpected status code from crates.io for {name}: {s}");
                     Err(StatusCode::INTERNAL_SERVER_ERROR)
                 }
             }
         }
         Err(e) => {
+            metrics::upstream_error(metrics::CRATES_IO);
             error!("Error fetching prefetch data from crates.io: {e}");
             Err(StatusCode::NOT_FOUND)
         }
@@ -695,9 +803,10 @@ mod tests {
     #[tokio::test]
     async fn fetch_cratesio_description_works() {
         let proxy_settings = kellnr_settings::Proxy::default();
-        let desc = fetch_cratesio_description("rocket", &proxy_settings)
+        let desc = fetch_cratesio_crate_info("rocket", &proxy_settings)
             .await
-            .unwrap();
+            .unwrap()
+            .description;
         assert_eq!(
             Some(
                 "Web framework with a focus on usability, security, extensibility, and speed.\n"
@@ -710,10 +819,158 @@ mod tests {
     #[tokio::test]
     async fn fetch_cratesio_description_not_existent_crate() {
         let proxy_settings = kellnr_settings::Proxy::default();
-        let desc = fetch_cratesio_description("does_not_exists123", &proxy_settings).await;
+        let desc = fetch_cratesio_crate_info("does_not_exists123", &proxy_settings).await;
         assert_eq!(Err(StatusCode::INTERNAL_SERVER_ERROR), desc);
     }
 
+    fn index_line(vers: &str, pubtime: Option<chrono::DateTime<chrono::Utc>>) -> String {
+        let pubtime = pubtime
+            .map(|p| format!(",\"pubtime\":\"{}\"", p.format("%Y-%m-%dT%H:%M:%SZ")))
+            .unwrap_or_default();
+        format!(
+            "{{\"name\":\"foo\",\"vers\":\"{vers}\",\"deps\":[],\"cksum\":\"abc\",\"features\":{{}},\"yanked\":false{pubtime}}}"
+        )
+    }
+
+    fn test_policy(deny: &[&str], licenses: &[&str], min_age_days: u64) -> ProxyPolicy {
+        let to_vec = |v: &[&str]| v.iter().map(ToString::to_string).collect::<Vec<_>>();
+        ProxyPolicy::new(&to_vec(deny), &to_vec(licenses), min_age_days).unwrap()
+    }
+
+    #[test]
+    fn apply_policy_removes_blocked_versions_and_changes_etag() {
+        let fresh = chrono::Utc::now() - chrono::Duration::days(1);
+        let data = [
+            index_line("1.0.0", None),
+            index_line("1.1.0", None),
+            index_line("1.2.0", Some(fresh)),
+        ]
+        .join("\n");
+        let prefetch = Prefetch {
+            data: format!("{data}\n").into_bytes(),
+            etag: "etag".to_string(),
+            last_modified: "date".to_string(),
+        };
+
+        let filtered = apply_policy_to_prefetch(
+            &test_policy(&["foo@=1.1.0"], &[], 7),
+            prefetch.clone(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("{}\n", index_line("1.0.0", None)).into_bytes(),
+            filtered.data
+        );
+        assert_ne!("etag", filtered.etag);
+        assert_eq!(
+            prefetch,
+            apply_policy_to_prefetch(&test_policy(&["bar"], &[], 0), prefetch.clone(), None)
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn policy_applies_to_clients_with_unfiltered_index() {
+        let data = format!(
+            "{}\n{}\n",
+            index_line("1.0.0", None),
+            index_line("1.1.0", None)
+        );
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_cratesio_cache_up_to_date()
+            .with(
+                mockall::predicate::always(),
+                mockall::predicate::eq(None),
+                mockall::predicate::eq(None),
+            )
+            .returning(move |_, _, _| {
+                Ok(PrefetchState::NeedsUpdate(Prefetch {
+                    data: data.clone().into_bytes(),
+                    etag: "etag".to_string(),
+                    last_modified: "date".to_string(),
+                }))
+            });
+        let db: Arc<dyn DbProvider> = Arc::new(mock_db);
+        let (sender, _receiver) = flume::unbounded::<CratesioPrefetchMsg>();
+        let proxy_settings = kellnr_settings::Proxy::default();
+        let policy = test_policy(&["foo@=1.1.0"], &[], 0);
+        let prefetch = |etag: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("if-none-match", etag.parse().unwrap());
+            headers.insert("if-modified-since", "date".parse().unwrap());
+            internal_prefetch_cratesio(
+                OriginalName::from_unchecked("foo".to_string()),
+                headers,
+                &db,
+                &proxy_settings.index,
+                &sender,
+                &proxy_settings,
+                &policy,
+            )
+        };
+
+        // The client holds the index from before the policy was enabled
+        let filtered = prefetch("etag").await.unwrap();
+        assert_eq!(
+            format!("{}\n", index_line("1.0.0", None)).into_bytes(),
+            filtered.data
+        );
+
+        assert_eq!(
+            Err(StatusCode::NOT_MODIFIED),
+            prefetch(&filtered.etag).await
+        );
+    }
+
+    #[test]
+    fn apply_policy_checks_licenses_from_crate_info() {
+        let version = |license: Option<&str>| kellnr_common::cratesio_downloader::VersionInfo {
+            license: license.map(ToString::to_string),
+            created_at: chrono::Utc::now() - chrono::Duration::days(100),
+        };
+        let info = CrateInfo {
+            description: None,
+            versions: [
+                ("1.0.0".to_string(), version(Some("MIT"))),
+                ("1.1.0".to_string(), version(Some("GPL-3.0"))),
+            ]
+            .into(),
+        };
+        let data = [
+            index_line("1.0.0", None),
+            index_line("1.1.0", None),
+            index_line("1.2.0", None),
+        ]
+        .join("\n");
+        let prefetch = Prefetch {
+            data: data.into_bytes(),
+            etag: "etag".to_string(),
+            last_modified: "date".to_string(),
+        };
+        let policy = test_policy(&[], &["MIT"], 0);
+
+        let filtered = apply_policy_to_prefetch(&policy, prefetch.clone(), Some(&info)).unwrap();
+        assert_eq!(
+            format!("{}\n", index_line("1.0.0", None)).into_bytes(),
+            filtered.data
+        );
+
+        // Without crate info, licenses are not checked
+        assert_eq!(
+            prefetch,
+            apply_policy_to_prefetch(&policy, prefetch.clone(), None).unwrap()
+        );
+
+        // Nothing left to serve
+        assert_eq!(
+            Err(StatusCode::NOT_FOUND),
+            apply_policy_to_prefetch(&test_policy(&["foo"], &[], 0), prefetch, None)
+        );
+    }
+
     #[tokio::test]
     async fn fetch_cratesio_prefetch_works() {
         let r = app()
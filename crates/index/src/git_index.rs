@@ -0,0 +1,396 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+
+use flume::Receiver;
+use kellnr_common::git_index_msg::GitIndexMsg;
+use kellnr_common::index_metadata::index_sub_path;
+use kellnr_common::normalized_name::NormalizedName;
+use kellnr_db::DbProvider;
+use kellnr_db::error::DbError;
+use kellnr_settings::Settings;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{error, info, trace};
+
+use crate::config_json::ConfigJson;
+
+/// Branch the index is committed to. `HEAD` of the bare repository points to it.
+const BRANCH: &str = "refs/heads/master";
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitIndexError {
+    #[error("Failed to run git: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("git {0} failed: {1}")]
+    Git(String, String),
+    #[error("Failed to read index data: {0}")]
+    Db(#[from] DbError),
+}
+
+/// Bare git repository that mirrors the sparse index for clients that
+/// only speak the git index protocol.
+///
+/// The database stays the source of truth. The repository is written with
+/// `git fast-import`, so it never needs a working tree.
+pub struct GitIndex {
+    path: PathBuf,
+    config_json: String,
+}
+
+impl GitIndex {
+    /// Open the bare repository at `path`, creating it if it does not exist.
+    pub async fn open(
+        path: impl Into<PathBuf>,
+        config_json: String,
+    ) -> Result<Self, GitIndexError> {
+        let index = Self {
+            path: path.into(),
+            config_json,
+        };
+
+        if !index.path.join("HEAD").exists() {
+            info!("Creating git index at {}", index.path.display());
+            tokio::fs::create_dir_all(&index.path).await?;
+            run_git(&index.path, &["init", "--bare", "--quiet"], None).await?;
+            run_git(&index.path, &["symbolic-ref", "HEAD", BRANCH], None).await?;
+        }
+
+        Ok(index)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rewrite the whole index from the database. Files of crates that no
+    /// longer exist are removed.
+    pub async fn sync_all(&self, db: &Arc<dyn DbProvider>) -> Result<(), GitIndexError> {
+        let mut files = vec![];
+        for krate in db.get_crate_summaries().await? {
+            let name = NormalizedName::from_unchecked(krate.name);
+            if let Some(data) = index_file(db, &name).await? {
+                files.push((index_sub_path(&name), Some(data)));
+            }
+        }
+
+        self.commit(files, true, "Sync index with database").await
+    }
+
+    /// Write the current index entries of `names` from the database.
+    pub async fn update_crates(
+        &self,
+        db: &Arc<dyn DbProvider>,
+        names: &[NormalizedName],
+    ) -> Result<(), GitIndexError> {
+        let mut files = vec![];
+        for name in names {
+            files.push((index_sub_path(name), index_file(db, name).await?));
+        }
+
+        let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        self.commit(files, false, &format!("Update {}", names.join(", ")))
+            .await
+    }
+
+    /// Commit `files` on top of the current branch. A file without content is
+    /// removed. With `replace_all`, all files not in `files` are removed as well.
+    /// `config.json` is written with every commit.
+    async fn commit(
+        &self,
+        files: Vec<(String, Option<Vec<u8>>)>,
+        replace_all: bool,
+        message: &str,
+    ) -> Result<(), GitIndexError> {
+        let parent = self.head().await?;
+
+        let mut stream = vec![];
+        writeln!(stream, "commit {BRANCH}")?;
+        writeln!(stream, "committer kellnr <kellnr@localhost> now")?;
+        write_data(&mut stream, message.as_bytes())?;
+        if let Some(parent) = &parent {
+            writeln!(stream, "from {parent}")?;
+        }
+        if replace_all {
+            writeln!(stream, "deleteall")?;
+        }
+        writeln!(stream, "M 100644 inline config.json")?;
+        write_data(&mut stream, self.config_json.as_bytes())?;
+        for (path, data) in files {
+            match data {
+                Some(data) => {
+                    writeln!(stream, "M 100644 inline {path}")?;
+                    write_data(&mut stream, &data)?;
+                }
+                None => writeln!(stream, "D {path}")?,
+            }
+        }
+
+        run_git(
+            &self.path,
+            &["fast-import", "--quiet", "--date-format=now"],
+            Some(stream),
+        )
+        .await?;
+
+        // Do not keep commits that change nothing, e.g. a sync on startup
+        // without changes since the last run.
+        if let (Some(parent), Some(head)) = (parent, self.head().await?)
+            && self.tree(&parent).await? == self.tree(&head).await?
+        {
+            run_git(&self.path, &["update-ref", BRANCH, &parent, &head], None).await?;
+            trace!("Git index unchanged");
+        } else {
+            trace!("Git index updated: {message}");
+        }
+
+        Ok(())
+    }
+
+    /// Current commit of the index branch, `None` if nothing was committed yet.
+    async fn head(&self) -> Result<Option<String>, GitIndexError> {
+        let output = git_command(&self.path, &["rev-parse", "--verify", "--quiet", BRANCH])
+            .output()
+            .await?;
+        if output.status.success() {
+            Ok(Some(
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn tree(&self, commit: &str) -> Result<String, GitIndexError> {
+        let output = run_git(
+            &self.path,
+            &["rev-parse", &format!("{commit}^{{tree}}")],
+            None,
+        )
+        .await?;
+        Ok(String::from_utf8_lossy(&output).trim().to_string())
+    }
+}
+
+/// Create the git index under `settings.git_index_path()` and keep it in
+/// sync with the database.
+///
+/// The whole index is rewritten on startup. Afterwards, only crates received
+/// on `recv` are updated, all messages that queued up are committed at once.
+pub async fn init_git_index(
+    settings: &Settings,
+    db: Arc<dyn DbProvider>,
+    recv: Receiver<GitIndexMsg>,
+) {
+    let config_json = ConfigJson::from((settings, "crates", true))
+        .to_json()
+        .expect("Failed to serialize config.json");
+    let index = GitIndex::open(settings.git_index_path(), config_json)
+        .await
+        .expect("Failed to initialize git index. Is git installed?");
+
+    tokio::spawn(async move {
+        if let Err(e) = index.sync_all(&db).await {
+            error!("Failed to sync git index: {e}");
+        }
+
+        while let Ok(msg) = recv.recv_async().await {
+            let names = std::iter::once(msg)
+                .chain(recv.drain())
+                .map(|GitIndexMsg::Update(name)| name.into_inner())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(NormalizedName::from_unchecked)
+                .collect::<Vec<_>>();
+
+            if let Err(e) = index.update_crates(&db, &names).await {
+                error!("Failed to update git index: {e}");
+            }
+        }
+    });
+}
+
+/// Index file of the crate, `None` if the crate has no versions.
+async fn index_file(
+    db: &Arc<dyn DbProvider>,
+    name: &NormalizedName,
+) -> Result<Option<Vec<u8>>, GitIndexError> {
+    match db.get_prefetch_data(name).await {
+        Ok(prefetch) if !prefetch.data.is_empty() => Ok(Some(prefetch.data)),
+        Ok(_) | Err(DbError::CrateNotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_data(stream: &mut Vec<u8>, data: &[u8]) -> std::io::Result<()> {
+    writeln!(stream, "data {}", data.len())?;
+    stream.extend_from_slice(data);
+    writeln!(stream)
+}
+
+fn git_command(git_dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("--git-dir").arg(git_dir).args(args);
+    cmd
+}
+
+/// Run git on the repository at `git_dir` and return its stdout.
+pub(crate) async fn run_git(
+    git_dir: &Path,
+    args: &[&str],
+    stdin: Option<Vec<u8>>,
+) -> Result<Vec<u8>, GitIndexError> {
+    let mut child = git_command(git_dir, args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Write stdin concurrently, git may fill the stdout pipe before it has
+    // read all of its input.
+    let writer = match (stdin, child.stdin.take()) {
+        (Some(data), Some(mut pipe)) => Some(tokio::spawn(async move {
+            pipe.write_all(&data).await?;
+            pipe.shutdown().await
+        })),
+        _ => None,
+    };
+
+    let output = child.wait_with_output().await?;
+    if let Some(writer) = writer {
+        writer.await.map_err(std::io::Error::other)??;
+    }
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(GitIndexError::Git(
+            args.first().copied().unwrap_or_default().to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kellnr_common::prefetch::Prefetch;
+    use kellnr_db::CrateSummary;
+    use kellnr_db::mock::MockDb;
+    use mockall::predicate::*;
+
+    use super::*;
+
+    fn summary(name: &str) -> CrateSummary {
+        CrateSummary {
+            name: name.to_string(),
+            max_version: "1.0.0".to_string(),
+            last_updated: String::new(),
+            total_downloads: 0,
+        }
+    }
+
+    fn prefetch(data: &str) -> Prefetch {
+        Prefetch {
+            data: data.as_bytes().to_vec(),
+            etag: String::new(),
+            last_modified: String::new(),
+        }
+    }
+
+    async fn show(index: &GitIndex, path: &str) -> Option<String> {
+        run_git(index.path(), &["show", &format!("HEAD:{path}")], None)
+            .await
+            .ok()
+            .map(|o| String::from_utf8(o).unwrap())
+    }
+
+    async fn commit_count(index: &GitIndex) -> usize {
+        let out = run_git(index.path(), &["rev-list", "--count", "HEAD"], None)
+            .await
+            .unwrap();
+        String::from_utf8(out).unwrap().trim().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn sync_all_writes_index_and_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut db = MockDb::new();
+        db.expect_get_crate_summaries()
+            .returning(|| Ok(vec![summary("serde"), summary("ab")]));
+        db.expect_get_prefetch_data()
+            .with(eq("serde"))
+            .returning(|_| Ok(prefetch("{\"name\":\"serde\"}\n")));
+        db.expect_get_prefetch_data()
+            .with(eq("ab"))
+            .returning(|_| Ok(prefetch("{\"name\":\"ab\"}\n")));
+        let db = Arc::new(db) as Arc<dyn DbProvider>;
+
+        let index = GitIndex::open(dir.path().join("index.git"), "{}".to_string())
+            .await
+            .unwrap();
+        index.sync_all(&db).await.unwrap();
+
+        assert_eq!(Some("{}".to_string()), show(&index, "config.json").await);
+        assert_eq!(
+            Some("{\"name\":\"serde\"}\n".to_string()),
+            show(&index, "se/rd/serde").await
+        );
+        assert_eq!(
+            Some("{\"name\":\"ab\"}\n".to_string()),
+            show(&index, "2/ab").await
+        );
+
+        // Nothing changed, so no new commit is created
+        index.sync_all(&db).await.unwrap();
+        assert_eq!(1, commit_count(&index).await);
+    }
+
+    #[tokio::test]
+    async fn update_crates_writes_and_removes_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut db = MockDb::new();
+        db.expect_get_prefetch_data()
+            .with(eq("serde"))
+            .returning(|_| Ok(prefetch("{\"name\":\"serde\"}\n")));
+        db.expect_get_prefetch_data()
+            .with(eq("gone"))
+            .returning(|_| Err(DbError::CrateNotFound("gone".to_string())));
+        let db = Arc::new(db) as Arc<dyn DbProvider>;
+
+        let index = GitIndex::open(dir.path().join("index.git"), "{}".to_string())
+            .await
+            .unwrap();
+        index
+            .commit(
+                vec![("go/ne/gone".to_string(), Some(b"old".to_vec()))],
+                false,
+                "init",
+            )
+            .await
+            .unwrap();
+
+        index
+            .update_crates(
+                &db,
+                &[
+                    NormalizedName::from_unchecked_str("gone"),
+                    NormalizedName::from_unchecked_str("serde"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(None, show(&index, "go/ne/gone").await);
+        assert_eq!(
+            Some("{\"name\":\"serde\"}\n".to_string()),
+            show(&index, "se/rd/serde").await
+        );
+        assert_eq!(2, commit_count(&index).await);
+    }
+}
@@ -0,0 +1,261 @@
+use std::io::Read;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::Response;
+use flate2::read::GzDecoder;
+use kellnr_appstate::SettingsState;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::git_index::run_git;
+
+const UPLOAD_PACK: &str = "git-upload-pack";
+/// Maximum size of a decompressed upload pack request. Negotiation requests
+/// only list object ids, so this is far above what a git client sends.
+const MAX_REQUEST_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct InfoRefsParams {
+    /// Requested git service, only `git-upload-pack` is supported
+    service: Option<String>,
+}
+
+/// Git index reference discovery
+///
+/// First request of a git smart HTTP fetch of the git index.
+#[utoipa::path(
+    get,
+    path = "/info/refs",
+    tag = "crates",
+    params(InfoRefsParams),
+    responses(
+        (status = 200, description = "Reference advertisement"),
+        (status = 403, description = "Unsupported git service")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn info_refs(
+    Query(params): Query<InfoRefsParams>,
+    State(settings): SettingsState,
+) -> Result<Response, StatusCode> {
+    // Only fetching is supported, the index is written by kellnr only.
+    // Dumb HTTP clients (no service) are rejected as well.
+    if params.service.as_deref() != Some(UPLOAD_PACK) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let path = settings.git_index_path();
+    let refs = run_git(
+        &path,
+        &[
+            "upload-pack",
+            "--stateless-rpc",
+            "--advertise-refs",
+            &path.to_string_lossy(),
+        ],
+        None,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to advertise git index refs: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut body = pkt_line(&format!("# service={UPLOAD_PACK}\n"));
+    body.extend_from_slice(b"0000");
+    body.extend_from_slice(&refs);
+
+    Ok(git_response(
+        "application/x-git-upload-pack-advertisement",
+        body,
+    ))
+}
+
+/// Git index upload pack
+///
+/// Sends the objects the git client asked for.
+#[utoipa::path(
+    post,
+    path = "/git-upload-pack",
+    tag = "crates",
+    request_body(content = Vec<u8>, content_type = "application/x-git-upload-pack-request"),
+    responses(
+        (status = 200, description = "Pack data"),
+        (status = 400, description = "Invalid request body"),
+        (status = 413, description = "Decompressed request body too large")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn upload_pack(
+    headers: HeaderMap,
+    State(settings): SettingsState,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    // The git CLI compresses large requests
+    let request = if headers
+        .get(header::CONTENT_ENCODING)
+        .is_some_and(|v| v == "gzip")
+    {
+        // Read one byte past the limit to detect oversized requests
+        let mut decoded = vec![];
+        GzDecoder::new(&body[..])
+            .take(MAX_REQUEST_SIZE + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        if decoded.len() as u64 > MAX_REQUEST_SIZE {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        decoded
+    } else {
+        body.to_vec()
+    };
+
+    let path = settings.git_index_path();
+    let pack = run_git(
+        &path,
+        &["upload-pack", "--stateless-rpc", &path.to_string_lossy()],
+        Some(request),
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to upload git index pack: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(git_response("application/x-git-upload-pack-result", pack))
+}
+
+fn pkt_line(line: &str) -> Vec<u8> {
+    format!("{:04x}{line}", line.len() + 4).into_bytes()
+}
+
+fn git_response(content_type: &'static str, body: Vec<u8>) -> Response {
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use http_body_util::BodyExt;
+    use kellnr_appstate::AppStateData;
+    use kellnr_db::DbProvider;
+    use kellnr_db::mock::MockDb;
+    use kellnr_settings::{Registry, Settings};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::git_index::GitIndex;
+
+    async fn app(data_dir: &std::path::Path) -> Router {
+        let settings = Settings {
+            registry: Registry {
+                data_dir: data_dir.to_string_lossy().to_string(),
+                git_index: true,
+                ..Registry::default()
+            },
+            ..kellnr_settings::test_settings()
+        };
+        let mut db = MockDb::new();
+        db.expect_get_crate_summaries().returning(|| Ok(vec![]));
+        let db = Arc::new(db) as Arc<dyn DbProvider>;
+        GitIndex::open(settings.git_index_path(), "{}".to_string())
+            .await
+            .unwrap()
+            .sync_all(&db)
+            .await
+            .unwrap();
+
+        let state = AppStateData {
+            settings: Arc::new(settings),
+            ..kellnr_appstate::test_state()
+        };
+        Router::new()
+            .route("/info/refs", get(info_refs))
+            .route("/git-upload-pack", post(upload_pack))
+            .with_state(state)
+    }
+
+    #[test]
+    fn pkt_line_prefixes_length() {
+        assert_eq!(
+            b"001e# service=git-upload-pack\n".to_vec(),
+            pkt_line("# service=git-upload-pack\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn info_refs_advertises_upload_pack() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let r = app(dir.path())
+            .await
+            .oneshot(
+                Request::get("/info/refs?service=git-upload-pack")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!(
+            "application/x-git-upload-pack-advertisement",
+            r.headers().get(header::CONTENT_TYPE).unwrap()
+        );
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.starts_with("001e# service=git-upload-pack\n0000"));
+        assert!(body.contains("refs/heads/master"));
+    }
+
+    #[tokio::test]
+    async fn upload_pack_rejects_oversized_gzip_body() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut encoder = GzEncoder::new(vec![], Compression::best());
+        encoder
+            .write_all(&vec![0; MAX_REQUEST_SIZE as usize + 1])
+            .unwrap();
+        let body = encoder.finish().unwrap();
+
+        let r = app(dir.path())
+            .await
+            .oneshot(
+                Request::post("/git-upload-pack")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, r.status());
+    }
+
+    #[tokio::test]
+    async fn info_refs_rejects_receive_pack() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let r = app(dir.path())
+            .await
+            .oneshot(
+                Request::get("/info/refs?service=git-receive-pack")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+}
@@ -1,3 +1,6 @@
 mod config_json;
 pub mod cratesio_prefetch_api;
+pub mod git_index;
+pub mod git_index_api;
 pub mod kellnr_prefetch_api;
+pub mod upstream_prefetch_api;
@@ -0,0 +1,332 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use kellnr_appstate::{ProxyClientState, SettingsState};
+use kellnr_common::index_metadata::index_sub_path;
+use kellnr_common::metrics;
+use kellnr_common::original_name::OriginalName;
+use kellnr_common::prefetch::Prefetch;
+use kellnr_settings::Upstream;
+use reqwest::Client;
+use tracing::{error, trace};
+
+use super::config_json::ConfigJson;
+
+/// Get upstream registry configuration
+///
+/// Returns the sparse registry configuration for a proxied upstream registry.
+#[utoipa::path(
+    get,
+    path = "/config.json",
+    tag = "upstreams",
+    params(
+        ("upstream" = String, Path, description = "Name of the upstream registry")
+    ),
+    responses(
+        (status = 200, description = "Upstream proxy configuration"),
+        (status = 404, description = "Upstream not configured")
+    ),
+    security(("cargo_token" = []))
+)]
+#[allow(clippy::unused_async)] // part of the router
+pub async fn config_upstream(
+    Path(upstream): Path<String>,
+    State(settings): SettingsState,
+) -> Result<Json<ConfigJson>, StatusCode> {
+    if settings.proxy.upstream(&upstream).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(ConfigJson::from((
+        &(*settings),
+        format!("proxy/{upstream}").as_str(),
+        false,
+    ))))
+}
+
+/// Prefetch crate metadata from an upstream registry (3+ char names)
+///
+/// Forwards the index request to the upstream registry.
+#[utoipa::path(
+    get,
+    path = "/{a}/{b}/{name}",
+    tag = "upstreams",
+    params(
+        ("upstream" = String, Path, description = "Name of the upstream registry"),
+        ("a" = String, Path, description = "First two characters of crate name"),
+        ("b" = String, Path, description = "Next two characters of crate name"),
+        ("name" = String, Path, description = "Full crate name")
+    ),
+    responses(
+        (status = 200, description = "Crate index metadata"),
+        (status = 304, description = "Not modified"),
+        (status = 404, description = "Crate or upstream not found"),
+        (status = 502, description = "Upstream registry not reachable")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn prefetch_upstream(
+    Path((upstream, _a, _b, name)): Path<(String, String, String, OriginalName)>,
+    headers: HeaderMap,
+    State(settings): SettingsState,
+    State(client): ProxyClientState,
+) -> Result<Prefetch, StatusCode> {
+    let upstream = settings
+        .proxy
+        .upstream(&upstream)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    fetch_upstream_index(&client, upstream, &name, &headers).await
+}
+
+/// Prefetch crate metadata from an upstream registry (1-2 char names)
+///
+/// Forwards the index request to the upstream registry.
+#[utoipa::path(
+    get,
+    path = "/{a}/{name}",
+    tag = "upstreams",
+    params(
+        ("upstream" = String, Path, description = "Name of the upstream registry"),
+        ("a" = String, Path, description = "Length prefix (1 or 2)"),
+        ("name" = String, Path, description = "Full crate name")
+    ),
+    responses(
+        (status = 200, description = "Crate index metadata"),
+        (status = 304, description = "Not modified"),
+        (status = 404, description = "Crate or upstream not found"),
+        (status = 502, description = "Upstream registry not reachable")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn prefetch_len2_upstream(
+    Path((upstream, _a, name)): Path<(String, String, OriginalName)>,
+    headers: HeaderMap,
+    State(settings): SettingsState,
+    State(client): ProxyClientState,
+) -> Result<Prefetch, StatusCode> {
+    let upstream = settings
+        .proxy
+        .upstream(&upstream)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    fetch_upstream_index(&client, upstream, &name, &headers).await
+}
+
+/// Fetch the index file of `name` from the upstream. The conditional headers
+/// of the cargo request are forwarded, so unchanged files are answered with a
+/// 304 by the upstream.
+async fn fetch_upstream_index(
+    client: &Client,
+    upstream: &Upstream,
+    name: &OriginalName,
+    headers: &HeaderMap,
+) -> Result<Prefetch, StatusCode> {
+    let url = upstream
+        .index
+        .join(&index_sub_path(name))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut request = client.get(url);
+    if let Some(token) = &upstream.token {
+        request = request.header(header::AUTHORIZATION, token);
+    }
+    for h in [header::IF_NONE_MATCH, header::IF_MODIFIED_SINCE] {
+        if let Some(value) = headers.get(&h) {
+            request = request.header(h, value);
+        }
+    }
+
+    let response = request.send().await.map_err(|e| {
+        metrics::upstream_error(&upstream.name);
+        error!(
+            "Could not fetch index of {name} from upstream {}: {e}",
+            upstream.name
+        );
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    match response.status() {
+        StatusCode::OK => {
+            let header_value = |h| {
+                response
+                    .headers()
+                    .get(h)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let etag = header_value(header::ETAG);
+            let last_modified = header_value(header::LAST_MODIFIED);
+            let data = response.bytes().await.map_err(|e| {
+                error!(
+                    "Could not read index of {name} from upstream {}: {e}",
+                    upstream.name
+                );
+                StatusCode::BAD_GATEWAY
+            })?;
+            Ok(Prefetch {
+                data: data.to_vec(),
+                etag,
+                last_modified,
+            })
+        }
+        status @ (StatusCode::NOT_MODIFIED
+        | StatusCode::NOT_FOUND
+        | StatusCode::GONE
+        | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS) => Err(status),
+        status => {
+            metrics::upstream_error(&upstream.name);
+            trace!(
+                "Unexpected status {status} for index of {name} from upstream {}",
+                upstream.name
+            );
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use kellnr_appstate::AppStateData;
+    use kellnr_settings::{Proxy, Settings};
+    use reqwest::Url;
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Serve a minimal sparse index that requires the token "secret".
+    async fn upstream_server() -> Url {
+        async fn index_file(headers: HeaderMap) -> Result<(HeaderMap, &'static str), StatusCode> {
+            if headers
+                .get(header::AUTHORIZATION)
+                .is_none_or(|t| t != "secret")
+            {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            if headers
+                .get(header::IF_NONE_MATCH)
+                .is_some_and(|t| t == "etag")
+            {
+                return Err(StatusCode::NOT_MODIFIED);
+            }
+            let mut response_headers = HeaderMap::new();
+            response_headers.insert(header::ETAG, "etag".parse().unwrap());
+            Ok((response_headers, "{\"name\":\"serde\"}\n"))
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let app = Router::new().route("/index/se/rd/serde", get(index_file));
+            axum::serve(listener, app).await.unwrap();
+        });
+        Url::parse(&format!("http://{addr}/index/")).unwrap()
+    }
+
+    async fn app(token: Option<&str>) -> Router {
+        let settings = Settings {
+            proxy: Proxy {
+                upstreams: vec![Upstream {
+                    name: "partner".to_string(),
+                    index: upstream_server().await,
+                    token: token.map(ToString::to_string),
+                    bucket: None,
+                }],
+                ..Proxy::default()
+            },
+            ..kellnr_settings::test_settings()
+        };
+        let state = AppStateData {
+            settings: Arc::new(settings),
+            proxy_client: Client::new(),
+            ..kellnr_appstate::test_state()
+        };
+
+        let upstream = Router::new()
+            .route("/config.json", get(config_upstream))
+            .route("/{a}/{b}/{name}", get(prefetch_upstream))
+            .route("/{a}/{name}", get(prefetch_len2_upstream));
+        Router::new()
+            .nest("/api/v1/proxy/{upstream}", upstream)
+            .with_state(state)
+    }
+
+    async fn get_uri(app: Router, uri: &str, etag: Option<&str>) -> axum::response::Response {
+        let mut request = Request::get(uri);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn config_points_downloads_to_upstream_route() {
+        let r = get_uri(app(None).await, "/api/v1/proxy/partner/config.json", None).await;
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let config = serde_json::from_slice::<ConfigJson>(&body).unwrap();
+        assert_eq!(
+            ConfigJson::new(
+                kellnr_settings::Protocol::Http,
+                "127.0.0.1",
+                8000,
+                None,
+                "proxy/partner",
+                false,
+                false
+            ),
+            config
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_upstream_is_not_found() {
+        let r = get_uri(app(None).await, "/api/v1/proxy/other/config.json", None).await;
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+
+        let r = get_uri(app(None).await, "/api/v1/proxy/other/se/rd/serde", None).await;
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+
+    #[tokio::test]
+    async fn prefetch_forwards_token_and_returns_index() {
+        let r = get_uri(
+            app(Some("secret")).await,
+            "/api/v1/proxy/partner/se/rd/serde",
+            None,
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!("etag", r.headers().get(header::ETAG).unwrap());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!("{\"name\":\"serde\"}\n", body);
+    }
+
+    #[tokio::test]
+    async fn prefetch_forwards_conditional_headers() {
+        let r = get_uri(
+            app(Some("secret")).await,
+            "/api/v1/proxy/partner/se/rd/serde",
+            Some("etag"),
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_MODIFIED, r.status());
+    }
+
+    #[tokio::test]
+    async fn prefetch_without_upstream_token_fails() {
+        let r = get_uri(app(None).await, "/api/v1/proxy/partner/se/rd/serde", None).await;
+        assert_eq!(StatusCode::BAD_GATEWAY, r.status());
+    }
+}
@@ -6,18 +6,26 @@ use std::time::Duration;
 use axum::response::Redirect;
 use axum::routing::get;
 use axum_extra::extract::cookie::Key;
-use kellnr_appstate::AppStateData;
+use chrono::{Days, TimeDelta, Utc};
+use kellnr_appstate::{AppStateData, UpstreamStorages};
 use kellnr_auth::oauth2::OAuth2Handler;
+use kellnr_backup::{
+    BackupTarget, MigrationEndpoint, create_backup, migrate, migrate_storage, restore_backup,
+};
 use kellnr_common::cratesio_downloader::build_client;
 use kellnr_common::cratesio_prefetch_msg::CratesioPrefetchMsg;
+use kellnr_common::git_index_msg::GitIndexMsg;
 use kellnr_common::token_cache::TokenCacheManager;
 use kellnr_db::download_counter::DownloadCounter;
 use kellnr_db::{ConString, Database, DbProvider, PgConString, SqliteConString};
+use kellnr_docs::sandbox::{BuildLimits, run_doc_build};
 use kellnr_index::cratesio_prefetch_api::{
     CratesIoPrefetchArgs, UPDATE_CACHE_TIMEOUT_SECS, init_cratesio_prefetch_thread,
 };
+use kellnr_index::git_index::init_git_index;
+use kellnr_registry::publish_validation;
 use kellnr_settings::{
-    CliResult, LogFormat, ResolvedSettings, Settings, ShowConfigOptions, parse_cli,
+    BackupOptions, CliResult, LogFormat, ResolvedSettings, Settings, ShowConfigOptions, parse_cli,
 };
 use kellnr_storage::cached_crate_storage::DynStorage;
 use kellnr_storage::cratesio_crate_storage::CratesIoCrateStorage;
@@ -35,10 +43,23 @@ mod config_printer;
 mod openapi;
 mod routes;
 
-#[tokio::main]
-async fn main() {
+fn main() {
     let cli_result = parse_cli().expect("Cannot read config");
 
+    // Doc builds are handled before the async runtime starts its threads,
+    // as the network of the build can only be disabled in a single thread.
+    if let CliResult::DocBuild(options) = &cli_result {
+        std::process::exit(run_doc_build(options));
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start the async runtime")
+        .block_on(run(cli_result));
+}
+
+async fn run(cli_result: CliResult) {
     match cli_result {
         CliResult::ShowConfig { resolved, options } => {
             show_config(&resolved, &options);
@@ -49,9 +70,23 @@ async fn main() {
         CliResult::RunServer(resolved) => {
             run_server(resolved).await;
         }
+        CliResult::Backup { resolved, options } => {
+            run_backup(&resolved.settings, &options).await;
+        }
+        CliResult::Restore { resolved, archive } => {
+            Box::pin(run_restore(&resolved.settings, &archive)).await;
+        }
+        CliResult::Migrate {
+            resolved,
+            target,
+            storage_only,
+        } => {
+            run_migrate(&resolved.settings, &target, storage_only).await;
+        }
         CliResult::ShowHelp => {
             // Help was already printed by parse_cli()
         }
+        CliResult::DocBuild(_) => unreachable!("doc builds are handled by main()"),
     }
 }
 
@@ -95,6 +130,145 @@ fn init_config(output: &Path) {
     }
 }
 
+/// Storages and database of the instance that is backed up or restored into.
+struct BackupInstance {
+    db: Database,
+    crate_storage: KellnrCrateStorage,
+    cratesio_storage: CratesIoCrateStorage,
+    toolchain_storage: ToolchainStorage,
+}
+
+impl BackupInstance {
+    async fn open(settings: &Settings) -> Self {
+        init_tracing(settings);
+        // Toolchain archives are only present if the toolchain rows are, so
+        // the storage is used regardless of `toolchain.enabled`.
+        let crate_storage = init_kellnr_crate_storage(settings);
+        let cratesio_storage = init_cratesio_storage(settings);
+        let toolchain_storage =
+            ToolchainStorage::new(init_storage(&settings.toolchain_path_or_bucket(), settings));
+        let db = open_database(settings).await;
+
+        Self {
+            db,
+            crate_storage,
+            cratesio_storage,
+            toolchain_storage,
+        }
+    }
+
+    fn target(&self, settings: &Settings, include_cratesio: bool) -> BackupTarget<'_> {
+        BackupTarget {
+            db: &self.db,
+            crate_storage: &self.crate_storage,
+            cratesio_storage: include_cratesio.then_some(&self.cratesio_storage),
+            toolchain_storage: Some(&self.toolchain_storage),
+            docs_path: settings.docs_path(),
+        }
+    }
+}
+
+/// Connect to the database of an instance that is not running a server.
+async fn open_database(settings: &Settings) -> Database {
+    if settings.registry.data_dir.is_empty() {
+        eprintln!("Error: No data directory configured.");
+        eprintln!("Set it with --registry-data-dir or KELLNR_REGISTRY__DATA_DIR.");
+        std::process::exit(1);
+    }
+    create_dir_all(&settings.registry.data_dir)
+        .await
+        .expect("Failed to create data directory.");
+    Database::new(
+        &get_connect_string(settings),
+        settings.registry.max_db_connections,
+    )
+    .await
+    .expect("Failed to create database")
+}
+
+async fn run_backup(settings: &Settings, options: &BackupOptions) {
+    let instance = BackupInstance::open(settings).await;
+    let target = instance.target(settings, !options.exclude_cratesio);
+    match create_backup(&options.archive, &target).await {
+        Ok(summary) => println!("Backup written to {}: {summary}", options.archive.display()),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn run_restore(settings: &Settings, archive: &Path) {
+    let instance = BackupInstance::open(settings).await;
+    match restore_backup(archive, &instance.target(settings, true)).await {
+        Ok(summary) => println!("Backup {} restored: {summary}", archive.display()),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn run_migrate(source: &Settings, target: &Settings, storage_only: bool) {
+    init_tracing(source);
+    let source_db = open_database(source).await;
+    // The target keeps using the source database if only the storage moves
+    let target_db = if storage_only {
+        None
+    } else {
+        Some(open_database(target).await)
+    };
+    let source = MigrationEndpoint {
+        db: &source_db,
+        storages: migration_storages(source),
+        docs_path: source.docs_path(),
+    };
+    let target = MigrationEndpoint {
+        db: target_db.as_ref().unwrap_or(&source_db),
+        storages: migration_storages(target),
+        docs_path: target.docs_path(),
+    };
+    let result = if storage_only {
+        migrate_storage(&source, &target).await
+    } else {
+        migrate(&source, &target).await
+    };
+    match result {
+        Ok(summary) => println!("Migration finished: {summary}"),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            eprintln!("Objects that were copied are kept, run the migration again to resume.");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// All storages of an instance, named so that source and target storages of
+/// a migration can be matched.
+fn migration_storages(settings: &Settings) -> Vec<(String, DynStorage)> {
+    let mut storages = vec![
+        (
+            "crates".to_string(),
+            init_storage(&settings.crates_path_or_bucket(), settings),
+        ),
+        (
+            "crates.io".to_string(),
+            init_storage(&settings.crates_io_path_or_bucket(), settings),
+        ),
+        (
+            "toolchains".to_string(),
+            init_storage(&settings.toolchain_path_or_bucket(), settings),
+        ),
+    ];
+    storages.extend(settings.proxy.upstreams.iter().map(|upstream| {
+        (
+            format!("proxy/{}", upstream.name),
+            init_storage(&settings.upstream_path_or_bucket(upstream), settings),
+        )
+    }));
+    storages
+}
+
 async fn run_server(resolved: ResolvedSettings) {
     let settings: Arc<Settings> = resolved.settings.into();
     let settings_prov = resolved.prov;
@@ -112,6 +286,35 @@ async fn run_server(resolved: ResolvedSettings) {
         std::process::exit(1);
     }
 
+    if let Err(e) = settings.proxy.validate() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    let proxy_policy = match settings.proxy.policy() {
+        Ok(policy) => Arc::new(policy),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = settings.advisories.validate() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+
+    if let Err(e) = settings.publish.validate() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    let publish_validators = match publish_validation::validators(&settings.publish) {
+        Ok(validators) => Arc::new(validators),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
     let addr = SocketAddr::from((settings.local.ip, settings.local.port));
 
     // Configure tracing subscriber
@@ -137,6 +340,7 @@ async fn run_server(resolved: ResolvedSettings) {
 
     // Crates.io Proxy
     let cratesio_storage: Arc<CratesIoCrateStorage> = init_cratesio_storage(&settings).into();
+    let upstream_storages = Arc::new(init_upstream_storages(&settings));
     let (cratesio_prefetch_sender, cratesio_prefetch_receiver) =
         flume::unbounded::<CratesioPrefetchMsg>();
 
@@ -159,11 +363,25 @@ async fn run_server(resolved: ResolvedSettings) {
         prefetch_args,
     );
 
+    // Git index, served alongside the sparse index
+    let git_index_sender = if settings.registry.git_index {
+        let (sender, receiver) = flume::unbounded::<GitIndexMsg>();
+        init_git_index(&settings, db.clone(), receiver).await;
+        Some(sender)
+    } else {
+        None
+    };
+
     // Docs hosting
     init_docs_hosting(&settings, crate_storage.clone(), db.clone()).await;
 
     // Webhook support
-    init_webhook_service(db.clone());
+    init_webhook_service(db.clone(), &settings);
+
+    // Security advisories
+    if settings.advisories.enabled {
+        kellnr_advisory::run_advisory_import(&settings.advisories, db.clone());
+    }
 
     let data_dir = settings.registry.data_dir.clone();
     let signing_key = init_cookie_signing_key(&settings);
@@ -224,6 +442,66 @@ async fn run_server(resolved: ResolvedSettings) {
         });
     }
 
+    // Remove per-day download counts that left the retention window once a day
+    let download_history_days = settings.registry.download_history_days;
+    if download_history_days > 0 {
+        let history_cleanup_db = db.clone();
+        trace!("Starting download history cleanup task (retention: {download_history_days} days)");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_hours(24));
+            loop {
+                interval.tick().await;
+                let Some(oldest) = Utc::now()
+                    .date_naive()
+                    .checked_sub_days(Days::new(download_history_days - 1))
+                else {
+                    continue;
+                };
+                match history_cleanup_db
+                    .delete_daily_downloads_before(oldest)
+                    .await
+                {
+                    Ok(n) if n > 0 => trace!("Removed {n} daily download count(s)"),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to delete old daily download counts: {e}"),
+                }
+            }
+        });
+    }
+
+    // Remove webhook deliveries and dead letters that left the retention window once a day
+    let webhook_delivery_days = settings.registry.webhook_delivery_days;
+    if webhook_delivery_days > 0 {
+        let delivery_cleanup_db = db.clone();
+        trace!("Starting webhook delivery cleanup task (retention: {webhook_delivery_days} days)");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_hours(24));
+            loop {
+                interval.tick().await;
+                let Some(oldest) = Utc::now().checked_sub_days(Days::new(webhook_delivery_days))
+                else {
+                    continue;
+                };
+                match delivery_cleanup_db
+                    .delete_webhook_deliveries_before(oldest)
+                    .await
+                {
+                    Ok(n) if n > 0 => trace!("Removed {n} webhook delivery(s)"),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to delete old webhook deliveries: {e}"),
+                }
+                match delivery_cleanup_db
+                    .delete_webhook_dead_letters_before(oldest)
+                    .await
+                {
+                    Ok(n) if n > 0 => trace!("Removed {n} webhook dead letter(s)"),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to delete old webhook dead letters: {e}"),
+                }
+            }
+        });
+    }
+
     let download_counter_for_shutdown = download_counter.clone();
 
     let proxy_client = build_client(
@@ -240,10 +518,14 @@ async fn run_server(resolved: ResolvedSettings) {
         crate_storage,
         cratesio_storage,
         cratesio_prefetch_sender,
+        upstream_storages,
+        git_index_sender,
         token_cache,
         toolchain_storage,
         download_counter,
         proxy_client,
+        proxy_policy,
+        publish_validators,
     };
 
     // Create router using the route module
@@ -284,7 +566,8 @@ async fn run_server(resolved: ResolvedSettings) {
     let sigterm = std::future::pending::<()>();
 
     tokio::select! {
-        result = axum::serve(listener, app) => {
+        // The peer address is recorded as source IP in the audit log
+        result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()) => {
             if let Err(e) = result {
                 error!("Server error: {e}");
             }
@@ -348,15 +631,28 @@ async fn init_docs_hosting(
         .await
         .expect("Failed to create docs directory.");
     if settings.docs.enabled {
+        if let Err(reason) = kellnr_docs::sandbox::check_network_isolation().await {
+            if settings.docs.require_network_isolation {
+                error!(
+                    "Doc builds are disabled, as their network and filesystem cannot be isolated: {reason}"
+                );
+                return;
+            }
+            error!(
+                "Doc builds run with access to the network and the data of kellnr, as they cannot be isolated and docs.require_network_isolation is off: {reason}"
+            );
+        }
         kellnr_docs::doc_queue::doc_extraction_queue(
             db,
             cs,
             settings.docs_path(),
             settings.origin.path.clone(),
+            settings.origin.url(),
             settings
                 .proxy
                 .cratesio_index_override()
                 .map(ToString::to_string),
+            BuildLimits::from(&settings.docs),
         );
     }
 }
@@ -366,6 +662,21 @@ fn init_cratesio_storage(settings: &Settings) -> CratesIoCrateStorage {
     CratesIoCrateStorage::new(settings, storage)
 }
 
+fn init_upstream_storages(settings: &Settings) -> UpstreamStorages {
+    settings
+        .proxy
+        .upstreams
+        .iter()
+        .map(|upstream| {
+            let storage = init_storage(&settings.upstream_path_or_bucket(upstream), settings);
+            (
+                upstream.name.clone(),
+                Arc::new(CratesIoCrateStorage::new(settings, storage)),
+            )
+        })
+        .collect()
+}
+
 fn init_kellnr_crate_storage(settings: &Settings) -> KellnrCrateStorage {
     let storage = init_storage(&settings.crates_path_or_bucket(), settings);
     KellnrCrateStorage::new(settings, storage)
@@ -381,8 +692,19 @@ fn init_storage(folder: &str, settings: &Settings) -> DynStorage {
     }
 }
 
-fn init_webhook_service(db: Arc<dyn DbProvider + 'static>) {
-    kellnr_webhooks::run_webhook_service(db);
+fn init_webhook_service(db: Arc<dyn DbProvider + 'static>, settings: &Settings) {
+    let disable_after = match settings.registry.webhook_disable_after_hours {
+        0 => None,
+        hours => i64::try_from(hours).ok().and_then(TimeDelta::try_hours),
+    };
+    kellnr_webhooks::run_webhook_service(
+        db,
+        kellnr_webhooks::DeliverySettings {
+            base_url: settings.origin.url(),
+            max_attempts: settings.registry.webhook_max_attempts,
+            disable_after,
+        },
+    );
 }
 
 fn init_toolchain_storage(settings: &Arc<Settings>) -> Option<Arc<ToolchainStorage>> {
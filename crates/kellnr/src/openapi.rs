@@ -33,14 +33,18 @@ impl Modify for SecurityAddon {
         (name = "users", description = "User management"),
         (name = "groups", description = "Group management"),
         (name = "acl", description = "Crate access control"),
+        (name = "audit", description = "Audit log"),
+        (name = "advisories", description = "Security advisories"),
         (name = "crates", description = "Kellnr registry API"),
         (name = "cratesio", description = "Crates.io proxy"),
+        (name = "upstreams", description = "Upstream registry proxies"),
         (name = "docs", description = "Documentation"),
+        (name = "source", description = "Crate file browser and diffs"),
         (name = "toolchains", description = "Toolchain distribution"),
         (name = "webhooks", description = "Webhooks"),
         (name = "oauth2", description = "OAuth2/OIDC"),
         (name = "ui", description = "Web UI API"),
-        (name = "health", description = "Health check")
+        (name = "health", description = "Health check and metrics")
     ),
     modifiers(&SecurityAddon)
 )]
@@ -0,0 +1,16 @@
+use axum::middleware;
+use kellnr_appstate::AppStateData;
+use kellnr_auth::auth_req_token;
+use kellnr_web_ui::advisory;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+/// Creates the security advisory routes
+pub fn create_routes(state: AppStateData) -> OpenApiRouter<AppStateData> {
+    OpenApiRouter::new()
+        .routes(routes!(advisory::list_advisories))
+        .layer(middleware::from_fn_with_state(
+            state,
+            auth_req_token::token_or_session_auth_when_required,
+        ))
+}
@@ -0,0 +1,9 @@
+use kellnr_appstate::AppStateData;
+use kellnr_web_ui::audit;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+/// Creates the audit log routes
+pub fn create_routes() -> OpenApiRouter<AppStateData> {
+    OpenApiRouter::new().routes(routes!(audit::list_entries))
+}
@@ -0,0 +1,17 @@
+use axum::middleware;
+use kellnr_appstate::AppStateData;
+use kellnr_auth::auth_req_token;
+use kellnr_index::git_index_api;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+/// Creates the git smart HTTP routes of the git index
+pub fn create_routes(state: AppStateData) -> OpenApiRouter<AppStateData> {
+    OpenApiRouter::new()
+        .routes(routes!(git_index_api::info_refs))
+        .routes(routes!(git_index_api::upload_pack))
+        .layer(middleware::from_fn_with_state(
+            state,
+            auth_req_token::cargo_auth_when_required,
+        ))
+}
@@ -1,10 +1,25 @@
-use kellnr_appstate::AppStateData;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::http::{StatusCode, header};
+use kellnr_appstate::{AppState, AppStateData};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use tokio::task::JoinSet;
+use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
-/// Health check route
+/// Upper bound for a single readiness check, so a hanging backend makes the
+/// probe fail instead of time out.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Health check routes
 pub fn create_routes() -> OpenApiRouter<AppStateData> {
-    OpenApiRouter::new().routes(routes!(health_check))
+    OpenApiRouter::new()
+        .routes(routes!(health_check))
+        .routes(routes!(readiness_check))
 }
 
 /// Health check endpoint
@@ -19,3 +34,238 @@ pub fn create_routes() -> OpenApiRouter<AppStateData> {
 pub async fn health_check() -> &'static str {
     "OK"
 }
+
+/// Result of all readiness checks
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    /// `true` if all components are available
+    pub ready: bool,
+    pub components: Vec<ComponentReport>,
+}
+
+/// Result of the readiness check of a single component
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentReport {
+    /// Component name, e.g. `database` or `storage/crates`
+    pub name: String,
+    pub status: ComponentStatus,
+    /// Duration of the check in milliseconds
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentStatus {
+    Ok,
+    Error,
+}
+
+/// Readiness check endpoint
+///
+/// Checks the database connection, read and write access on all storage
+/// backends and, if the proxy is enabled, the reachability of the upstream
+/// registries. Unlike `/health`, this fails if kellnr cannot serve requests.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "All components are available", body = ReadinessReport),
+        (status = 503, description = "At least one component is unavailable", body = ReadinessReport)
+    )
+)]
+pub async fn readiness_check(
+    axum::extract::State(state): AppState,
+) -> (StatusCode, Json<ReadinessReport>) {
+    let mut checks = JoinSet::new();
+
+    let db = state.db.clone();
+    checks.spawn(check("database", async move { db.ping().await }));
+
+    let storage = state.crate_storage.clone();
+    checks.spawn(check("storage/crates", async move {
+        storage.check_access().await
+    }));
+
+    if let Some(storage) = state.toolchain_storage.clone() {
+        checks.spawn(check("storage/toolchains", async move {
+            storage.check_access().await
+        }));
+    }
+
+    let proxy = &state.settings.proxy;
+    if proxy.enabled {
+        let storage = state.cratesio_storage.clone();
+        checks.spawn(check("storage/crates.io", async move {
+            storage.check_access().await
+        }));
+        checks.spawn(check(
+            "proxy/crates.io",
+            check_index(state.proxy_client.clone(), proxy.index.clone(), None),
+        ));
+    }
+
+    for upstream in &proxy.upstreams {
+        if let Some(storage) = state.upstream_storages.get(&upstream.name).cloned() {
+            checks.spawn(check(
+                format!("storage/proxy/{}", upstream.name),
+                async move { storage.check_access().await },
+            ));
+        }
+        checks.spawn(check(
+            format!("proxy/{}", upstream.name),
+            check_index(
+                state.proxy_client.clone(),
+                upstream.index.clone(),
+                upstream.token.clone(),
+            ),
+        ));
+    }
+
+    let mut components = checks.join_all().await;
+    components.sort_by(|a, b| a.name.cmp(&b.name));
+    let ready = components.iter().all(|c| c.status == ComponentStatus::Ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(ReadinessReport { ready, components }))
+}
+
+async fn check<E: Display>(
+    name: impl Into<String>,
+    check: impl Future<Output = Result<(), E>>,
+) -> ComponentReport {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!(
+            "No response within {} seconds",
+            CHECK_TIMEOUT.as_secs()
+        )),
+    };
+
+    ComponentReport {
+        name: name.into(),
+        status: if result.is_ok() {
+            ComponentStatus::Ok
+        } else {
+            ComponentStatus::Error
+        },
+        latency_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        error: result.err(),
+    }
+}
+
+/// An upstream is reachable if its sparse index serves the `config.json`.
+async fn check_index(client: Client, index: Url, token: Option<String>) -> Result<(), String> {
+    let url = index.join("config.json").map_err(|e| e.to_string())?;
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, token);
+    }
+    request
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use axum::routing::get;
+    use kellnr_db::DbProvider;
+    use kellnr_db::error::DbError;
+    use kellnr_db::mock::MockDb;
+    use kellnr_settings::{Registry, Settings};
+    use kellnr_storage::cached_crate_storage::DynStorage;
+    use kellnr_storage::fs_storage::FSStorage;
+    use kellnr_storage::kellnr_crate_storage::KellnrCrateStorage;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(db: MockDb, data_dir: &std::path::Path) -> Router {
+        let settings = Settings {
+            registry: Registry {
+                data_dir: data_dir.to_string_lossy().to_string(),
+                ..Registry::default()
+            },
+            ..kellnr_settings::test_settings()
+        };
+        let storage = FSStorage::new(&settings.crates_path()).unwrap();
+        let state = AppStateData {
+            db: Arc::new(db) as Arc<dyn DbProvider>,
+            crate_storage: Arc::new(KellnrCrateStorage::new(
+                &settings,
+                Box::new(storage) as DynStorage,
+            )),
+            settings: Arc::new(settings),
+            ..kellnr_appstate::test_state()
+        };
+        Router::new()
+            .route("/ready", get(readiness_check))
+            .with_state(state)
+    }
+
+    async fn get_report(app: Router) -> (StatusCode, serde_json::Value) {
+        let r = app
+            .oneshot(Request::get("/ready").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = r.status();
+        let body = to_bytes(r.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn ready_if_all_components_are_available() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut db = MockDb::new();
+        db.expect_ping().returning(|| Ok(()));
+
+        let (status, report) = get_report(app(db, dir.path())).await;
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, report["ready"]);
+        let components = report["components"].as_array().unwrap();
+        assert_eq!(2, components.len());
+        assert_eq!("database", components[0]["name"]);
+        assert_eq!("ok", components[0]["status"]);
+        assert_eq!("storage/crates", components[1]["name"]);
+        assert_eq!("ok", components[1]["status"]);
+        assert!(components[1]["latency_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn not_ready_if_database_is_unavailable() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut db = MockDb::new();
+        db.expect_ping()
+            .returning(|| Err(DbError::InitializationError("connection refused".into())));
+
+        let (status, report) = get_report(app(db, dir.path())).await;
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+        assert_eq!(false, report["ready"]);
+        assert_eq!("database", report["components"][0]["name"]);
+        assert_eq!("error", report["components"][0]["status"]);
+        assert!(
+            report["components"][0]["error"]
+                .as_str()
+                .unwrap()
+                .contains("connection refused")
+        );
+        assert_eq!("ok", report["components"][1]["status"]);
+    }
+}
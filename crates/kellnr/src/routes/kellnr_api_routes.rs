@@ -68,6 +68,8 @@ pub fn create_routes(
         .routes(routes!(kellnr_api::list_crate_groups))
         // Version routes
         .routes(routes!(kellnr_api::list_crate_versions))
+        .routes(routes!(kellnr_api::list_reverse_dependencies))
+        .routes(routes!(kellnr_api::get_download_history))
         // Search
         .routes(routes!(kellnr_api::search))
         // Download (with concurrency limit and timeout)
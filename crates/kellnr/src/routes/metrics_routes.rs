@@ -0,0 +1,125 @@
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use kellnr_appstate::{AppStateData, DbState};
+use kellnr_common::metrics;
+use tracing::error;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+/// Prometheus metrics route
+pub fn create_routes() -> OpenApiRouter<AppStateData> {
+    OpenApiRouter::new().routes(routes!(prometheus_metrics))
+}
+
+/// Prometheus metrics
+///
+/// Returns all metrics in the Prometheus text exposition format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Metrics in Prometheus text format", body = String, content_type = "text/plain")
+    )
+)]
+pub async fn prometheus_metrics(State(db): DbState) -> impl IntoResponse {
+    // Queue depths are read from the database on scrape, so they are correct
+    // across multiple kellnr instances that share one database.
+    match db.get_doc_queue().await {
+        Ok(queue) => metrics::DOC_QUEUE_DEPTH.set(i64::try_from(queue.len()).unwrap_or(i64::MAX)),
+        Err(e) => error!("Could not read doc queue for metrics: {e}"),
+    }
+    match db.get_webhook_queue_length().await {
+        Ok(length) => metrics::WEBHOOK_QUEUE_DEPTH.set(i64::try_from(length).unwrap_or(i64::MAX)),
+        Err(e) => error!("Could not read webhook queue for metrics: {e}"),
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::gather(),
+    )
+}
+
+/// Record latency and status of every request by its route template.
+///
+/// Requests that match no route are recorded as `fallback` to keep the
+/// number of series bounded.
+pub async fn track_request_metrics(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "fallback".to_string(), |p| p.as_str().to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    metrics::HTTP_REQUESTS.with_label_values(&labels).inc();
+    metrics::HTTP_REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::http::StatusCode;
+    use axum::middleware;
+    use axum::routing::get;
+    use kellnr_db::DbProvider;
+    use kellnr_db::mock::MockDb;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app() -> Router {
+        let mut db = MockDb::new();
+        db.expect_get_doc_queue().returning(|| Ok(vec![]));
+        db.expect_get_webhook_queue_length().returning(|| Ok(3));
+        let state = AppStateData {
+            db: Arc::new(db) as Arc<dyn DbProvider>,
+            ..kellnr_appstate::test_state()
+        };
+        Router::new()
+            .route("/metrics", get(prometheus_metrics))
+            .route("/api/v1/crates/{name}", get(|| async { "crate" }))
+            .layer(middleware::from_fn(track_request_metrics))
+            .with_state(state)
+    }
+
+    async fn get_uri(uri: &str) -> Response {
+        app()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn metrics_reports_queue_depths_and_requests() {
+        get_uri("/api/v1/crates/foo").await;
+
+        let r = get_uri("/metrics").await;
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!(
+            "text/plain; version=0.0.4",
+            r.headers().get(header::CONTENT_TYPE).unwrap()
+        );
+        let body = to_bytes(r.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("kellnr_webhook_queue_depth 3"));
+        assert!(body.contains("kellnr_doc_queue_depth 0"));
+        assert!(body.contains(
+            "kellnr_http_requests_total{method=\"GET\",route=\"/api/v1/crates/{name}\",status=\"200\"}"
+        ));
+    }
+}
@@ -8,6 +8,7 @@ use axum::routing::{get, get_service};
 use axum::{Extension, Router, middleware};
 use kellnr_appstate::AppStateData;
 use kellnr_auth::oauth2::OAuth2Handler;
+use kellnr_common::metrics;
 use kellnr_embedded_resources::{embedded_static_handler, embedded_static_root_handler};
 use kellnr_settings::Registry;
 use kellnr_web_ui::session;
@@ -20,16 +21,22 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::openapi::ApiDoc;
 
+mod advisory_routes;
+mod audit_routes;
 mod auth_routes;
 mod crate_access_routes;
 mod cratesio_api_routes;
 mod docs_routes;
+mod git_index_routes;
 mod group_routes;
 mod health_routes;
 mod kellnr_api_routes;
+mod metrics_routes;
 mod oauth2_routes;
+mod source_routes;
 mod toolchain_routes;
 mod ui_routes;
+mod upstream_routes;
 mod user_routes;
 mod webhook_routes;
 
@@ -70,7 +77,12 @@ pub fn create_router(
             .nest("/api/v1/users", user_routes::create_routes())
             .nest("/api/v1/groups", group_routes::create_routes())
             .nest("/api/v1/acl", crate_access_routes::create_routes())
+            .nest("/api/v1/audit", audit_routes::create_routes())
             .nest("/api/v1/docs", docs_routes::create_ui_routes(state.clone()))
+            .nest(
+                "/api/v1/source",
+                source_routes::create_routes(state.clone()),
+            )
             .nest("/api/v1/webhooks", webhook_routes::create_routes())
             .nest("/api/v1/oauth2", oauth2_routes::create_routes())
             .nest(
@@ -79,9 +91,38 @@ pub fn create_router(
             )
             .nest(
                 "/api/v1/crates",
-                kellnr_api_routes::create_routes(state.clone(), max_crate_size, download_semaphore),
+                kellnr_api_routes::create_routes(
+                    state.clone(),
+                    max_crate_size,
+                    download_semaphore.clone(),
+                ),
             )
-            .nest("/api/v1", health_routes::create_routes());
+            .nest("/api/v1", health_routes::create_routes())
+            .merge(metrics_routes::create_routes());
+
+    // Add upstream registry proxies if any are configured
+    if !state.settings.proxy.upstreams.is_empty() {
+        api_router = api_router.nest(
+            "/api/v1/proxy/{upstream}",
+            upstream_routes::create_routes(state.clone(), download_semaphore.clone()),
+        );
+    }
+
+    // Conditionally add git index routes if enabled
+    if state.settings.registry.git_index {
+        api_router = api_router.nest(
+            "/api/v1/git/index",
+            git_index_routes::create_routes(state.clone()),
+        );
+    }
+
+    // Conditionally add advisory routes if the advisory import is enabled
+    if state.settings.advisories.enabled {
+        api_router = api_router.nest(
+            "/api/v1/advisories",
+            advisory_routes::create_routes(state.clone()),
+        );
+    }
 
     // Conditionally add toolchain routes if enabled
     if state.settings.toolchain.enabled {
@@ -115,6 +156,8 @@ pub fn create_router(
         .with_state(state)
         // Add OAuth2 handler as an extension (accessible via Extension<Option<Arc<OAuth2Handler>>>)
         .layer(Extension(oauth2_handler))
+        // Request latency and status per route for the Prometheus metrics.
+        .layer(middleware::from_fn(metrics_routes::track_request_metrics))
         // Baseline security headers on every response.
         .layer(middleware::map_response(add_security_headers))
         .layer(tower_http::trace::TraceLayer::new_for_http())
@@ -199,7 +242,11 @@ pub(crate) fn apply_download_limits(
                 let sem = semaphore.clone();
                 async move {
                     match sem.acquire_owned().await {
-                        Ok(_permit) => next.run(req).await,
+                        Ok(_permit) => {
+                            let _in_use =
+                                metrics::GaugeGuard::new(&metrics::DOWNLOAD_PERMITS_IN_USE);
+                            next.run(req).await
+                        }
                         Err(error) => {
                             tracing::error!("Download semaphore closed unexpectedly: {error}");
                             StatusCode::SERVICE_UNAVAILABLE.into_response()
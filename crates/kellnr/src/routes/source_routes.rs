@@ -0,0 +1,28 @@
+use axum::middleware;
+use kellnr_appstate::AppStateData;
+use kellnr_registry::cratesio_api;
+use kellnr_web_ui::{crate_source, session};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+/// Creates the routes to browse the files of stored crates
+pub fn create_routes(state: AppStateData) -> OpenApiRouter<AppStateData> {
+    let cratesio_routes = OpenApiRouter::new()
+        .routes(routes!(crate_source::cratesio_list_files))
+        .routes(routes!(crate_source::cratesio_file_content))
+        .routes(routes!(crate_source::cratesio_diff))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            cratesio_api::cratesio_enabled,
+        ));
+
+    OpenApiRouter::new()
+        .routes(routes!(crate_source::list_files))
+        .routes(routes!(crate_source::file_content))
+        .routes(routes!(crate_source::diff))
+        .merge(cratesio_routes)
+        .layer(middleware::from_fn_with_state(
+            state,
+            session::session_auth_when_required,
+        ))
+}
@@ -10,11 +10,14 @@ use axum::response::{IntoResponse, Response};
 use axum::routing::put;
 use axum::{Json, Router};
 use bytes::Bytes;
+use chrono::Utc;
 use kellnr_appstate::{AppStateData, DbState, SettingsState, ToolchainStorageState};
+use kellnr_common::webhook::WebhookEvent;
 use kellnr_db::{ChannelInfo, ToolchainWithTargets};
 use kellnr_storage::toolchain_storage::ToolchainStorage;
 use kellnr_web_ui::session::AdminUser;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tracing::trace;
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
@@ -219,6 +222,20 @@ async fn upload_toolchain(
                 }),
             )
         })?;
+    kellnr_webhooks::notify(
+        WebhookEvent::ToolchainAdd,
+        &Utc::now(),
+        None,
+        json!({
+            "name": params.name,
+            "version": params.version,
+            "target": params.target,
+            "date": params.date,
+            "channel": params.channel
+        }),
+        &db,
+    )
+    .await;
 
     // Spawn background task to split the combined archive into individual component archives.
     // This can take a while for large archives, so we don't block the upload response.
@@ -615,6 +632,14 @@ async fn set_channel(
                 }),
             )
         })?;
+    kellnr_webhooks::notify(
+        WebhookEvent::ToolchainChannelChange,
+        &Utc::now(),
+        None,
+        json!({ "channel": channel, "name": req.name, "version": req.version }),
+        &db,
+    )
+    .await;
 
     Ok(Json(ToolchainResponse {
         success: true,
@@ -1546,6 +1571,11 @@ mod tests {
         mock_db
             .expect_add_toolchain_target()
             .returning(|_, _, _, _, _| Ok(1));
+        mock_db
+            .expect_add_webhook_queue()
+            .with(eq(WebhookEvent::ToolchainAdd), eq(None), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
 
         let state = create_app_state(Arc::new(mock_db), toolchain_storage);
         let router = create_test_router(state);
@@ -1601,6 +1631,16 @@ mod tests {
             .expect_set_channel()
             .with(eq("stable"), eq("rust"), eq("1.0.0"))
             .returning(|_, _, _| Ok(()));
+        mock_db
+            .expect_add_webhook_queue()
+            .with(
+                eq(WebhookEvent::ToolchainChannelChange),
+                eq(None),
+                always(),
+                always(),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
 
         let state = create_app_state(Arc::new(mock_db), None);
         let router = create_test_router(state);
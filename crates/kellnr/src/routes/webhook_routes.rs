@@ -10,6 +10,14 @@ pub fn create_routes() -> OpenApiRouter<AppStateData> {
             endpoints::get_all_webhooks,
             endpoints::register_webhook
         ))
+        .routes(routes!(endpoints::get_webhook_events))
         .routes(routes!(endpoints::get_webhook, endpoints::delete_webhook))
         .routes(routes!(endpoints::test_webhook))
+        .routes(routes!(endpoints::rotate_webhook_secret))
+        .routes(routes!(endpoints::get_webhook_deliveries))
+        .routes(routes!(endpoints::redeliver_webhook_delivery))
+        .routes(routes!(endpoints::enable_webhook))
+        .routes(routes!(endpoints::get_webhook_dead_letters))
+        .routes(routes!(endpoints::retry_webhook_dead_letter))
+        .routes(routes!(endpoints::delete_webhook_dead_letter))
 }
@@ -1,16 +1,24 @@
+use std::sync::Arc;
+
 use axum::extract::{Path, Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::Response;
 use bytes::Bytes;
-use kellnr_appstate::{CrateIoStorageState, DownloadCounterState, ProxyClientState, SettingsState};
-use kellnr_common::cratesio_downloader::download_crate;
+use kellnr_appstate::{
+    CrateIoStorageState, DownloadCounterState, ProxyClientState, ProxyPolicyState, SettingsState,
+};
+use kellnr_common::cratesio_downloader::{download_crate, fetch_crate_info_cached};
+use kellnr_common::metrics;
 use kellnr_common::original_name::OriginalName;
+use kellnr_common::proxy_policy::ProxyPolicy;
 use kellnr_common::version::Version;
 use kellnr_error::api_error::ApiResult;
+use kellnr_settings::Proxy;
+use kellnr_storage::cratesio_crate_storage::CratesIoCrateStorage;
 use kellnr_storage::storage_error::StorageError;
-use reqwest::Url;
-use tracing::{error, trace};
+use reqwest::{Client, Url};
+use tracing::{error, trace, warn};
 
 use crate::registry_error::RegistryError;
 use crate::search_params::SearchParams;
@@ -86,6 +94,7 @@ pub async fn search(
     responses(
         (status = 200, description = "Crate archive", content_type = "application/octet-stream"),
         (status = 400, description = "Invalid package name or version"),
+        (status = 403, description = "Crate version blocked by the proxy policy"),
         (status = 404, description = "Crate not found or proxy disabled"),
         (status = 422, description = "Failed to save crate")
     ),
@@ -97,39 +106,30 @@ pub async fn download(
     State(download_counter): DownloadCounterState,
     State(settings): SettingsState,
     State(proxy_client): ProxyClientState,
+    State(proxy_policy): ProxyPolicyState,
 ) -> Result<Bytes, StatusCode> {
     trace!("Downloading crate: {name} ({version})");
 
+    // Checked for cached crates as well, as the policy may have changed
+    check_proxy_policy(
+        &proxy_client,
+        &settings.proxy,
+        &proxy_policy,
+        &name,
+        &version,
+    )
+    .await?;
+
     let file = if let Some(file) = crate_storage.get(&name, &version).await {
         file
     } else {
         let crate_data =
             download_crate(&proxy_client, &name, &version, &settings.proxy.url).await?;
-
-        match crate_storage.put(&name, &version, crate_data.clone()).await {
-            Ok(_) => crate_storage
-                .get(&name, &version)
-                .await
-                .ok_or(StatusCode::NOT_FOUND)?,
-            Err(StorageError::CrateExists(_, _)) => {
-                trace!(
-                    "Crate cache population raced for {name} ({version}); using the cached copy"
-                );
-                crate_storage.get(&name, &version).await.ok_or_else(|| {
-                    error!(
-                        "Crate {name} ({version}) already existed after a cache race but could not be read afterward"
-                    );
-                    StatusCode::UNPROCESSABLE_ENTITY
-                })?
-            }
-            Err(error) => {
-                error!("Failed to save crate to disk: {error}");
-                return Err(StatusCode::UNPROCESSABLE_ENTITY);
-            }
-        }
+        cache_crate(&crate_storage, &name, &version, crate_data).await?
     };
 
     // Count ALL downloads (both cache hits and upstream fetches)
+    metrics::crate_download(metrics::CRATES_IO);
     download_counter
         .increment_cached_and_maybe_flush(name.to_normalized(), version.clone())
         .await;
@@ -137,6 +137,67 @@ pub async fn download(
     Ok(file)
 }
 
+/// Reject downloads of crate versions that are blocked by the proxy policy.
+async fn check_proxy_policy(
+    client: &Client,
+    proxy: &Proxy,
+    policy: &ProxyPolicy,
+    name: &OriginalName,
+    version: &Version,
+) -> Result<(), StatusCode> {
+    if policy.is_empty() {
+        return Ok(());
+    }
+
+    let mut result = policy.check_version(name, version, None);
+    if result.is_ok() && (policy.checks_license() || policy.checks_age()) {
+        let info = fetch_crate_info_cached(client, name, &proxy.api)
+            .await
+            .map_err(|e| {
+                error!("Could not fetch crate info of {name} from crates.io: {e}");
+                StatusCode::BAD_GATEWAY
+            })?;
+        let version_info = info.versions.get(&**version);
+        result = policy
+            .check_version(name, version, version_info.map(|v| v.created_at))
+            .and_then(|()| policy.check_license(version_info.and_then(|v| v.license.as_deref())));
+    }
+
+    result.map_err(|blocked| {
+        warn!("Download of {name} ({version}) from crates.io blocked: {blocked}");
+        StatusCode::FORBIDDEN
+    })
+}
+
+/// Store a crate downloaded from an upstream in the cache storage and return
+/// the cached copy.
+pub(crate) async fn cache_crate(
+    crate_storage: &CratesIoCrateStorage,
+    name: &OriginalName,
+    version: &Version,
+    crate_data: Arc<[u8]>,
+) -> Result<Bytes, StatusCode> {
+    match crate_storage.put(name, version, crate_data).await {
+        Ok(_) => crate_storage
+            .get(name, version)
+            .await
+            .ok_or(StatusCode::NOT_FOUND),
+        Err(StorageError::CrateExists(_, _)) => {
+            trace!("Crate cache population raced for {name} ({version}); using the cached copy");
+            crate_storage.get(name, version).await.ok_or_else(|| {
+                error!(
+                    "Crate {name} ({version}) already existed after a cache race but could not be read afterward"
+                );
+                StatusCode::UNPROCESSABLE_ENTITY
+            })
+        }
+        Err(error) => {
+            error!("Failed to save crate to disk: {error}");
+            Err(StatusCode::UNPROCESSABLE_ENTITY)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -253,6 +314,25 @@ mod tests {
         assert_eq!(12778, body.len());
     }
 
+    #[tokio::test]
+    async fn download_denied_package_is_forbidden() {
+        let mut settings = get_settings();
+        settings.proxy.deny = vec!["adler@<1.0.3".to_string()];
+        let kellnr = TestKellnr::new(settings);
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/cratesio/adler/1.0.2/download")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(r.status(), StatusCode::FORBIDDEN);
+    }
+
     #[tokio::test]
     async fn cratesio_disabled_returns_404() {
         let mut settings = get_settings();
@@ -284,9 +364,9 @@ mod tests {
                 session_age_seconds: 10,
                 ..kellnr_settings::Registry::default()
             },
-            proxy: kellnr_settings::Proxy {
+            proxy: Proxy {
                 enabled: true,
-                ..kellnr_settings::Proxy::default()
+                ..Proxy::default()
             },
             ..Settings::default()
         }
@@ -314,6 +394,7 @@ mod tests {
         let db = MockDb::new();
 
         let state = AppStateData {
+            proxy_policy: Arc::new(settings.proxy.policy().unwrap()),
             settings: settings.into(),
             cratesio_storage: cs.into(),
             db: Arc::<MockDb>::new(db),
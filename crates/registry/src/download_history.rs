@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate};
+use kellnr_db::download_history::{DAY_FORMAT, DailyDownloads};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Days returned if no number of days is requested
+pub const DEFAULT_DAYS: u64 = 30;
+
+/// Most days returned if the download history is kept forever
+pub const MAX_DAYS: u64 = 3660;
+
+#[derive(Default, Debug, Clone, Deserialize, IntoParams)]
+pub struct DownloadHistoryParams {
+    /// Number of days including today. Limited to the retention window.
+    pub days: Option<u64>,
+}
+
+/// Per-day downloads of all versions of a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DownloadHistory {
+    pub name: String,
+    /// Days of the series (`%Y-%m-%d`, UTC), oldest first
+    pub days: Vec<String>,
+    /// Versions of the crate, newest first
+    pub versions: Vec<VersionDownloads>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct VersionDownloads {
+    pub version: String,
+    /// Downloads in the whole series
+    pub total: i64,
+    /// Downloads per day, one value for each entry of `days`
+    pub downloads: Vec<i64>,
+}
+
+/// First day of a series of `days` days that ends with `today`.
+pub fn first_day(today: NaiveDate, days: u64) -> NaiveDate {
+    today
+        .checked_sub_days(Days::new(days.saturating_sub(1)))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+impl DownloadHistory {
+    /// Dense series from `start` to `end` for each of `versions`. Days
+    /// without downloads are zero.
+    pub fn new(
+        name: String,
+        start: NaiveDate,
+        end: NaiveDate,
+        versions: Vec<String>,
+        downloads: Vec<DailyDownloads>,
+    ) -> Self {
+        let days = start
+            .iter_days()
+            .take_while(|d| *d <= end)
+            .collect::<Vec<_>>();
+        let index = days
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (*d, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut versions = versions
+            .into_iter()
+            .map(|version| VersionDownloads {
+                version,
+                total: 0,
+                downloads: vec![0; days.len()],
+            })
+            .collect::<Vec<_>>();
+        for d in downloads {
+            let (Some(i), Some(v)) = (
+                index.get(&d.day),
+                versions.iter_mut().find(|v| v.version == d.version),
+            ) else {
+                continue;
+            };
+            v.downloads[*i] += d.downloads;
+            v.total += d.downloads;
+        }
+
+        Self {
+            name,
+            days: days
+                .iter()
+                .map(|d| d.format(DAY_FORMAT).to_string())
+                .collect(),
+            versions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(d, DAY_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn first_day_includes_today() {
+        assert_eq!(day("2024-03-01"), first_day(day("2024-03-01"), 1));
+        assert_eq!(day("2024-02-28"), first_day(day("2024-03-01"), 3));
+        assert_eq!(day("2024-03-01"), first_day(day("2024-03-01"), 0));
+    }
+
+    #[test]
+    fn history_fills_missing_days_with_zero() {
+        let downloads = vec![
+            DailyDownloads {
+                version: "1.0.0".to_string(),
+                day: day("2024-03-01"),
+                downloads: 4,
+            },
+            DailyDownloads {
+                version: "1.0.0".to_string(),
+                day: day("2024-03-03"),
+                downloads: 2,
+            },
+            DailyDownloads {
+                version: "2.0.0".to_string(),
+                day: day("2024-03-03"),
+                downloads: 7,
+            },
+        ];
+
+        let history = DownloadHistory::new(
+            "foo".to_string(),
+            day("2024-03-01"),
+            day("2024-03-03"),
+            vec!["2.0.0".to_string(), "1.0.0".to_string()],
+            downloads,
+        );
+
+        assert_eq!(vec!["2024-03-01", "2024-03-02", "2024-03-03"], history.days);
+        assert_eq!(
+            vec![
+                VersionDownloads {
+                    version: "2.0.0".to_string(),
+                    total: 7,
+                    downloads: vec![0, 0, 7],
+                },
+                VersionDownloads {
+                    version: "1.0.0".to_string(),
+                    total: 6,
+                    downloads: vec![4, 0, 2],
+                },
+            ],
+            history.versions
+        );
+    }
+}
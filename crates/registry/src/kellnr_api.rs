@@ -2,32 +2,39 @@ use std::convert::TryFrom;
 use std::sync::Arc;
 
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use bytes::Bytes;
 use chrono::Utc;
-use kellnr_appstate::{AppState, DbState};
+use kellnr_appstate::{AppState, DbState, GitIndexSenderState, SettingsState};
+use kellnr_auth::audit::{self, SourceIp};
 use kellnr_auth::{maybe_user, token};
+use kellnr_common::git_index_msg::notify_git_index;
+use kellnr_common::metrics;
 use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::search_result;
 use kellnr_common::search_result::{Crate, SearchResult};
+use kellnr_common::token_scope::{TokenOperation, TokenScopes};
 use kellnr_common::version::Version;
 use kellnr_common::webhook::WebhookEvent;
-use kellnr_db::DbProvider;
+use kellnr_db::{AuditAction, DbProvider, NewAuditEntry};
 use kellnr_error::api_error::{ApiError, ApiResult};
+use serde_json::json;
 
 use crate::pub_data::{EmptyCrateData, PubData};
 use crate::pub_success::{EmptyCrateSuccess, PubDataSuccess};
+use crate::publish_validation::{self, PublishedCrate};
 use crate::registry_error::RegistryError;
 use crate::search_params::SearchParams;
+use crate::tarball_verification;
 
 #[cfg(test)]
 #[path = "test_cookie_helper.rs"]
 mod test_cookie_helper;
 
 use crate::yank_success::YankSuccess;
-use crate::{crate_group, crate_user, crate_version};
+use crate::{crate_group, crate_user, crate_version, download_history, reverse_dependency};
 
 pub async fn check_ownership(
     crate_name: &NormalizedName,
@@ -49,6 +56,24 @@ pub fn check_can_modify(user: &maybe_user::MaybeUser) -> Result<(), ApiError> {
     }
 }
 
+/// Check that the token the user authenticated with may perform `operation`
+/// on the crate. Users logged in with a session are never restricted.
+pub fn check_token_scope(
+    crate_name: &NormalizedName,
+    operation: TokenOperation,
+    scopes: &TokenScopes,
+) -> Result<(), ApiError> {
+    if scopes.allows(operation, crate_name) {
+        Ok(())
+    } else {
+        Err(RegistryError::TokenScope(
+            Into::<&str>::into(operation).to_string(),
+            crate_name.to_string(),
+        )
+        .into())
+    }
+}
+
 pub async fn check_download_auth(
     crate_name: &NormalizedName,
     token: &token::OptionToken,
@@ -63,6 +88,8 @@ pub async fn check_download_auth(
         token::OptionToken::None => return Err(RegistryError::DownloadUnauthorized.into()),
     };
 
+    check_token_scope(crate_name, TokenOperation::Download, &token.scopes)?;
+
     if token.is_admin
         || db.is_crate_user(crate_name, &token.user).await?
         || db.is_crate_group_user(crate_name, &token.user).await?
@@ -95,6 +122,7 @@ pub async fn check_download_auth(
 )]
 pub async fn remove_owner(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     state: AppState,
     Path(crate_name): Path<OriginalName>,
     Json(input): Json<crate_user::CrateUserRequest>,
@@ -109,6 +137,7 @@ pub async fn remove_owner(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     if !settings.registry.allow_ownerless_crates {
         // Never allow removing the last owner.
@@ -119,8 +148,23 @@ pub async fn remove_owner(
         }
     }
 
-    for user in &input.users {
-        db.delete_owner(&crate_name, user).await?;
+    let actor = user.audit_actor(source_ip);
+    for owner in &input.users {
+        db.delete_owner(&crate_name, owner).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::OwnerRemove,
+            &Utc::now(),
+            &crate_name,
+            json!({ "user": owner }),
+            db,
+        )
+        .await;
+        audit::record(
+            db,
+            NewAuditEntry::new(actor.clone(), AuditAction::RemoveOwner, &*crate_name)
+                .with_detail(owner),
+        )
+        .await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -148,6 +192,7 @@ pub async fn remove_owner(
 )]
 pub async fn remove_owner_single(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     state: AppState,
     Path((crate_name, removed_user)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_user::CrateUserResponse>> {
@@ -158,6 +203,7 @@ pub async fn remove_owner_single(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     if !settings.registry.allow_ownerless_crates {
         // Never allow removing the last owner.
@@ -168,6 +214,24 @@ pub async fn remove_owner_single(
     }
 
     db.delete_owner(&crate_name, &removed_user).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::OwnerRemove,
+        &Utc::now(),
+        &crate_name,
+        json!({ "user": removed_user }),
+        db,
+    )
+    .await;
+    audit::record(
+        db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::RemoveOwner,
+            &*crate_name,
+        )
+        .with_detail(removed_user),
+    )
+    .await;
 
     Ok(Json(crate_user::CrateUserResponse::from(
         "Removed owner from crate.",
@@ -193,6 +257,7 @@ pub async fn remove_owner_single(
 )]
 pub async fn remove_crate_user(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_user::CrateUserResponse>> {
@@ -203,8 +268,27 @@ pub async fn remove_crate_user(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     db.delete_crate_user(&crate_name, &name).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::AclChange,
+        &Utc::now(),
+        &crate_name,
+        json!({ "change": "crate_user_remove", "user": name }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::RemoveCrateUser,
+            &*crate_name,
+        )
+        .with_detail(name),
+    )
+    .await;
     Ok(Json(crate_user::CrateUserResponse::from(
         "Removed users from crate.",
     )))
@@ -229,6 +313,7 @@ pub async fn remove_crate_user(
 )]
 pub async fn remove_crate_group(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_group::CrateGroupResponse>> {
@@ -239,8 +324,27 @@ pub async fn remove_crate_group(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     db.delete_crate_group(&crate_name, &name).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::AclChange,
+        &Utc::now(),
+        &crate_name,
+        json!({ "change": "crate_group_remove", "group": name }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::RemoveCrateGroup,
+            &*crate_name,
+        )
+        .with_detail(name),
+    )
+    .await;
     Ok(Json(crate_group::CrateGroupResponse::from(
         "Removed groups from crate.",
     )))
@@ -265,6 +369,7 @@ pub async fn remove_crate_group(
 )]
 pub async fn add_owner(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path(crate_name): Path<OriginalName>,
     Json(input): Json<crate_user::CrateUserRequest>,
@@ -276,9 +381,25 @@ pub async fn add_owner(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
-
-    for user in &input.users {
-        db.add_owner(&crate_name, user).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
+
+    let actor = user.audit_actor(source_ip);
+    for owner in &input.users {
+        db.add_owner(&crate_name, owner).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::OwnerAdd,
+            &Utc::now(),
+            &crate_name,
+            json!({ "user": owner }),
+            &db,
+        )
+        .await;
+        audit::record(
+            &db,
+            NewAuditEntry::new(actor.clone(), AuditAction::AddOwner, &*crate_name)
+                .with_detail(owner),
+        )
+        .await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -305,6 +426,7 @@ pub async fn add_owner(
 )]
 pub async fn add_owner_single(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path((crate_name, added_user)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_user::CrateUserResponse>> {
@@ -312,8 +434,27 @@ pub async fn add_owner_single(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     db.add_owner(&crate_name, &added_user).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::OwnerAdd,
+        &Utc::now(),
+        &crate_name,
+        json!({ "user": added_user }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::AddOwner,
+            &*crate_name,
+        )
+        .with_detail(added_user),
+    )
+    .await;
 
     Ok(Json(crate_user::CrateUserResponse::from(
         "Added owner to crate.",
@@ -374,6 +515,7 @@ pub async fn list_owners(
 )]
 pub async fn add_crate_user(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_user::CrateUserResponse>> {
@@ -384,9 +526,28 @@ pub async fn add_crate_user(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     if !db.is_crate_user(&crate_name, &name).await? {
         db.add_crate_user(&crate_name, &name).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::AclChange,
+            &Utc::now(),
+            &crate_name,
+            json!({ "change": "crate_user_add", "user": name }),
+            &db,
+        )
+        .await;
+        audit::record(
+            &db,
+            NewAuditEntry::new(
+                user.audit_actor(source_ip),
+                AuditAction::AddCrateUser,
+                &*crate_name,
+            )
+            .with_detail(name),
+        )
+        .await;
     }
 
     Ok(Json(crate_user::CrateUserResponse::from(
@@ -451,6 +612,7 @@ pub async fn list_crate_users(
 )]
 pub async fn add_crate_group(
     user: maybe_user::MaybeUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path((crate_name, name)): Path<(OriginalName, String)>,
 ) -> ApiResult<Json<crate_group::CrateGroupResponse>> {
@@ -461,9 +623,28 @@ pub async fn add_crate_group(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::ChangeOwners, &user.scopes)?;
 
     if !db.is_crate_group(&crate_name, &name).await? {
         db.add_crate_group(&crate_name, &name).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::AclChange,
+            &Utc::now(),
+            &crate_name,
+            json!({ "change": "crate_group_add", "group": name }),
+            &db,
+        )
+        .await;
+        audit::record(
+            &db,
+            NewAuditEntry::new(
+                user.audit_actor(source_ip),
+                AuditAction::AddCrateGroup,
+                &*crate_name,
+            )
+            .with_detail(name),
+        )
+        .await;
     }
 
     Ok(Json(crate_group::CrateGroupResponse::from(
@@ -541,15 +722,119 @@ pub async fn list_crate_versions(
     Ok(Json(crate_version::CrateVersionList::from(versions)))
 }
 
+/// Get the download history of a crate
+///
+/// Returns the downloads per day of every version of a crate, for the last
+/// 30 days by default. Days without downloads are zero.
+#[utoipa::path(
+    get,
+    path = "/{crate_name}/downloads",
+    tag = "crates",
+    params(
+        ("crate_name" = String, Path, description = "Crate name"),
+        download_history::DownloadHistoryParams
+    ),
+    responses(
+        (status = 200, description = "Downloads per version and day", body = download_history::DownloadHistory),
+        (status = 404, description = "Crate not found")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn get_download_history(
+    Path(crate_name): Path<OriginalName>,
+    Query(params): Query<download_history::DownloadHistoryParams>,
+    State(db): DbState,
+    State(settings): SettingsState,
+) -> ApiResult<Json<download_history::DownloadHistory>> {
+    let normalized_name = crate_name.to_normalized();
+    if db.get_crate_id(&normalized_name).await?.is_none() {
+        return Err(RegistryError::CrateNotFound.into());
+    }
+
+    let max_days = match settings.registry.download_history_days {
+        0 => download_history::MAX_DAYS,
+        days => days.min(download_history::MAX_DAYS),
+    };
+    let days = params
+        .days
+        .unwrap_or(download_history::DEFAULT_DAYS)
+        .clamp(1, max_days);
+    let today = Utc::now().date_naive();
+    let start = download_history::first_day(today, days);
+
+    let mut versions = db.get_crate_versions(&normalized_name).await?;
+    versions.sort_by(|a, b| b.cmp(a));
+    let downloads = db.get_daily_downloads(&normalized_name, start).await?;
+
+    Ok(Json(download_history::DownloadHistory::new(
+        crate_name.to_string(),
+        start,
+        today,
+        versions.into_iter().map(Version::into_inner).collect(),
+        downloads,
+    )))
+}
+
+/// List reverse dependencies
+///
+/// Returns all hosted crate versions that depend on the crate, grouped by
+/// their version requirement. Each requirement is flagged if it accepts the
+/// newest version and if it breaks on the next breaking release.
+#[utoipa::path(
+    get,
+    path = "/{crate_name}/reverse_dependencies",
+    tag = "crates",
+    params(
+        ("crate_name" = String, Path, description = "Crate name"),
+        reverse_dependency::ReverseDependencyParams
+    ),
+    responses(
+        (status = 200, description = "Dependents grouped by requirement", body = reverse_dependency::ReverseDependencies),
+        (status = 400, description = "Invalid next version"),
+        (status = 404, description = "Crate not found")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn list_reverse_dependencies(
+    Path(crate_name): Path<OriginalName>,
+    Query(params): Query<reverse_dependency::ReverseDependencyParams>,
+    State(db): DbState,
+) -> ApiResult<Json<reverse_dependency::ReverseDependencies>> {
+    let normalized_name = crate_name.to_normalized();
+    if db.get_crate_id(&normalized_name).await?.is_none() {
+        return Err(RegistryError::CrateNotFound.into());
+    }
+
+    let max_version = db.get_max_version_from_name(&normalized_name).await?;
+    let max_version = semver::Version::parse(&max_version)
+        .map_err(|_| RegistryError::InvalidVersion(max_version.to_string()))?;
+    let next_version = match params.next {
+        Some(next) => {
+            semver::Version::parse(&next).map_err(|_| RegistryError::InvalidVersion(next))?
+        }
+        None => reverse_dependency::next_breaking_version(&max_version),
+    };
+
+    let dependents = db.get_dependents(&normalized_name).await?;
+    Ok(Json(reverse_dependency::ReverseDependencies::new(
+        crate_name.to_string(),
+        &max_version,
+        &next_version,
+        dependents,
+    )))
+}
+
 /// Search crates in Kellnr registry
 ///
-/// Searches for crates by name in the Kellnr registry.
+/// Ranked full-text search over crate names, keywords, categories,
+/// descriptions and readmes. The query accepts the filters `keyword:`,
+/// `category:`, `owner:` and `license:`, e.g. `pool keyword:async`.
 #[utoipa::path(
     get,
     path = "/",
     tag = "crates",
     params(
-        ("q" = String, Query, description = "Search query"),
+        ("q" = String, Query, description = "Search query with optional filters"),
         ("per_page" = Option<u32>, Query, description = "Results per page")
     ),
     responses(
@@ -558,9 +843,9 @@ pub async fn list_crate_versions(
     security(("cargo_token" = []))
 )]
 pub async fn search(State(db): DbState, params: SearchParams) -> ApiResult<Json<SearchResult>> {
-    let crates = db
-        .search_in_crate_name(&params.q, false)
-        .await?
+    let found = db.search_crates(&params.q, false).await?;
+    let total = found.len();
+    let crates = found
         .into_iter()
         .map(|c| Crate {
             name: c.name,
@@ -574,7 +859,7 @@ pub async fn search(State(db): DbState, params: SearchParams) -> ApiResult<Json<
 
     Ok(Json(SearchResult {
         meta: search_result::Meta {
-            total: crates.len() as i32,
+            total: i32::try_from(total).unwrap_or(i32::MAX),
         },
         crates,
     }))
@@ -615,7 +900,10 @@ pub async fn download(
         .await;
 
     match cs.get(&package, &version).await {
-        Some(file) => Ok(file),
+        Some(file) => {
+            metrics::crate_download(metrics::KELLNR);
+            Ok(file)
+        }
         None => Err(RegistryError::CrateNotFound.into()),
     }
 }
@@ -680,6 +968,7 @@ pub async fn add_empty_crate(
 pub async fn publish(
     State(state): AppState,
     token: token::Token,
+    source_ip: SourceIp,
     pub_data: PubData,
 ) -> ApiResult<Json<PubDataSuccess>> {
     let db = state.db;
@@ -695,6 +984,7 @@ pub async fn publish(
     // Admin users bypass this check as they can modify
     // their read-only status.
     check_can_modify(&user)?;
+    check_token_scope(&normalized_name, TokenOperation::Publish, &user.scopes)?;
 
     // Check if user from token is an owner of the crate.
     // If not, he is not allowed push a new version.
@@ -747,6 +1037,20 @@ pub async fn publish(
         }
     }
 
+    // The tarball has to contain the crate described by the metadata
+    tarball_verification::verify_tarball(&pub_data.cratedata, &pub_data.metadata)?;
+
+    // Run the pre-publish validation before anything is stored
+    publish_validation::validate(
+        &state.publish_validators,
+        &PublishedCrate {
+            metadata: &pub_data.metadata,
+            cratedata: &pub_data.cratedata,
+            user: &token_user,
+        },
+    )
+    .await?;
+
     // Set SHA256 from crate file
     let version = Version::try_from(&pub_data.metadata.vers)?;
     let cksum = cs
@@ -765,6 +1069,20 @@ pub async fn publish(
         return Err(e.into());
     }
 
+    notify_git_index(state.git_index_sender.as_ref(), &normalized_name);
+    metrics::CRATE_PUBLISHES.inc();
+
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::Publish,
+            &*normalized_name,
+        )
+        .with_version(&*version),
+    )
+    .await;
+
     kellnr_webhooks::notify_crate(
         if id.is_none() {
             WebhookEvent::CrateAdd
@@ -774,6 +1092,7 @@ pub async fn publish(
         &created,
         &normalized_name,
         &version,
+        Some(&user.name),
         &db,
     )
     .await;
@@ -811,7 +1130,9 @@ pub async fn publish(
 pub async fn yank(
     Path((crate_name, version)): Path<(OriginalName, Version)>,
     token: token::Token,
+    source_ip: SourceIp,
     State(db): DbState,
+    State(git_index): GitIndexSenderState,
 ) -> ApiResult<Json<YankSuccess>> {
     // Check if user is read-only and can't yank crates.
     // Admin users bypass this check as they can modify
@@ -821,14 +1142,23 @@ pub async fn yank(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::Yank, &user.scopes)?;
 
     db.yank_crate(&crate_name, &version).await?;
+    notify_git_index(git_index.as_ref(), &crate_name);
+    audit::record(
+        &db,
+        NewAuditEntry::new(user.audit_actor(source_ip), AuditAction::Yank, &*crate_name)
+            .with_version(&*version),
+    )
+    .await;
 
     kellnr_webhooks::notify_crate(
         WebhookEvent::CrateYank,
         &Utc::now(),
         &crate_name,
         &version,
+        Some(&user.name),
         &db,
     )
     .await;
@@ -856,7 +1186,9 @@ pub async fn yank(
 pub async fn unyank(
     Path((crate_name, version)): Path<(OriginalName, Version)>,
     token: token::Token,
+    source_ip: SourceIp,
     State(db): DbState,
+    State(git_index): GitIndexSenderState,
 ) -> ApiResult<Json<YankSuccess>> {
     // Check if user is read-only and can't unyank crates.
     // Admin users bypass this check as they can modify
@@ -866,14 +1198,27 @@ pub async fn unyank(
 
     let crate_name = crate_name.to_normalized();
     check_ownership(&crate_name, &user, &db).await?;
+    check_token_scope(&crate_name, TokenOperation::Yank, &user.scopes)?;
 
     db.unyank_crate(&crate_name, &version).await?;
+    notify_git_index(git_index.as_ref(), &crate_name);
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            user.audit_actor(source_ip),
+            AuditAction::Unyank,
+            &*crate_name,
+        )
+        .with_version(&*version),
+    )
+    .await;
 
     kellnr_webhooks::notify_crate(
         WebhookEvent::CrateUnyank,
         &Utc::now(),
         &crate_name,
         &version,
+        Some(&user.name),
         &db,
     )
     .await;
@@ -912,6 +1257,9 @@ mod reg_api_tests {
     const NON_ADMIN_TOKEN: &str = "g8kfzxSrMswNOVio5kBoTEFBBVm3fRS7";
     const RO_TOKEN: &str = "lJh6orU1Ye376ApXJR8I7V9gI3V6UZWU";
     const RO_ADMIN_TOKEN: &str = "GUOMPlZwN1kliXRW5wJ0ixh54NqYlE6X";
+    const PUBLISH_ONLY_TOKEN: &str = "v2WbQ5pXH6dNsT8cK1mYzR4jLfA9uE3o";
+    const OTHER_CRATES_TOKEN: &str = "Jq7nBw2ZxC5kVt9LsH4dRm1PgY8fNa6e";
+    const EXPIRED_TOKEN: &str = "Tz3eWq9XoK6bNc1VrM8sLy4HdG7jPf2u";
 
     // Test that removal of the last owner is prevented with default settings.
     #[tokio::test]
@@ -1809,7 +2157,7 @@ mod reg_api_tests {
     async fn search_verify_query_and_default() {
         let mut mock_db = MockDb::new();
         mock_db
-            .expect_search_in_crate_name()
+            .expect_search_crates()
             .with(eq("foo"), eq(false))
             .returning(|_, _| Ok(vec![]));
 
@@ -1827,11 +2175,128 @@ mod reg_api_tests {
         assert!(serde_json::from_slice::<SearchResult>(&result_msg).is_ok());
     }
 
+    #[tokio::test]
+    async fn search_accepts_text_with_filters() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_search_crates()
+            .with(eq("postgres pool keyword:async"), eq(false))
+            .returning(|_, _| Ok(vec![]));
+
+        let kellnr = app_search(Arc::new(mock_db));
+        let r = kellnr
+            .oneshot(
+                Request::get("/api/v1/crates?q=postgres%20pool%20keyword%3Aasync")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+    }
+
+    #[tokio::test]
+    async fn reverse_dependencies_flags_breaking_requirements() {
+        let mut mock_db = MockDb::new();
+        mock_db.expect_get_crate_id().returning(|_| Ok(Some(1)));
+        mock_db
+            .expect_get_max_version_from_name()
+            .returning(|_| Ok(Version::from_unchecked_str("1.4.0")));
+        mock_db.expect_get_dependents().returning(|_| {
+            Ok(vec![kellnr_db::Dependent {
+                name: "app".to_string(),
+                version: "0.3.0".to_string(),
+                req: "^1.2".to_string(),
+                kind: "normal".to_string(),
+                optional: false,
+                yanked: false,
+                is_latest: true,
+            }])
+        });
+
+        let r = app_reverse_dependencies(Arc::new(mock_db))
+            .oneshot(
+                Request::get("/api/v1/crates/foo/reverse_dependencies")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let rd: reverse_dependency::ReverseDependencies = serde_json::from_slice(&body).unwrap();
+        assert_eq!("2.0.0", rd.next_version);
+        assert_eq!(1, rd.requirements.len());
+        assert!(rd.requirements[0].matches_current);
+        assert!(rd.requirements[0].breaks_on_next);
+    }
+
+    #[tokio::test]
+    async fn download_history_returns_series_per_version() {
+        let mut mock_db = MockDb::new();
+        mock_db.expect_get_crate_id().returning(|_| Ok(Some(1)));
+        mock_db.expect_get_crate_versions().returning(|_| {
+            Ok(vec![
+                Version::from_unchecked_str("1.0.0"),
+                Version::from_unchecked_str("1.1.0"),
+            ])
+        });
+        mock_db.expect_get_daily_downloads().returning(|_, _| {
+            Ok(vec![kellnr_db::download_history::DailyDownloads {
+                version: "1.0.0".to_string(),
+                day: Utc::now().date_naive(),
+                downloads: 3,
+            }])
+        });
+
+        let r = app_download_history(Arc::new(mock_db))
+            .oneshot(
+                Request::get("/api/v1/crates/foo/downloads?days=7")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let history: download_history::DownloadHistory = serde_json::from_slice(&body).unwrap();
+        assert_eq!(7, history.days.len());
+        assert_eq!(
+            vec!["1.1.0", "1.0.0"],
+            history
+                .versions
+                .iter()
+                .map(|v| v.version.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 3], history.versions[1].downloads);
+    }
+
+    #[tokio::test]
+    async fn reverse_dependencies_of_unknown_crate_is_not_found() {
+        let mut mock_db = MockDb::new();
+        mock_db.expect_get_crate_id().returning(|_| Ok(None));
+
+        let r = app_reverse_dependencies(Arc::new(mock_db))
+            .oneshot(
+                Request::get("/api/v1/crates/foo/reverse_dependencies")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+
     #[tokio::test]
     async fn search_verify_per_page() {
         let mut mock_db = MockDb::new();
         mock_db
-            .expect_search_in_crate_name()
+            .expect_search_crates()
             .with(eq("foo"), eq(false))
             .returning(|_, _| Ok(vec![]));
 
@@ -2151,6 +2616,146 @@ mod reg_api_tests {
         );
     }
 
+    async fn publish_with_token(kellnr: &TestKellnr, token: &str) -> StatusCode {
+        let valid_pub_package = read("../../tests/fixtures/test-data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, token)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn publish_with_scoped_token() {
+        let kellnr = TestKellnr::fake(get_settings()).await;
+
+        let status = publish_with_token(&kellnr, PUBLISH_ONLY_TOKEN).await;
+
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[tokio::test]
+    async fn publish_with_token_scoped_to_other_crates_is_forbidden() {
+        let kellnr = TestKellnr::fake(get_settings()).await;
+
+        let status = publish_with_token(&kellnr, OTHER_CRATES_TOKEN).await;
+
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        assert!(
+            kellnr
+                .db
+                .get_crate_id(&NormalizedName::from_unchecked("test_lib".to_string()))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_with_expired_token_is_forbidden() {
+        let kellnr = TestKellnr::fake(get_settings()).await;
+
+        let status = publish_with_token(&kellnr, EXPIRED_TOKEN).await;
+
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
+    #[tokio::test]
+    async fn yank_with_publish_only_token_is_forbidden() {
+        let kellnr = TestKellnr::fake(get_settings()).await;
+        assert_eq!(StatusCode::OK, publish_with_token(&kellnr, TOKEN).await);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, PUBLISH_ONLY_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+
+    #[tokio::test]
+    async fn remove_owner_with_publish_only_token_is_forbidden() {
+        let mut settings = get_settings();
+        settings.registry.allow_ownerless_crates = true;
+        let kellnr = TestKellnr::fake(settings).await;
+        assert_eq!(StatusCode::OK, publish_with_token(&kellnr, TOKEN).await);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/owners/admin")
+                    .header(header::AUTHORIZATION, PUBLISH_ONLY_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+        assert_eq!(
+            1,
+            kellnr
+                .db
+                .get_crate_owners(&NormalizedName::from_unchecked("test_lib".to_string()))
+                .await
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_and_yank_are_audited() {
+        let kellnr = TestKellnr::fake(get_settings()).await;
+        assert_eq!(StatusCode::OK, publish_with_token(&kellnr, TOKEN).await);
+
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::delete("/api/v1/crates/test_lib/0.2.0/yank")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, r.status());
+
+        let page = kellnr
+            .db
+            .get_audit_entries(&kellnr_db::AuditFilter::default(), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(2, page.total);
+        let (yank, publish) = (&page.entries[0], &page.entries[1]);
+        assert_eq!(AuditAction::Yank, yank.action);
+        assert_eq!(AuditAction::Publish, publish.action);
+        for entry in [yank, publish] {
+            assert_eq!("admin", entry.actor);
+            assert_eq!(Some("test".to_string()), entry.token_name);
+            assert_eq!("test_lib", entry.target);
+            assert_eq!(Some("0.2.0".to_string()), entry.version);
+        }
+    }
+
     #[tokio::test]
     async fn try_publish_as_read_only_non_admin() {
         // Use valid crate publish data to test.
@@ -2456,6 +3061,99 @@ mod reg_api_tests {
         );
     }
 
+    #[tokio::test]
+    async fn publish_crate_rejected_by_publish_policy() {
+        let valid_pub_package = read("../../tests/fixtures/test-data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        let mut settings = get_settings();
+        settings.publish.allowed_licenses = vec!["MIT".to_string()];
+
+        let kellnr = TestKellnr::fake(settings).await;
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(valid_pub_package))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let response_status = r.status();
+        let msg = r.into_body().collect().await.unwrap().to_bytes();
+
+        let error: ErrorDetails =
+            serde_json::from_slice(&msg).expect("Cannot deserialize error message");
+
+        assert_eq!(StatusCode::BAD_REQUEST, response_status);
+        assert_eq!(
+            "ERROR: Crate test_lib rejected: a license is required, allowed licenses: MIT",
+            error.errors[0].detail
+        );
+        assert!(
+            kellnr
+                .db
+                .get_crate_id(&NormalizedName::from_unchecked("test_lib".to_string()))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_crate_with_mismatched_tarball_is_rejected() {
+        let valid_pub_package = read("../../tests/fixtures/test-data/pub_data.bin")
+            .await
+            .expect("Cannot open valid package file.");
+        // Publish version 0.3.0 with the tarball of version 0.2.0. The
+        // replacement has the same length, so the metadata length stays valid.
+        let vers = br#""vers":"0.2.0""#;
+        let start = valid_pub_package
+            .windows(vers.len())
+            .position(|w| w == vers)
+            .unwrap();
+        let mut tampered = valid_pub_package.clone();
+        tampered[start..start + vers.len()].copy_from_slice(br#""vers":"0.3.0""#);
+
+        let kellnr = TestKellnr::fake(get_settings()).await;
+        let r = kellnr
+            .client
+            .clone()
+            .oneshot(
+                Request::put("/api/v1/crates/new")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, TOKEN)
+                    .body(Body::from(tampered))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let response_status = r.status();
+        let msg = r.into_body().collect().await.unwrap().to_bytes();
+
+        let error: ErrorDetails =
+            serde_json::from_slice(&msg).expect("Cannot deserialize error message");
+
+        assert_eq!(StatusCode::BAD_REQUEST, response_status);
+        assert_eq!(
+            "ERROR: Invalid crate tarball: test_lib-0.2.0/.cargo/config is not in the directory test_lib-0.3.0",
+            error.errors[0].detail
+        );
+        assert!(
+            kellnr
+                .db
+                .get_crate_id(&NormalizedName::from_unchecked("test_lib".to_string()))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
     // Missing some but not all required fields
     #[tokio::test]
     async fn publish_crate_with_some_required_fields() {
@@ -2622,9 +3320,46 @@ mod reg_api_tests {
         db.add_auth_token("test non admin", NON_ADMIN_TOKEN, "non_admin")
             .await
             .unwrap();
+        db.add_scoped_auth_token(
+            "test publish only",
+            PUBLISH_ONLY_TOKEN,
+            "admin",
+            &TokenScopes {
+                operations: vec![TokenOperation::Publish],
+                crates: vec!["test_*".to_string()],
+                expires_at: Some(Utc::now() + chrono::Duration::days(1)),
+            },
+        )
+        .await
+        .unwrap();
+        db.add_scoped_auth_token(
+            "test other crates",
+            OTHER_CRATES_TOKEN,
+            "admin",
+            &TokenScopes {
+                crates: vec!["other".to_string()],
+                ..TokenScopes::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_scoped_auth_token(
+            "test expired",
+            EXPIRED_TOKEN,
+            "admin",
+            &TokenScopes {
+                expires_at: Some(Utc::now() - chrono::Duration::days(1)),
+                ..TokenScopes::default()
+            },
+        )
+        .await
+        .unwrap();
 
         let state = AppStateData {
             db: Arc::new(db),
+            publish_validators: Arc::new(
+                publish_validation::validators(&settings.publish).unwrap(),
+            ),
             settings: settings.into(),
             crate_storage: cs.into(),
             // Use a fixed signing key matching the test cookie helper so that
@@ -2651,6 +3386,30 @@ mod reg_api_tests {
             .with_state(state)
     }
 
+    fn app_download_history(db: Arc<dyn DbProvider>) -> Router {
+        Router::new()
+            .route(
+                "/api/v1/crates/{crate_name}/downloads",
+                get(get_download_history),
+            )
+            .with_state(AppStateData {
+                db,
+                ..kellnr_appstate::test_state()
+            })
+    }
+
+    fn app_reverse_dependencies(db: Arc<dyn DbProvider>) -> Router {
+        Router::new()
+            .route(
+                "/api/v1/crates/{crate_name}/reverse_dependencies",
+                get(list_reverse_dependencies),
+            )
+            .with_state(AppStateData {
+                db,
+                ..kellnr_appstate::test_state()
+            })
+    }
+
     fn app_search(db: Arc<dyn DbProvider>) -> Router {
         Router::new()
             .route("/api/v1/crates", get(search))
@@ -2,9 +2,14 @@ pub mod crate_group;
 pub mod crate_user;
 pub mod crate_version;
 pub mod cratesio_api;
+pub mod download_history;
 pub mod kellnr_api;
 pub mod pub_data;
 mod pub_success;
+pub mod publish_validation;
 pub mod registry_error;
+pub mod reverse_dependency;
 pub mod search_params;
+pub mod tarball_verification;
+pub mod upstream_api;
 mod yank_success;
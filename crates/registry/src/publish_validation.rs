@@ -0,0 +1,419 @@
+//! Validation of published crates before they are stored.
+//!
+//! Every [`PublishValidator`] gets the metadata and tarball of the crate and
+//! can reject it with a reason, which is returned to cargo as the error of the
+//! publish. The validators are built from the `[publish]` settings.
+
+use std::io::Read;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use kellnr_common::publish_metadata::PublishMetadata;
+use kellnr_common::publish_policy::{CrateContents, PublishPolicy};
+pub use kellnr_common::publish_validator::{PublishValidator, PublishedCrate};
+use kellnr_settings::Publish;
+use serde::{Deserialize, Serialize};
+use tar::{Archive, EntryType};
+use tracing::warn;
+use url::Url;
+
+use crate::registry_error::RegistryError;
+
+/// Max. size of the response of the external validator that is shown to the user
+const MAX_REASON_LENGTH: usize = 1024;
+
+/// Validators configured by the `[publish]` settings. Built once on startup,
+/// fails if the settings are invalid.
+pub fn validators(settings: &Publish) -> Result<Vec<Box<dyn PublishValidator>>, String> {
+    let mut validators: Vec<Box<dyn PublishValidator>> =
+        vec![Box::new(PolicyValidator(settings.policy()?))];
+    if let Some(url) = &settings.validator_url {
+        validators.push(Box::new(HttpValidator::new(
+            url.clone(),
+            settings.validator_token.clone(),
+            Duration::from_secs(settings.validator_timeout_seconds),
+        )?));
+    }
+    Ok(validators)
+}
+
+/// Run all validators, the first rejection fails the publish.
+pub async fn validate(
+    validators: &[Box<dyn PublishValidator>],
+    krate: &PublishedCrate<'_>,
+) -> Result<(), RegistryError> {
+    for validator in validators {
+        validator.validate(krate).await.map_err(|reason| {
+            RegistryError::PublishRejected(krate.metadata.name.clone(), reason)
+        })?;
+    }
+    Ok(())
+}
+
+/// Checks the rules of the [`PublishPolicy`].
+pub struct PolicyValidator(pub PublishPolicy);
+
+#[async_trait]
+impl PublishValidator for PolicyValidator {
+    async fn validate(&self, krate: &PublishedCrate<'_>) -> Result<(), String> {
+        let policy = &self.0;
+        policy
+            .check_metadata(krate.metadata)
+            .map_err(|e| e.to_string())?;
+        if policy.checks_contents() {
+            let contents = read_crate_contents(krate.cratedata, policy)?;
+            policy
+                .check_contents(&contents)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the files and dependencies of a crate tarball. Stops reading once a
+/// limit of the policy is exceeded, as the tarball may unpack to much more
+/// data than it has. Fails if the dependencies have to be checked, but the
+/// tarball has no `Cargo.toml.orig`.
+fn read_crate_contents(cratedata: &[u8], policy: &PublishPolicy) -> Result<CrateContents, String> {
+    let invalid = |e: std::io::Error| format!("invalid crate tarball: {e}");
+    let mut archive = Archive::new(GzDecoder::new(cratedata));
+    let mut contents = CrateContents::default();
+    let mut has_manifest = false;
+
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+        contents.files += 1;
+        contents.unpacked_size = contents.unpacked_size.saturating_add(entry.size());
+        if (policy.max_files > 0 && contents.files > policy.max_files)
+            || (policy.max_unpacked_size > 0 && contents.unpacked_size > policy.max_unpacked_size)
+        {
+            // Rejected by the limit, the dependencies do not matter anymore
+            return Ok(contents);
+        }
+
+        // The original manifest is only in the root directory of the crate,
+        // the normalized `Cargo.toml` has no git and path sources anymore.
+        let path = entry.path().map_err(invalid)?;
+        if path.components().count() == 2 && path.ends_with("Cargo.toml.orig") {
+            let mut manifest = String::new();
+            entry.read_to_string(&mut manifest).map_err(invalid)?;
+            let manifest = toml::from_str::<toml::Table>(&manifest)
+                .map_err(|e| format!("invalid Cargo.toml.orig: {e}"))?;
+            collect_sourced_dependencies(&manifest, &mut contents);
+            has_manifest = true;
+        }
+    }
+
+    if !has_manifest && (policy.deny_git_dependencies || policy.deny_path_dependencies) {
+        return Err("the crate has no Cargo.toml.orig, its dependencies cannot be checked".into());
+    }
+    Ok(contents)
+}
+
+/// Collect the dependencies with a `git` or `path` source of all dependency
+/// tables of the manifest, including target specific ones. Dependencies
+/// inherited from a workspace are not part of the tarball and not found.
+fn collect_sourced_dependencies(manifest: &toml::Table, contents: &mut CrateContents) {
+    let targets = manifest
+        .get("target")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(toml::Value::as_table));
+
+    for table in std::iter::once(manifest).chain(targets) {
+        for kind in [
+            "dependencies",
+            "dev-dependencies",
+            "dev_dependencies",
+            "build-dependencies",
+            "build_dependencies",
+        ] {
+            let Some(dependencies) = table.get(kind).and_then(toml::Value::as_table) else {
+                continue;
+            };
+            for (name, dependency) in dependencies {
+                let Some(dependency) = dependency.as_table() else {
+                    continue;
+                };
+                if dependency.contains_key("git") {
+                    contents.git_dependencies.push(name.clone());
+                }
+                if dependency.contains_key("path") {
+                    contents.path_dependencies.push(name.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Request sent to the external validator.
+#[derive(Debug, Serialize)]
+struct ValidationRequest<'a> {
+    name: &'a str,
+    vers: &'a str,
+    user: &'a str,
+    metadata: &'a PublishMetadata,
+}
+
+/// Optional JSON body of a rejection by the external validator.
+#[derive(Debug, Deserialize)]
+struct ValidationResponse {
+    reason: String,
+}
+
+/// Sends the metadata of the crate to an external HTTP service. A 2xx
+/// response accepts the crate, every other response rejects it. The reason
+/// is taken from a JSON body `{"reason": "..."}` or else from the plain text
+/// body. The crate is rejected if the validator cannot be reached.
+pub struct HttpValidator {
+    client: reqwest::Client,
+    url: Url,
+    token: Option<String>,
+}
+
+impl HttpValidator {
+    pub fn new(url: Url, token: Option<String>, timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to create publish validator client: {e}"))?;
+        Ok(Self { client, url, token })
+    }
+}
+
+#[async_trait]
+impl PublishValidator for HttpValidator {
+    async fn validate(&self, krate: &PublishedCrate<'_>) -> Result<(), String> {
+        let request = ValidationRequest {
+            name: &krate.metadata.name,
+            vers: &krate.metadata.vers,
+            user: krate.user,
+            metadata: krate.metadata,
+        };
+        let mut builder = self.client.post(self.url.clone()).json(&request);
+        if let Some(token) = &self.token {
+            builder = builder.header(reqwest::header::AUTHORIZATION, token);
+        }
+
+        let response = builder.send().await.map_err(|e| {
+            warn!("Failed to call publish validator: {e}");
+            "publish validator is not available".to_string()
+        })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let reason = match serde_json::from_str::<ValidationResponse>(&body) {
+            Ok(response) => response.reason,
+            Err(_) => body.trim().to_string(),
+        };
+        if reason.is_empty() {
+            return Err(format!("rejected by publish validator ({status})"));
+        }
+        Err(truncate(reason, MAX_REASON_LENGTH))
+    }
+}
+
+fn truncate(mut s: String, max: usize) -> String {
+    if s.len() > max {
+        let mut end = max;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let mut encoder = builder.into_inner().unwrap();
+        encoder.flush().unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const MANIFEST: &str = r#"
+        [package]
+        name = "foo"
+        version = "1.0.0"
+
+        [dependencies]
+        serde = "1"
+        from-git = { git = "https://github.com/my-org/from-git" }
+        sibling = { path = "../sibling", version = "1" }
+
+        [target.'cfg(unix)'.dev-dependencies]
+        unix-only = { path = "../unix-only" }
+    "#;
+
+    #[test]
+    fn read_crate_contents_finds_files_and_sourced_dependencies() {
+        let files = [
+            ("foo-1.0.0/Cargo.toml.orig", MANIFEST),
+            ("foo-1.0.0/src/lib.rs", "pub fn foo() {}"),
+            // Only the manifest in the crate root is read
+            (
+                "foo-1.0.0/tests/Cargo.toml.orig",
+                "[dependencies]\nnested = { path = \"x\" }",
+            ),
+        ];
+        let data = tarball(&files);
+
+        let contents = read_crate_contents(&data, &PublishPolicy::default()).unwrap();
+
+        assert_eq!(3, contents.files);
+        let size: usize = files.iter().map(|(_, content)| content.len()).sum();
+        assert_eq!(size as u64, contents.unpacked_size);
+        assert_eq!(vec!["from-git"], contents.git_dependencies);
+        assert_eq!(vec!["sibling", "unix-only"], contents.path_dependencies);
+    }
+
+    #[test]
+    fn read_crate_contents_stops_at_limit() {
+        let data = tarball(&[
+            ("foo-1.0.0/a", "a"),
+            ("foo-1.0.0/b", "b"),
+            ("foo-1.0.0/c", "c"),
+        ]);
+        let policy = PublishPolicy {
+            max_files: 1,
+            ..PublishPolicy::default()
+        };
+
+        let contents = read_crate_contents(&data, &policy).unwrap();
+
+        assert_eq!(2, contents.files);
+    }
+
+    #[test]
+    fn read_crate_contents_requires_manifest_to_check_dependencies() {
+        let data = tarball(&[("foo-1.0.0/src/lib.rs", "pub fn foo() {}")]);
+        let deny_git = PublishPolicy {
+            deny_git_dependencies: true,
+            ..PublishPolicy::default()
+        };
+
+        assert_eq!(
+            Err("the crate has no Cargo.toml.orig, its dependencies cannot be checked".to_string()),
+            read_crate_contents(&data, &deny_git).map(|c| c.files)
+        );
+        assert_eq!(
+            Ok(1),
+            read_crate_contents(&data, &PublishPolicy::default()).map(|c| c.files)
+        );
+    }
+
+    #[test]
+    fn read_crate_contents_rejects_invalid_tarball() {
+        let result = read_crate_contents(b"not a tarball", &PublishPolicy::default());
+
+        assert!(result.unwrap_err().starts_with("invalid crate tarball"));
+    }
+
+    #[tokio::test]
+    async fn policy_validator_rejects_path_dependencies() {
+        let data = tarball(&[("foo-1.0.0/Cargo.toml.orig", MANIFEST)]);
+        let metadata = PublishMetadata::minimal("foo", "1.0.0");
+        let krate = PublishedCrate {
+            metadata: &metadata,
+            cratedata: &data,
+            user: "admin",
+        };
+        let validator = PolicyValidator(PublishPolicy {
+            deny_path_dependencies: true,
+            ..PublishPolicy::default()
+        });
+
+        let result = validator.validate(&krate).await;
+
+        assert_eq!(
+            Err("path dependencies are not allowed, found \"sibling\"".to_string()),
+            result
+        );
+    }
+
+    /// Start a validator that answers with the given status and body.
+    async fn start_validator(status: StatusCode, body: &'static str) -> Url {
+        let app = Router::new().route("/check", post(move || async move { (status, body) }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Url::parse(&format!("http://{addr}/check")).unwrap()
+    }
+
+    async fn validate_with(url: Url) -> Result<(), String> {
+        let metadata = PublishMetadata::minimal("foo", "1.0.0");
+        let krate = PublishedCrate {
+            metadata: &metadata,
+            cratedata: &[],
+            user: "admin",
+        };
+        HttpValidator::new(url, None, Duration::from_secs(5))
+            .unwrap()
+            .validate(&krate)
+            .await
+    }
+
+    #[tokio::test]
+    async fn http_validator_accepts_on_success() {
+        let url = start_validator(StatusCode::NO_CONTENT, "").await;
+
+        assert_eq!(Ok(()), validate_with(url).await);
+    }
+
+    #[tokio::test]
+    async fn http_validator_returns_reason_of_rejection() {
+        let url = start_validator(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            r#"{"reason": "crate name is reserved"}"#,
+        )
+        .await;
+
+        assert_eq!(
+            Err("crate name is reserved".to_string()),
+            validate_with(url).await
+        );
+    }
+
+    #[tokio::test]
+    async fn http_validator_rejects_if_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let result = validate_with(Url::parse(&format!("http://{addr}/check")).unwrap()).await;
+
+        assert_eq!(
+            Err("publish validator is not available".to_string()),
+            result
+        );
+    }
+}
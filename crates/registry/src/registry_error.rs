@@ -38,6 +38,14 @@ pub enum RegistryError {
     NewCratesRestricted,
     #[error("A crate must have at least one owner")]
     LastOwner,
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
+    #[error("Token is not allowed to {0} crate {1}")]
+    TokenScope(String, String),
+    #[error("Crate {0} rejected: {1}")]
+    PublishRejected(String, String),
+    #[error("Invalid crate tarball: {0}")]
+    InvalidCrateTarball(String),
 }
 
 impl From<RegistryError> for ApiError {
@@ -45,9 +53,9 @@ impl From<RegistryError> for ApiError {
         match e {
             RegistryError::CrateNotFound => ApiError::from_err(&e, StatusCode::NOT_FOUND),
             RegistryError::DownloadUnauthorized => ApiError::from_err(&e, StatusCode::UNAUTHORIZED),
-            RegistryError::NotOwner | RegistryError::NotCrateUser => {
-                ApiError::from_err(&e, StatusCode::FORBIDDEN)
-            }
+            RegistryError::NotOwner
+            | RegistryError::NotCrateUser
+            | RegistryError::TokenScope(_, _) => ApiError::from_err(&e, StatusCode::FORBIDDEN),
             RegistryError::LastOwner => ApiError::from_err(&e, StatusCode::CONFLICT),
             _ => ApiError::from_err(&e, StatusCode::BAD_REQUEST),
         }
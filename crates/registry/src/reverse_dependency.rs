@@ -0,0 +1,139 @@
+use kellnr_db::Dependent;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Default, Debug, Clone, Deserialize, IntoParams)]
+pub struct ReverseDependencyParams {
+    /// Version to check the requirements against. Defaults to the next
+    /// breaking release of the newest version.
+    pub next: Option<String>,
+}
+
+/// Hosted crates that depend on a crate, grouped by their version requirement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ReverseDependencies {
+    pub name: String,
+    /// Newest version of the crate
+    pub max_version: String,
+    /// Version the requirements are checked against, e.g. the next major version
+    pub next_version: String,
+    pub requirements: Vec<RequirementGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RequirementGroup {
+    pub req: String,
+    /// `true` if the requirement accepts the newest version
+    pub matches_current: bool,
+    /// `true` if the dependents have to change the requirement to use the
+    /// next version. Requirements that are not valid semver always break.
+    pub breaks_on_next: bool,
+    pub dependents: Vec<Dependent>,
+}
+
+/// The next release with breaking changes according to the semver rules of
+/// Cargo, e.g. `2.0.0` for `1.4.2` and `0.5.0` for `0.4.1`.
+pub fn next_breaking_version(version: &Version) -> Version {
+    if version.major > 0 {
+        Version::new(version.major + 1, 0, 0)
+    } else if version.minor > 0 {
+        Version::new(0, version.minor + 1, 0)
+    } else {
+        Version::new(0, 0, version.patch + 1)
+    }
+}
+
+impl ReverseDependencies {
+    /// Group `dependents` by their requirement. Groups are ordered by the
+    /// requirement, dependents keep their order.
+    pub fn new(
+        name: String,
+        max_version: &Version,
+        next_version: &Version,
+        dependents: Vec<Dependent>,
+    ) -> Self {
+        let mut requirements: Vec<RequirementGroup> = Vec::new();
+        for dependent in dependents {
+            if let Some(group) = requirements.iter_mut().find(|g| g.req == dependent.req) {
+                group.dependents.push(dependent);
+                continue;
+            }
+            let req = VersionReq::parse(&dependent.req).ok();
+            requirements.push(RequirementGroup {
+                req: dependent.req.clone(),
+                matches_current: req.as_ref().is_some_and(|r| r.matches(max_version)),
+                breaks_on_next: !req.is_some_and(|r| r.matches(next_version)),
+                dependents: vec![dependent],
+            });
+        }
+        requirements.sort_by(|a, b| a.req.cmp(&b.req));
+
+        Self {
+            name,
+            max_version: max_version.to_string(),
+            next_version: next_version.to_string(),
+            requirements,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependent(name: &str, req: &str) -> Dependent {
+        Dependent {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            req: req.to_string(),
+            kind: "normal".to_string(),
+            optional: false,
+            yanked: false,
+            is_latest: true,
+        }
+    }
+
+    #[test]
+    fn next_breaking_version_follows_cargo_semver() {
+        let next = |v| next_breaking_version(&Version::parse(v).unwrap()).to_string();
+
+        assert_eq!("2.0.0", next("1.4.2"));
+        assert_eq!("0.5.0", next("0.4.1"));
+        assert_eq!("0.0.4", next("0.0.3"));
+    }
+
+    #[test]
+    fn groups_dependents_and_flags_breaking_requirements() {
+        let rd = ReverseDependencies::new(
+            "foo".to_string(),
+            &Version::new(1, 4, 2),
+            &Version::new(2, 0, 0),
+            vec![
+                dependent("a", "^1.2"),
+                dependent("b", ">=1"),
+                dependent("c", "^1.2"),
+                dependent("d", "^0.9"),
+                dependent("e", "not a req"),
+            ],
+        );
+
+        let groups = rd
+            .requirements
+            .iter()
+            .map(|g| {
+                let names = g.dependents.iter().map(|d| d.name.as_str()).collect();
+                (g.req.as_str(), g.matches_current, g.breaks_on_next, names)
+            })
+            .collect::<Vec<(_, _, _, Vec<_>)>>();
+        assert_eq!(
+            vec![
+                (">=1", true, false, vec!["b"]),
+                ("^0.9", false, true, vec!["d"]),
+                ("^1.2", true, true, vec!["a", "c"]),
+                ("not a req", false, true, vec!["e"]),
+            ],
+            groups
+        );
+    }
+}
@@ -5,10 +5,12 @@ use axum::RequestPartsExt;
 use axum::extract::Query;
 use axum::http::request::Parts;
 use hyper::StatusCode;
-use kellnr_common::original_name::OriginalName;
+/// Longest accepted search query in characters
+const MAX_QUERY_LEN: usize = 256;
 
 pub struct SearchParams {
-    pub q: OriginalName,
+    /// Search text with optional filters like `keyword:async`
+    pub q: String,
     pub per_page: PerPage,
 }
 
@@ -53,7 +55,13 @@ where
         let q = query_params
             .get("q")
             .ok_or((StatusCode::BAD_REQUEST, "missing q".to_owned()))?;
-        let q = OriginalName::try_from(q).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if q.chars().count() > MAX_QUERY_LEN {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("q must not be longer than {MAX_QUERY_LEN} characters"),
+            ));
+        }
+        let q = q.clone();
 
         let per_page = query_params
             .get("per_page")
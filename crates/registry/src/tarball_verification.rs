@@ -0,0 +1,301 @@
+//! Verification of the tarball of a published crate.
+//!
+//! The metadata of a publish request is sent by the client next to the
+//! tarball and is not derived from it by the server. Without a check, a
+//! tarball could contain another crate than the metadata describes, or paths
+//! that escape the directory it is unpacked to, e.g. by the doc builder.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Component, Path};
+
+use flate2::read::GzDecoder;
+use kellnr_common::publish_metadata::PublishMetadata;
+use tar::Archive;
+
+use crate::registry_error::RegistryError;
+
+/// Max. size of the `Cargo.toml` of a crate in bytes
+const MAX_MANIFEST_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Check that the tarball only contains files and directories below the
+/// directory `{name}-{version}` and that its `Cargo.toml` has the name and
+/// version of the metadata.
+pub fn verify_tarball(cratedata: &[u8], metadata: &PublishMetadata) -> Result<(), RegistryError> {
+    let root = format!("{}-{}", metadata.name, metadata.vers);
+    let manifest_path = Path::new(&root).join("Cargo.toml");
+    let mut archive = Archive::new(GzDecoder::new(cratedata));
+    let mut paths = HashSet::new();
+    let mut manifest = None;
+
+    for entry in archive.entries().map_err(|e| invalid(e.to_string()))? {
+        let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+        let entry_type = entry.header().entry_type();
+        // Global extensions only contain metadata of the following entries
+        if entry_type.is_pax_global_extensions() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(|e| invalid(e.to_string()))?
+            .into_owned();
+        check_path(&path, &root)?;
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            return Err(invalid(format!(
+                "{} is not a regular file or directory",
+                path.display()
+            )));
+        }
+        if !paths.insert(path.clone()) && entry_type.is_file() {
+            return Err(invalid(format!("{} is contained twice", path.display())));
+        }
+
+        if entry_type.is_file() && path == manifest_path {
+            if entry.size() > MAX_MANIFEST_SIZE {
+                return Err(invalid("Cargo.toml is too large".to_string()));
+            }
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| invalid(e.to_string()))?;
+            manifest = Some(content);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| invalid(format!("{root}/Cargo.toml is missing")))?;
+    check_manifest(&manifest, metadata)
+}
+
+/// Check that the path is relative, has no `..` components and is inside
+/// the root directory.
+fn check_path(path: &Path, root: &str) -> Result<(), RegistryError> {
+    let mut components = path.components();
+    match components.next() {
+        Some(Component::Normal(first)) if first == root => {}
+        Some(Component::Normal(_)) => {
+            return Err(invalid(format!(
+                "{} is not in the directory {root}",
+                path.display()
+            )));
+        }
+        _ => {
+            return Err(invalid(format!(
+                "{} is not a relative path",
+                path.display()
+            )));
+        }
+    }
+    if components.any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(invalid(format!(
+            "{} is not a normalized path",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+fn check_manifest(manifest: &str, metadata: &PublishMetadata) -> Result<(), RegistryError> {
+    let manifest = toml::from_str::<toml::Table>(manifest)
+        .map_err(|e| invalid(format!("Cargo.toml cannot be parsed: {e}")))?;
+    let package = manifest
+        .get("package")
+        .and_then(toml::Value::as_table)
+        .ok_or_else(|| invalid("Cargo.toml has no [package] section".to_string()))?;
+    let field = |key| package.get(key).and_then(toml::Value::as_str);
+
+    let name = field("name").unwrap_or_default();
+    if name != metadata.name {
+        return Err(invalid(format!(
+            "name \"{name}\" in Cargo.toml does not match the published name \"{}\"",
+            metadata.name
+        )));
+    }
+    let version = field("version").unwrap_or_default();
+    let same_version = match (
+        semver::Version::parse(version),
+        semver::Version::parse(&metadata.vers),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => version == metadata.vers,
+    };
+    if !same_version {
+        return Err(invalid(format!(
+            "version \"{version}\" in Cargo.toml does not match the published version \"{}\"",
+            metadata.vers
+        )));
+    }
+    Ok(())
+}
+
+fn invalid(reason: String) -> RegistryError {
+    RegistryError::InvalidCrateTarball(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tar::{EntryType, Header};
+
+    use super::*;
+
+    const MANIFEST: &str = "[package]\nname = \"foo\"\nversion = \"1.0.0\"\n";
+
+    /// Build a tarball without the path checks of the tar builder.
+    fn tarball(entries: &[(&str, EntryType, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, entry_type, content) in entries {
+            let mut header = Header::new_gnu();
+            let name = &mut header.as_gnu_mut().unwrap().name;
+            name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_entry_type(*entry_type);
+            header.set_mode(0o644);
+            if entry_type.is_symlink() {
+                header.set_link_name(content).unwrap();
+                header.set_size(0);
+            } else {
+                header.set_size(content.len() as u64);
+            }
+            header.set_cksum();
+            let data = if entry_type.is_file() {
+                content.as_bytes()
+            } else {
+                &[]
+            };
+            builder.append(&header, data).unwrap();
+        }
+        let mut encoder = builder.into_inner().unwrap();
+        encoder.flush().unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn verify(entries: &[(&str, EntryType, &str)]) -> Result<(), String> {
+        verify_tarball(&tarball(entries), &PublishMetadata::minimal("foo", "1.0.0"))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn valid_tarball_is_accepted() {
+        let result = verify(&[
+            ("foo-1.0.0/", EntryType::Directory, ""),
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+            (
+                "foo-1.0.0/src/lib.rs",
+                EntryType::Regular,
+                "pub fn foo() {}",
+            ),
+        ]);
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn fixture_tarball_is_accepted() {
+        let data = std::fs::read("../../tests/fixtures/test-data/full-toml-0.1.4.crate").unwrap();
+        let metadata = PublishMetadata::minimal("full-toml", "0.1.4");
+
+        assert!(verify_tarball(&data, &metadata).is_ok());
+    }
+
+    #[test]
+    fn mismatched_name_is_rejected() {
+        let result = verify(&[(
+            "foo-1.0.0/Cargo.toml",
+            EntryType::Regular,
+            "[package]\nname = \"bar\"\nversion = \"1.0.0\"\n",
+        )]);
+
+        assert_eq!(
+            Err("Invalid crate tarball: name \"bar\" in Cargo.toml does not match the published name \"foo\"".to_string()),
+            result
+        );
+    }
+
+    #[test]
+    fn mismatched_version_is_rejected() {
+        let result = verify(&[(
+            "foo-1.0.0/Cargo.toml",
+            EntryType::Regular,
+            "[package]\nname = \"foo\"\nversion = \"2.0.0\"\n",
+        )]);
+
+        assert!(result.unwrap_err().contains("version \"2.0.0\""));
+    }
+
+    #[test]
+    fn missing_manifest_is_rejected() {
+        let result = verify(&[("foo-1.0.0/src/lib.rs", EntryType::Regular, "")]);
+
+        assert_eq!(
+            Err("Invalid crate tarball: foo-1.0.0/Cargo.toml is missing".to_string()),
+            result
+        );
+    }
+
+    #[test]
+    fn other_top_level_directory_is_rejected() {
+        let result = verify(&[
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+            ("bar-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+        ]);
+
+        assert_eq!(
+            Err(
+                "Invalid crate tarball: bar-1.0.0/Cargo.toml is not in the directory foo-1.0.0"
+                    .to_string()
+            ),
+            result
+        );
+    }
+
+    #[test]
+    fn path_traversal_is_rejected() {
+        for path in [
+            "foo-1.0.0/../../etc/passwd",
+            "/foo-1.0.0/src/lib.rs",
+            "../foo-1.0.0/src/lib.rs",
+        ] {
+            let result = verify(&[
+                ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+                (path, EntryType::Regular, ""),
+            ]);
+
+            assert!(result.is_err(), "{path} must be rejected");
+        }
+    }
+
+    #[test]
+    fn links_are_rejected() {
+        let result = verify(&[
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+            ("foo-1.0.0/src", EntryType::Symlink, "/etc"),
+        ]);
+
+        assert_eq!(
+            Err(
+                "Invalid crate tarball: foo-1.0.0/src is not a regular file or directory"
+                    .to_string()
+            ),
+            result
+        );
+    }
+
+    #[test]
+    fn duplicate_manifest_is_rejected() {
+        let result = verify(&[
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+            ("foo-1.0.0/Cargo.toml", EntryType::Regular, MANIFEST),
+        ]);
+
+        assert!(result.unwrap_err().contains("contained twice"));
+    }
+
+    #[test]
+    fn invalid_archive_is_rejected() {
+        let result = verify_tarball(b"garbage", &PublishMetadata::minimal("foo", "1.0.0"));
+
+        assert!(matches!(result, Err(RegistryError::InvalidCrateTarball(_))));
+    }
+}
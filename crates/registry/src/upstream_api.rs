@@ -0,0 +1,418 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use bytes::Bytes;
+use kellnr_appstate::{ProxyClientState, SettingsState, UpstreamStoragesState};
+use kellnr_common::index_metadata::{IndexMetadata, index_sub_path};
+use kellnr_common::metrics;
+use kellnr_common::original_name::OriginalName;
+use kellnr_common::version::Version;
+use kellnr_settings::Upstream;
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+use tracing::{error, trace};
+
+use crate::cratesio_api::cache_crate;
+
+/// Download a crate from an upstream registry
+///
+/// Downloads and caches a crate from a proxied upstream registry. Returns the
+/// cached version if available.
+#[utoipa::path(
+    get,
+    path = "/dl/{package}/{version}/download",
+    tag = "upstreams",
+    params(
+        ("upstream" = String, Path, description = "Name of the upstream registry"),
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version")
+    ),
+    responses(
+        (status = 200, description = "Crate archive", content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid package name or version"),
+        (status = 404, description = "Crate or upstream not found"),
+        (status = 422, description = "Failed to save crate"),
+        (status = 502, description = "Upstream registry not reachable")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn download(
+    Path((upstream, name, version)): Path<(String, OriginalName, Version)>,
+    State(storages): UpstreamStoragesState,
+    State(settings): SettingsState,
+    State(client): ProxyClientState,
+) -> Result<Bytes, StatusCode> {
+    let (Some(upstream), Some(storage)) =
+        (settings.proxy.upstream(&upstream), storages.get(&upstream))
+    else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    if let Some(file) = storage.get(&name, &version).await {
+        metrics::crate_download(&upstream.name);
+        return Ok(file);
+    }
+
+    trace!(
+        "Downloading crate {name} ({version}) from upstream {}",
+        upstream.name
+    );
+    let url = download_url(&client, upstream, &name, &version).await?;
+    let response = upstream_request(&client, url, upstream)
+        .send()
+        .await
+        .map_err(|e| {
+            metrics::upstream_error(&upstream.name);
+            error!(
+                "Could not download {name} ({version}) from upstream {}: {e}",
+                upstream.name
+            );
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => return Err(StatusCode::NOT_FOUND),
+        status => {
+            metrics::upstream_error(&upstream.name);
+            error!(
+                "Unexpected status {status} downloading {name} ({version}) from upstream {}",
+                upstream.name
+            );
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    }
+
+    let crate_data = response
+        .bytes()
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+    let file = cache_crate(storage, &name, &version, Arc::from(&*crate_data)).await?;
+    metrics::crate_download(&upstream.name);
+    Ok(file)
+}
+
+/// GET request to the upstream. The token is only sent to the origin of the
+/// upstream index, not to other hosts named by its `config.json`.
+fn upstream_request(client: &Client, url: Url, upstream: &Upstream) -> RequestBuilder {
+    let same_origin = url.origin() == upstream.index.origin();
+    let request = client.get(url);
+    match &upstream.token {
+        Some(token) if same_origin => request.header(header::AUTHORIZATION, token),
+        _ => request,
+    }
+}
+
+async fn get_upstream(
+    client: &Client,
+    upstream: &Upstream,
+    url: Url,
+) -> Result<reqwest::Response, StatusCode> {
+    upstream_request(client, url, upstream)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| {
+            metrics::upstream_error(&upstream.name);
+            error!("Request to upstream {} failed: {e}", upstream.name);
+            StatusCode::BAD_GATEWAY
+        })
+}
+
+/// Download URL of the crate, from the `dl` template of the upstream's
+/// `config.json`.
+async fn download_url(
+    client: &Client,
+    upstream: &Upstream,
+    name: &OriginalName,
+    version: &Version,
+) -> Result<Url, StatusCode> {
+    #[derive(Deserialize)]
+    struct UpstreamConfig {
+        dl: String,
+    }
+
+    let config_url = upstream
+        .index
+        .join("config.json")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let config = get_upstream(client, upstream, config_url)
+        .await?
+        .json::<UpstreamConfig>()
+        .await
+        .map_err(|e| {
+            error!("Invalid config.json of upstream {}: {e}", upstream.name);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    let cksum = if config.dl.contains("{sha256-checksum}") {
+        Some(checksum(client, upstream, name, version).await?)
+    } else {
+        None
+    };
+
+    Url::parse(&expand_dl_template(
+        &config.dl,
+        name,
+        version,
+        cksum.as_deref(),
+    ))
+    .map_err(|e| {
+        error!("Invalid download URL of upstream {}: {e}", upstream.name);
+        StatusCode::BAD_GATEWAY
+    })
+}
+
+/// Checksum of the crate version from the upstream index.
+async fn checksum(
+    client: &Client,
+    upstream: &Upstream,
+    name: &OriginalName,
+    version: &Version,
+) -> Result<String, StatusCode> {
+    let url = upstream
+        .index
+        .join(&index_sub_path(name))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let index = get_upstream(client, upstream, url)
+        .await?
+        .text()
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    index
+        .lines()
+        .filter_map(|l| serde_json::from_str::<IndexMetadata>(l).ok())
+        .find(|m| m.vers == **version)
+        .map(|m| m.cksum)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Expand the markers of a registry `dl` template. Without markers,
+/// `/{crate}/{version}/download` is appended, as cargo does.
+/// See: <https://doc.rust-lang.org/cargo/reference/registry-index.html#index-configuration>
+fn expand_dl_template(dl: &str, name: &str, version: &str, cksum: Option<&str>) -> String {
+    const MARKERS: [&str; 5] = [
+        "{crate}",
+        "{version}",
+        "{prefix}",
+        "{lowerprefix}",
+        "{sha256-checksum}",
+    ];
+    if !MARKERS.iter().any(|m| dl.contains(m)) {
+        return format!("{}/{name}/{version}/download", dl.trim_end_matches('/'));
+    }
+
+    let prefix = match name.len() {
+        1 => "1".to_string(),
+        2 => "2".to_string(),
+        3 => format!("3/{}", &name[0..1]),
+        _ => format!("{}/{}", &name[0..2], &name[2..4]),
+    };
+    dl.replace("{crate}", name)
+        .replace("{version}", version)
+        .replace("{prefix}", &prefix)
+        .replace("{lowerprefix}", &prefix.to_lowercase())
+        .replace("{sha256-checksum}", cksum.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::Router;
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use kellnr_appstate::AppStateData;
+    use kellnr_settings::{Proxy, Settings};
+    use kellnr_storage::cached_crate_storage::DynStorage;
+    use kellnr_storage::cratesio_crate_storage::CratesIoCrateStorage;
+    use kellnr_storage::fs_storage::FSStorage;
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn dl_template_without_markers() {
+        assert_eq!(
+            "https://up.example/api/v1/crates/serde/1.0.0/download",
+            expand_dl_template("https://up.example/api/v1/crates/", "serde", "1.0.0", None)
+        );
+    }
+
+    #[test]
+    fn dl_template_with_markers() {
+        assert_eq!(
+            "https://up.example/Se/rD/SerDe/SerDe-1.0.0.crate?c=abc",
+            expand_dl_template(
+                "https://up.example/{prefix}/{crate}/{crate}-{version}.crate?c={sha256-checksum}",
+                "SerDe",
+                "1.0.0",
+                Some("abc")
+            )
+        );
+        assert_eq!(
+            "https://up.example/3/A/Abc",
+            expand_dl_template("https://up.example/{prefix}/{crate}", "Abc", "1.0.0", None)
+        );
+        assert_eq!(
+            "https://up.example/3/a/Abc",
+            expand_dl_template(
+                "https://up.example/{lowerprefix}/{crate}",
+                "Abc",
+                "1.0.0",
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn token_is_only_sent_to_the_index_origin() {
+        let upstream = Upstream {
+            name: "partner".to_string(),
+            index: Url::parse("https://index.example/registry/").unwrap(),
+            token: Some("secret".to_string()),
+            bucket: None,
+        };
+        let token = |url: &str| {
+            upstream_request(&Client::new(), Url::parse(url).unwrap(), &upstream)
+                .build()
+                .unwrap()
+                .headers()
+                .get(header::AUTHORIZATION)
+                .map(|t| t.to_str().unwrap().to_string())
+        };
+
+        assert_eq!(
+            Some("secret".to_string()),
+            token("https://index.example/files/foo-1.0.0.crate")
+        );
+        assert_eq!(None, token("https://files.example/foo-1.0.0.crate"));
+        assert_eq!(None, token("http://index.example/foo-1.0.0.crate"));
+        assert_eq!(None, token("https://index.example:8443/foo-1.0.0.crate"));
+    }
+
+    /// Upstream registry that requires the token "secret".
+    async fn upstream_server() -> Url {
+        fn authorized(headers: &HeaderMap) -> Result<(), StatusCode> {
+            if headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|t| t == "secret")
+            {
+                Ok(())
+            } else {
+                Err(StatusCode::UNAUTHORIZED)
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route(
+                "/index/config.json",
+                get(move |headers: HeaderMap| async move {
+                    authorized(&headers)?;
+                    Ok::<_, StatusCode>(format!(
+                        "{{\"dl\":\"http://{addr}/files/{{crate}}-{{version}}.crate\"}}"
+                    ))
+                }),
+            )
+            .route(
+                "/files/{file}",
+                get(|headers: HeaderMap, request: Request| async move {
+                    authorized(&headers)?;
+                    if request.uri().path() == "/files/foo-1.0.0.crate" {
+                        Ok(vec![1u8, 2, 3])
+                    } else {
+                        Err(StatusCode::NOT_FOUND)
+                    }
+                }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Url::parse(&format!("http://{addr}/index/")).unwrap()
+    }
+
+    async fn app(data_dir: &std::path::Path) -> Router {
+        let upstream = Upstream {
+            name: "partner".to_string(),
+            index: upstream_server().await,
+            token: Some("secret".to_string()),
+            bucket: None,
+        };
+        let settings = Settings {
+            proxy: Proxy {
+                upstreams: vec![upstream.clone()],
+                ..Proxy::default()
+            },
+            ..kellnr_settings::test_settings()
+        };
+        let storage = CratesIoCrateStorage::new(
+            &settings,
+            Box::new(FSStorage::new(&data_dir.to_string_lossy()).unwrap()) as DynStorage,
+        );
+        let state = AppStateData {
+            settings: Arc::new(settings),
+            upstream_storages: Arc::new(HashMap::from([(upstream.name, Arc::new(storage))])),
+            proxy_client: Client::new(),
+            ..kellnr_appstate::test_state()
+        };
+        Router::new()
+            .route(
+                "/api/v1/proxy/{upstream}/dl/{package}/{version}/download",
+                get(download),
+            )
+            .with_state(state)
+    }
+
+    async fn get_uri(app: Router, uri: &str) -> axum::response::Response {
+        app.oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn download_fetches_and_caches_crate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let r = get_uri(
+            app(dir.path()).await,
+            "/api/v1/proxy/partner/dl/foo/1.0.0/download",
+        )
+        .await;
+
+        assert_eq!(StatusCode::OK, r.status());
+        assert_eq!(
+            vec![1u8, 2, 3],
+            r.into_body().collect().await.unwrap().to_bytes()
+        );
+        assert!(dir.path().join("foo-1.0.0.crate").exists());
+    }
+
+    #[tokio::test]
+    async fn download_unknown_crate_is_not_found() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let r = get_uri(
+            app(dir.path()).await,
+            "/api/v1/proxy/partner/dl/bar/1.0.0/download",
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+
+    #[tokio::test]
+    async fn download_unknown_upstream_is_not_found() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let r = get_uri(
+            app(dir.path()).await,
+            "/api/v1/proxy/other/dl/foo/1.0.0/download",
+        )
+        .await;
+
+        assert_eq!(StatusCode::NOT_FOUND, r.status());
+    }
+}
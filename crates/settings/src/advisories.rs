@@ -0,0 +1,63 @@
+use provcfg::{ClapArgs, Configurable};
+use serde::{Deserialize, Serialize};
+
+/// Import of security advisories from a local copy of the `RustSec` advisory
+/// database (<https://github.com/rustsec/advisory-db>).
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Configurable, ClapArgs)]
+#[serde(default)]
+#[configurable(clap_prefix = "advisories")]
+pub struct Advisories {
+    /// Enable the advisory import
+    pub enabled: bool,
+
+    /// Path to a clone of the advisory database or to a (gzipped) tarball of it
+    pub path: Option<String>,
+
+    /// Interval in seconds to re-import the advisories (0 imports on startup only)
+    #[arg(long = "advisories-import-interval")]
+    pub import_interval_seconds: u64,
+}
+
+impl Default for Advisories {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            import_interval_seconds: 3600,
+        }
+    }
+}
+
+impl Advisories {
+    /// Check that a path is configured if the import is enabled.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.path.as_deref().is_none_or(str::is_empty) {
+            return Err(
+                "Advisory import is enabled but no advisory database path is set (advisories.path)"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enabled_advisories_require_a_path() {
+        let advisories = Advisories {
+            enabled: true,
+            ..Advisories::default()
+        };
+        assert!(advisories.validate().is_err());
+
+        let advisories = Advisories {
+            path: Some("/var/lib/advisory-db".to_string()),
+            ..advisories
+        };
+        assert!(advisories.validate().is_ok());
+        assert!(Advisories::default().validate().is_ok());
+    }
+}
@@ -6,7 +6,8 @@ use clap::{CommandFactory, Parser, Subcommand};
 
 use crate::compile_time_config;
 use crate::settings::{
-    Settings, SettingsArgs, SettingsError, SettingsPartial, SettingsProv, build_prov_with_cli,
+    Settings, SettingsArgs, SettingsError, SettingsPartial, SettingsProv, build_prov_from_file,
+    build_prov_with_cli,
 };
 
 #[derive(Parser)]
@@ -31,6 +32,84 @@ pub enum Command {
         #[command(flatten)]
         server: SettingsArgs,
     },
+    /// Write a backup of the database, crates, docs and toolchains
+    ///
+    /// Stop kellnr or make sure no crates are published while the backup
+    /// runs, if the crate storage is shared with a running instance.
+    Backup {
+        /// Path of the backup archive to create
+        archive: PathBuf,
+
+        /// Leave out the crates.io proxy cache
+        #[arg(long = "exclude-cratesio")]
+        exclude_cratesio: bool,
+
+        #[command(flatten)]
+        server: SettingsArgs,
+    },
+    /// Restore a backup into an empty data directory or database
+    Restore {
+        /// Path of the backup archive to restore
+        archive: PathBuf,
+
+        #[command(flatten)]
+        server: SettingsArgs,
+    },
+    /// Copy all data into another database and storage backend
+    ///
+    /// The source is configured like for `start`, the target by its own
+    /// configuration file. Environment variables only apply to the source.
+    Migrate {
+        /// Configuration file of the target instance
+        #[arg(long = "to")]
+        to: PathBuf,
+
+        /// Only copy crates, toolchains and docs into the storage of the
+        /// target, e.g. from the filesystem to S3. Both use the source database.
+        #[arg(long = "storage-only")]
+        storage_only: bool,
+
+        #[command(flatten)]
+        server: SettingsArgs,
+    },
+    /// Build the docs of an unpacked crate. Started by the server for each
+    /// doc build, so the build runs in its own process.
+    #[command(hide = true)]
+    DocBuild {
+        /// Directory of the unpacked crate
+        crate_path: PathBuf,
+
+        /// Sparse index that replaces crates.io
+        #[arg(long = "cratesio-index")]
+        cratesio_index: Option<String>,
+
+        /// URL of this Kellnr instance, used to link the docs of
+        /// dependencies hosted in it
+        #[arg(long = "kellnr-url")]
+        kellnr_url: Option<String>,
+
+        /// Only download the dependencies, without building anything
+        #[arg(long = "fetch")]
+        fetch: bool,
+
+        /// Max memory of each process in MB (0 = unlimited)
+        #[arg(long = "max-memory", default_value_t = 0)]
+        max_memory: u64,
+
+        /// Fail if the network access cannot be disabled for the build
+        #[arg(long = "require-network-isolation")]
+        require_network_isolation: bool,
+
+        /// Only check if the build can be isolated, without building
+        /// anything
+        #[arg(long = "check-network-isolation")]
+        check_network_isolation: bool,
+
+        /// Empty directory where the isolated filesystem of the build is
+        /// mounted
+        #[arg(long = "sandbox-root")]
+        sandbox_root: Option<PathBuf>,
+    },
     /// Configuration management commands
     Config {
         #[command(subcommand)]
@@ -67,6 +146,38 @@ pub struct ShowConfigOptions {
     pub show_sources: bool,
 }
 
+/// Options for the `backup` command
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    /// Path of the backup archive to create
+    pub archive: PathBuf,
+    /// Leave out the crates.io proxy cache
+    pub exclude_cratesio: bool,
+}
+
+/// Options for the internal `doc-build` command
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocBuildOptions {
+    /// Directory of the unpacked crate
+    pub crate_path: PathBuf,
+    /// Sparse index that replaces crates.io
+    pub cratesio_index: Option<String>,
+    /// URL of this Kellnr instance, used to link the docs of dependencies
+    /// hosted in it
+    pub kellnr_url: Option<String>,
+    /// Only download the dependencies, without building anything
+    pub fetch: bool,
+    /// Max memory of each process in MB (0 = unlimited)
+    pub max_memory: u64,
+    /// Fail if the network access and the filesystem of the build cannot
+    /// be isolated
+    pub require_network_isolation: bool,
+    /// Only check if the build can be isolated
+    pub check_network_isolation: bool,
+    /// Empty directory where the isolated filesystem of the build is mounted
+    pub sandbox_root: Option<PathBuf>,
+}
+
 /// A `Settings` paired with its per-leaf `SettingsProv`. Bundled because every
 /// non-init `CliResult` variant needs both, the active values for runtime use
 /// and the provenance for source attribution (`/settings` API and
@@ -84,11 +195,27 @@ pub enum CliResult {
         resolved: ResolvedSettings,
         options: ShowConfigOptions,
     },
+    Backup {
+        resolved: ResolvedSettings,
+        options: BackupOptions,
+    },
+    Restore {
+        resolved: ResolvedSettings,
+        archive: PathBuf,
+    },
+    Migrate {
+        resolved: ResolvedSettings,
+        target: Box<Settings>,
+        storage_only: bool,
+    },
     /// `config init` writes a fresh `Settings::default()` to `output`; no
     /// settings field is carried because there's nothing to vary per call.
     InitConfig {
         output: PathBuf,
     },
+    /// `doc-build` gets everything it needs from its arguments, the server
+    /// that starts it has already resolved the settings.
+    DocBuild(DocBuildOptions),
     ShowHelp,
 }
 
@@ -113,6 +240,26 @@ pub fn parse_cli() -> Result<CliResult, SettingsError> {
             output: output.unwrap_or_else(|| PathBuf::from("kellnr.toml")),
         }),
 
+        Some(Command::DocBuild {
+            crate_path,
+            cratesio_index,
+            kellnr_url,
+            fetch,
+            max_memory,
+            require_network_isolation,
+            check_network_isolation,
+            sandbox_root,
+        }) => Ok(CliResult::DocBuild(DocBuildOptions {
+            crate_path,
+            cratesio_index,
+            kellnr_url,
+            fetch,
+            max_memory,
+            require_network_isolation,
+            check_network_isolation,
+            sandbox_root,
+        })),
+
         Some(ref command) => build_from_command(cli.config_file, command),
     }
 }
@@ -132,8 +279,11 @@ fn build_from_command(
     // `Some(...)` values from clap. Unset flags stay `None`, so the CLI
     // source contributes provenance only for fields explicitly passed.
     let cli_partial: Option<SettingsPartial> = match command {
-        Command::Start { server } => Some(SettingsPartial::from(server)),
-        Command::Config { .. } => None,
+        Command::Start { server }
+        | Command::Backup { server, .. }
+        | Command::Restore { server, .. }
+        | Command::Migrate { server, .. } => Some(SettingsPartial::from(server)),
+        Command::Config { .. } | Command::DocBuild { .. } => None,
     };
 
     let prov = build_prov_with_cli(config_file.as_deref(), cli_partial)?;
@@ -144,6 +294,28 @@ fn build_from_command(
 
     match command {
         Command::Start { .. } => Ok(CliResult::RunServer(resolved)),
+        Command::Backup {
+            archive,
+            exclude_cratesio,
+            ..
+        } => Ok(CliResult::Backup {
+            resolved,
+            options: BackupOptions {
+                archive: archive.clone(),
+                exclude_cratesio: *exclude_cratesio,
+            },
+        }),
+        Command::Restore { archive, .. } => Ok(CliResult::Restore {
+            resolved,
+            archive: archive.clone(),
+        }),
+        Command::Migrate {
+            to, storage_only, ..
+        } => Ok(CliResult::Migrate {
+            resolved,
+            target: Box::new(Settings::from(&build_prov_from_file(to)?)),
+            storage_only: *storage_only,
+        }),
         Command::Config {
             action:
                 ConfigAction::Show {
@@ -160,6 +332,9 @@ fn build_from_command(
         Command::Config {
             action: ConfigAction::Init { .. },
         } => unreachable!("`Init` is handled by `parse_cli` before this point"),
+        Command::DocBuild { .. } => {
+            unreachable!("`DocBuild` is handled by `parse_cli` before this point")
+        }
     }
 }
 
@@ -298,6 +473,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn backup_accepts_archive_and_settings_flags() {
+        let argv = [
+            "kellnr",
+            "backup",
+            "/tmp/kellnr.tar.gz",
+            "--exclude-cratesio",
+            "--registry-data-dir",
+            "/tmp/from-cli",
+        ];
+        let cli = Cli::try_parse_from(argv).expect("clap parse");
+        let command = cli.command.expect("backup subcommand present");
+
+        let result = build_from_command(None, &command).unwrap();
+        let CliResult::Backup { resolved, options } = result else {
+            panic!("expected Backup variant");
+        };
+        assert_eq!(options.archive, PathBuf::from("/tmp/kellnr.tar.gz"));
+        assert!(options.exclude_cratesio);
+        assert_eq!(resolved.settings.registry.data_dir, "/tmp/from-cli");
+    }
+
+    #[test]
+    fn migrate_reads_target_settings_from_file_only() {
+        let target = write_toml("[registry]\ndata_dir = \"/tmp/target\"\n");
+        let argv = [
+            "kellnr",
+            "migrate",
+            "--to",
+            target.path().to_str().unwrap(),
+            "--registry-data-dir",
+            "/tmp/source",
+        ];
+        let cli = Cli::try_parse_from(argv).expect("clap parse");
+        let command = cli.command.expect("migrate subcommand present");
+
+        let result = build_from_command(None, &command).unwrap();
+        let CliResult::Migrate {
+            resolved,
+            target,
+            storage_only,
+        } = result
+        else {
+            panic!("expected Migrate variant");
+        };
+        assert_eq!(resolved.settings.registry.data_dir, "/tmp/source");
+        assert_eq!(target.registry.data_dir, "/tmp/target");
+        assert!(!storage_only);
+    }
+
+    #[test]
+    fn doc_build_accepts_limits() {
+        let argv = [
+            "kellnr",
+            "doc-build",
+            "/tmp/foo-1.0.0",
+            "--cratesio-index",
+            "https://index.example.com/",
+            "--kellnr-url",
+            "https://kellnr.example.com:443",
+            "--max-memory",
+            "512",
+            "--sandbox-root",
+            "/tmp/root",
+        ];
+        let cli = Cli::try_parse_from(argv).expect("clap parse");
+
+        let Some(Command::DocBuild {
+            crate_path,
+            cratesio_index,
+            kellnr_url,
+            fetch,
+            max_memory,
+            require_network_isolation,
+            check_network_isolation,
+            sandbox_root,
+        }) = cli.command
+        else {
+            panic!("expected DocBuild command");
+        };
+        assert_eq!(crate_path, PathBuf::from("/tmp/foo-1.0.0"));
+        assert_eq!(
+            cratesio_index.as_deref(),
+            Some("https://index.example.com/")
+        );
+        assert_eq!(
+            kellnr_url.as_deref(),
+            Some("https://kellnr.example.com:443")
+        );
+        assert!(!fetch);
+        assert_eq!(max_memory, 512);
+        assert!(!require_network_isolation);
+        assert!(!check_network_isolation);
+        assert_eq!(sandbox_root, Some(PathBuf::from("/tmp/root")));
+    }
+
     #[test]
     fn id_to_dotted_path_converts_dashes_after_first_segment() {
         assert_eq!(
@@ -10,6 +10,19 @@ pub struct Docs {
 
     /// Max docs size in MB
     pub max_size: usize,
+
+    /// Max duration of a doc build in seconds (0 = unlimited)
+    #[arg(long = "docs-build-timeout")]
+    pub build_timeout_seconds: u64,
+
+    /// Max memory of each process of a doc build in MB (0 = unlimited, Unix only)
+    pub build_max_memory: u64,
+
+    /// Only build docs if the builds can be isolated (Linux only): no network
+    /// access, and a filesystem without the data directory of kellnr. Only
+    /// turn off if every published crate is trusted, builds can read and
+    /// write the database, crates and configuration otherwise.
+    pub require_network_isolation: bool,
 }
 
 impl Default for Docs {
@@ -17,6 +30,9 @@ impl Default for Docs {
         Self {
             enabled: false,
             max_size: 100,
+            build_timeout_seconds: 600,
+            build_max_memory: 4096,
+            require_network_isolation: true,
         }
     }
 }
@@ -29,6 +29,12 @@ pub fn leaf_label(dotted_path: &str) -> Option<&'static str> {
             "Request Timeout (seconds)"
         }
         "toolchain.max_size" => "Max Size (MB)",
+        "docs.build_timeout_seconds" => "Build Timeout (seconds)",
+        "docs.build_max_memory" => "Build Max Memory (MB)",
+        "proxy.min_age_days" => "Minimum Age (days)",
+        "advisories.import_interval_seconds" => "Import Interval (seconds)",
+        "publish.max_unpacked_size" => "Max Unpacked Size (MB)",
+        "publish.validator_timeout_seconds" => "Validator Timeout (seconds)",
 
         // Acronyms, humanizer would emit "Db", "Url", "Api", "Ip", "Http".
         "registry.max_db_connections" => "Max DB Connections",
@@ -36,6 +42,7 @@ pub fn leaf_label(dotted_path: &str) -> Option<&'static str> {
         "proxy.url" => "URL",
         "proxy.index" => "Index URL",
         "proxy.api" => "API URL",
+        "publish.validator_url" => "Validator URL",
         "postgresql.db" => "Database",
         "postgresql.address" => "Address",
         "local.ip" => "IP",
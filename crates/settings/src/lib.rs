@@ -1,3 +1,4 @@
+pub mod advisories;
 pub mod cli;
 pub mod compile_time_config;
 pub mod config_source;
@@ -11,13 +12,18 @@ pub mod origin;
 pub mod postgresql;
 pub mod protocol;
 pub mod proxy;
+pub mod publish;
 pub mod registry;
 pub mod s3;
 pub mod settings;
 pub mod setup;
 pub mod toolchain;
 
-pub use cli::{CliResult, ResolvedSettings, ShowConfigOptions, cli_flag_map, parse_cli};
+pub use advisories::Advisories;
+pub use cli::{
+    BackupOptions, CliResult, DocBuildOptions, ResolvedSettings, ShowConfigOptions, cli_flag_map,
+    parse_cli,
+};
 pub use config_source::{ConfigSource, SourceMap};
 pub use docs::Docs;
 pub use leaf_labels::leaf_label;
@@ -33,7 +39,8 @@ pub use protocol::Protocol;
 // re-export `erased_serde` so the walk visitor's value parameter can be
 // referenced without a direct provcfg dep.
 pub use provcfg::{Category, Config, Provenance, erased_serde};
-pub use proxy::Proxy;
+pub use proxy::{Proxy, Upstream};
+pub use publish::Publish;
 pub use registry::Registry;
 pub use settings::{
     Settings, SettingsError, SettingsProv, build_prov_with_cli, sources_from_prov, test_settings,
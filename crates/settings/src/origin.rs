@@ -42,3 +42,41 @@ impl Default for Origin {
         }
     }
 }
+
+impl Origin {
+    /// Absolute URL of the Kellnr instance, including the path prefix but
+    /// without a trailing slash.
+    pub fn url(&self) -> String {
+        let path = self.path.trim().trim_end_matches('/');
+        format!("{}://{}:{}{path}", self.protocol, self.hostname, self.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_without_path() {
+        let origin = Origin {
+            hostname: "kellnr.example.com".to_string(),
+            port: 8000,
+            protocol: Protocol::Http,
+            path: String::new(),
+        };
+
+        assert_eq!("http://kellnr.example.com:8000", origin.url());
+    }
+
+    #[test]
+    fn url_with_path() {
+        let origin = Origin {
+            hostname: "example.com".to_string(),
+            port: 443,
+            protocol: Protocol::Https,
+            path: " /kellnr/ ".to_string(),
+        };
+
+        assert_eq!("https://example.com:443/kellnr", origin.url());
+    }
+}
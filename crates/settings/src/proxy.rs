@@ -1,5 +1,6 @@
+use kellnr_common::proxy_policy::ProxyPolicy;
 use provcfg::{ClapArgs, Configurable};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use url::Url;
 
 fn default_proxy_url() -> Url {
@@ -53,6 +54,59 @@ pub struct Proxy {
     /// to get in contact, e.g. "kellnr.io/kellnr (contact@example.com)".
     #[arg(long = "proxy-user-agent")]
     pub user_agent: String,
+
+    /// Crates that are never served from crates.io (comma-separated). Use
+    /// "name@<version requirement>" to deny a version range only, e.g.
+    /// "openssl@<0.10.55".
+    #[configurable(env_list)]
+    #[arg(long = "proxy-deny", value_delimiter = ',')]
+    pub deny: Vec<String>,
+
+    /// SPDX licenses crates.io crates must be available under (comma-separated).
+    /// Empty allows all licenses.
+    #[configurable(env_list)]
+    #[arg(long = "proxy-allowed-licenses", value_delimiter = ',')]
+    pub allowed_licenses: Vec<String>,
+
+    /// Hide crates.io versions published less than this many days ago (0 disables)
+    #[arg(long = "proxy-min-age-days")]
+    pub min_age_days: u64,
+
+    /// Additional upstream registries, each served as its own sparse index
+    /// at /api/v1/proxy/{name}/. Independent of `enabled`.
+    #[arg(skip)]
+    #[configurable(secret)]
+    pub upstreams: Vec<Upstream>,
+}
+
+/// An additional sparse registry that is proxied next to crates.io.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Upstream {
+    /// Unique name, used as route prefix: /api/v1/proxy/{name}/
+    pub name: String,
+
+    /// Sparse index URL of the upstream registry. A missing trailing `/` is
+    /// added, so the index files are looked up below the full path.
+    #[serde(deserialize_with = "deserialize_index_url")]
+    pub index: Url,
+
+    /// Token sent as Authorization header with every upstream request
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// S3 bucket for cached crates, if S3 is enabled. Defaults to "kellnr-{name}".
+    /// Without S3, crates are cached in `{data_dir}/upstreams/{name}`.
+    #[serde(default)]
+    pub bucket: Option<String>,
+}
+
+fn deserialize_index_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Url, D::Error> {
+    let mut url = Url::deserialize(deserializer)?;
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
 }
 
 impl Default for Proxy {
@@ -67,6 +121,10 @@ impl Default for Proxy {
             connect_timeout_seconds: 5,
             request_timeout_seconds: 30,
             user_agent: default_user_agent(),
+            deny: Vec::new(),
+            allowed_licenses: Vec::new(),
+            min_age_days: 0,
+            upstreams: Vec::new(),
         }
     }
 }
@@ -88,6 +146,41 @@ impl Proxy {
             None
         }
     }
+
+    /// Look up a configured upstream registry by its name.
+    pub fn upstream(&self, name: &str) -> Option<&Upstream> {
+        self.upstreams.iter().find(|u| u.name == name)
+    }
+
+    /// Policy that decides which crates.io crate versions are served. Built
+    /// once on startup, fails if the policy settings are invalid.
+    pub fn policy(&self) -> Result<ProxyPolicy, String> {
+        ProxyPolicy::new(&self.deny, &self.allowed_licenses, self.min_age_days)
+    }
+
+    /// Check that the crates.io policy is valid and that upstream names are
+    /// unique and usable as route prefix.
+    pub fn validate(&self) -> Result<(), String> {
+        self.policy()?;
+
+        let mut names = std::collections::HashSet::new();
+        for upstream in &self.upstreams {
+            let name = &upstream.name;
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Invalid upstream name '{name}'. Only lowercase letters, digits, '-' and '_' are allowed"
+                ));
+            }
+            if !names.insert(name) {
+                return Err(format!("Upstream '{name}' is configured more than once"));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -162,6 +255,111 @@ mod tests {
         assert_eq!(proxy.user_agent, "kellnr.io/kellnr (admin@example.com)");
     }
 
+    #[test]
+    fn deserialize_upstreams_from_toml() {
+        let toml = r#"
+            [[upstreams]]
+            name = "partner"
+            index = "https://registry.partner.example/index/"
+            token = "secret"
+
+            [[upstreams]]
+            name = "internal"
+            index = "https://kellnr.internal.example/api/v1/crates/"
+            bucket = "internal-cache"
+        "#;
+        let proxy: Proxy = toml::from_str(toml).unwrap();
+        assert_eq!(
+            proxy.upstreams,
+            vec![
+                Upstream {
+                    name: "partner".to_string(),
+                    index: Url::parse("https://registry.partner.example/index/").unwrap(),
+                    token: Some("secret".to_string()),
+                    bucket: None,
+                },
+                Upstream {
+                    name: "internal".to_string(),
+                    index: Url::parse("https://kellnr.internal.example/api/v1/crates/").unwrap(),
+                    token: None,
+                    bucket: Some("internal-cache".to_string()),
+                },
+            ]
+        );
+        assert_eq!(proxy.upstream("internal"), proxy.upstreams.get(1));
+        assert_eq!(proxy.upstream("unknown"), None);
+        assert!(proxy.validate().is_ok());
+    }
+
+    #[test]
+    fn upstream_index_with_sub_path_gets_trailing_slash() {
+        let toml = r#"
+            [[upstreams]]
+            name = "partner"
+            index = "https://host.example/registry"
+        "#;
+        let proxy: Proxy = toml::from_str(toml).unwrap();
+        let index = &proxy.upstreams[0].index;
+
+        assert_eq!("https://host.example/registry/", index.as_str());
+        assert_eq!(
+            "https://host.example/registry/config.json",
+            index.join("config.json").unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_upstream_names() {
+        let toml = r#"
+            [[upstreams]]
+            name = "partner"
+            index = "https://a.example/"
+
+            [[upstreams]]
+            name = "partner"
+            index = "https://b.example/"
+        "#;
+        let proxy: Proxy = toml::from_str(toml).unwrap();
+        assert!(proxy.validate().unwrap_err().contains("more than once"));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_upstream_names() {
+        for name in ["", "Partner", "a/b", "a b"] {
+            let proxy = Proxy {
+                upstreams: vec![Upstream {
+                    name: name.to_string(),
+                    index: Url::parse("https://a.example/").unwrap(),
+                    token: None,
+                    bucket: None,
+                }],
+                ..Proxy::default()
+            };
+            assert!(proxy.validate().is_err(), "{name} should be invalid");
+        }
+    }
+
+    #[test]
+    fn deserialize_policy_from_toml() {
+        let toml = r#"
+            deny = ["evil-crate", "openssl@>=0.10.0, <0.10.55"]
+            allowed_licenses = ["MIT", "Apache-2.0"]
+            min_age_days = 7
+        "#;
+        let proxy: Proxy = toml::from_str(toml).unwrap();
+        assert!(proxy.validate().is_ok());
+        let policy = proxy.policy().unwrap();
+        assert!(policy.check_version("openssl", "0.10.54", None).is_err());
+        assert!(policy.check_license(Some("MIT OR Apache-2.0")).is_ok());
+        assert!(policy.checks_age());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_deny_rule() {
+        let proxy: Proxy = toml::from_str(r#"deny = ["openssl@latest"]"#).unwrap();
+        assert!(proxy.validate().unwrap_err().contains("openssl@latest"));
+    }
+
     #[test]
     fn index_override_none_when_disabled() {
         let proxy: Proxy = toml::from_str(r#"index = "https://rsproxy.cn/index/""#).unwrap();
@@ -0,0 +1,143 @@
+use kellnr_common::publish_policy::{PublishPolicy, RepositoryPattern};
+use provcfg::{ClapArgs, Configurable};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Rules that are checked before a published crate is stored.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Configurable, ClapArgs)]
+#[serde(default)]
+#[configurable(clap_prefix = "publish")]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Publish {
+    /// SPDX licenses published crates must be available under (comma-separated).
+    /// Empty allows all licenses.
+    #[configurable(env_list)]
+    #[arg(value_delimiter = ',')]
+    pub allowed_licenses: Vec<String>,
+
+    /// Reject crates with git dependencies
+    pub deny_git_dependencies: bool,
+
+    /// Reject crates with path dependencies, also if they have a version
+    pub deny_path_dependencies: bool,
+
+    /// Regular expression the whole repository URL has to match, e.g.
+    /// `https://github\.com/my-org/.+`. Empty allows all repositories.
+    pub repository_pattern: String,
+
+    /// Prefixes crate names have to start with (comma-separated). Empty allows all names.
+    #[configurable(env_list)]
+    #[arg(value_delimiter = ',')]
+    pub name_prefixes: Vec<String>,
+
+    /// Max number of files in a crate (0 = unlimited)
+    pub max_files: u64,
+
+    /// Max unpacked size of a crate in MB (0 = unlimited)
+    pub max_unpacked_size: u64,
+
+    /// URL of an external validator, that gets the metadata of every
+    /// published crate as JSON and rejects it with a non-2xx response
+    pub validator_url: Option<Url>,
+
+    /// Token sent as Authorization header to the external validator
+    #[serde(skip_serializing)]
+    #[configurable(secret)]
+    pub validator_token: Option<String>,
+
+    /// Timeout in seconds for requests to the external validator
+    #[arg(long = "publish-validator-timeout")]
+    pub validator_timeout_seconds: u64,
+}
+
+impl Default for Publish {
+    fn default() -> Self {
+        Self {
+            allowed_licenses: Vec::new(),
+            deny_git_dependencies: false,
+            deny_path_dependencies: false,
+            repository_pattern: String::new(),
+            name_prefixes: Vec::new(),
+            max_files: 0,
+            max_unpacked_size: 0,
+            validator_url: None,
+            validator_token: None,
+            validator_timeout_seconds: 10,
+        }
+    }
+}
+
+impl Publish {
+    /// Check that the repository pattern is a valid regular expression.
+    pub fn validate(&self) -> Result<(), String> {
+        self.policy().map(|_| ())
+    }
+
+    /// Policy that decides which crates can be published. Built once on
+    /// startup, fails if the repository pattern is invalid.
+    pub fn policy(&self) -> Result<PublishPolicy, String> {
+        let repository_pattern = if self.repository_pattern.is_empty() {
+            None
+        } else {
+            Some(RepositoryPattern::new(&self.repository_pattern)?)
+        };
+        Ok(PublishPolicy {
+            allowed_licenses: self.allowed_licenses.clone(),
+            deny_git_dependencies: self.deny_git_dependencies,
+            deny_path_dependencies: self.deny_path_dependencies,
+            repository_pattern,
+            name_prefixes: self.name_prefixes.clone(),
+            max_files: self.max_files,
+            max_unpacked_size: self.max_unpacked_size.saturating_mul(1024 * 1024),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_repository_pattern_is_rejected() {
+        let publish = Publish {
+            repository_pattern: "https://(github".to_string(),
+            ..Publish::default()
+        };
+        assert!(publish.validate().is_err());
+        assert!(Publish::default().validate().is_ok());
+    }
+
+    #[test]
+    fn policy_from_settings() {
+        let publish = Publish {
+            max_files: 100,
+            max_unpacked_size: 2,
+            ..Publish::default()
+        };
+
+        let policy = publish.policy().unwrap();
+
+        assert_eq!(100, policy.max_files);
+        assert_eq!(2 * 1024 * 1024, policy.max_unpacked_size);
+        assert!(policy.repository_pattern.is_none());
+    }
+
+    #[test]
+    fn lists_from_toml() {
+        let publish: Publish = toml::from_str(
+            r#"
+            allowed_licenses = ["MIT", "Apache-2.0"]
+            name_prefixes = ["acme-"]
+            validator_url = "https://validator.example.com/check"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(vec!["MIT", "Apache-2.0"], publish.allowed_licenses);
+        assert_eq!(vec!["acme-"], publish.name_prefixes);
+        assert_eq!(
+            Some("https://validator.example.com/check"),
+            publish.validator_url.as_ref().map(Url::as_str)
+        );
+    }
+}
@@ -77,6 +77,24 @@ pub struct Registry {
     /// Download counter flush interval in seconds (0 = flush every download)
     #[arg(long = "registry-download-counter-flush")]
     pub download_counter_flush_seconds: u64,
+
+    /// Days to keep per-day download counts of crate versions (0 = keep forever)
+    pub download_history_days: u64,
+
+    /// Days to keep the delivery log and the dead letters of webhooks (0 = keep forever)
+    pub webhook_delivery_days: u64,
+
+    /// Attempts to deliver a webhook message before it is moved to the dead letters
+    pub webhook_max_attempts: u32,
+
+    /// Hours a webhook may keep failing before it is disabled (0 = never disable)
+    pub webhook_disable_after_hours: u64,
+
+    /// Take the client IP from the X-Forwarded-For header (only enable behind a reverse proxy)
+    pub trust_proxy_headers: bool,
+
+    /// Also serve the index over git smart HTTP at /api/v1/git/index (requires git on the PATH)
+    pub git_index: bool,
 }
 
 impl Default for Registry {
@@ -100,6 +118,12 @@ impl Default for Registry {
             download_timeout_seconds: 60,
             download_max_concurrent: 20,
             download_counter_flush_seconds: 30,
+            download_history_days: 90,
+            webhook_delivery_days: 30,
+            webhook_max_attempts: 10,
+            webhook_disable_after_hours: 72,
+            trust_proxy_headers: false,
+            git_index: false,
         }
     }
 }
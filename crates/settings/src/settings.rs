@@ -8,6 +8,7 @@ pub use provcfg::Error as SettingsError;
 use provcfg::{ClapArgs, Configurable, Provenance};
 use serde::{Deserialize, Serialize};
 
+use crate::advisories::{Advisories, AdvisoriesArgs, AdvisoriesPartial, AdvisoriesProv};
 use crate::config_source::SourceMap;
 use crate::docs::{Docs, DocsArgs, DocsPartial, DocsProv};
 use crate::local::{Local, LocalArgs, LocalPartial, LocalProv};
@@ -15,7 +16,8 @@ use crate::log::{Log, LogArgs, LogPartial, LogProv};
 use crate::oauth2::{OAuth2, OAuth2Args, OAuth2Partial, OAuth2Prov};
 use crate::origin::{Origin, OriginArgs, OriginPartial, OriginProv};
 use crate::postgresql::{Postgresql, PostgresqlArgs, PostgresqlPartial, PostgresqlProv};
-use crate::proxy::{Proxy, ProxyArgs, ProxyPartial, ProxyProv};
+use crate::proxy::{Proxy, ProxyArgs, ProxyPartial, ProxyProv, Upstream};
+use crate::publish::{Publish, PublishArgs, PublishPartial, PublishProv};
 use crate::registry::{Registry, RegistryArgs, RegistryPartial, RegistryProv};
 use crate::s3::{S3, S3Args, S3Partial, S3Prov};
 use crate::setup::{Setup, SetupArgs, SetupPartial, SetupProv};
@@ -50,6 +52,10 @@ pub struct Settings {
     pub oauth2: OAuth2,
     #[configurable(nested)]
     pub toolchain: Toolchain,
+    #[configurable(nested)]
+    pub advisories: Advisories,
+    #[configurable(nested)]
+    pub publish: Publish,
 }
 
 /// Build a `SettingsProv` from the configured sources: optional TOML file,
@@ -75,11 +81,27 @@ pub fn build_prov_with_cli(
     cfg.build::<SettingsProv>()
 }
 
+/// Build a `SettingsProv` from a TOML file only, without environment
+/// variables or CLI flags. Used for a second instance next to the one the
+/// process is configured for, e.g. the target of `kellnr migrate`.
+pub fn build_prov_from_file(config_file: &Path) -> Result<SettingsProv, SettingsError> {
+    provcfg::Config::new()
+        .add_toml_file(config_file)?
+        .build::<SettingsProv>()
+}
+
 /// Dotted paths that the env source should treat as comma-separated lists.
 /// Matches the kellnr 6.x behaviour driven by the `config` crate's
 /// `with_list_parse_key`.
 pub(crate) fn env_list_keys() -> &'static [&'static str] {
-    &["registry.required_crate_fields", "oauth2.scopes"]
+    &[
+        "registry.required_crate_fields",
+        "oauth2.scopes",
+        "proxy.deny",
+        "proxy.allowed_licenses",
+        "publish.allowed_licenses",
+        "publish.name_prefixes",
+    ]
 }
 
 /// Convert provcfg's per-leaf provenance map into kellnr's `SourceMap`.
@@ -112,6 +134,10 @@ impl Settings {
         PathBuf::from(&self.registry.data_dir).join("git")
     }
 
+    pub fn git_index_path(&self) -> PathBuf {
+        self.base_path().join("index.git")
+    }
+
     pub fn crates_io_bin_path(&self) -> PathBuf {
         PathBuf::from(&self.registry.data_dir).join("cratesio")
     }
@@ -140,6 +166,21 @@ impl Settings {
         }
     }
 
+    pub fn upstream_path(&self, upstream: &Upstream) -> String {
+        format!("{}/upstreams/{}", self.registry.data_dir, upstream.name)
+    }
+
+    pub fn upstream_path_or_bucket(&self, upstream: &Upstream) -> String {
+        if self.s3.enabled {
+            upstream
+                .bucket
+                .clone()
+                .unwrap_or_else(|| format!("kellnr-{}", upstream.name))
+        } else {
+            self.upstream_path(upstream)
+        }
+    }
+
     pub fn toolchain_path(&self) -> String {
         format!("{}/toolchains", self.registry.data_dir)
     }
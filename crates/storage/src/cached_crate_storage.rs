@@ -101,6 +101,11 @@ impl CachedCrateStorage {
             .is_some_and(|cache| cache.contains_key(&file_name))
     }
 
+    /// Check read and write access on the underlying storage.
+    pub async fn check_access(&self) -> Result<(), StorageError> {
+        self.storage.check_access().await
+    }
+
     // Check if a crate exists in the storage unrelated to the cache.
     pub async fn exists(
         &self,
@@ -169,6 +174,10 @@ mod tests {
         async fn exists(&self, key: &str) -> Result<bool, StorageError> {
             Ok(self.data.contains_key(key))
         }
+
+        async fn list(&self) -> Result<Vec<String>, StorageError> {
+            Ok(self.data.keys().cloned().collect())
+        }
     }
 
     /// Wrapper to make `CountingStorage` usable through `Arc` (needed for concurrent test)
@@ -189,6 +198,10 @@ mod tests {
         async fn exists(&self, key: &str) -> Result<bool, StorageError> {
             (**self).exists(key).await
         }
+
+        async fn list(&self) -> Result<Vec<String>, StorageError> {
+            (**self).list().await
+        }
     }
 
     fn test_settings(cache_size: u64) -> Settings {
@@ -463,4 +476,27 @@ mod tests {
             "Expected at most 6 storage reads for 2 unique keys, got {count}"
         );
     }
+
+    #[tokio::test]
+    async fn check_access_writes_reads_and_deletes_probe() {
+        let dir = std::env::temp_dir().join(format!(
+            "kellnr-test-check-access-{}",
+            kellnr_common::util::generate_rand_string(8)
+        ));
+        let storage = crate::fs_storage::FSStorage::new(&dir.to_string_lossy()).unwrap();
+        let cs = CachedCrateStorage::new(&test_settings(0), Box::new(storage));
+
+        cs.check_access().await.unwrap();
+        assert_eq!(0, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_access_fails_if_probe_cannot_be_read() {
+        // CountingStorage drops all writes, so the probe is never found
+        let storage = CountingStorage::new(vec![], metrics());
+        let cs = CachedCrateStorage::new(&test_settings(0), Box::new(storage));
+
+        assert!(cs.check_access().await.is_err());
+    }
 }
@@ -2,6 +2,8 @@ use std::fs::DirBuilder;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::TryStreamExt;
+use kellnr_common::metrics;
 use object_store::local::LocalFileSystem;
 use object_store::path::Path;
 use object_store::{ObjectStore, ObjectStoreExt, PutMode};
@@ -14,6 +16,7 @@ pub struct FSStorage(LocalFileSystem);
 #[async_trait]
 impl Storage for FSStorage {
     async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let _timer = metrics::storage_timer("fs", "get");
         self.storage()
             .get(&Path::from(key))
             .await?
@@ -23,6 +26,7 @@ impl Storage for FSStorage {
     }
 
     async fn put(&self, key: &str, object: Bytes) -> Result<(), StorageError> {
+        let _timer = metrics::storage_timer("fs", "put");
         self.storage()
             .put_opts(&Path::from(key), object.into(), PutMode::Create.into())
             .await?;
@@ -30,11 +34,13 @@ impl Storage for FSStorage {
     }
 
     async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let _timer = metrics::storage_timer("fs", "delete");
         self.storage().delete(&Path::from(key)).await?;
         Ok(())
     }
 
     async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        let _timer = metrics::storage_timer("fs", "exists");
         self.storage()
             .head(&Path::from(key))
             .await
@@ -44,6 +50,16 @@ impl Storage for FSStorage {
                 _ => Err(StorageError::from(e)),
             })
     }
+
+    async fn list(&self) -> Result<Vec<String>, StorageError> {
+        let _timer = metrics::storage_timer("fs", "list");
+        self.storage()
+            .list(None)
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect()
+            .await
+            .map_err(StorageError::from)
+    }
 }
 
 impl FSStorage {
@@ -2,6 +2,8 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::TryStreamExt;
+use kellnr_common::metrics;
 use kellnr_settings::Settings;
 use object_store::aws::{AmazonS3, AmazonS3Builder};
 use object_store::path::Path;
@@ -15,6 +17,7 @@ pub struct S3Storage(AmazonS3);
 #[async_trait]
 impl Storage for S3Storage {
     async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let _timer = metrics::storage_timer("s3", "get");
         self.storage()
             .get(&Self::try_path_from(key)?)
             .await?
@@ -24,6 +27,7 @@ impl Storage for S3Storage {
     }
 
     async fn put(&self, key: &str, object: Bytes) -> Result<(), StorageError> {
+        let _timer = metrics::storage_timer("s3", "put");
         self.storage()
             .put_opts(
                 &Self::try_path_from(key)?,
@@ -35,12 +39,14 @@ impl Storage for S3Storage {
     }
 
     async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let _timer = metrics::storage_timer("s3", "delete");
         let path = Self::try_path_from(key)?;
         self.storage().delete(&path).await?;
         Ok(())
     }
 
     async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        let _timer = metrics::storage_timer("s3", "exists");
         let path = Self::try_path_from(key)?;
         self.storage()
             .head(&path)
@@ -51,6 +57,16 @@ impl Storage for S3Storage {
                 _ => Err(StorageError::from(e)),
             })
     }
+
+    async fn list(&self) -> Result<Vec<String>, StorageError> {
+        let _timer = metrics::storage_timer("s3", "list");
+        self.storage()
+            .list(None)
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect()
+            .await
+            .map_err(StorageError::from)
+    }
 }
 
 impl S3Storage {
@@ -1,12 +1,37 @@
 use async_trait::async_trait;
 use bytes::Bytes;
+use kellnr_common::util::generate_rand_string;
 
 use crate::storage_error::StorageError;
 
+/// Prefix of the short-lived objects written by [`Storage::check_access`].
+pub const PROBE_PREFIX: &str = ".ready-probe-";
+
 #[async_trait]
 pub trait Storage {
     async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
     async fn put(&self, key: &str, object: Bytes) -> Result<(), StorageError>;
     async fn delete(&self, key: &str) -> Result<(), StorageError>;
     async fn exists(&self, key: &str) -> Result<bool, StorageError>;
+    /// Keys of all objects in the storage.
+    async fn list(&self) -> Result<Vec<String>, StorageError>;
+
+    /// Check read and write access by writing, reading back and deleting a
+    /// probe object.
+    async fn check_access(&self) -> Result<(), StorageError>
+    where
+        Self: Sync,
+    {
+        const PROBE: &[u8] = b"kellnr";
+        let key = format!("{PROBE_PREFIX}{}", generate_rand_string(10));
+        self.put(&key, Bytes::from_static(PROBE)).await?;
+        let read = self.get(&key).await;
+        self.delete(&key).await?;
+        if read? != PROBE {
+            return Err(StorageError::GenericError(
+                "Probe object differs after reading it back".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
@@ -57,6 +57,11 @@ impl ToolchainStorage {
         Ok((path, hash))
     }
 
+    /// Check read and write access on the underlying storage.
+    pub async fn check_access(&self) -> Result<(), StorageError> {
+        self.storage.check_access().await
+    }
+
     pub async fn put_raw(&self, path: &str, data: Bytes) -> Result<(), StorageError> {
         self.storage
             .put(path, data)
@@ -0,0 +1,118 @@
+use axum::Json;
+use axum::extract::{Query, State};
+use kellnr_appstate::DbState;
+use kellnr_common::normalized_name::NormalizedName;
+use kellnr_common::original_name::OriginalName;
+use kellnr_db::AdvisoryMatch;
+use serde::Deserialize;
+
+use crate::error::RouteError;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct AdvisoryParams {
+    /// Only advisories of this crate
+    name: Option<OriginalName>,
+}
+
+/// List advisories affecting known crate versions
+///
+/// Returns the imported `RustSec` advisories that affect at least one hosted
+/// or cached crate version, together with the affected versions.
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "advisories",
+    params(AdvisoryParams),
+    responses(
+        (status = 200, description = "Matching advisories", body = Vec<AdvisoryMatch>),
+        (status = 401, description = "Authentication required")
+    ),
+    security(("cargo_token" = []), ("session_cookie" = []))
+)]
+pub async fn list_advisories(
+    Query(params): Query<AdvisoryParams>,
+    State(db): DbState,
+) -> Result<Json<Vec<AdvisoryMatch>>, RouteError> {
+    let name = params.name.map(NormalizedName::from);
+    let matches = db
+        .get_advisory_matches()
+        .await?
+        .into_iter()
+        .filter(|m| {
+            name.as_ref()
+                .is_none_or(|name| m.advisory.package == name.to_string())
+        })
+        .collect();
+    Ok(Json(matches))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use kellnr_appstate::AppStateData;
+    use kellnr_db::mock::MockDb;
+    use kellnr_db::{Advisory, DbProvider};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn advisory_match(id: &str, package: &str) -> AdvisoryMatch {
+        AdvisoryMatch {
+            advisory: Advisory {
+                id: id.to_string(),
+                package: package.to_string(),
+                title: "Memory corruption".to_string(),
+                description: String::new(),
+                date: "2024-01-01".to_string(),
+                url: None,
+                aliases: vec![],
+                informational: None,
+                patched: vec![">= 1.0.1".to_string()],
+                unaffected: vec![],
+            },
+            hosted_versions: vec!["1.0.0".to_string()],
+            cached_versions: vec![],
+        }
+    }
+
+    async fn get_advisories(uri: &str) -> Vec<AdvisoryMatch> {
+        let mut db = MockDb::new();
+        db.expect_get_advisory_matches().returning(|| {
+            Ok(vec![
+                advisory_match("RUSTSEC-2024-0001", "foo"),
+                advisory_match("RUSTSEC-2024-0002", "bar"),
+            ])
+        });
+        let state = AppStateData {
+            db: Arc::new(db) as Arc<dyn DbProvider>,
+            ..kellnr_appstate::test_state()
+        };
+        let r = Router::new()
+            .route("/", get(list_advisories))
+            .with_state(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_all_advisories() {
+        assert_eq!(2, get_advisories("/").await.len());
+    }
+
+    #[tokio::test]
+    async fn list_advisories_of_crate() {
+        let advisories = get_advisories("/?name=Foo").await;
+        assert_eq!(vec![advisory_match("RUSTSEC-2024-0001", "foo")], advisories);
+    }
+}
@@ -0,0 +1,205 @@
+use axum::Json;
+use axum::extract::{Query, State};
+use chrono::{DateTime, Utc};
+use kellnr_appstate::DbState;
+use kellnr_auth::maybe_user;
+use kellnr_db::{AuditAction, AuditEntry, AuditFilter};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::RouteError;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct AuditParams {
+    /// Only entries of this user
+    actor: Option<String>,
+    /// Only entries of this action
+    action: Option<AuditAction>,
+    /// Only entries for this crate or user
+    target: Option<String>,
+    /// Only entries at or after this time (RFC 3339)
+    since: Option<DateTime<Utc>>,
+    /// Only entries before this time (RFC 3339)
+    until: Option<DateTime<Utc>>,
+    /// Page number, starting at 0
+    page: Option<u64>,
+    /// Entries per page (default 50, max 500)
+    page_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditLog {
+    pub entries: Vec<AuditEntry>,
+    /// Number of entries matching the filter across all pages
+    pub total: u64,
+    pub page: u64,
+    pub page_size: u64,
+}
+
+/// Query the audit log (admin only)
+///
+/// Returns the recorded registry mutations matching the filters, newest first.
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "audit",
+    params(AuditParams),
+    responses(
+        (status = 200, description = "Audit log entries", body = AuditLog),
+        (status = 401, description = "Not logged in"),
+        (status = 403, description = "Admin access required")
+    ),
+    security(("cargo_token" = []), ("session_cookie" = []))
+)]
+pub async fn list_entries(
+    user: maybe_user::MaybeUser,
+    Query(params): Query<AuditParams>,
+    State(db): DbState,
+) -> Result<Json<AuditLog>, RouteError> {
+    if !user.is_admin {
+        return Err(RouteError::InsufficientPrivileges);
+    }
+
+    let page = params.page.unwrap_or(0);
+    let page_size = params
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let filter = AuditFilter {
+        actor: params.actor,
+        action: params.action,
+        target: params.target,
+        since: params.since,
+        until: params.until,
+    };
+
+    let result = db
+        .get_audit_entries(&filter, page_size, page.saturating_mul(page_size))
+        .await?;
+
+    Ok(Json(AuditLog {
+        entries: result.entries,
+        total: result.total,
+        page,
+        page_size,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use http_body_util::BodyExt;
+    use kellnr_appstate::AppStateData;
+    use kellnr_common::token_cache::{CachedTokenData, TokenCacheManager};
+    use kellnr_common::token_scope::TokenScopes;
+    use kellnr_db::mock::MockDb;
+    use kellnr_db::{AuditPage, DbProvider};
+    use mockall::predicate::*;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn app(db: MockDb, is_admin: bool) -> Router {
+        let token_cache = Arc::new(TokenCacheManager::new(true, 60, 100));
+        token_cache
+            .insert(
+                "token".to_string(),
+                CachedTokenData {
+                    user: "auditor".to_string(),
+                    token_name: "test_token".to_string(),
+                    is_admin,
+                    is_read_only: false,
+                    scopes: TokenScopes::default(),
+                },
+            )
+            .await;
+
+        let state = AppStateData {
+            db: Arc::new(db) as Arc<dyn DbProvider>,
+            token_cache,
+            ..kellnr_appstate::test_state()
+        };
+        Router::new()
+            .route("/", get(list_entries))
+            .with_state(state)
+    }
+
+    fn entry() -> AuditEntry {
+        AuditEntry {
+            id: 1,
+            created: "2026-10-18 12:00:00".to_string(),
+            actor: "admin".to_string(),
+            token_name: Some("ci".to_string()),
+            source_ip: Some("10.0.0.1".to_string()),
+            action: AuditAction::Yank,
+            target: "foo".to_string(),
+            version: Some("1.0.0".to_string()),
+            detail: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn list_entries_as_admin() {
+        let mut db = MockDb::new();
+        db.expect_get_audit_entries()
+            .with(
+                eq(AuditFilter {
+                    actor: Some("admin".to_string()),
+                    action: Some(AuditAction::Yank),
+                    ..AuditFilter::default()
+                }),
+                eq(10),
+                eq(20),
+            )
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(AuditPage {
+                    entries: vec![entry()],
+                    total: 21,
+                })
+            });
+
+        let r = app(db, true)
+            .await
+            .oneshot(
+                Request::get("/?actor=admin&action=yank&page=2&page_size=10")
+                    .header("Authorization", "token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let log: AuditLog = serde_json::from_slice(&body).unwrap();
+        assert_eq!(vec![entry()], log.entries);
+        assert_eq!(21, log.total);
+        assert_eq!(2, log.page);
+        assert_eq!(10, log.page_size);
+    }
+
+    #[tokio::test]
+    async fn list_entries_as_non_admin_is_forbidden() {
+        let r = app(MockDb::new(), false)
+            .await
+            .oneshot(
+                Request::get("/")
+                    .header("Authorization", "token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::FORBIDDEN, r.status());
+    }
+}
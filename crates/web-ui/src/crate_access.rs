@@ -1,10 +1,15 @@
 use axum::Json;
 use axum::extract::{Path, State};
+use chrono::Utc;
 use kellnr_appstate::DbState;
+use kellnr_auth::audit::{self, SourceIp};
 use kellnr_common::original_name::OriginalName;
+use kellnr_common::webhook::WebhookEvent;
+use kellnr_db::{AuditAction, NewAuditEntry};
 use kellnr_registry::crate_group::{CrateGroup, CrateGroupList};
 use kellnr_registry::crate_user::{CrateUser, CrateUserList};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use utoipa::ToSchema;
 
 use crate::error::RouteError;
@@ -66,7 +71,8 @@ pub async fn list_users(
     security(("session_cookie" = []))
 )]
 pub async fn add_user(
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
@@ -78,6 +84,24 @@ pub async fn add_user(
 
     if !db.is_crate_user(&crate_name, &name).await? {
         db.add_crate_user(&crate_name, &name).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::AclChange,
+            &Utc::now(),
+            &crate_name,
+            json!({ "change": "crate_user_add", "user": name }),
+            &db,
+        )
+        .await;
+        audit::record(
+            &db,
+            NewAuditEntry::new(
+                source_ip.actor(user.name(), None),
+                AuditAction::AddCrateUser,
+                &*crate_name,
+            )
+            .with_detail(name),
+        )
+        .await;
     }
 
     Ok(())
@@ -99,12 +123,33 @@ pub async fn add_user(
     security(("session_cookie" = []))
 )]
 pub async fn delete_user(
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     let crate_name = crate_name.to_normalized();
-    Ok(db.delete_crate_user(&crate_name, &name).await?)
+    db.delete_crate_user(&crate_name, &name).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::AclChange,
+        &Utc::now(),
+        &crate_name,
+        json!({ "change": "crate_user_remove", "user": name }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            source_ip.actor(user.name(), None),
+            AuditAction::RemoveCrateUser,
+            &*crate_name,
+        )
+        .with_detail(name),
+    )
+    .await;
+
+    Ok(())
 }
 
 /// List groups with access to a crate (admin only)
@@ -156,13 +201,32 @@ pub async fn list_groups(
     security(("session_cookie" = []))
 )]
 pub async fn add_group(
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     let crate_name = crate_name.to_normalized();
     if !db.is_crate_group(&crate_name, &name).await? {
         db.add_crate_group(&crate_name, &name).await?;
+        kellnr_webhooks::notify_crate_change(
+            WebhookEvent::AclChange,
+            &Utc::now(),
+            &crate_name,
+            json!({ "change": "crate_group_add", "group": name }),
+            &db,
+        )
+        .await;
+        audit::record(
+            &db,
+            NewAuditEntry::new(
+                source_ip.actor(user.name(), None),
+                AuditAction::AddCrateGroup,
+                &*crate_name,
+            )
+            .with_detail(name),
+        )
+        .await;
     }
 
     Ok(())
@@ -184,12 +248,33 @@ pub async fn add_group(
     security(("session_cookie" = []))
 )]
 pub async fn delete_group(
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     Path((crate_name, name)): Path<(OriginalName, String)>,
     State(db): DbState,
 ) -> Result<(), RouteError> {
     let crate_name = crate_name.to_normalized();
-    Ok(db.delete_crate_group(&crate_name, &name).await?)
+    db.delete_crate_group(&crate_name, &name).await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::AclChange,
+        &Utc::now(),
+        &crate_name,
+        json!({ "change": "crate_group_remove", "group": name }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            source_ip.actor(user.name(), None),
+            AuditAction::RemoveCrateGroup,
+            &*crate_name,
+        )
+        .with_detail(name),
+    )
+    .await;
+
+    Ok(())
 }
 
 /// Get a crate's access settings (admin only)
@@ -233,7 +318,8 @@ pub async fn get_access_data(
     security(("session_cookie" = []))
 )]
 pub async fn set_access_data(
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     State(db): DbState,
     Path(crate_name): Path<OriginalName>,
     Json(input): Json<AccessData>,
@@ -241,6 +327,27 @@ pub async fn set_access_data(
     let crate_name = crate_name.to_normalized();
     db.change_download_restricted(&crate_name, input.download_restricted)
         .await?;
+    kellnr_webhooks::notify_crate_change(
+        WebhookEvent::AclChange,
+        &Utc::now(),
+        &crate_name,
+        json!({
+            "change": "download_restriction",
+            "download_restricted": input.download_restricted
+        }),
+        &db,
+    )
+    .await;
+    audit::record(
+        &db,
+        NewAuditEntry::new(
+            source_ip.actor(user.name(), None),
+            AuditAction::ChangeDownloadRestriction,
+            &*crate_name,
+        )
+        .with_detail(input.download_restricted.to_string()),
+    )
+    .await;
 
     Ok(Json(AccessData {
         download_restricted: db.is_download_restricted(&crate_name).await?,
@@ -0,0 +1,695 @@
+//! Browse the files of stored crates and compare two versions of a crate.
+//!
+//! The `.crate` tarballs are read from the crate storage and never unpacked
+//! to disk. File contents are limited to [`MAX_FILE_SIZE`] bytes, diffs are
+//! only computed for crates of up to [`MAX_DIFF_SIZE`] unpacked bytes.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::path::Component;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use kellnr_appstate::AppState;
+use kellnr_common::normalized_name::NormalizedName;
+use kellnr_common::original_name::OriginalName;
+use kellnr_common::version::Version;
+use kellnr_db::DbProvider;
+use kellnr_db::error::DbError;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use tar::Archive;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::session::MaybeUser;
+
+/// Max. number of bytes of a file that are returned or diffed
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Max. unpacked size in bytes of a crate that can be diffed
+pub const MAX_DIFF_SIZE: u64 = 32 * 1024 * 1024;
+
+/// File in a crate tarball
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateFile {
+    /// Path relative to the root directory of the crate
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Files of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateFiles {
+    pub name: String,
+    pub version: String,
+    pub files: Vec<CrateFile>,
+}
+
+/// Content of a file in a crate tarball
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FileContent {
+    pub path: String,
+    /// Size of the whole file in bytes
+    pub size: u64,
+    /// The file is not UTF-8 text, `content` is empty
+    pub binary: bool,
+    /// The file is larger than the limit, `content` is only its beginning
+    pub truncated: bool,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Change of a file between two crate versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FileDiff {
+    pub path: String,
+    pub change: FileChange,
+    /// Unified diff of the file, `None` if the file is binary or larger than
+    /// the limit
+    pub diff: Option<String>,
+}
+
+/// Changed files between two versions of a crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateDiff {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileParams {
+    /// Path of the file relative to the root directory of the crate
+    path: String,
+}
+
+/// List the files of a crate version
+#[utoipa::path(
+    get,
+    path = "/crates/{package}/{version}/files",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version")
+    ),
+    responses(
+        (status = 200, description = "Files of the crate version", body = CrateFiles),
+        (status = 401, description = "Not logged in for a restricted crate"),
+        (status = 403, description = "No access to a restricted crate"),
+        (status = 404, description = "Crate version not found")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_files(
+    State(state): AppState,
+    user: Option<MaybeUser>,
+    Path((package, version)): Path<(OriginalName, Version)>,
+) -> Result<Json<CrateFiles>, StatusCode> {
+    check_access(&state.db, user.as_ref(), &package.to_normalized()).await?;
+    let cratedata = state.crate_storage.get(&package, &version).await;
+    crate_files(cratedata, &package, &version).await
+}
+
+/// Get the content of a file of a crate version
+#[utoipa::path(
+    get,
+    path = "/crates/{package}/{version}/file",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version"),
+        ("path" = String, Query, description = "Path of the file relative to the root directory of the crate")
+    ),
+    responses(
+        (status = 200, description = "Content of the file", body = FileContent),
+        (status = 401, description = "Not logged in for a restricted crate"),
+        (status = 403, description = "No access to a restricted crate"),
+        (status = 404, description = "Crate version or file not found")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn file_content(
+    State(state): AppState,
+    user: Option<MaybeUser>,
+    Path((package, version)): Path<(OriginalName, Version)>,
+    Query(params): Query<FileParams>,
+) -> Result<Json<FileContent>, StatusCode> {
+    check_access(&state.db, user.as_ref(), &package.to_normalized()).await?;
+    let cratedata = state.crate_storage.get(&package, &version).await;
+    crate_file_content(cratedata, params.path).await
+}
+
+/// Compare two versions of a crate
+///
+/// Returns the added, removed and modified files with a unified diff.
+#[utoipa::path(
+    get,
+    path = "/crates/{package}/{from}/diff/{to}",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("from" = String, Path, description = "Old package version"),
+        ("to" = String, Path, description = "New package version")
+    ),
+    responses(
+        (status = 200, description = "Changed files", body = CrateDiff),
+        (status = 401, description = "Not logged in for a restricted crate"),
+        (status = 403, description = "No access to a restricted crate"),
+        (status = 404, description = "Crate version not found"),
+        (status = 413, description = "Crate is too large to compare")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn diff(
+    State(state): AppState,
+    user: Option<MaybeUser>,
+    Path((package, from, to)): Path<(OriginalName, Version, Version)>,
+) -> Result<Json<CrateDiff>, StatusCode> {
+    check_access(&state.db, user.as_ref(), &package.to_normalized()).await?;
+    let old = state.crate_storage.get(&package, &from).await;
+    let new = state.crate_storage.get(&package, &to).await;
+    crate_diff(old, new, &package, &from, &to).await
+}
+
+/// List the files of a cached crates.io crate version
+#[utoipa::path(
+    get,
+    path = "/cratesio/{package}/{version}/files",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version")
+    ),
+    responses(
+        (status = 200, description = "Files of the crate version", body = CrateFiles),
+        (status = 404, description = "Crate version not cached or proxy disabled")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn cratesio_list_files(
+    State(state): AppState,
+    Path((package, version)): Path<(OriginalName, Version)>,
+) -> Result<Json<CrateFiles>, StatusCode> {
+    let cratedata = state.cratesio_storage.get(&package, &version).await;
+    crate_files(cratedata, &package, &version).await
+}
+
+/// Get the content of a file of a cached crates.io crate version
+#[utoipa::path(
+    get,
+    path = "/cratesio/{package}/{version}/file",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version"),
+        ("path" = String, Query, description = "Path of the file relative to the root directory of the crate")
+    ),
+    responses(
+        (status = 200, description = "Content of the file", body = FileContent),
+        (status = 404, description = "Crate version not cached, file not found or proxy disabled")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn cratesio_file_content(
+    State(state): AppState,
+    Path((package, version)): Path<(OriginalName, Version)>,
+    Query(params): Query<FileParams>,
+) -> Result<Json<FileContent>, StatusCode> {
+    let cratedata = state.cratesio_storage.get(&package, &version).await;
+    crate_file_content(cratedata, params.path).await
+}
+
+/// Compare two cached versions of a crates.io crate
+///
+/// Returns the added, removed and modified files with a unified diff.
+#[utoipa::path(
+    get,
+    path = "/cratesio/{package}/{from}/diff/{to}",
+    tag = "source",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("from" = String, Path, description = "Old package version"),
+        ("to" = String, Path, description = "New package version")
+    ),
+    responses(
+        (status = 200, description = "Changed files", body = CrateDiff),
+        (status = 404, description = "Crate version not cached or proxy disabled"),
+        (status = 413, description = "Crate is too large to compare")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn cratesio_diff(
+    State(state): AppState,
+    Path((package, from, to)): Path<(OriginalName, Version, Version)>,
+) -> Result<Json<CrateDiff>, StatusCode> {
+    let old = state.cratesio_storage.get(&package, &from).await;
+    let new = state.cratesio_storage.get(&package, &to).await;
+    crate_diff(old, new, &package, &from, &to).await
+}
+
+/// Check that the user may download the crate, if downloads are restricted.
+pub(crate) async fn check_access(
+    db: &Arc<dyn DbProvider>,
+    user: Option<&MaybeUser>,
+    name: &NormalizedName,
+) -> Result<(), StatusCode> {
+    let internal = |e: DbError| {
+        error!("Failed to check access to crate {name}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    match db.is_download_restricted(name).await {
+        Ok(false) => return Ok(()),
+        Ok(true) => {}
+        Err(DbError::CrateNotFound(_)) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => return Err(internal(e)),
+    }
+
+    let user = match user {
+        None => return Err(StatusCode::UNAUTHORIZED),
+        Some(MaybeUser::Admin(_)) => return Ok(()),
+        Some(MaybeUser::Normal(user)) => user,
+    };
+    if db.is_crate_user(name, user).await.map_err(internal)?
+        || db.is_crate_group_user(name, user).await.map_err(internal)?
+        || db.is_owner(name, user).await.map_err(internal)?
+    {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+async fn crate_files(
+    cratedata: Option<Bytes>,
+    package: &OriginalName,
+    version: &Version,
+) -> Result<Json<CrateFiles>, StatusCode> {
+    let cratedata = cratedata.ok_or(StatusCode::NOT_FOUND)?;
+    let files = blocking(move || read_files(&cratedata)).await?;
+    Ok(Json(CrateFiles {
+        name: package.to_string(),
+        version: version.to_string(),
+        files,
+    }))
+}
+
+async fn crate_file_content(
+    cratedata: Option<Bytes>,
+    path: String,
+) -> Result<Json<FileContent>, StatusCode> {
+    let cratedata = cratedata.ok_or(StatusCode::NOT_FOUND)?;
+    blocking(move || read_file(&cratedata, &path))
+        .await
+        .map(Json)
+}
+
+async fn crate_diff(
+    old: Option<Bytes>,
+    new: Option<Bytes>,
+    package: &OriginalName,
+    from: &Version,
+    to: &Version,
+) -> Result<Json<CrateDiff>, StatusCode> {
+    let (old, new) = old.zip(new).ok_or(StatusCode::NOT_FOUND)?;
+    let files = blocking(move || diff_crates(&old, &new)).await?;
+    Ok(Json(CrateDiff {
+        name: package.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+        files,
+    }))
+}
+
+/// Run the reading of a tarball outside of the async runtime.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, StatusCode> + Send + 'static,
+) -> Result<T, StatusCode> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| {
+        error!("Reading crate tarball failed: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+}
+
+type Entry<'a> = tar::Entry<'a, GzDecoder<&'a [u8]>>;
+
+/// Call `f` with the path relative to the root directory of the crate and the
+/// entry of every regular file in the tarball.
+fn for_each_file(
+    cratedata: &[u8],
+    mut f: impl FnMut(String, &mut Entry<'_>) -> Result<ControlFlow<()>, StatusCode>,
+) -> Result<(), StatusCode> {
+    let mut archive = Archive::new(GzDecoder::new(cratedata));
+    for entry in archive.entries().map_err(|e| invalid_tarball(&e))? {
+        let mut entry = entry.map_err(|e| invalid_tarball(&e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(|e| invalid_tarball(&e))?;
+        let path = path
+            .components()
+            .skip(1)
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if f(path, &mut entry)?.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn invalid_tarball(e: &std::io::Error) -> StatusCode {
+    error!("Stored crate tarball cannot be read: {e}");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn read_files(cratedata: &[u8]) -> Result<Vec<CrateFile>, StatusCode> {
+    let mut files = Vec::new();
+    for_each_file(cratedata, |path, entry| {
+        files.push(CrateFile {
+            path,
+            size: entry.size(),
+        });
+        Ok(ControlFlow::Continue(()))
+    })?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn read_file(cratedata: &[u8], path: &str) -> Result<FileContent, StatusCode> {
+    let mut content = None;
+    for_each_file(cratedata, |file_path, entry| {
+        if file_path != path {
+            return Ok(ControlFlow::Continue(()));
+        }
+        let size = entry.size();
+        let mut data = Vec::new();
+        entry
+            .take(MAX_FILE_SIZE)
+            .read_to_end(&mut data)
+            .map_err(|e| invalid_tarball(&e))?;
+        let truncated = size > MAX_FILE_SIZE;
+        let text = to_text(data, truncated);
+        content = Some(FileContent {
+            path: file_path,
+            size,
+            binary: text.is_none(),
+            truncated,
+            content: text.unwrap_or_default(),
+        });
+        Ok(ControlFlow::Break(()))
+    })?;
+    content.ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Text of the file, or `None` if it is binary. A UTF-8 character that is
+/// cut off at the end of truncated data is removed.
+fn to_text(data: Vec<u8>, truncated: bool) -> Option<String> {
+    let text = match String::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut data = e.into_bytes();
+            data.truncate(valid);
+            String::from_utf8(data).ok()?
+        }
+        Err(_) => return None,
+    };
+    (!text.contains('\0')).then_some(text)
+}
+
+fn read_all_files(cratedata: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, StatusCode> {
+    let mut files = BTreeMap::new();
+    let mut total = 0u64;
+    for_each_file(cratedata, |path, entry| {
+        total = total.saturating_add(entry.size());
+        if total > MAX_DIFF_SIZE {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| invalid_tarball(&e))?;
+        files.insert(path, data);
+        Ok(ControlFlow::Continue(()))
+    })?;
+    Ok(files)
+}
+
+fn diff_crates(old: &[u8], new: &[u8]) -> Result<Vec<FileDiff>, StatusCode> {
+    let old = read_all_files(old)?;
+    let new = read_all_files(new)?;
+    let paths: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+
+    let diffs = paths
+        .into_iter()
+        .filter_map(|path| {
+            let (old, new) = (old.get(path), new.get(path));
+            let change = match (old, new) {
+                (Some(old), Some(new)) if old == new => return None,
+                (Some(_), Some(_)) => FileChange::Modified,
+                (Some(_), None) => FileChange::Removed,
+                (None, _) => FileChange::Added,
+            };
+            Some(FileDiff {
+                path: path.clone(),
+                change,
+                diff: unified_diff(path, old, new),
+            })
+        })
+        .collect();
+    Ok(diffs)
+}
+
+/// Unified diff of a file, where `None` is a missing file. `None` if one of
+/// the files is binary or too large.
+fn unified_diff(path: &str, old: Option<&Vec<u8>>, new: Option<&Vec<u8>>) -> Option<String> {
+    let text = |data: Option<&Vec<u8>>| match data {
+        Some(data) if data.len() as u64 > MAX_FILE_SIZE => None,
+        Some(data) => to_text(data.clone(), false),
+        None => Some(String::new()),
+    };
+    let (old_text, new_text) = (text(old)?, text(new)?);
+    let header = |data: Option<&Vec<u8>>, prefix| match data {
+        Some(_) => format!("{prefix}/{path}"),
+        None => "/dev/null".to_string(),
+    };
+
+    Some(
+        TextDiff::from_lines(&old_text, &new_text)
+            .unified_diff()
+            .header(&header(old, "a"), &header(new, "b"))
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use kellnr_db::mock::MockDb;
+
+    use super::*;
+
+    fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("foo-1.0.0/{path}"), *content)
+                .unwrap();
+        }
+        let mut encoder = builder.into_inner().unwrap();
+        encoder.flush().unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn files_are_listed_relative_to_the_root() {
+        let data = tarball(&[("src/lib.rs", b"pub fn foo() {}"), ("Cargo.toml", b"")]);
+
+        assert_eq!(
+            vec![
+                CrateFile {
+                    path: "Cargo.toml".to_string(),
+                    size: 0
+                },
+                CrateFile {
+                    path: "src/lib.rs".to_string(),
+                    size: 15
+                },
+            ],
+            read_files(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn fixture_files_are_listed() {
+        let data = std::fs::read("../../tests/fixtures/test-data/full-toml-0.1.4.crate").unwrap();
+
+        let files = read_files(&data).unwrap();
+
+        assert!(files.iter().any(|f| f.path == "Cargo.toml"));
+        assert!(files.iter().all(|f| !f.path.starts_with("full-toml")));
+    }
+
+    #[test]
+    fn file_content_is_returned() {
+        let data = tarball(&[("src/lib.rs", b"pub fn foo() {}")]);
+
+        let content = read_file(&data, "src/lib.rs").unwrap();
+
+        assert_eq!("pub fn foo() {}", content.content);
+        assert!(!content.binary);
+        assert!(!content.truncated);
+        assert_eq!(Err(StatusCode::NOT_FOUND), read_file(&data, "src/main.rs"));
+    }
+
+    #[test]
+    fn large_file_is_truncated() {
+        // The multi-byte character is cut off by the limit
+        let mut large = vec![b'a'; usize::try_from(MAX_FILE_SIZE).unwrap() - 1];
+        large.extend_from_slice("ä".as_bytes());
+        let data = tarball(&[("large.txt", &large)]);
+
+        let content = read_file(&data, "large.txt").unwrap();
+
+        assert!(content.truncated);
+        assert!(!content.binary);
+        assert_eq!(MAX_FILE_SIZE + 1, content.size);
+        assert_eq!(
+            usize::try_from(MAX_FILE_SIZE).unwrap() - 1,
+            content.content.len()
+        );
+    }
+
+    #[test]
+    fn binary_file_has_no_content() {
+        let data = tarball(&[("logo.png", &[0x89, b'P', b'N', b'G', 0, 0xff])]);
+
+        let content = read_file(&data, "logo.png").unwrap();
+
+        assert!(content.binary);
+        assert_eq!("", content.content);
+    }
+
+    #[test]
+    fn diff_contains_changed_files() {
+        let old = tarball(&[
+            ("Cargo.toml", b"[package]\n"),
+            ("src/lib.rs", b"fn a() {}\nfn b() {}\n"),
+            ("src/old.rs", b"old\n"),
+            ("logo.png", &[0, 1]),
+        ]);
+        let new = tarball(&[
+            ("Cargo.toml", b"[package]\n"),
+            ("src/lib.rs", b"fn a() {}\nfn c() {}\n"),
+            ("src/new.rs", b"new\n"),
+            ("logo.png", &[0, 2]),
+        ]);
+
+        let diffs = diff_crates(&old, &new).unwrap();
+
+        assert_eq!(
+            vec![
+                FileDiff {
+                    path: "logo.png".to_string(),
+                    change: FileChange::Modified,
+                    diff: None,
+                },
+                FileDiff {
+                    path: "src/lib.rs".to_string(),
+                    change: FileChange::Modified,
+                    diff: Some(
+                        "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n"
+                            .to_string()
+                    ),
+                },
+                FileDiff {
+                    path: "src/new.rs".to_string(),
+                    change: FileChange::Added,
+                    diff: Some("--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1 @@\n+new\n".to_string()),
+                },
+                FileDiff {
+                    path: "src/old.rs".to_string(),
+                    change: FileChange::Removed,
+                    diff: Some("--- a/src/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-old\n".to_string()),
+                },
+            ],
+            diffs
+        );
+    }
+
+    fn restricted_db(is_owner: bool) -> Arc<dyn DbProvider> {
+        let mut db = MockDb::new();
+        db.expect_is_download_restricted().returning(|_| Ok(true));
+        db.expect_is_crate_user().returning(|_, _| Ok(false));
+        db.expect_is_crate_group_user().returning(|_, _| Ok(false));
+        db.expect_is_owner().returning(move |_, _| Ok(is_owner));
+        Arc::new(db)
+    }
+
+    #[tokio::test]
+    async fn restricted_crate_requires_access() {
+        let name = NormalizedName::from_unchecked("foo".to_string());
+        let user = MaybeUser::Normal("user".to_string());
+        let admin = MaybeUser::Admin("admin".to_string());
+
+        assert_eq!(
+            Err(StatusCode::UNAUTHORIZED),
+            check_access(&restricted_db(false), None, &name).await
+        );
+        assert_eq!(
+            Err(StatusCode::FORBIDDEN),
+            check_access(&restricted_db(false), Some(&user), &name).await
+        );
+        assert_eq!(
+            Ok(()),
+            check_access(&restricted_db(true), Some(&user), &name).await
+        );
+        assert_eq!(
+            Ok(()),
+            check_access(&restricted_db(false), Some(&admin), &name).await
+        );
+    }
+
+    #[tokio::test]
+    async fn unrestricted_crate_is_readable_by_everyone() {
+        let mut db = MockDb::new();
+        db.expect_is_download_restricted().returning(|_| Ok(false));
+        let db: Arc<dyn DbProvider> = Arc::new(db);
+
+        assert_eq!(
+            Ok(()),
+            check_access(
+                &db,
+                None,
+                &NormalizedName::from_unchecked("foo".to_string())
+            )
+            .await
+        );
+    }
+}
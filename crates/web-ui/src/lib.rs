@@ -1,4 +1,7 @@
+pub mod advisory;
+pub mod audit;
 pub mod crate_access;
+pub mod crate_source;
 pub mod error;
 pub mod group;
 pub mod oauth2;
@@ -12,8 +12,10 @@ use axum::http::StatusCode;
 use axum::response::Redirect;
 use axum::{Extension, Json};
 use axum_extra::extract::PrivateCookieJar;
+use chrono::Utc;
 use kellnr_appstate::{AppState, AppStateData, DbState, SettingsState};
 use kellnr_auth::oauth2::{OAuth2Handler, UserInfo, generate_unique_username};
+use kellnr_common::webhook::WebhookEvent;
 use kellnr_db::User;
 use serde::{Deserialize, Serialize};
 use tracing::{error, trace, warn};
@@ -229,7 +231,7 @@ pub async fn callback(
             );
 
             // Create new user with OAuth2 identity
-            app_state
+            let user = app_state
                 .db
                 .create_oauth2_user(
                     &username,
@@ -243,7 +245,15 @@ pub async fn callback(
                 .map_err(|e| {
                     error!("Failed to create OAuth2 user: {}", e);
                     RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR)
-                })?
+                })?;
+            kellnr_webhooks::notify_user(
+                WebhookEvent::UserAdd,
+                &Utc::now(),
+                &user.name,
+                &app_state.db,
+            )
+            .await;
+            user
         }
     };
 
@@ -1,23 +1,32 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use axum::Json;
 use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
-use kellnr_appstate::{AppState, DbState, SettingsProvState, SettingsState};
+use chrono::Utc;
+use kellnr_appstate::{AppState, AppStateData, DbState, SettingsProvState, SettingsState};
+use kellnr_auth::audit::{self, SourceIp};
 use kellnr_common::crate_data::CrateData;
 use kellnr_common::crate_overview::CrateOverview;
+use kellnr_common::git_index_msg::notify_git_index;
 use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::original_name::OriginalName;
 use kellnr_common::version::Version;
+use kellnr_common::webhook::WebhookEvent;
 use kellnr_db::error::DbError;
+use kellnr_db::{AuditAction, AuditActor, CrateAdvisory, DbProvider, DocBuildState, NewAuditEntry};
+use kellnr_docs::doc_queue_response::{DocBuildResponse, DocQueueEntryResponse};
 use kellnr_settings::{
     ConfigSource, Provenance, Settings, SettingsProv, SourceMap, cli_flag_map, compile_time_config,
     erased_serde, leaf_label, sources_from_prov,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tracing::error;
 use utoipa::ToSchema;
 
+use crate::crate_source::check_access;
 use crate::error::RouteError;
 use crate::session::{AdminUser, MaybeUser};
 
@@ -257,11 +266,17 @@ pub async fn crates(Query(params): Query<CratesParams>, State(db): DbState) -> J
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, ToSchema, utoipa::IntoParams)]
 pub struct SearchParams {
-    name: OriginalName,
+    /// Search text with optional filters like `keyword:async`
+    name: String,
     cache: Option<bool>,
 }
 
-/// Search for crates by name
+/// Search for crates
+///
+/// Ranked full-text search over crate names, keywords, categories,
+/// descriptions and readmes. The query accepts the filters `keyword:`,
+/// `category:`, `owner:` and `license:`. Cached crates.io crates are only
+/// matched by name.
 #[utoipa::path(
     get,
     path = "/search",
@@ -273,7 +288,7 @@ pub struct SearchParams {
 )]
 pub async fn search(Query(params): Query<SearchParams>, State(db): DbState) -> Json<Pagination> {
     let crates = db
-        .search_in_crate_name(&params.name, params.cache.unwrap_or(false))
+        .search_crates(&params.name, params.cache.unwrap_or(false))
         .await
         .unwrap_or_default();
     Json(Pagination {
@@ -288,6 +303,18 @@ pub struct CrateDataParams {
     name: OriginalName,
 }
 
+/// Crate details together with the security advisories that affect any of
+/// its versions.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CrateDataResponse {
+    #[serde(flatten)]
+    pub data: CrateData,
+    pub advisories: Vec<CrateAdvisory>,
+    /// Latest documentation builds of the versions, if docs are built on the
+    /// server
+    pub doc_builds: Vec<DocQueueEntryResponse>,
+}
+
 /// Get detailed crate data
 #[utoipa::path(
     get,
@@ -295,17 +322,31 @@ pub struct CrateDataParams {
     tag = "ui",
     params(CrateDataParams),
     responses(
-        (status = 200, description = "Crate details", body = CrateData),
+        (status = 200, description = "Crate details", body = CrateDataResponse),
         (status = 404, description = "Crate not found")
     )
 )]
 pub async fn crate_data(
     Query(params): Query<CrateDataParams>,
     State(db): DbState,
-) -> Result<Json<CrateData>, StatusCode> {
+    State(settings): SettingsState,
+) -> Result<Json<CrateDataResponse>, StatusCode> {
     let index_name = NormalizedName::from(params.name);
     match db.get_crate_data(&index_name).await {
-        Ok(cd) => Ok(Json(cd)),
+        Ok(data) => {
+            let versions = data
+                .versions
+                .iter()
+                .map(|v| v.version.as_str())
+                .collect::<Vec<_>>();
+            let advisories = crate_advisories(&settings, &db, &index_name, &versions).await;
+            let doc_builds = doc_builds(&settings, &db, &index_name).await;
+            Ok(Json(CrateDataResponse {
+                data,
+                advisories,
+                doc_builds,
+            }))
+        }
         Err(e) => match e {
             DbError::CrateNotFound(_) => Err(StatusCode::NOT_FOUND),
             _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
@@ -318,6 +359,77 @@ pub struct CratesIoDataParams {
     name: OriginalName,
 }
 
+/// Advisories of the crate that affect any of `versions`. Empty if the
+/// advisory import is disabled.
+async fn crate_advisories(
+    settings: &Settings,
+    db: &Arc<dyn DbProvider>,
+    name: &NormalizedName,
+    versions: &[&str],
+) -> Vec<CrateAdvisory> {
+    if !settings.advisories.enabled {
+        return vec![];
+    }
+    match db.get_advisories(name).await {
+        Ok(advisories) => CrateAdvisory::for_versions(advisories, versions),
+        Err(e) => {
+            error!("Failed to get advisories of crate {name}: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Documentation builds of the crate. Empty if docs are not built on the
+/// server.
+async fn doc_builds(
+    settings: &Settings,
+    db: &Arc<dyn DbProvider>,
+    name: &NormalizedName,
+) -> Vec<DocQueueEntryResponse> {
+    if !settings.docs.enabled {
+        return vec![];
+    }
+    match db.get_crate_doc_builds(name).await {
+        Ok(builds) => builds
+            .into_iter()
+            .map(DocQueueEntryResponse::from)
+            .collect(),
+        Err(e) => {
+            error!("Failed to get doc builds of crate {name}: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Add the advisories affecting the listed versions to a crates.io crate
+/// response. Responses that are not valid JSON are returned unchanged.
+async fn add_cratesio_advisories(
+    settings: &Settings,
+    db: &Arc<dyn DbProvider>,
+    name: &NormalizedName,
+    data: String,
+) -> String {
+    let Ok(serde_json::Value::Object(mut json)) = serde_json::from_str(&data) else {
+        return data;
+    };
+    let versions = json
+        .get("versions")
+        .and_then(serde_json::Value::as_array)
+        .map(|versions| {
+            versions
+                .iter()
+                .filter_map(|v| v.get("num").and_then(serde_json::Value::as_str))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let advisories = crate_advisories(settings, db, name, &versions).await;
+    json.insert(
+        "advisories".to_string(),
+        serde_json::to_value(advisories).unwrap_or_default(),
+    );
+    serde_json::Value::Object(json).to_string()
+}
+
 /// Get crate data from crates.io
 #[utoipa::path(
     get,
@@ -325,12 +437,13 @@ pub struct CratesIoDataParams {
     tag = "ui",
     params(CratesIoDataParams),
     responses(
-        (status = 200, description = "Crates.io crate data", body = String),
+        (status = 200, description = "Crates.io crate data, with the advisories affecting its versions", body = String),
         (status = 404, description = "Crate not found")
     )
 )]
 pub async fn cratesio_data(
     State(settings): SettingsState,
+    State(db): DbState,
     Query(params): Query<CratesIoDataParams>,
 ) -> Result<String, StatusCode> {
     let url = settings
@@ -352,7 +465,10 @@ pub async fn cratesio_data(
             StatusCode::OK => {
                 let data = resp.text().await;
                 match data {
-                    Ok(data) => Ok(data),
+                    Ok(data) => {
+                        let name = NormalizedName::from(&params.name);
+                        Ok(add_cratesio_advisories(&settings, &db, &name, data).await)
+                    }
                     Err(e) => {
                         error!("Failed to parse crates.io data: {e}");
                         Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -386,10 +502,21 @@ pub struct DeleteCrateParams {
 /// Helper function to delete crate versions from db, storage, and docs.
 /// If `versions` is `None`, all versions of the crate are deleted.
 async fn delete_crate_versions_impl(
-    state: &kellnr_appstate::AppStateData,
+    state: &AppStateData,
+    actor: AuditActor,
     name: &OriginalName,
     versions: Option<Vec<Version>>,
 ) -> Result<(), RouteError> {
+    let whole_crate = versions.is_none();
+    // The groups are gone with the last version, but are still needed to
+    // notify the webhooks filtering on them.
+    let crate_groups: Vec<String> = state
+        .db
+        .get_crate_groups(&name.to_normalized())
+        .await?
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
     let versions_to_delete = if let Some(v) = versions {
         v
     } else {
@@ -405,6 +532,7 @@ async fn delete_crate_versions_impl(
             error!("Failed to delete crate from database: {e:?}");
             return Err(RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR));
         }
+        notify_git_index(state.git_index_sender.as_ref(), &name.to_normalized());
 
         if let Err(e) = state.crate_storage.delete(name, version).await {
             error!("Failed to delete crate from storage: {e}");
@@ -415,6 +543,38 @@ async fn delete_crate_versions_impl(
             error!("Failed to delete crate from docs: {e}");
             return Err(RouteError::Status(StatusCode::INTERNAL_SERVER_ERROR));
         }
+
+        audit::record(
+            &state.db,
+            NewAuditEntry::new(
+                actor.clone(),
+                AuditAction::DeleteVersion,
+                &*name.to_normalized(),
+            )
+            .with_version(&**version),
+        )
+        .await;
+        kellnr_webhooks::notify_crate_deletion(
+            WebhookEvent::VersionDelete,
+            &Utc::now(),
+            &name.to_normalized(),
+            crate_groups.clone(),
+            json!({ "crate_version": version, "actor": actor.user }),
+            &state.db,
+        )
+        .await;
+    }
+
+    if whole_crate {
+        kellnr_webhooks::notify_crate_deletion(
+            WebhookEvent::CrateDelete,
+            &Utc::now(),
+            &name.to_normalized(),
+            crate_groups,
+            json!({ "actor": actor.user }),
+            &state.db,
+        )
+        .await;
     }
 
     Ok(())
@@ -422,18 +582,22 @@ async fn delete_crate_versions_impl(
 
 pub async fn delete_version(
     Query(params): Query<DeleteCrateVersionParams>,
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     State(state): AppState,
 ) -> Result<(), RouteError> {
-    delete_crate_versions_impl(&state, &params.name, Some(vec![params.version])).await
+    let actor = source_ip.actor(user.name(), None);
+    delete_crate_versions_impl(&state, actor, &params.name, Some(vec![params.version])).await
 }
 
 pub async fn delete_crate(
     Query(params): Query<DeleteCrateParams>,
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     State(state): AppState,
 ) -> Result<(), RouteError> {
-    delete_crate_versions_impl(&state, &params.name, None).await
+    let actor = source_ip.actor(user.name(), None);
+    delete_crate_versions_impl(&state, actor, &params.name, None).await
 }
 
 /// Delete a specific version of a crate (path parameter version)
@@ -454,7 +618,8 @@ pub async fn delete_crate(
 )]
 pub async fn delete_crate_version(
     Path((name, version)): Path<(String, String)>,
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     State(state): AppState,
 ) -> Result<(), RouteError> {
     let name = OriginalName::try_from(name.as_str())
@@ -462,7 +627,8 @@ pub async fn delete_crate_version(
     let version = Version::try_from(version.as_str())
         .map_err(|_| RouteError::Status(StatusCode::BAD_REQUEST))?;
 
-    delete_crate_versions_impl(&state, &name, Some(vec![version])).await
+    let actor = source_ip.actor(user.name(), None);
+    delete_crate_versions_impl(&state, actor, &name, Some(vec![version])).await
 }
 
 /// Delete all versions of a crate (path parameter version)
@@ -482,13 +648,15 @@ pub async fn delete_crate_version(
 )]
 pub async fn delete_crate_all(
     Path(name): Path<String>,
-    _user: AdminUser,
+    user: AdminUser,
+    source_ip: SourceIp,
     State(state): AppState,
 ) -> Result<(), RouteError> {
     let name = OriginalName::try_from(name.as_str())
         .map_err(|_| RouteError::Status(StatusCode::BAD_REQUEST))?;
 
-    delete_crate_versions_impl(&state, &name, None).await
+    let actor = source_ip.actor(user.name(), None);
+    delete_crate_versions_impl(&state, actor, &name, None).await
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, ToSchema)]
@@ -590,23 +758,121 @@ pub async fn build_rustdoc(
     Query(params): Query<BuildParams>,
     State(state): AppState,
     user: MaybeUser,
+) -> Result<(), StatusCode> {
+    let normalized_name = NormalizedName::from(params.package);
+    check_can_build_docs(&state, &user, &normalized_name, &params.version).await?;
+    queue_doc_build(&state, &normalized_name, &params.version).await
+}
+
+/// Get documentation build of a crate version
+///
+/// Returns the state and the captured cargo output of the latest
+/// documentation build of a crate version.
+/// Requires access to the crate, if its downloads are restricted.
+#[utoipa::path(
+    get,
+    path = "/builds/{package}/{version}",
+    tag = "docs",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version")
+    ),
+    responses(
+        (status = 200, description = "Documentation build", body = DocBuildResponse),
+        (status = 401, description = "Not logged in for a restricted crate"),
+        (status = 403, description = "No access to a restricted crate"),
+        (status = 404, description = "Crate version was never built")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn rustdoc_build(
+    Path((package, version)): Path<(OriginalName, Version)>,
+    State(db): DbState,
+    user: Option<MaybeUser>,
+) -> Result<Json<DocBuildResponse>, StatusCode> {
+    let name = package.to_normalized();
+    check_access(&db, user.as_ref(), &name).await?;
+
+    let build = db
+        .get_doc_build(&name, &version)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let log = db
+        .get_doc_build_log(build.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(DocBuildResponse {
+        build: build.into(),
+        log,
+    }))
+}
+
+/// Retry a failed documentation build
+///
+/// Add a crate version, whose latest documentation build failed, to the
+/// documentation build queue again.
+/// Requires ownership of the crate or admin access.
+#[utoipa::path(
+    post,
+    path = "/builds/{package}/{version}/retry",
+    tag = "docs",
+    params(
+        ("package" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Package version")
+    ),
+    responses(
+        (status = 200, description = "Build queued successfully"),
+        (status = 400, description = "Crate or version does not exist"),
+        (status = 401, description = "Not authorized or not an owner"),
+        (status = 404, description = "Crate version was never built"),
+        (status = 409, description = "Latest build did not fail")
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn retry_rustdoc_build(
+    Path((package, version)): Path<(OriginalName, Version)>,
+    State(state): AppState,
+    user: MaybeUser,
+) -> Result<(), StatusCode> {
+    let normalized_name = NormalizedName::from(package);
+    check_can_build_docs(&state, &user, &normalized_name, &version).await?;
+
+    let build = state
+        .db
+        .get_doc_build(&normalized_name, &version)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if build.state != DocBuildState::Failed {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    queue_doc_build(&state, &normalized_name, &version).await
+}
+
+/// Check that docs are built on the server, the crate version exists and the
+/// user is an owner of the crate or an admin.
+async fn check_can_build_docs(
+    state: &AppStateData,
+    user: &MaybeUser,
+    normalized_name: &NormalizedName,
+    version: &Version,
 ) -> Result<(), StatusCode> {
     if !state.settings.docs.enabled {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let normalized_name = NormalizedName::from(params.package);
-    let db = state.db;
-    let version = params.version;
+    let db = &state.db;
 
     // Check if crate with the version exists.
     if let Some(id) = db
-        .get_crate_id(&normalized_name)
+        .get_crate_id(normalized_name)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     {
         if !db
-            .crate_version_exists(id, &version)
+            .crate_version_exists(id, version)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         {
@@ -620,28 +886,37 @@ pub async fn build_rustdoc(
     // the build operation is allowed.
     let is_allowed = match user {
         MaybeUser::Normal(user) => db
-            .is_owner(&normalized_name, &user)
+            .is_owner(normalized_name, user)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
         MaybeUser::Admin(_) => true,
     };
 
-    if !is_allowed {
-        return Err(StatusCode::UNAUTHORIZED);
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
     }
+}
 
-    // Add to build queue
-    db.add_doc_queue(
-        &normalized_name,
-        &version,
-        &state
-            .crate_storage
-            .create_rand_doc_queue_path()
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
-    )
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+async fn queue_doc_build(
+    state: &AppStateData,
+    normalized_name: &NormalizedName,
+    version: &Version,
+) -> Result<(), StatusCode> {
+    state
+        .db
+        .add_doc_queue(
+            normalized_name,
+            version,
+            &state
+                .crate_storage
+                .create_rand_doc_queue_path()
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(())
 }
@@ -1221,6 +1496,206 @@ mod tests {
         assert_eq!(r.status(), StatusCode::OK);
     }
 
+    fn admin_build_mock_db() -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_get_crate_id()
+            .with(eq(NormalizedName::from_unchecked("foobar".to_string())))
+            .returning(move |_| Ok(Some(1)));
+        mock_db
+            .expect_validate_session()
+            .with(eq("cookie"))
+            .returning(move |_| {
+                Ok(kellnr_db::SessionInfo {
+                    name: "admin".to_string(),
+                    is_admin: true,
+                    is_read_only: false,
+                })
+            });
+        mock_db
+            .expect_crate_version_exists()
+            .with(eq(1), eq("1.0.0"))
+            .returning(move |_, _| Ok(true));
+        mock_db
+    }
+
+    fn doc_build(state: DocBuildState) -> kellnr_db::DocQueueEntry {
+        kellnr_db::DocQueueEntry {
+            id: 1,
+            normalized_name: NormalizedName::from_unchecked("foobar".to_string()),
+            version: "1.0.0".to_string(),
+            path: std::path::PathBuf::default(),
+            state,
+            queued: "2026-10-18 10:00:00".to_string(),
+            started: None,
+            finished: None,
+            duration_ms: None,
+        }
+    }
+
+    async fn retry_build(mock_db: MockDb) -> StatusCode {
+        let (mut settings, storage) = test_deps();
+        settings.docs.enabled = true;
+        app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(
+            Request::post("/builds/foobar/1.0.0/retry")
+                .header(
+                    header::COOKIE,
+                    encode_cookies([(constants::COOKIE_SESSION_ID, "cookie")]),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    async fn get_build(mock_db: MockDb, cookie: bool) -> axum::response::Response {
+        let (settings, storage) = test_deps();
+        let mut request = Request::get("/builds/foobar/1.0.0");
+        if cookie {
+            request = request.header(
+                header::COOKIE,
+                encode_cookies([(constants::COOKIE_SESSION_ID, "cookie")]),
+            );
+        }
+        app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rustdoc_build_returns_state_and_log() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_download_restricted()
+            .returning(|_| Ok(false));
+        mock_db
+            .expect_get_doc_build()
+            .with(
+                eq(NormalizedName::from_unchecked("foobar".to_string())),
+                eq(Version::try_from("1.0.0").unwrap()),
+            )
+            .returning(|_, _| Ok(Some(doc_build(DocBuildState::Failed))));
+        mock_db
+            .expect_get_doc_build_log()
+            .with(eq(1))
+            .returning(|_| Ok(Some("error: broken".to_string())));
+
+        let r = get_build(mock_db, false).await;
+
+        assert_eq!(StatusCode::OK, r.status());
+        let actual = r.into_body().collect().await.unwrap().to_bytes();
+        let actual = serde_json::from_slice::<DocBuildResponse>(&actual).unwrap();
+        assert_eq!(
+            DocBuildResponse {
+                build: doc_build(DocBuildState::Failed).into(),
+                log: Some("error: broken".to_string()),
+            },
+            actual
+        );
+    }
+
+    #[tokio::test]
+    async fn rustdoc_build_not_found() {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_download_restricted()
+            .returning(|_| Ok(false));
+        mock_db.expect_get_doc_build().returning(|_, _| Ok(None));
+
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            get_build(mock_db, false).await.status()
+        );
+    }
+
+    fn restricted_build_mock_db() -> MockDb {
+        let mut mock_db = MockDb::new();
+        mock_db
+            .expect_is_download_restricted()
+            .returning(|_| Ok(true));
+        mock_db
+            .expect_validate_session()
+            .with(eq("cookie"))
+            .returning(move |_| {
+                Ok(kellnr_db::SessionInfo {
+                    name: "user".to_string(),
+                    is_admin: false,
+                    is_read_only: false,
+                })
+            });
+        mock_db.expect_is_crate_user().returning(|_, _| Ok(false));
+        mock_db
+            .expect_is_crate_group_user()
+            .returning(|_, _| Ok(false));
+        mock_db.expect_is_owner().returning(|_, _| Ok(false));
+        mock_db.expect_get_doc_build().never();
+        mock_db
+    }
+
+    #[tokio::test]
+    async fn rustdoc_build_of_restricted_crate_requires_access() {
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            get_build(restricted_build_mock_db(), false).await.status()
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get_build(restricted_build_mock_db(), true).await.status()
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_rust_doc_build_queues_failed_build() {
+        let mut mock_db = admin_build_mock_db();
+        mock_db
+            .expect_get_doc_build()
+            .returning(|_, _| Ok(Some(doc_build(DocBuildState::Failed))));
+        mock_db
+            .expect_add_doc_queue()
+            .with(
+                eq(NormalizedName::from_unchecked("foobar".to_string())),
+                eq(Version::try_from("1.0.0").unwrap()),
+                always(),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(()));
+
+        assert_eq!(StatusCode::OK, retry_build(mock_db).await);
+    }
+
+    #[tokio::test]
+    async fn retry_rust_doc_build_rejects_builds_which_did_not_fail() {
+        for state in [DocBuildState::Queued, DocBuildState::Succeeded] {
+            let mut mock_db = admin_build_mock_db();
+            mock_db
+                .expect_get_doc_build()
+                .returning(move |_, _| Ok(Some(doc_build(state))));
+            mock_db.expect_add_doc_queue().never();
+
+            assert_eq!(StatusCode::CONFLICT, retry_build(mock_db).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_rust_doc_build_without_build() {
+        let mut mock_db = admin_build_mock_db();
+        mock_db.expect_get_doc_build().returning(|_, _| Ok(None));
+
+        assert_eq!(StatusCode::NOT_FOUND, retry_build(mock_db).await);
+    }
+
     #[tokio::test]
     async fn statistic_returns_sparse_statistics() {
         let mut mock_db = MockDb::new();
@@ -1441,7 +1916,7 @@ mod tests {
         let (settings, storage) = test_deps();
 
         mock_db
-            .expect_search_in_crate_name()
+            .expect_search_crates()
             .with(eq("doesnotexist"), eq(false))
             .returning(move |_name, _| Ok(vec![]));
 
@@ -1483,7 +1958,7 @@ mod tests {
 
         let tc = test_crate_summary.clone();
         mock_db
-            .expect_search_in_crate_name()
+            .expect_search_crates()
             .with(eq("hello"), eq(false))
             .returning(move |_, _| Ok(vec![tc.clone()]));
 
@@ -1521,6 +1996,7 @@ mod tests {
             owners: vec!["owner1".to_string(), "owner2".to_string()],
             max_version: "1.0.0".to_string(),
             total_downloads: 5,
+            recent_downloads: 0,
             last_updated: "12-10-2021 05:41:00".to_string(),
             homepage: Some("homepage".to_string()),
             description: Some("description".to_string()),
@@ -1532,6 +2008,7 @@ mod tests {
                 version: "1.0.0".to_string(),
                 created: "12-10-2021 05:41:00".to_string(),
                 downloads: 5,
+                recent_downloads: 0,
                 readme: Some("readme".to_string()),
                 license: Some("MIT".to_string()),
                 license_file: Some("license".to_string()),
@@ -1579,6 +2056,76 @@ mod tests {
         assert_eq!(expected_crate_data, result_crate_data);
     }
 
+    #[tokio::test]
+    async fn crate_data_contains_affecting_advisories() {
+        let mut mock_db = MockDb::new();
+        let (mut settings, storage) = test_deps();
+        settings.advisories.enabled = true;
+
+        let crate_data = CrateData {
+            name: "crate1".to_string(),
+            versions: ["1.0.0", "1.1.0"]
+                .iter()
+                .map(|v| CrateVersionData {
+                    version: (*v).to_string(),
+                    ..CrateVersionData::default()
+                })
+                .collect(),
+            ..CrateData::default()
+        };
+        mock_db
+            .expect_get_crate_data()
+            .returning(move |_| Ok(crate_data.clone()));
+        mock_db
+            .expect_get_advisories()
+            .with(eq(NormalizedName::from_unchecked("crate1".to_string())))
+            .returning(|_| {
+                Ok(["< 1.0.0", ">= 1.1.0"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, patched)| kellnr_db::Advisory {
+                        id: format!("RUSTSEC-2024-000{i}"),
+                        package: "crate1".to_string(),
+                        title: "Use after free".to_string(),
+                        description: String::new(),
+                        date: "2024-01-01".to_string(),
+                        url: None,
+                        aliases: vec![],
+                        informational: None,
+                        patched: vec![(*patched).to_string()],
+                        unaffected: vec![],
+                    })
+                    .collect())
+            });
+
+        let r = app(
+            mock_db,
+            KellnrCrateStorage::new(&settings, storage),
+            settings,
+        )
+        .oneshot(
+            Request::get("/crate_data?name=crate1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(StatusCode::OK, r.status());
+        let body = r.into_body().collect().await.unwrap().to_bytes();
+        let response = serde_json::from_slice::<CrateDataResponse>(&body).unwrap();
+        assert_eq!("crate1", response.data.name);
+        assert_eq!(2, response.advisories.len());
+        assert_eq!(
+            vec!["1.0.0".to_string(), "1.1.0".to_string()],
+            response.advisories[0].affected_versions
+        );
+        assert_eq!(
+            vec!["1.0.0".to_string()],
+            response.advisories[1].affected_versions
+        );
+    }
+
     #[tokio::test]
     async fn crates_get_page() {
         let mut mock_db = MockDb::new();
@@ -1747,6 +2294,11 @@ mod tests {
             .route("/version", get(kellnr_version))
             .route("/statistic", get(statistic))
             .route("/build", post(build_rustdoc))
+            .route("/builds/{package}/{version}", get(rustdoc_build))
+            .route(
+                "/builds/{package}/{version}/retry",
+                post(retry_rustdoc_build),
+            )
             .route("/cratesio_data", get(cratesio_data))
             .route("/settings", get(crate::ui::settings))
             .route("/docs_enabled", get(docs_enabled))
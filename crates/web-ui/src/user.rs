@@ -3,11 +3,15 @@ use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum_extra::extract::PrivateCookieJar;
 use axum_extra::extract::cookie::Cookie;
+use chrono::Utc;
 use kellnr_appstate::{AppState, DbState, TokenCacheState};
+use kellnr_auth::audit::{self, SourceIp};
 use kellnr_auth::token;
+use kellnr_common::token_scope::TokenScopes;
 use kellnr_common::util::generate_rand_string;
+use kellnr_common::webhook::WebhookEvent;
 use kellnr_db::password::generate_salt;
-use kellnr_db::{self, AuthToken, User};
+use kellnr_db::{self, AuditAction, AuthToken, NewAuditEntry, User};
 use kellnr_settings::constants::{COOKIE_SESSION_ID, COOKIE_SESSION_USER};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -19,9 +23,13 @@ use crate::session::{AdminUser, MaybeUser, create_session_jar};
 pub struct NewTokenResponse {
     name: String,
     token: String,
+    scopes: TokenScopes,
 }
 
 /// Add a new auth token for the current user
+///
+/// The token can optionally be limited to some operations and crate name
+/// patterns and be given an expiry date.
 #[utoipa::path(
     post,
     path = "/me/tokens",
@@ -29,6 +37,7 @@ pub struct NewTokenResponse {
     request_body = token::NewTokenReqData,
     responses(
         (status = 200, description = "Token created successfully", body = NewTokenResponse),
+        (status = 400, description = "Invalid crate pattern or expiry date in the past"),
         (status = 401, description = "Not authenticated")
     ),
     security(("session_cookie" = []))
@@ -39,15 +48,24 @@ pub async fn add_token(
     State(cache): TokenCacheState,
     Json(auth_token): Json<token::NewTokenReqData>,
 ) -> Result<Json<NewTokenResponse>, RouteError> {
+    if let Err(e) = auth_token.scopes.validate() {
+        tracing::warn!("Rejected token scopes: {e}");
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+    if auth_token.scopes.is_expired() {
+        return Err(RouteError::Status(StatusCode::BAD_REQUEST));
+    }
+
     let token = token::generate_token();
-    db.add_auth_token(&auth_token.name, &token, user.name())
+    db.add_scoped_auth_token(&auth_token.name, &token, user.name(), &auth_token.scopes)
         .await?;
 
     cache.invalidate_all();
 
     Ok(NewTokenResponse {
-        name: auth_token.name.clone(),
+        name: auth_token.name,
         token,
+        scopes: auth_token.scopes,
     }
     .into())
 }
@@ -221,6 +239,7 @@ pub struct AdminState {
 )]
 pub async fn admin(
     user: AdminUser,
+    source_ip: SourceIp,
     Path(name): Path<String>,
     State(db): DbState,
     State(cache): TokenCacheState,
@@ -232,6 +251,16 @@ pub async fn admin(
     }
 
     db.change_admin_state(&name, admin_state.state).await?;
+    let action = if admin_state.state {
+        AuditAction::GrantAdmin
+    } else {
+        AuditAction::RevokeAdmin
+    };
+    audit::record(
+        &db,
+        NewAuditEntry::new(source_ip.actor(user.name(), None), action, name),
+    )
+    .await;
 
     cache.invalidate_all();
 
@@ -265,6 +294,7 @@ pub async fn delete(
     }
 
     db.delete_user(&name).await?;
+    kellnr_webhooks::notify_user(WebhookEvent::UserDelete, &Utc::now(), &name, &db).await;
 
     cache.invalidate_all();
 
@@ -496,6 +526,7 @@ pub async fn add(
         new_user.is_read_only,
     )
     .await?;
+    kellnr_webhooks::notify_user(WebhookEvent::UserAdd, &Utc::now(), &new_user.name, &db).await;
 
     cache.invalidate_all();
 
@@ -551,10 +582,14 @@ mod tests {
             crate_storage,
             cratesio_storage,
             cratesio_prefetch_sender,
+            upstream_storages: Arc::default(),
+            git_index_sender: None,
             token_cache: cache,
             toolchain_storage: None,
             download_counter,
             proxy_client: kellnr_common::cratesio_downloader::CLIENT.clone(),
+            proxy_policy: Arc::default(),
+            publish_validators: Arc::default(),
         }
     }
 
@@ -567,8 +602,10 @@ mod tests {
                 "existing_token".to_string(),
                 CachedTokenData {
                     user: "test_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -585,9 +622,9 @@ mod tests {
             })
         });
         mock_db
-            .expect_add_auth_token()
+            .expect_add_scoped_auth_token()
             .times(1)
-            .returning(|_, _, _| Ok(()));
+            .returning(|_, _, _, _| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -618,6 +655,45 @@ mod tests {
         assert!(cache.get("existing_token").await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_add_token_with_invalid_scopes_is_rejected() {
+        let cache = Arc::new(TokenCacheManager::new(true, 60, 100));
+
+        let mut mock_db = MockDb::new();
+        mock_db.expect_validate_session().times(1).returning(|_| {
+            Ok(kellnr_db::SessionInfo {
+                name: "test_user".to_string(),
+                is_admin: false,
+                is_read_only: false,
+            })
+        });
+        // No token must be stored
+        mock_db.expect_add_scoped_auth_token().times(0);
+
+        let state = test_state_with_cache(mock_db, cache);
+        let app = Router::new()
+            .route("/add_token", post(add_token))
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::post("/add_token")
+                    .header(
+                        header::COOKIE,
+                        encode_cookies([(COOKIE_SESSION_ID, "session")]),
+                    )
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"name":"ci","scopes":{"operations":["publish"],"crates":["foo/bar"]}}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_delete_token_invalidates_cache() {
         let cache = Arc::new(TokenCacheManager::new(true, 60, 100));
@@ -626,8 +702,10 @@ mod tests {
                 "token_to_keep".to_string(),
                 CachedTokenData {
                     user: "test_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -691,8 +769,10 @@ mod tests {
                 "user_token".to_string(),
                 CachedTokenData {
                     user: "user_to_delete".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -712,6 +792,11 @@ mod tests {
             .times(1)
             .with(eq("user_to_delete"))
             .returning(|_| Ok(()));
+        mock_db
+            .expect_add_webhook_queue()
+            .with(eq(WebhookEvent::UserDelete), eq(None), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -749,8 +834,10 @@ mod tests {
                 "user_token".to_string(),
                 CachedTokenData {
                     user: "target_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false, // Currently NOT read-only
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -940,8 +1027,10 @@ mod tests {
                 "existing_token".to_string(),
                 CachedTokenData {
                     user: "existing_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -960,6 +1049,11 @@ mod tests {
             .expect_add_user()
             .times(1)
             .returning(|_, _, _, _, _| Ok(()));
+        mock_db
+            .expect_add_webhook_queue()
+            .with(eq(WebhookEvent::UserAdd), eq(None), always(), always())
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new().route("/add", post(add)).with_state(state);
@@ -994,8 +1088,10 @@ mod tests {
                 "existing_token".to_string(),
                 CachedTokenData {
                     user: "test_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false,
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -1011,9 +1107,9 @@ mod tests {
             })
         });
         mock_db
-            .expect_add_auth_token()
+            .expect_add_scoped_auth_token()
             .times(1)
-            .returning(|_, _, _| {
+            .returning(|_, _, _, _| {
                 Err(DbError::InitializationError(
                     "Connection timeout".to_string(),
                 ))
@@ -1053,8 +1149,10 @@ mod tests {
                 "user_token".to_string(),
                 CachedTokenData {
                     user: "target_user".to_string(),
+                    token_name: "test_token".to_string(),
                     is_admin: false, // Currently NOT admin
                     is_read_only: false,
+                    scopes: TokenScopes::default(),
                 },
             )
             .await;
@@ -1074,6 +1172,15 @@ mod tests {
             .times(1)
             .with(eq("target_user"), eq(true))
             .returning(|_, _| Ok(()));
+        mock_db
+            .expect_add_audit_entry()
+            .times(1)
+            .withf(|e| {
+                e.action == AuditAction::GrantAdmin
+                    && e.target == "target_user"
+                    && e.actor.user == "admin"
+            })
+            .returning(|_| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -1204,6 +1311,15 @@ mod tests {
             .times(1)
             .with(eq("other_admin"), eq(false)) // Demoting other_admin
             .returning(|_, _| Ok(()));
+        mock_db
+            .expect_add_audit_entry()
+            .times(1)
+            .withf(|e| {
+                e.action == AuditAction::RevokeAdmin
+                    && e.target == "other_admin"
+                    && e.actor.user == "admin"
+            })
+            .returning(|_| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -1296,6 +1412,15 @@ mod tests {
             .times(1)
             .with(eq("admin"), eq(true)) // Self-promotion (no-op but allowed)
             .returning(|_, _| Ok(()));
+        mock_db
+            .expect_add_audit_entry()
+            .times(1)
+            .withf(|e| {
+                e.action == AuditAction::GrantAdmin
+                    && e.target == "admin"
+                    && e.actor.user == "admin"
+            })
+            .returning(|_| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -1381,6 +1506,10 @@ mod tests {
             .times(1)
             .with(eq("other_user"))
             .returning(|_| Ok(()));
+        mock_db
+            .expect_add_webhook_queue()
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
 
         let state = test_state_with_cache(mock_db, cache.clone());
         let app = Router::new()
@@ -1,13 +1,21 @@
+use std::sync::Arc;
+
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
-use kellnr_appstate::DbState;
+use chrono::{TimeDelta, Utc};
+use kellnr_appstate::{DbState, SettingsState};
 use kellnr_auth::token;
-use kellnr_common::webhook::Webhook;
+use kellnr_common::util::generate_rand_string;
+use kellnr_common::webhook::{
+    Webhook, WebhookDelivery, WebhookEvent, WebhookFormat, WebhookSecrets,
+};
+use kellnr_db::DbProvider;
+use kellnr_db::error::DbError;
 use kellnr_error::api_error::{ApiError, ApiResult};
 use tracing::trace;
 
-use crate::types;
+use crate::{format, service, signature, types};
 
 // Re-export types for utoipa
 
@@ -19,6 +27,7 @@ use crate::types;
     request_body = types::RegisterWebhookRequest,
     responses(
         (status = 200, description = "Webhook registered successfully", body = types::RegisterWebhookResponse),
+        (status = 400, description = "No event, invalid secret or invalid template"),
         (status = 401, description = "Admin access required")
     ),
     security(("cargo_token" = []))
@@ -33,16 +42,120 @@ pub async fn register_webhook(
         return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
     }
 
+    let mut events = Vec::new();
+    for event in input.events.into_iter().chain(input.event) {
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return Err(ApiError::new(
+            "At least one event is required",
+            "",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let template = match input.format {
+        WebhookFormat::Template => {
+            let template = input
+                .template
+                .filter(|t| !t.trim().is_empty())
+                .ok_or_else(|| {
+                    ApiError::new(
+                        "A template is required for the template format",
+                        "",
+                        StatusCode::BAD_REQUEST,
+                    )
+                })?;
+            format::validate_template(&template)
+                .map_err(|e| ApiError::from_err(&e, StatusCode::BAD_REQUEST))?;
+            Some(template)
+        }
+        _ => None,
+    };
+    let secret = secret_or_generate(input.secret)?;
     let id = db
-        .register_webhook(Webhook {
-            id: None,
-            event: input.event,
-            callback_url: input.callback_url,
-            name: input.name,
-        })
+        .register_webhook(
+            Webhook {
+                id: None,
+                events,
+                callback_url: input.callback_url,
+                name: input.name,
+                crate_filter: input.crate_filter.filter(|f| !f.is_empty()),
+                group_filter: input.group_filter.filter(|g| !g.is_empty()),
+                format: input.format,
+                template,
+                ..Webhook::default()
+            },
+            Some(secret.clone()),
+        )
         .await?;
 
-    Ok(Json(types::RegisterWebhookResponse { id }))
+    Ok(Json(types::RegisterWebhookResponse { id, secret }))
+}
+
+/// Rotate the secret of a webhook (admin only)
+///
+/// Deliveries are signed with the new and the previous secret until the
+/// grace period ends, so receivers can switch to the new secret without
+/// rejecting deliveries.
+#[utoipa::path(
+    post,
+    path = "/{id}/secret",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    request_body = types::RotateWebhookSecretRequest,
+    responses(
+        (status = 200, description = "Secret rotated successfully", body = types::RotateWebhookSecretResponse),
+        (status = 400, description = "Invalid secret"),
+        (status = 401, description = "Admin access required")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn rotate_webhook_secret(
+    token: token::Token,
+    Path(id): Path<String>,
+    State(db): DbState,
+    Json(input): Json<types::RotateWebhookSecretRequest>,
+) -> ApiResult<Json<types::RotateWebhookSecretResponse>> {
+    trace!(user = %token.user, webhook_id = %id, "Rotating webhook secret");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    let secret = secret_or_generate(input.secret)?;
+    let current = db.get_webhook_secrets(&id).await?;
+    let previous_secret_expires = current
+        .secret
+        .as_ref()
+        .map(|_| Utc::now() + TimeDelta::seconds(i64::from(input.grace_period_seconds)));
+    db.set_webhook_secrets(
+        &id,
+        &WebhookSecrets {
+            secret: Some(secret.clone()),
+            previous_secret: current.secret,
+            previous_secret_expires,
+        },
+    )
+    .await?;
+
+    Ok(Json(types::RotateWebhookSecretResponse {
+        secret,
+        previous_secret_expires,
+    }))
+}
+
+fn secret_or_generate(secret: Option<String>) -> ApiResult<String> {
+    match secret {
+        Some(secret) => {
+            signature::secret_key(&secret)
+                .map_err(|e| ApiError::from_err(&e, StatusCode::BAD_REQUEST))?;
+            Ok(secret)
+        }
+        None => Ok(signature::generate_secret()),
+    }
 }
 
 /// Get a webhook by ID (admin only)
@@ -72,12 +185,42 @@ pub async fn get_webhook(
     let w = db.get_webhook(&id).await?;
     Ok(Json(types::GetWebhookResponse {
         id: w.id.unwrap_or_default(),
-        event: w.event,
+        events: w.events,
         callback_url: w.callback_url,
         name: w.name,
+        crate_filter: w.crate_filter,
+        group_filter: w.group_filter,
+        format: w.format,
+        template: w.template,
+        failing_since: w.failing_since,
+        disabled_at: w.disabled_at,
     }))
 }
 
+/// List the events webhooks can subscribe to (admin only)
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "List of all webhook events", body = types::GetWebhookEventsResponse),
+        (status = 401, description = "Admin access required")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn get_webhook_events(
+    token: token::Token,
+) -> ApiResult<Json<types::GetWebhookEventsResponse>> {
+    trace!(user = %token.user, "Listing webhook events");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    Ok(Json(types::GetWebhookEventsResponse(
+        WebhookEvent::ALL.to_vec(),
+    )))
+}
+
 /// List all webhooks (admin only)
 #[utoipa::path(
     get,
@@ -131,6 +274,9 @@ pub async fn delete_webhook(
 }
 
 /// Test a webhook by sending a test payload (admin only)
+///
+/// The test message is sent in the payload format of the webhook and
+/// recorded in the delivery log of the webhook.
 #[utoipa::path(
     post,
     path = "/{id}/test",
@@ -149,6 +295,7 @@ pub async fn test_webhook(
     token: token::Token,
     Path(id): Path<String>,
     State(db): DbState,
+    State(settings): SettingsState,
 ) -> ApiResult<()> {
     trace!(user = %token.user, webhook_id = %id, "Testing webhook");
     if !token.is_admin {
@@ -156,20 +303,282 @@ pub async fn test_webhook(
     }
 
     let w = db.get_webhook(&id).await?;
-    let client = types::build_client();
-    let resp = client
-        .post(&w.callback_url)
-        .json("Test Payload")
-        .send()
-        .await
-        .map_err(|e| ApiError::new(&e.to_string(), "", StatusCode::INTERNAL_SERVER_ERROR))?;
+    let payload = match w.format {
+        WebhookFormat::Json => serde_json::json!("Test Payload"),
+        _ => format::render(
+            w.format,
+            w.template.as_deref(),
+            &format::test_event(),
+            &settings.origin.url(),
+        )
+        .map_err(|e| ApiError::from_err(&e, StatusCode::INTERNAL_SERVER_ERROR))?,
+    };
+    let message_id = format!("test_{}", generate_rand_string(24));
+    let delivery = send_and_record(&db, &id, &w.callback_url, &message_id, &payload).await?;
+
+    if delivery.is_success() {
+        return Ok(());
+    }
+    match (delivery.status, delivery.error) {
+        (Some(status), _) => Err(ApiError::new(
+            &delivery.response_body.unwrap_or_default(),
+            "",
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )),
+        (None, error) => Err(ApiError::new(
+            &error.unwrap_or_default(),
+            "",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )),
+    }
+}
+
+/// List the latest deliveries of a webhook, newest first (admin only)
+///
+/// Every attempt to deliver a message is listed with its payload, the
+/// response and the latency. Deliveries are kept for the configured number
+/// of days.
+#[utoipa::path(
+    get,
+    path = "/{id}/deliveries",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        types::GetWebhookDeliveriesParams
+    ),
+    responses(
+        (status = 200, description = "Deliveries of the webhook", body = types::GetWebhookDeliveriesResponse),
+        (status = 401, description = "Admin access required")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn get_webhook_deliveries(
+    token: token::Token,
+    Path(id): Path<String>,
+    Query(params): Query<types::GetWebhookDeliveriesParams>,
+    State(db): DbState,
+) -> ApiResult<Json<types::GetWebhookDeliveriesResponse>> {
+    trace!(user = %token.user, webhook_id = %id, "Listing webhook deliveries");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    db.get_webhook(&id).await?;
+    let limit = params
+        .limit
+        .unwrap_or(types::DEFAULT_DELIVERIES_LIMIT)
+        .min(types::MAX_DELIVERIES_LIMIT);
+    let deliveries = db.get_webhook_deliveries(&id, limit).await?;
+    Ok(Json(types::GetWebhookDeliveriesResponse(deliveries)))
+}
+
+/// Send the message of a past delivery again (admin only)
+///
+/// The message keeps its id and payload, but is signed with the current
+/// secrets. The new attempt is recorded and returned, also if the webhook
+/// did not accept it.
+#[utoipa::path(
+    post,
+    path = "/{id}/deliveries/{delivery_id}/redeliver",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        ("delivery_id" = String, Path, description = "ID of the delivery to send again")
+    ),
+    responses(
+        (status = 200, description = "Message sent again", body = WebhookDelivery),
+        (status = 401, description = "Admin access required"),
+        (status = 404, description = "Delivery not found")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn redeliver_webhook_delivery(
+    token: token::Token,
+    Path((id, delivery_id)): Path<(String, String)>,
+    State(db): DbState,
+) -> ApiResult<Json<WebhookDelivery>> {
+    trace!(user = %token.user, webhook_id = %id, delivery_id = %delivery_id, "Redelivering webhook message");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    let not_found = || ApiError::new("Webhook delivery not found", "", StatusCode::NOT_FOUND);
+    let past = match db.get_webhook_delivery(&delivery_id).await {
+        Ok(d) if d.webhook_id == id => d,
+        Ok(_) | Err(DbError::WebhookDeliveryNotFound | DbError::InvalidId(_)) => {
+            return Err(not_found());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let w = db.get_webhook(&id).await?;
+    let delivery =
+        send_and_record(&db, &id, &w.callback_url, &past.message_id, &past.payload).await?;
+    Ok(Json(delivery))
+}
+
+/// Enable a webhook again after it was disabled for failing too long (admin only)
+///
+/// Queued messages are delivered again and new events are queued. Dead
+/// letters stay until they are retried.
+#[utoipa::path(
+    post,
+    path = "/{id}/enable",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook enabled"),
+        (status = 401, description = "Admin access required")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn enable_webhook(
+    token: token::Token,
+    Path(id): Path<String>,
+    State(db): DbState,
+) -> ApiResult<()> {
+    trace!(user = %token.user, webhook_id = %id, "Enabling webhook");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    db.set_webhook_health(&id, None, None).await?;
+    Ok(())
+}
+
+/// List the messages of a webhook that were given up, newest first (admin only)
+///
+/// Messages are given up after the configured number of failed attempts.
+/// Dead letters are kept for the configured number of days.
+#[utoipa::path(
+    get,
+    path = "/{id}/dead-letters",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Dead letters of the webhook", body = types::GetWebhookDeadLettersResponse),
+        (status = 401, description = "Admin access required")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn get_webhook_dead_letters(
+    token: token::Token,
+    Path(id): Path<String>,
+    State(db): DbState,
+) -> ApiResult<Json<types::GetWebhookDeadLettersResponse>> {
+    trace!(user = %token.user, webhook_id = %id, "Listing webhook dead letters");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    db.get_webhook(&id).await?;
+    let dead_letters = db.get_webhook_dead_letters(&id).await?;
+    Ok(Json(types::GetWebhookDeadLettersResponse(dead_letters)))
+}
+
+/// Queue a dead letter again (admin only)
+///
+/// The message is delivered with the next round of the queue and gets the
+/// full number of attempts again.
+#[utoipa::path(
+    post,
+    path = "/{id}/dead-letters/{entry_id}/retry",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        ("entry_id" = String, Path, description = "ID of the dead letter")
+    ),
+    responses(
+        (status = 200, description = "Dead letter queued again"),
+        (status = 401, description = "Admin access required"),
+        (status = 404, description = "Dead letter not found")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn retry_webhook_dead_letter(
+    token: token::Token,
+    Path((id, entry_id)): Path<(String, String)>,
+    State(db): DbState,
+) -> ApiResult<()> {
+    trace!(user = %token.user, webhook_id = %id, entry_id = %entry_id, "Retrying webhook dead letter");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    check_dead_letter(&db, &id, &entry_id).await?;
+    db.retry_webhook_dead_letter(&entry_id).await?;
+    Ok(())
+}
+
+/// Delete a dead letter (admin only)
+#[utoipa::path(
+    delete,
+    path = "/{id}/dead-letters/{entry_id}",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        ("entry_id" = String, Path, description = "ID of the dead letter")
+    ),
+    responses(
+        (status = 200, description = "Dead letter deleted"),
+        (status = 401, description = "Admin access required"),
+        (status = 404, description = "Dead letter not found")
+    ),
+    security(("cargo_token" = []))
+)]
+pub async fn delete_webhook_dead_letter(
+    token: token::Token,
+    Path((id, entry_id)): Path<(String, String)>,
+    State(db): DbState,
+) -> ApiResult<()> {
+    trace!(user = %token.user, webhook_id = %id, entry_id = %entry_id, "Deleting webhook dead letter");
+    if !token.is_admin {
+        return Err(ApiError::new("Unauthorized", "", StatusCode::UNAUTHORIZED));
+    }
+
+    check_dead_letter(&db, &id, &entry_id).await?;
+    db.delete_webhook_queue(&entry_id).await?;
+    Ok(())
+}
 
-    match resp.status() {
-        a if a.as_u16() < 300 => Ok(()),
-        a => Err(ApiError::new(&resp.text().await.unwrap_or_default(), "", a)),
+/// Fail with 404 unless `entry_id` is a dead letter of the webhook `id`.
+async fn check_dead_letter(db: &Arc<dyn DbProvider>, id: &str, entry_id: &str) -> ApiResult<()> {
+    let dead_letters = match db.get_webhook_dead_letters(id).await {
+        Ok(d) => d,
+        Err(DbError::InvalidId(_)) => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if dead_letters.iter().any(|d| d.id == entry_id) {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            "Webhook dead letter not found",
+            "",
+            StatusCode::NOT_FOUND,
+        ))
     }
 }
 
+/// Send a message signed with the active secrets of the webhook and record
+/// the delivery.
+async fn send_and_record(
+    db: &Arc<dyn DbProvider>,
+    id: &str,
+    callback_url: &str,
+    message_id: &str,
+    payload: &serde_json::Value,
+) -> ApiResult<WebhookDelivery> {
+    let secrets = db.get_webhook_secrets(id).await?.active(Utc::now());
+    let client = types::build_client();
+    let delivery = service::deliver(&client, id, callback_url, message_id, &secrets, payload)
+        .await
+        .map_err(|e| ApiError::from_err(&e, StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(db.add_webhook_delivery(&delivery).await?)
+}
+
 #[cfg(test)]
 mod endpoint_tests {
     use std::sync::Arc;
@@ -181,14 +590,18 @@ mod endpoint_tests {
     use axum::routing::{delete, get, post};
     use hyper::header;
     use kellnr_appstate::AppStateData;
-    use kellnr_common::webhook::WebhookEvent;
+    use kellnr_common::webhook::{NewWebhookDelivery, WebhookEvent};
     use kellnr_db::{ConString, Database, DbProvider, SqliteConString};
     use serde::de::DeserializeOwned;
     use tower::ServiceExt;
 
     use super::*;
     use crate::tests::get_test_listener;
-    use crate::types::{GetAllWebhooksResponse, GetWebhookResponse, RegisterWebhookResponse};
+    use crate::types::{
+        GetAllWebhooksResponse, GetWebhookDeadLettersResponse, GetWebhookDeliveriesResponse,
+        GetWebhookEventsResponse, GetWebhookResponse, RegisterWebhookResponse,
+        RotateWebhookSecretResponse,
+    };
 
     const ADMIN_TOKEN: &str = "jkjkashd09128u3019283o1i3j";
     const NON_ADMIN_TOKEN: &str = "kjas09ed8o1i23k1jh";
@@ -216,146 +629,334 @@ mod endpoint_tests {
         let response: RegisterWebhookResponse = parse_response(response).await;
 
         let webhook = db.get_webhook(&response.id).await.unwrap();
-        assert_eq!(webhook.event, WebhookEvent::CrateAdd);
+        assert_eq!(webhook.events, vec![WebhookEvent::CrateAdd]);
         assert_eq!(webhook.callback_url, "http://my-service:8000".to_string());
+
+        let secrets = db.get_webhook_secrets(&response.id).await.unwrap();
+        assert!(signature::secret_key(&response.secret).is_ok());
+        assert_eq!(Some(response.secret), secrets.secret);
     }
 
     #[tokio::test]
-    async fn test_register_webhook_non_admin() {
-        let (router, _) = get_app().await;
+    async fn test_register_webhook_with_events_and_filters() {
+        let (router, db) = get_app().await;
 
-        let payload = "{\"type\": \"crate_add\", \"callback_url\": \"http://my-service:8000\"}";
+        let payload = r#"{
+            "events": ["crate_add", "docs_build_failure", "crate_add"],
+            "callback_url": "http://my-service:8000",
+            "crate_filter": "acme-*",
+            "group_filter": ""
+        }"#;
 
         let response = router
             .clone()
             .oneshot(
                 Request::post("/api/v1/webhook")
                     .header(header::CONTENT_TYPE, "application/json")
-                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
                     .body(Body::from(payload))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(401, response.status().as_u16());
+        assert_eq!(200, response.status().as_u16());
+        let response: RegisterWebhookResponse = parse_response(response).await;
+
+        let webhook = db.get_webhook(&response.id).await.unwrap();
+        assert_eq!(
+            webhook.events,
+            vec![WebhookEvent::CrateAdd, WebhookEvent::DocsBuildFailure]
+        );
+        assert_eq!(webhook.crate_filter, Some("acme-*".to_string()));
+        assert_eq!(webhook.group_filter, None);
     }
 
     #[tokio::test]
-    async fn test_get_webhook() {
+    async fn test_register_webhook_without_event() {
         let (router, db) = get_app().await;
 
-        let id = db.register_webhook(sample_webhook()).await.unwrap();
+        let payload = "{\"events\": [], \"callback_url\": \"http://my-service:8000\"}";
 
         let response = router
             .clone()
             .oneshot(
-                Request::get(format!("/api/v1/webhook/{id}"))
+                Request::post("/api/v1/webhook")
+                    .header(header::CONTENT_TYPE, "application/json")
                     .header(header::AUTHORIZATION, ADMIN_TOKEN)
-                    .body(Body::empty())
+                    .body(Body::from(payload))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(200, response.status().as_u16());
-
-        let response: GetWebhookResponse = parse_response(response).await;
-
-        assert_eq!(response.event, WebhookEvent::CrateUpdate);
-        assert_eq!(response.callback_url, sample_webhook().callback_url);
-        assert_eq!(response.name, sample_webhook().name);
+        assert_eq!(400, response.status().as_u16());
+        assert!(db.get_all_webhooks().await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_get_webhook_non_admin() {
+    async fn test_register_webhook_with_format() {
         let (router, db) = get_app().await;
 
-        let id = db.register_webhook(sample_webhook()).await.unwrap();
+        let payloads = [
+            (
+                r#"{"type": "crate_add", "callback_url": "http://chat:8000", "format": "slack", "template": "{}"}"#,
+                200,
+            ),
+            (
+                r#"{"type": "crate_add", "callback_url": "http://chat:8000", "format": "template", "template": "{\"text\": \"{{summary}}\"}"}"#,
+                200,
+            ),
+            (
+                r#"{"type": "crate_add", "callback_url": "http://chat:8000", "format": "template"}"#,
+                400,
+            ),
+            (
+                r#"{"type": "crate_add", "callback_url": "http://chat:8000", "format": "template", "template": "{{summary}}"}"#,
+                400,
+            ),
+            (
+                r#"{"type": "crate_add", "callback_url": "http://chat:8000", "format": "discord"}"#,
+                422,
+            ),
+        ];
+        for (payload, status) in payloads {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post("/api/v1/webhook")
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                        .body(Body::from(payload))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(status, response.status().as_u16(), "{payload}");
+        }
+
+        let mut webhooks = db.get_all_webhooks().await.unwrap();
+        webhooks.sort_by_key(|w| Into::<&str>::into(w.format));
+        assert_eq!(2, webhooks.len());
+        assert_eq!(WebhookFormat::Slack, webhooks[0].format);
+        // Templates are only kept for the template format
+        assert_eq!(None, webhooks[0].template);
+        assert_eq!(WebhookFormat::Template, webhooks[1].format);
+        assert_eq!(
+            Some("{\"text\": \"{{summary}}\"}".to_string()),
+            webhooks[1].template
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_webhook_events() {
+        let (router, _) = get_app().await;
 
         let response = router
             .clone()
             .oneshot(
-                Request::get(format!("/api/v1/webhook/{id}"))
-                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                Request::get("/api/v1/webhook/events")
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(401, response.status().as_u16());
+        assert_eq!(200, response.status().as_u16());
+        let response: GetWebhookEventsResponse = parse_response(response).await;
+        assert_eq!(WebhookEvent::ALL.to_vec(), response.0);
     }
 
     #[tokio::test]
-    async fn test_get_all_webhooks() {
+    async fn test_register_webhook_with_secret() {
         let (router, db) = get_app().await;
 
-        let mut ids = vec![];
-        for _ in 0..5 {
-            ids.push(db.register_webhook(sample_webhook()).await.unwrap());
-        }
+        let secret = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+        let payload = format!(
+            "{{\"type\": \"crate_add\", \"callback_url\": \"http://my-service:8000\", \"secret\": \"{secret}\"}}"
+        );
 
         let response = router
             .clone()
             .oneshot(
-                Request::get("/api/v1/webhook")
+                Request::post("/api/v1/webhook")
+                    .header(header::CONTENT_TYPE, "application/json")
                     .header(header::AUTHORIZATION, ADMIN_TOKEN)
-                    .body(Body::empty())
+                    .body(Body::from(payload))
                     .unwrap(),
             )
             .await
             .unwrap();
 
         assert_eq!(200, response.status().as_u16());
-        let response: GetAllWebhooksResponse = parse_response(response).await;
+        let response: RegisterWebhookResponse = parse_response(response).await;
+        assert_eq!(secret, response.secret);
 
-        assert_eq!(5, response.0.len());
+        let secrets = db.get_webhook_secrets(&response.id).await.unwrap();
+        assert_eq!(Some(secret.to_string()), secrets.secret);
     }
 
     #[tokio::test]
-    async fn test_get_all_webhooks_non_admin() {
+    async fn test_register_webhook_invalid_secret() {
         let (router, db) = get_app().await;
 
-        let mut ids = vec![];
-        for _ in 0..2 {
-            ids.push(db.register_webhook(sample_webhook()).await.unwrap());
-        }
+        let payload = "{\"type\": \"crate_add\", \"callback_url\": \"http://my-service:8000\", \"secret\": \"password\"}";
 
         let response = router
             .clone()
             .oneshot(
-                Request::get("/api/v1/webhook")
-                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
-                    .body(Body::empty())
+                Request::post("/api/v1/webhook")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::from(payload))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(401, response.status().as_u16());
+        assert_eq!(400, response.status().as_u16());
+        assert!(db.get_all_webhooks().await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_delete_webhook() {
-        let (router, db) = get_app().await;
+    async fn test_register_webhook_non_admin() {
+        let (router, _) = get_app().await;
 
-        let id = db.register_webhook(sample_webhook()).await.unwrap();
+        let payload = "{\"type\": \"crate_add\", \"callback_url\": \"http://my-service:8000\"}";
 
         let response = router
             .clone()
             .oneshot(
-                Request::delete(format!("/api/v1/webhook/{id}"))
-                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
-                    .body(Body::empty())
+                Request::post("/api/v1/webhook")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::from(payload))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(200, response.status().as_u16());
+        assert_eq!(401, response.status().as_u16());
+    }
 
-        let result = db.get_webhook(&id).await;
+    #[tokio::test]
+    async fn test_get_webhook() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+
+        let response: GetWebhookResponse = parse_response(response).await;
+
+        assert_eq!(response.events, vec![WebhookEvent::CrateUpdate]);
+        assert_eq!(response.callback_url, sample_webhook().callback_url);
+        assert_eq!(response.name, sample_webhook().name);
+        assert_eq!(response.crate_filter, sample_webhook().crate_filter);
+    }
+
+    #[tokio::test]
+    async fn test_get_webhook_non_admin() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}"))
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    #[tokio::test]
+    async fn test_get_all_webhooks() {
+        let (router, db) = get_app().await;
+
+        let mut ids = vec![];
+        for _ in 0..5 {
+            ids.push(db.register_webhook(sample_webhook(), None).await.unwrap());
+        }
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/webhook")
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: GetAllWebhooksResponse = parse_response(response).await;
+
+        assert_eq!(5, response.0.len());
+    }
+
+    #[tokio::test]
+    async fn test_get_all_webhooks_non_admin() {
+        let (router, db) = get_app().await;
+
+        let mut ids = vec![];
+        for _ in 0..2 {
+            ids.push(db.register_webhook(sample_webhook(), None).await.unwrap());
+        }
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/webhook")
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    #[tokio::test]
+    async fn test_delete_webhook() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::delete(format!("/api/v1/webhook/{id}"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+
+        let result = db.get_webhook(&id).await;
         assert!(result.is_err());
     }
 
@@ -363,7 +964,7 @@ mod endpoint_tests {
     async fn test_delete_webhook_non_admin() {
         let (router, db) = get_app().await;
 
-        let id = db.register_webhook(sample_webhook()).await.unwrap();
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
 
         let response = router
             .clone()
@@ -382,6 +983,88 @@ mod endpoint_tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_rotate_webhook_secret() {
+        let (router, db) = get_app().await;
+
+        let old = signature::generate_secret();
+        let id = db
+            .register_webhook(sample_webhook(), Some(old.clone()))
+            .await
+            .unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!("/api/v1/webhook/{id}/secret"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::from("{\"grace_period_seconds\": 3600}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: RotateWebhookSecretResponse = parse_response(response).await;
+        assert_ne!(old, response.secret);
+        let expires = response.previous_secret_expires.unwrap();
+        assert!(expires > Utc::now() + TimeDelta::minutes(59));
+
+        let secrets = db.get_webhook_secrets(&id).await.unwrap();
+        assert_eq!(vec![response.secret, old], secrets.active(Utc::now()));
+        assert_eq!(1, secrets.active(expires).len());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_webhook_secret_without_previous_secret() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!("/api/v1/webhook/{id}/secret"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: RotateWebhookSecretResponse = parse_response(response).await;
+        assert_eq!(None, response.previous_secret_expires);
+
+        let secrets = db.get_webhook_secrets(&id).await.unwrap();
+        assert_eq!(vec![response.secret], secrets.active(Utc::now()));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_webhook_secret_non_admin() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!("/api/v1/webhook/{id}/secret"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(401, response.status().as_u16());
+        let secrets = db.get_webhook_secrets(&id).await.unwrap();
+        assert_eq!(None, secrets.secret);
+    }
+
     #[tokio::test]
     async fn test_test_webhook() {
         let (router, db) = get_app().await;
@@ -389,7 +1072,7 @@ mod endpoint_tests {
 
         let mut webhook = sample_webhook();
         webhook.callback_url = "http://0.0.0.0:9977".to_string();
-        let id = db.register_webhook(webhook).await.unwrap();
+        let id = db.register_webhook(webhook, None).await.unwrap();
 
         let response = router
             .clone()
@@ -406,6 +1089,11 @@ mod endpoint_tests {
 
         let listener_resp = listener.rx.recv().await.unwrap();
         assert_eq!(0, listener_resp);
+
+        let deliveries = db.get_webhook_deliveries(&id, 10).await.unwrap();
+        assert_eq!(1, deliveries.len());
+        assert!(deliveries[0].message_id.starts_with("test_"));
+        assert_eq!(Some(200), deliveries[0].status);
     }
 
     #[tokio::test]
@@ -415,7 +1103,7 @@ mod endpoint_tests {
 
         let mut webhook = sample_webhook();
         webhook.callback_url = "http://0.0.0.0:9978".to_string();
-        let id = db.register_webhook(webhook).await.unwrap();
+        let id = db.register_webhook(webhook, None).await.unwrap();
 
         let response = router
             .clone()
@@ -434,6 +1122,369 @@ mod endpoint_tests {
         assert!(listener_resp.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_webhook_deliveries() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let first = db
+            .add_webhook_delivery(&sample_delivery(&id, "msg_1"))
+            .await
+            .unwrap();
+        let second = db
+            .add_webhook_delivery(&sample_delivery(&id, "msg_2"))
+            .await
+            .unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}/deliveries"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: GetWebhookDeliveriesResponse = parse_response(response).await;
+        assert_eq!(vec![second.clone(), first], response.0);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}/deliveries?limit=1"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let response: GetWebhookDeliveriesResponse = parse_response(response).await;
+        assert_eq!(vec![second], response.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_webhook_deliveries_non_admin() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}/deliveries"))
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_webhook_delivery() {
+        let (router, db) = get_app().await;
+        let mut listener = get_test_listener(9976, 200).await;
+
+        let mut webhook = sample_webhook();
+        webhook.callback_url = "http://0.0.0.0:9976".to_string();
+        let id = db.register_webhook(webhook, None).await.unwrap();
+        let failed = db
+            .add_webhook_delivery(&sample_delivery(&id, "msg_1"))
+            .await
+            .unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!(
+                    "/api/v1/webhook/{id}/deliveries/{}/redeliver",
+                    failed.id
+                ))
+                .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                .body(Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: WebhookDelivery = parse_response(response).await;
+        assert_eq!("msg_1", response.message_id);
+        assert_eq!(failed.payload, response.payload);
+        assert_eq!(2, response.attempt);
+        assert_eq!(Some(200), response.status);
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+
+        let deliveries = db.get_webhook_deliveries(&id, 10).await.unwrap();
+        assert_eq!(2, deliveries.len());
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_webhook_delivery_of_other_webhook() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let other = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let delivery = db
+            .add_webhook_delivery(&sample_delivery(&other, "msg_1"))
+            .await
+            .unwrap();
+
+        for delivery_id in [delivery.id.as_str(), "unknown"] {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post(format!(
+                        "/api/v1/webhook/{id}/deliveries/{delivery_id}/redeliver"
+                    ))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(404, response.status().as_u16());
+        }
+        assert_eq!(
+            1,
+            db.get_webhook_deliveries(&other, 10).await.unwrap().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_webhook_delivery_non_admin() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let delivery = db
+            .add_webhook_delivery(&sample_delivery(&id, "msg_1"))
+            .await
+            .unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!(
+                    "/api/v1/webhook/{id}/deliveries/{}/redeliver",
+                    delivery.id
+                ))
+                .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                .body(Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(401, response.status().as_u16());
+        assert_eq!(1, db.get_webhook_deliveries(&id, 10).await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_dead_letters() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let entry_id = add_dead_letter(&db, &id).await;
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}/dead-letters"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let response: GetWebhookDeadLettersResponse = parse_response(response).await;
+        assert_eq!(1, response.0.len());
+        assert_eq!(entry_id, response.0[0].id);
+        assert_eq!(
+            serde_json::json!({"type": "crate_update"}),
+            response.0[0].payload
+        );
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!(
+                    "/api/v1/webhook/{id}/dead-letters/{entry_id}/retry"
+                ))
+                .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                .body(Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        assert!(db.get_webhook_dead_letters(&id).await.unwrap().is_empty());
+        assert_eq!(1, db.get_webhook_queue_length().await.unwrap());
+
+        // A queued message is no dead letter
+        let response = router
+            .clone()
+            .oneshot(
+                Request::delete(format!("/api/v1/webhook/{id}/dead-letters/{entry_id}"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(404, response.status().as_u16());
+        assert_eq!(1, db.get_webhook_queue_length().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_webhook_dead_letter() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let other = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let entry_id = add_dead_letter(&db, &id).await;
+        let other_entry_id = db.get_webhook_dead_letters(&other).await.unwrap()[0]
+            .id
+            .clone();
+
+        for (entry, status) in [
+            (other_entry_id.as_str(), 404),
+            ("unknown", 404),
+            (entry_id.as_str(), 200),
+        ] {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::delete(format!("/api/v1/webhook/{id}/dead-letters/{entry}"))
+                        .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(status, response.status().as_u16());
+        }
+        assert!(db.get_webhook_dead_letters(&id).await.unwrap().is_empty());
+        assert_eq!(1, db.get_webhook_dead_letters(&other).await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_dead_letters_non_admin() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let entry_id = add_dead_letter(&db, &id).await;
+
+        for request in [
+            Request::get(format!("/api/v1/webhook/{id}/dead-letters")),
+            Request::post(format!(
+                "/api/v1/webhook/{id}/dead-letters/{entry_id}/retry"
+            )),
+            Request::delete(format!("/api/v1/webhook/{id}/dead-letters/{entry_id}")),
+        ] {
+            let response = router
+                .clone()
+                .oneshot(
+                    request
+                        .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(401, response.status().as_u16());
+        }
+        assert_eq!(1, db.get_webhook_dead_letters(&id).await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_enable_webhook() {
+        let (router, db) = get_app().await;
+
+        let id = db.register_webhook(sample_webhook(), None).await.unwrap();
+        let now = Utc::now();
+        db.set_webhook_health(&id, Some(now), Some(now))
+            .await
+            .unwrap();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v1/webhook/{id}"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let response: GetWebhookResponse = parse_response(response).await;
+        assert!(response.failing_since.is_some());
+        assert!(response.disabled_at.is_some());
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!("/api/v1/webhook/{id}/enable"))
+                    .header(header::AUTHORIZATION, NON_ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(401, response.status().as_u16());
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(format!("/api/v1/webhook/{id}/enable"))
+                    .header(header::AUTHORIZATION, ADMIN_TOKEN)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(200, response.status().as_u16());
+        let webhook = db.get_webhook(&id).await.unwrap();
+        assert_eq!(None, webhook.failing_since);
+        assert_eq!(None, webhook.disabled_at);
+    }
+
+    /// Queue a message for every webhook and give all of them up. Returns
+    /// the id of the dead letter of the webhook `id`.
+    async fn add_dead_letter(db: &Arc<Database>, id: &str) -> String {
+        db.add_webhook_queue(
+            WebhookEvent::CrateUpdate,
+            Some("test-crate".to_string()),
+            None,
+            serde_json::json!({"type": "crate_update"}),
+        )
+        .await
+        .unwrap();
+        let pending = db
+            .get_pending_webhook_queue_entries(Utc::now())
+            .await
+            .unwrap();
+        for entry in &pending {
+            db.dead_letter_webhook_queue(&entry.id, Utc::now())
+                .await
+                .unwrap();
+        }
+        db.get_webhook_dead_letters(id).await.unwrap()[0].id.clone()
+    }
+
     async fn get_app() -> (Router, Arc<Database>) {
         let con_string = ConString::Sqlite(SqliteConString::new(
             std::path::Path::new(":memory:"),
@@ -462,9 +1513,26 @@ mod endpoint_tests {
         let routes = Router::new()
             .route("/", get(get_all_webhooks))
             .route("/", post(register_webhook))
+            .route("/events", get(get_webhook_events))
             .route("/{id}", get(get_webhook))
             .route("/{id}", delete(delete_webhook))
-            .route("/{id}/test", get(test_webhook));
+            .route("/{id}/test", get(test_webhook))
+            .route("/{id}/secret", post(rotate_webhook_secret))
+            .route("/{id}/deliveries", get(get_webhook_deliveries))
+            .route(
+                "/{id}/deliveries/{delivery_id}/redeliver",
+                post(redeliver_webhook_delivery),
+            )
+            .route("/{id}/enable", post(enable_webhook))
+            .route("/{id}/dead-letters", get(get_webhook_dead_letters))
+            .route(
+                "/{id}/dead-letters/{entry_id}/retry",
+                post(retry_webhook_dead_letter),
+            )
+            .route(
+                "/{id}/dead-letters/{entry_id}",
+                delete(delete_webhook_dead_letter),
+            );
 
         (
             Router::new()
@@ -481,9 +1549,24 @@ mod endpoint_tests {
     fn sample_webhook() -> Webhook {
         Webhook {
             id: None,
-            event: WebhookEvent::CrateUpdate,
+            events: vec![WebhookEvent::CrateUpdate],
             callback_url: "https://some-callback:8000".to_string(),
             name: Some("My callback".to_string()),
+            crate_filter: Some("*".to_string()),
+            group_filter: None,
+            ..Webhook::default()
+        }
+    }
+
+    fn sample_delivery(webhook_id: &str, message_id: &str) -> NewWebhookDelivery {
+        NewWebhookDelivery {
+            webhook_id: webhook_id.to_string(),
+            message_id: message_id.to_string(),
+            payload: serde_json::json!({"type": "crate_update"}),
+            status: Some(500),
+            response_body: Some("Internal Server Error".to_string()),
+            error: None,
+            duration_ms: 42,
         }
     }
 }
@@ -0,0 +1,475 @@
+//! Payload formats of webhook deliveries.
+//!
+//! Events are queued in Kellnr's native JSON format
+//! `{"type": ..., "timestamp": ..., "data": {...}}` and converted to the
+//! format of the webhook when they are delivered, so chat services can
+//! receive them without a translation service in between.
+
+use kellnr_common::webhook::{WebhookEvent, WebhookFormat};
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::types::WebhookError;
+
+/// Placeholders of templates, which are replaced by the JSON escaped field
+/// of the event. `{{data}}` is replaced by the event data as JSON.
+const TEMPLATE_PLACEHOLDERS: [&str; 9] = [
+    "event",
+    "timestamp",
+    "crate_name",
+    "version",
+    "actor",
+    "user",
+    "url",
+    "summary",
+    "data",
+];
+
+/// Convert a queued event to the payload of a webhook with the given format.
+/// `base_url` is the URL of the Kellnr instance, used for links to crates.
+pub fn render(
+    format: WebhookFormat,
+    template: Option<&str>,
+    payload: &Value,
+    base_url: &str,
+) -> Result<Value, WebhookError> {
+    let fields = || EventFields::new(payload, base_url);
+    match format {
+        WebhookFormat::Json => Ok(payload.clone()),
+        WebhookFormat::Slack => Ok(slack(&fields())),
+        WebhookFormat::Teams => Ok(teams(&fields())),
+        WebhookFormat::AdaptiveCard => Ok(adaptive_card(&fields())),
+        WebhookFormat::Template => {
+            let template = template
+                .ok_or_else(|| WebhookError::InvalidTemplate("template is missing".to_string()))?;
+            render_template(template, &fields())
+        }
+    }
+}
+
+/// Check that a template results in valid JSON.
+pub fn validate_template(template: &str) -> Result<(), WebhookError> {
+    let sample = json!({
+        "type": WebhookEvent::CrateAdd,
+        "timestamp": "2026-01-01T00:00:00Z",
+        "data": {
+            "crate_name": "my-crate",
+            "crate_version": "1.0.0",
+            "actor": "admin"
+        }
+    });
+    render_template(
+        template,
+        &EventFields::new(&sample, "http://localhost:8000"),
+    )
+    .map(|_| ())
+}
+
+/// Event sent to test a webhook
+pub fn test_event() -> Value {
+    json!({
+        "type": "test",
+        "timestamp": chrono::Utc::now(),
+        "data": {}
+    })
+}
+
+/// Fields of an event, which are shown in chat messages and can be used
+/// in templates
+struct EventFields {
+    event: String,
+    timestamp: String,
+    crate_name: Option<String>,
+    version: Option<String>,
+    actor: Option<String>,
+    user: Option<String>,
+    url: Option<String>,
+    summary: String,
+    data: Value,
+}
+
+impl EventFields {
+    fn new(payload: &Value, base_url: &str) -> Self {
+        let data = payload.get("data").cloned().unwrap_or(Value::Null);
+        let field = |key: &str| {
+            data.get(key)
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+        };
+        let event_name = payload
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let event = WebhookEvent::try_from(event_name).ok();
+
+        let crate_name = field("crate_name");
+        let version = field("crate_version").or_else(|| field("version"));
+        let url = match event {
+            Some(WebhookEvent::AdvisoryAdd) => field("url"),
+            Some(WebhookEvent::CrateDelete) | None => None,
+            Some(WebhookEvent::VersionDelete) => crate_url(base_url, crate_name.as_deref(), None),
+            Some(_) => crate_url(base_url, crate_name.as_deref(), version.as_deref()),
+        };
+
+        let mut fields = Self {
+            event: event_name.to_string(),
+            timestamp: payload
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            crate_name,
+            version,
+            actor: field("actor"),
+            user: field("user"),
+            url,
+            summary: String::new(),
+            data,
+        };
+        fields.summary = summary(event, &fields);
+        fields
+    }
+
+    fn get(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "event" => Some(&self.event),
+            "timestamp" => Some(&self.timestamp),
+            "crate_name" => self.crate_name.as_deref(),
+            "version" => self.version.as_deref(),
+            "actor" => self.actor.as_deref(),
+            "user" => self.user.as_deref(),
+            "url" => self.url.as_deref(),
+            "summary" => Some(&self.summary),
+            _ => None,
+        }
+    }
+
+    /// Name and value of the fields shown in cards
+    fn facts(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("Crate", self.crate_name.as_deref()),
+            ("Version", self.version.as_deref()),
+            ("User", self.user.as_deref()),
+            ("By", self.actor.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+/// Link to the crate page of the UI
+fn crate_url(base_url: &str, crate_name: Option<&str>, version: Option<&str>) -> Option<String> {
+    let mut url = Url::parse(&format!("{base_url}/crate")).ok()?;
+    url.query_pairs_mut().append_pair("name", crate_name?);
+    if let Some(version) = version {
+        url.query_pairs_mut().append_pair("version", version);
+    }
+    Some(url.into())
+}
+
+/// One line description of the event, e.g. `my-crate 1.0.0 published by alice`
+fn summary(event: Option<WebhookEvent>, fields: &EventFields) -> String {
+    let data = |key: &str| {
+        fields
+            .data
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    };
+    let name = fields.crate_name.as_deref().unwrap_or_default();
+    let krate = match &fields.version {
+        Some(version) => format!("{name} {version}"),
+        None => name.to_string(),
+    };
+    let user = fields.user.as_deref().unwrap_or_default();
+
+    let text = match event {
+        Some(WebhookEvent::CrateAdd | WebhookEvent::CrateUpdate) => format!("{krate} published"),
+        Some(WebhookEvent::CrateYank) => format!("{krate} yanked"),
+        Some(WebhookEvent::CrateUnyank) => format!("{krate} unyanked"),
+        Some(WebhookEvent::CrateDelete | WebhookEvent::VersionDelete) => {
+            format!("{krate} deleted")
+        }
+        Some(WebhookEvent::OwnerAdd) => format!("{user} added as owner of {name}"),
+        Some(WebhookEvent::OwnerRemove) => format!("{user} removed as owner of {name}"),
+        Some(WebhookEvent::AclChange) => format!("Access to {name} changed"),
+        Some(WebhookEvent::UserAdd) => format!("User {user} added"),
+        Some(WebhookEvent::UserDelete) => format!("User {user} deleted"),
+        Some(WebhookEvent::DocsBuildSuccess) => format!("Documentation of {krate} built"),
+        Some(WebhookEvent::DocsBuildFailure) => format!("Documentation build of {krate} failed"),
+        Some(WebhookEvent::ToolchainAdd) => format!(
+            "Toolchain {} {} for {} added",
+            data("name"),
+            data("version"),
+            data("target")
+        ),
+        Some(WebhookEvent::ToolchainChannelChange) => format!(
+            "Channel {} points to toolchain {} {}",
+            data("channel"),
+            data("name"),
+            data("version")
+        ),
+        Some(WebhookEvent::AdvisoryAdd) => format!(
+            "Advisory {} affects {name}: {}",
+            data("advisory_id"),
+            data("title")
+        ),
+        None => "Test message from Kellnr".to_string(),
+    };
+
+    match &fields.actor {
+        Some(actor) => format!("{text} by {actor}"),
+        None => text,
+    }
+}
+
+/// Message for Slack compatible incoming webhooks. Links are plain URLs,
+/// which Slack, Mattermost and Matrix clients all turn into links.
+fn slack(fields: &EventFields) -> Value {
+    let text = match &fields.url {
+        Some(url) => format!("{}\n{url}", fields.summary),
+        None => fields.summary.clone(),
+    };
+    json!({ "text": text })
+}
+
+/// Legacy `MessageCard` of Microsoft Teams connectors
+fn teams(fields: &EventFields) -> Value {
+    let facts: Vec<_> = fields
+        .facts()
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+    let mut card = json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": fields.summary,
+        "title": fields.summary,
+        "sections": [{ "facts": facts }]
+    });
+    if let Some(url) = &fields.url {
+        card["potentialAction"] = json!([{
+            "@type": "OpenUri",
+            "name": "Open in Kellnr",
+            "targets": [{ "os": "default", "uri": url }]
+        }]);
+    }
+    card
+}
+
+/// Adaptive Card in a message, as expected by Microsoft Teams workflows
+fn adaptive_card(fields: &EventFields) -> Value {
+    let facts: Vec<_> = fields
+        .facts()
+        .into_iter()
+        .map(|(title, value)| json!({ "title": title, "value": value }))
+        .collect();
+    let mut content = json!({
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "body": [
+            {
+                "type": "TextBlock",
+                "text": fields.summary,
+                "weight": "Bolder",
+                "wrap": true
+            },
+            { "type": "FactSet", "facts": facts }
+        ]
+    });
+    if let Some(url) = &fields.url {
+        content["actions"] = json!([{
+            "type": "Action.OpenUrl",
+            "title": "Open in Kellnr",
+            "url": url
+        }]);
+    }
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": content
+        }]
+    })
+}
+
+/// Replace the placeholders of a template and parse the result as JSON.
+/// Field values are JSON escaped without quotes, so they can be used inside
+/// strings, e.g. `{"text": "{{crate_name}} {{version}} released"}`.
+fn render_template(template: &str, fields: &EventFields) -> Result<Value, WebhookError> {
+    let mut rendered = template.to_string();
+    for placeholder in TEMPLATE_PLACEHOLDERS {
+        let pattern = format!("{{{{{placeholder}}}}}");
+        if !rendered.contains(&pattern) {
+            continue;
+        }
+        let value = if placeholder == "data" {
+            fields.data.to_string()
+        } else {
+            escape(fields.get(placeholder).unwrap_or_default())
+        };
+        rendered = rendered.replace(&pattern, &value);
+    }
+    serde_json::from_str(&rendered).map_err(|e| {
+        WebhookError::InvalidTemplate(format!("template does not result in valid JSON: {e}"))
+    })
+}
+
+/// JSON string escaping without the surrounding quotes
+fn escape(value: &str) -> String {
+    let quoted = Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: &str = "https://kellnr.example.com";
+
+    fn crate_add() -> Value {
+        json!({
+            "type": "crate_add",
+            "timestamp": "2026-10-18T12:00:00Z",
+            "data": {
+                "crate_name": "my-crate",
+                "crate_version": "1.0.0+build.1",
+                "actor": "alice"
+            }
+        })
+    }
+
+    fn render_crate_add(format: WebhookFormat, template: Option<&str>) -> Value {
+        render(format, template, &crate_add(), BASE_URL).unwrap()
+    }
+
+    #[test]
+    fn json_is_sent_unchanged() {
+        assert_eq!(crate_add(), render_crate_add(WebhookFormat::Json, None));
+    }
+
+    #[test]
+    fn slack_message_with_link() {
+        assert_eq!(
+            json!({
+                "text": "my-crate 1.0.0+build.1 published by alice\nhttps://kellnr.example.com/crate?name=my-crate&version=1.0.0%2Bbuild.1"
+            }),
+            render_crate_add(WebhookFormat::Slack, None)
+        );
+    }
+
+    #[test]
+    fn slack_message_without_crate() {
+        let payload = json!({
+            "type": "user_add",
+            "timestamp": "2026-10-18T12:00:00Z",
+            "data": { "user": "bob" }
+        });
+
+        let message = render(WebhookFormat::Slack, None, &payload, BASE_URL).unwrap();
+
+        assert_eq!(json!({ "text": "User bob added" }), message);
+    }
+
+    #[test]
+    fn teams_message_card() {
+        let card = render_crate_add(WebhookFormat::Teams, None);
+
+        assert_eq!("MessageCard", card["@type"]);
+        assert_eq!("my-crate 1.0.0+build.1 published by alice", card["title"]);
+        assert_eq!(
+            json!([
+                { "name": "Crate", "value": "my-crate" },
+                { "name": "Version", "value": "1.0.0+build.1" },
+                { "name": "By", "value": "alice" }
+            ]),
+            card["sections"][0]["facts"]
+        );
+        assert_eq!(
+            "https://kellnr.example.com/crate?name=my-crate&version=1.0.0%2Bbuild.1",
+            card["potentialAction"][0]["targets"][0]["uri"]
+        );
+    }
+
+    #[test]
+    fn adaptive_card_message() {
+        let message = render_crate_add(WebhookFormat::AdaptiveCard, None);
+
+        let attachment = &message["attachments"][0];
+        assert_eq!(
+            "application/vnd.microsoft.card.adaptive",
+            attachment["contentType"]
+        );
+        assert_eq!("AdaptiveCard", attachment["content"]["type"]);
+        assert_eq!(
+            "my-crate 1.0.0+build.1 published by alice",
+            attachment["content"]["body"][0]["text"]
+        );
+        assert_eq!(
+            "Action.OpenUrl",
+            attachment["content"]["actions"][0]["type"]
+        );
+    }
+
+    #[test]
+    fn deleted_crate_has_no_link() {
+        let payload = json!({
+            "type": "crate_delete",
+            "timestamp": "2026-10-18T12:00:00Z",
+            "data": { "crate_name": "my-crate", "actor": "alice" }
+        });
+
+        let card = render(WebhookFormat::Teams, None, &payload, BASE_URL).unwrap();
+
+        assert_eq!("my-crate deleted by alice", card["summary"]);
+        assert!(card.get("potentialAction").is_none());
+    }
+
+    #[test]
+    fn template_placeholders_are_escaped() {
+        let template = r#"{"msg": "{{crate_name}} {{version}} by {{actor}}", "link": "{{url}}", "raw": {{data}}}"#;
+        let mut payload = crate_add();
+        payload["data"]["actor"] = json!("alice \"admin\"");
+
+        let message = render(WebhookFormat::Template, Some(template), &payload, BASE_URL).unwrap();
+
+        assert_eq!("my-crate 1.0.0+build.1 by alice \"admin\"", message["msg"]);
+        assert_eq!(
+            "https://kellnr.example.com/crate?name=my-crate&version=1.0.0%2Bbuild.1",
+            message["link"]
+        );
+        assert_eq!(payload["data"], message["raw"]);
+    }
+
+    #[test]
+    fn missing_fields_are_empty_in_templates() {
+        let payload = json!({ "type": "user_delete", "timestamp": "", "data": { "user": "bob" } });
+
+        let message = render(
+            WebhookFormat::Template,
+            Some(r#"{"text": "{{summary}}", "crate": "{{crate_name}}"}"#),
+            &payload,
+            BASE_URL,
+        )
+        .unwrap();
+
+        assert_eq!(json!({ "text": "User bob deleted", "crate": "" }), message);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(validate_template(r#"{"text": "{{summary}}"}"#).is_ok());
+        assert!(validate_template(r#"{"text": {{summary}}}"#).is_err());
+        assert!(validate_template("not json").is_err());
+        assert!(render(WebhookFormat::Template, None, &crate_add(), BASE_URL).is_err());
+    }
+
+    #[test]
+    fn test_event_is_rendered() {
+        let message = render(WebhookFormat::Slack, None, &test_event(), BASE_URL).unwrap();
+
+        assert_eq!(json!({ "text": "Test message from Kellnr" }), message);
+    }
+}
@@ -2,37 +2,156 @@ use chrono::{DateTime, Utc};
 use kellnr_common::normalized_name::NormalizedName;
 use kellnr_common::version::Version;
 use kellnr_common::webhook::WebhookEvent;
-use kellnr_db::DbProvider;
-use serde_json::json;
+use kellnr_db::{AdvisoryMatch, DbProvider};
+use serde_json::{Value, json};
 
 pub mod endpoints;
+pub mod format;
 mod service;
+pub mod signature;
 #[cfg(test)]
 mod tests;
 pub mod types;
 
 pub use endpoints::{
-    delete_webhook, get_all_webhooks, get_webhook, register_webhook, test_webhook,
+    delete_webhook, delete_webhook_dead_letter, enable_webhook, get_all_webhooks, get_webhook,
+    get_webhook_dead_letters, get_webhook_deliveries, get_webhook_events,
+    redeliver_webhook_delivery, register_webhook, retry_webhook_dead_letter, rotate_webhook_secret,
+    test_webhook,
 };
-pub use service::run_webhook_service;
+pub use service::{DeliverySettings, run_webhook_service};
 
-pub async fn notify_crate(
+/// Queue `event` with the event specific `data` for every webhook accepting
+/// it. `crate_name` is the crate the event is about, which webhooks filter on.
+pub async fn notify(
     event: WebhookEvent,
     timestamp: &DateTime<Utc>,
-    normalized_name: &NormalizedName,
-    version: &Version,
+    crate_name: Option<String>,
+    data: Value,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    queue(event, timestamp, crate_name, None, data, db).await;
+}
+
+async fn queue(
+    event: WebhookEvent,
+    timestamp: &DateTime<Utc>,
+    crate_name: Option<String>,
+    crate_groups: Option<Vec<String>>,
+    data: Value,
     db: &std::sync::Arc<dyn DbProvider>,
 ) {
     let payload = json!({
         "type": event,
         "timestamp": timestamp,
-        "data": {
-            "crate_name": format!("{}", normalized_name),
-            "crate_version": version
-        }
+        "data": data
     });
 
-    if let Err(err) = db.add_webhook_queue(event, payload).await {
+    if let Err(err) = db
+        .add_webhook_queue(event, crate_name, crate_groups, payload)
+        .await
+    {
         tracing::error!("Db: {err:?}");
     }
 }
+
+/// Queue `event` about a version of a crate. `actor` is the user who caused
+/// the event, if any.
+pub async fn notify_crate(
+    event: WebhookEvent,
+    timestamp: &DateTime<Utc>,
+    normalized_name: &NormalizedName,
+    version: &Version,
+    actor: Option<&str>,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    notify_crate_change(
+        event,
+        timestamp,
+        normalized_name,
+        json!({ "crate_version": version, "actor": actor }),
+        db,
+    )
+    .await;
+}
+
+/// Queue `event` about a crate, e.g. an added owner. The name of the crate is
+/// added to the event specific `data`.
+pub async fn notify_crate_change(
+    event: WebhookEvent,
+    timestamp: &DateTime<Utc>,
+    normalized_name: &NormalizedName,
+    mut data: Value,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    if let Value::Object(fields) = &mut data {
+        fields.insert("crate_name".to_string(), json!(normalized_name.to_string()));
+    }
+    notify(
+        event,
+        timestamp,
+        Some(normalized_name.to_string()),
+        data,
+        db,
+    )
+    .await;
+}
+
+/// Queue `event` about a crate that is deleted, like
+/// [`notify_crate_change`]. The groups of the crate must be read before it
+/// is deleted, as webhooks filtering on groups cannot be matched otherwise.
+pub async fn notify_crate_deletion(
+    event: WebhookEvent,
+    timestamp: &DateTime<Utc>,
+    normalized_name: &NormalizedName,
+    crate_groups: Vec<String>,
+    mut data: Value,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    if let Value::Object(fields) = &mut data {
+        fields.insert("crate_name".to_string(), json!(normalized_name.to_string()));
+    }
+    queue(
+        event,
+        timestamp,
+        Some(normalized_name.to_string()),
+        Some(crate_groups),
+        data,
+        db,
+    )
+    .await;
+}
+
+/// Queue `event` about a user, e.g. a new user.
+pub async fn notify_user(
+    event: WebhookEvent,
+    timestamp: &DateTime<Utc>,
+    name: &str,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    notify(event, timestamp, None, json!({ "user": name }), db).await;
+}
+
+pub async fn notify_advisory(
+    timestamp: &DateTime<Utc>,
+    advisory: &AdvisoryMatch,
+    db: &std::sync::Arc<dyn DbProvider>,
+) {
+    let data = json!({
+        "advisory_id": advisory.advisory.id,
+        "crate_name": advisory.advisory.package,
+        "title": advisory.advisory.title,
+        "url": advisory.advisory.url,
+        "hosted_versions": advisory.hosted_versions,
+        "cached_versions": advisory.cached_versions
+    });
+
+    notify(
+        WebhookEvent::AdvisoryAdd,
+        timestamp,
+        Some(advisory.advisory.package.clone()),
+        data,
+        db,
+    )
+    .await;
+}
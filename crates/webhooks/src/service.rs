@@ -1,74 +1,364 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use chrono::{DateTime, TimeDelta, Utc};
-use kellnr_common::webhook::WebhookQueue;
+use kellnr_common::metrics;
+use kellnr_common::webhook::{NewWebhookDelivery, WebhookQueue};
 use kellnr_db::DbProvider;
+use serde_json::Value;
+use tokio::task::{Id, JoinError, JoinSet};
 
 use crate::types::WebhookError;
+use crate::{format, signature};
+
+/// Max. number of bytes of a response body kept in the delivery log
+const MAX_RESPONSE_EXCERPT: usize = 4096;
+
+/// Settings of the delivery of queued events
+#[derive(Debug, Clone)]
+pub struct DeliverySettings {
+    /// URL of the Kellnr instance, used for links in chat messages
+    pub base_url: String,
+    /// Attempts to deliver a message before it is moved to the dead letters
+    pub max_attempts: u32,
+    /// Time a webhook may keep failing before it is disabled, never if `None`
+    pub disable_after: Option<TimeDelta>,
+}
 
-pub fn run_webhook_service(db: Arc<dyn DbProvider>) {
+/// Deliver queued events in the background. Every webhook is served by its
+/// own task, so a slow or unreachable host does not delay the others.
+pub fn run_webhook_service(db: Arc<dyn DbProvider>, settings: DeliverySettings) {
     tokio::spawn(async move {
         let http_client = crate::types::build_client();
+        let settings = Arc::new(settings);
+        let mut deliveries = Deliveries::default();
         loop {
             tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            if let Err(err) = handle_queue(&db, &http_client).await {
+            deliveries.reap();
+            if let Err(err) = handle_queue(&db, &http_client, &settings, &mut deliveries).await {
                 tracing::error!("Webhook queue failed. Reason {err}");
             }
         }
     });
 }
 
+/// Delivery tasks in progress, at most one per webhook
+#[derive(Default)]
+struct Deliveries {
+    /// Tasks return the time their webhook is tried again if it failed
+    tasks: JoinSet<Option<DateTime<Utc>>>,
+    /// Webhook served by each task
+    webhooks: HashMap<Id, String>,
+    /// Failing webhooks by the time they are tried again
+    retry_at: HashMap<String, DateTime<Utc>>,
+}
+
+impl Deliveries {
+    /// `false` if the webhook is served by a task or backs off.
+    fn is_ready(&self, webhook_id: &str, now: DateTime<Utc>) -> bool {
+        !self.webhooks.values().any(|id| id == webhook_id)
+            && self.retry_at.get(webhook_id).is_none_or(|at| *at <= now)
+    }
+
+    fn spawn<F>(&mut self, webhook_id: String, task: F)
+    where
+        F: Future<Output = Option<DateTime<Utc>>> + Send + 'static,
+    {
+        let handle = self.tasks.spawn(task);
+        self.webhooks.insert(handle.id(), webhook_id);
+    }
+
+    /// Forget finished tasks, so their webhooks are served again.
+    fn reap(&mut self) {
+        while let Some(result) = self.tasks.try_join_next_with_id() {
+            self.finished(result);
+        }
+    }
+
+    #[cfg(test)]
+    async fn join_all(&mut self) {
+        while let Some(result) = self.tasks.join_next_with_id().await {
+            self.finished(result);
+        }
+    }
+
+    fn finished(&mut self, result: Result<(Id, Option<DateTime<Utc>>), JoinError>) {
+        match result {
+            Ok((id, retry_at)) => {
+                let Some(webhook_id) = self.webhooks.remove(&id) else {
+                    return;
+                };
+                match retry_at {
+                    Some(at) => self.retry_at.insert(webhook_id, at),
+                    None => self.retry_at.remove(&webhook_id),
+                };
+            }
+            Err(err) => {
+                tracing::error!("Webhook delivery task failed. Reason {err}");
+                self.webhooks.remove(&err.id());
+            }
+        }
+    }
+}
+
 async fn handle_queue(
     db: &Arc<dyn DbProvider>,
     client: &reqwest::Client,
+    settings: &Arc<DeliverySettings>,
+    deliveries: &mut Deliveries,
 ) -> Result<(), WebhookError> {
     let now = Utc::now();
     let pending = db.get_pending_webhook_queue_entries(now).await?;
 
+    // Entries stay ordered by their next attempt within a webhook
+    let mut by_webhook: HashMap<String, Vec<WebhookQueue>> = HashMap::new();
     for entry in pending {
-        let request = client.post(&entry.callback_url).json(&entry.payload);
+        if deliveries.is_ready(&entry.webhook_id, now) {
+            by_webhook
+                .entry(entry.webhook_id.clone())
+                .or_default()
+                .push(entry);
+        }
+    }
+
+    for (webhook_id, entries) in by_webhook {
+        let db = db.clone();
+        let client = client.clone();
+        let settings = settings.clone();
+        deliveries.spawn(webhook_id, async move {
+            handle_webhook(&db, &client, &settings, entries).await
+        });
+    }
+    Ok(())
+}
 
-        match request.send().await {
-            Ok(resp) if resp.status().as_u16() < 300 => {
-                if let Err(err) = db.delete_webhook_queue(&entry.id).await {
-                    tracing::error!("Cannot delete webhook queue entry. Reason {err}");
+/// Deliver the pending entries of a single webhook in order. After a failed
+/// delivery the remaining entries wait, and the time the webhook is tried
+/// again is returned. So a failing host gets no request until then, also
+/// not for new messages.
+async fn handle_webhook(
+    db: &Arc<dyn DbProvider>,
+    client: &reqwest::Client,
+    settings: &DeliverySettings,
+    entries: Vec<WebhookQueue>,
+) -> Option<DateTime<Utc>> {
+    let first = entries.first()?;
+    let webhook_id = first.webhook_id.clone();
+    let mut failing_since = first.failing_since;
+
+    for entry in entries {
+        match handle_entry(db, client, settings, &entry).await {
+            Ok(true) => {
+                if failing_since.take().is_some() {
+                    set_health(db, &webhook_id, None, None).await;
                 }
             }
-            Ok(resp) => {
-                tracing::error!(
-                    "Webhook callback failed for: {}. Response status: {}. Msg: {:?}",
-                    entry.callback_url,
-                    resp.status(),
-                    resp.text().await
-                );
-                if let Err(err) = handle_failed_entry(db, &entry).await {
-                    tracing::error!("Error while handling webhook failure: {err}");
+            Ok(false) => {
+                let now = Utc::now();
+                let since = failing_since.unwrap_or(now);
+                let disabled_at = settings
+                    .disable_after
+                    .filter(|after| now - since >= *after)
+                    .map(|_| now);
+                if let Some(disabled_at) = disabled_at {
+                    tracing::warn!(
+                        "Webhook {} disabled at {disabled_at}, it fails since {since}",
+                        entry.callback_url
+                    );
+                }
+                if failing_since.is_none() || disabled_at.is_some() {
+                    set_health(db, &webhook_id, Some(since), disabled_at).await;
                 }
+                return Some(get_webhook_retry(&since, &now));
             }
             Err(err) => {
-                tracing::error!(
-                    "Webhook callback failed for: {}. Reason: {err}",
-                    entry.callback_url
-                );
-                if let Err(err) = handle_failed_entry(db, &entry).await {
-                    tracing::error!("Error while handling webhook failure: {err}");
-                }
+                tracing::error!("Webhook queue failed. Reason {err}");
+                break;
             }
         }
     }
-    Ok(())
+    None
+}
+
+/// Time a failing webhook is tried again. The delay grows with the time the
+/// webhook has been failing, up to a day.
+fn get_webhook_retry(failing_since: &DateTime<Utc>, now: &DateTime<Utc>) -> DateTime<Utc> {
+    let delay = ((*now - failing_since) / 2).clamp(TimeDelta::seconds(5), TimeDelta::hours(24));
+    *now + delay
+}
+
+/// Deliver a queue entry and record the attempt. Returns `false` if the
+/// webhook did not accept the message.
+async fn handle_entry(
+    db: &Arc<dyn DbProvider>,
+    client: &reqwest::Client,
+    settings: &DeliverySettings,
+    entry: &WebhookQueue,
+) -> Result<bool, WebhookError> {
+    let payload = match format::render(
+        entry.format,
+        entry.template.as_deref(),
+        &entry.payload,
+        &settings.base_url,
+    ) {
+        Ok(payload) => payload,
+        Err(err) => {
+            // Rendering fails again on every retry, so the entry is given up
+            // right away. This is no failure of the webhook itself.
+            tracing::error!(
+                "Cannot render webhook payload for: {}. Reason: {err}",
+                entry.callback_url
+            );
+            record_render_failure(db, entry, &err).await;
+            return Ok(true);
+        }
+    };
+    let delivery = deliver(
+        client,
+        &entry.webhook_id,
+        &entry.callback_url,
+        &entry.id,
+        &entry.secrets,
+        &payload,
+    )
+    .await?;
+    if let Err(err) = db.add_webhook_delivery(&delivery).await {
+        tracing::error!("Cannot record webhook delivery. Reason {err}");
+    }
+
+    if delivery.is_success() {
+        if let Err(err) = db.delete_webhook_queue(&entry.id).await {
+            tracing::error!("Cannot delete webhook queue entry. Reason {err}");
+        }
+        return Ok(true);
+    }
+
+    match (delivery.status, &delivery.error) {
+        (Some(status), _) => tracing::error!(
+            "Webhook callback failed for: {}. Response status: {status}. Msg: {:?}",
+            entry.callback_url,
+            delivery.response_body
+        ),
+        (None, error) => tracing::error!(
+            "Webhook callback failed for: {}. Reason: {}",
+            entry.callback_url,
+            error.as_deref().unwrap_or_default()
+        ),
+    }
+    if let Err(err) = handle_failed_entry(db, entry, settings.max_attempts).await {
+        tracing::error!("Error while handling webhook failure: {err}");
+    }
+    Ok(false)
+}
+
+async fn set_health(
+    db: &Arc<dyn DbProvider>,
+    webhook_id: &str,
+    failing_since: Option<DateTime<Utc>>,
+    disabled_at: Option<DateTime<Utc>>,
+) {
+    if let Err(err) = db
+        .set_webhook_health(webhook_id, failing_since, disabled_at)
+        .await
+    {
+        tracing::error!("Cannot update webhook health. Reason {err}");
+    }
+}
+
+/// Send a message to a webhook. The outcome is returned for the delivery
+/// log, also if no response was received.
+pub(crate) async fn deliver(
+    client: &reqwest::Client,
+    webhook_id: &str,
+    callback_url: &str,
+    message_id: &str,
+    secrets: &[String],
+    payload: &Value,
+) -> Result<NewWebhookDelivery, WebhookError> {
+    let body = serde_json::to_vec(payload)?;
+    let request = signature::signed_request(client.post(callback_url), secrets, message_id, body);
+
+    let start = Instant::now();
+    let (status, response_body, error) = match request.send().await {
+        Ok(resp) => (
+            Some(resp.status().as_u16()),
+            Some(response_excerpt(resp).await),
+            None,
+        ),
+        Err(err) => (None, None, Some(err.to_string())),
+    };
+
+    Ok(NewWebhookDelivery {
+        webhook_id: webhook_id.to_string(),
+        message_id: message_id.to_string(),
+        payload: payload.clone(),
+        status,
+        response_body,
+        error,
+        duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+/// Start of the response body. Reading stops after [`MAX_RESPONSE_EXCERPT`]
+/// bytes, so large responses are not loaded into memory.
+async fn response_excerpt(mut resp: reqwest::Response) -> String {
+    let mut body = Vec::new();
+    while body.len() < MAX_RESPONSE_EXCERPT {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(err) => {
+                tracing::debug!("Cannot read webhook response body. Reason: {err}");
+                break;
+            }
+        }
+    }
+    body.truncate(MAX_RESPONSE_EXCERPT);
+    String::from_utf8_lossy(&body).into_owned()
+}
+
+async fn record_render_failure(db: &Arc<dyn DbProvider>, entry: &WebhookQueue, err: &WebhookError) {
+    let delivery = NewWebhookDelivery {
+        webhook_id: entry.webhook_id.clone(),
+        message_id: entry.id.clone(),
+        payload: entry.payload.clone(),
+        status: None,
+        response_body: None,
+        error: Some(err.to_string()),
+        duration_ms: 0,
+    };
+    if let Err(err) = db.add_webhook_delivery(&delivery).await {
+        tracing::error!("Cannot record webhook delivery. Reason {err}");
+    }
+    if let Err(err) = db.dead_letter_webhook_queue(&entry.id, Utc::now()).await {
+        tracing::error!("Cannot move webhook queue entry to the dead letters. Reason {err}");
+    }
 }
 
+/// Schedule the next attempt of a failed entry or move it to the dead letters
+/// after `max_attempts` attempts or the last retry of the schedule.
 async fn handle_failed_entry(
     db: &Arc<dyn DbProvider>,
     entry: &WebhookQueue,
+    max_attempts: u32,
 ) -> Result<(), WebhookError> {
+    metrics::WEBHOOK_FAILURES.inc();
+    let attempts = entry.attempts + 1;
     match get_next_attempt(entry.last_attempt.as_ref(), &entry.next_attempt) {
-        Some(next) => {
+        Some(next) if attempts < max_attempts => {
             db.update_webhook_queue(&entry.id, entry.next_attempt, next)
                 .await?;
         }
-        None => db.delete_webhook_queue(&entry.id).await?,
+        _ => {
+            tracing::warn!(
+                "Webhook message {} for: {} moved to the dead letters after {attempts} attempt(s)",
+                entry.id,
+                entry.callback_url
+            );
+            db.dead_letter_webhook_queue(&entry.id, entry.next_attempt)
+                .await?;
+        }
     }
     Ok(())
 }
@@ -105,7 +395,7 @@ mod service_tests {
     use chrono::Utc;
     use kellnr_common::normalized_name::NormalizedName;
     use kellnr_common::version::Version;
-    use kellnr_common::webhook::{Webhook, WebhookEvent};
+    use kellnr_common::webhook::{Webhook, WebhookEvent, WebhookFormat};
     use kellnr_db::{ConString, Database, DbProvider, SqliteConString};
 
     use super::*;
@@ -118,7 +408,7 @@ mod service_tests {
 
         for _ in 0..5 {
             let _ = db
-                .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9980))
+                .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9980), None)
                 .await
                 .unwrap();
         }
@@ -128,13 +418,13 @@ mod service_tests {
             &Utc::now(),
             &NormalizedName::from_unchecked_str("Test-Crate"),
             &Version::from_unchecked_str("0.1.0"),
+            None,
             &db,
         )
         .await;
 
         let mut listener = get_test_listener(9980, 200).await;
-        let http_client = reqwest::Client::new();
-        handle_queue(&db, &http_client).await.unwrap();
+        run_queue(&db, delivery_settings()).await;
 
         for _ in 0..5 {
             let listener_resp = listener.rx.recv().await.unwrap();
@@ -146,12 +436,50 @@ mod service_tests {
         assert!(pending.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_handle_queue_renders_format() {
+        let db = get_db().await;
+
+        let id = db
+            .register_webhook(
+                Webhook {
+                    format: WebhookFormat::Slack,
+                    ..sample_webhook(WebhookEvent::CrateAdd, 9983)
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        notify_crate(
+            WebhookEvent::CrateAdd,
+            &Utc::now(),
+            &NormalizedName::from_unchecked_str("test-crate"),
+            &Version::from_unchecked_str("0.1.0"),
+            Some("alice"),
+            &db,
+        )
+        .await;
+
+        let mut listener = get_test_listener(9983, 200).await;
+        run_queue(&db, delivery_settings()).await;
+
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+        let deliveries = db.get_webhook_deliveries(&id, 10).await.unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "text": "test-crate 0.1.0 published by alice\nhttp://localhost:8000/crate?name=test-crate&version=0.1.0"
+            }),
+            deliveries[0].payload
+        );
+    }
+
     #[tokio::test]
     async fn test_handle_queue_send_fail() {
         let db = get_db().await;
 
-        let _ = db
-            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9981))
+        let id = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9981), None)
             .await
             .unwrap();
 
@@ -160,13 +488,13 @@ mod service_tests {
             &Utc::now(),
             &NormalizedName::from_unchecked_str("Test-Crate"),
             &Version::from_unchecked_str("0.1.0"),
+            None,
             &db,
         )
         .await;
 
         let mut listener = get_test_listener(9981, 400).await;
-        let http_client = reqwest::Client::new();
-        handle_queue(&db, &http_client).await.unwrap();
+        run_queue(&db, delivery_settings()).await;
 
         let listener_resp = listener.rx.recv().await.unwrap();
         assert_eq!(0, listener_resp);
@@ -182,7 +510,7 @@ mod service_tests {
 
         // Try again to check the increasing interval
         tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        handle_queue(&db, &http_client).await.unwrap();
+        run_queue(&db, delivery_settings()).await;
 
         let ts = Utc::now() + TimeDelta::minutes(5);
         let pending = db.get_pending_webhook_queue_entries(ts).await.unwrap();
@@ -191,14 +519,23 @@ mod service_tests {
             pending[0].next_attempt,
             pending[0].last_attempt.unwrap() + TimeDelta::minutes(5)
         );
+
+        // Both attempts are recorded for the same message
+        let deliveries = db.get_webhook_deliveries(&id, 10).await.unwrap();
+        assert_eq!(2, deliveries.len());
+        assert_eq!(2, deliveries[0].attempt);
+        assert_eq!(1, deliveries[1].attempt);
+        assert_eq!(pending[0].id, deliveries[0].message_id);
+        assert_eq!(Some(400), deliveries[0].status);
+        assert_eq!(pending[0].payload, deliveries[0].payload);
     }
 
     #[tokio::test]
     async fn test_handle_queue_send_no_response() {
         let db = get_db().await;
 
-        let _ = db
-            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9982))
+        let id = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9982), None)
             .await
             .unwrap();
 
@@ -207,16 +544,218 @@ mod service_tests {
             &Utc::now(),
             &NormalizedName::from_unchecked_str("Test-Crate"),
             &Version::from_unchecked_str("0.1.0"),
+            None,
             &db,
         )
         .await;
 
-        let http_client = reqwest::Client::new();
-        handle_queue(&db, &http_client).await.unwrap();
+        run_queue(&db, delivery_settings()).await;
 
         let ts = Utc::now() + TimeDelta::minutes(5);
         let pending = db.get_pending_webhook_queue_entries(ts).await.unwrap();
         assert_eq!(pending.len(), 1);
+
+        let deliveries = db.get_webhook_deliveries(&id, 10).await.unwrap();
+        assert_eq!(1, deliveries.len());
+        assert_eq!(None, deliveries[0].status);
+        assert!(deliveries[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_queue_dead_letter_after_max_attempts() {
+        let db = get_db().await;
+
+        let id = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9984), None)
+            .await
+            .unwrap();
+
+        notify_crate(
+            WebhookEvent::CrateAdd,
+            &Utc::now(),
+            &NormalizedName::from_unchecked_str("Test-Crate"),
+            &Version::from_unchecked_str("0.1.0"),
+            None,
+            &db,
+        )
+        .await;
+
+        // Nothing listens on the port, so the delivery fails
+        let settings = DeliverySettings {
+            max_attempts: 1,
+            ..delivery_settings()
+        };
+        run_queue(&db, settings.clone()).await;
+
+        let ts = Utc::now() + TimeDelta::minutes(5);
+        assert!(
+            db.get_pending_webhook_queue_entries(ts)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let dead_letters = db.get_webhook_dead_letters(&id).await.unwrap();
+        assert_eq!(1, dead_letters.len());
+        assert_eq!(1, dead_letters[0].attempts);
+        let webhook = db.get_webhook(&id).await.unwrap();
+        assert!(webhook.failing_since.is_some());
+        assert_eq!(None, webhook.disabled_at);
+
+        // A retried dead letter is delivered and the webhook is healthy again
+        db.retry_webhook_dead_letter(&dead_letters[0].id)
+            .await
+            .unwrap();
+        let mut listener = get_test_listener(9984, 200).await;
+        run_queue(&db, settings).await;
+
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+        assert!(db.get_webhook_dead_letters(&id).await.unwrap().is_empty());
+        assert_eq!(0, db.get_webhook_queue_length().await.unwrap());
+        assert_eq!(None, db.get_webhook(&id).await.unwrap().failing_since);
+    }
+
+    #[tokio::test]
+    async fn test_handle_queue_disables_failing_webhook() {
+        let db = get_db().await;
+
+        let failing = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9985), None)
+            .await
+            .unwrap();
+        let healthy = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9986), None)
+            .await
+            .unwrap();
+
+        for version in ["0.1.0", "0.2.0"] {
+            notify_crate(
+                WebhookEvent::CrateAdd,
+                &Utc::now(),
+                &NormalizedName::from_unchecked_str("Test-Crate"),
+                &Version::from_unchecked_str(version),
+                None,
+                &db,
+            )
+            .await;
+        }
+
+        let mut listener = get_test_listener(9986, 200).await;
+        let settings = DeliverySettings {
+            disable_after: Some(TimeDelta::zero()),
+            ..delivery_settings()
+        };
+        run_queue(&db, settings).await;
+
+        // Both messages reached the healthy webhook
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+        assert_eq!(None, db.get_webhook(&healthy).await.unwrap().disabled_at);
+
+        // The failing webhook got a single attempt before it was disabled
+        let webhook = db.get_webhook(&failing).await.unwrap();
+        assert!(webhook.disabled_at.is_some());
+        assert_eq!(
+            1,
+            db.get_webhook_deliveries(&failing, 10).await.unwrap().len()
+        );
+        let ts = Utc::now() + TimeDelta::minutes(5);
+        assert!(
+            db.get_pending_webhook_queue_entries(ts)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(2, db.get_webhook_queue_length().await.unwrap());
+
+        db.set_webhook_health(&failing, None, None).await.unwrap();
+        assert_eq!(
+            2,
+            db.get_pending_webhook_queue_entries(ts)
+                .await
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_queue_backs_off_failing_webhook() {
+        let db = get_db().await;
+
+        let id = db
+            .register_webhook(sample_webhook(WebhookEvent::CrateAdd, 9987), None)
+            .await
+            .unwrap();
+        let notify = |version: &'static str| {
+            let db = db.clone();
+            async move {
+                notify_crate(
+                    WebhookEvent::CrateAdd,
+                    &Utc::now(),
+                    &NormalizedName::from_unchecked_str("Test-Crate"),
+                    &Version::from_unchecked_str(version),
+                    None,
+                    &db,
+                )
+                .await;
+            }
+        };
+
+        // Nothing listens on the port, so the delivery fails
+        notify("0.1.0").await;
+        let mut deliveries = Deliveries::default();
+        run_queue_with(&db, delivery_settings(), &mut deliveries).await;
+        assert!(deliveries.retry_at.contains_key(&id));
+
+        // A new message is not sent before the webhook is tried again
+        notify("0.2.0").await;
+        run_queue_with(&db, delivery_settings(), &mut deliveries).await;
+        assert_eq!(1, db.get_webhook_deliveries(&id, 10).await.unwrap().len());
+
+        // The new message is sent once the back-off has passed, the failed
+        // one keeps its own retry schedule
+        deliveries.retry_at.insert(id.clone(), Utc::now());
+        let mut listener = get_test_listener(9987, 200).await;
+        run_queue_with(&db, delivery_settings(), &mut deliveries).await;
+        assert_eq!(0, listener.rx.recv().await.unwrap());
+        assert_eq!(2, db.get_webhook_deliveries(&id, 10).await.unwrap().len());
+        assert!(!deliveries.retry_at.contains_key(&id));
+    }
+
+    #[test]
+    fn webhook_retry_grows_with_failure_time() {
+        let now = Utc::now();
+        assert_eq!(now + TimeDelta::seconds(5), get_webhook_retry(&now, &now));
+        assert_eq!(
+            now + TimeDelta::minutes(30),
+            get_webhook_retry(&(now - TimeDelta::hours(1)), &now)
+        );
+        assert_eq!(
+            now + TimeDelta::hours(24),
+            get_webhook_retry(&(now - TimeDelta::days(7)), &now)
+        );
+    }
+
+    async fn run_queue(db: &Arc<dyn DbProvider>, settings: DeliverySettings) {
+        run_queue_with(db, settings, &mut Deliveries::default()).await;
+    }
+
+    async fn run_queue_with(
+        db: &Arc<dyn DbProvider>,
+        settings: DeliverySettings,
+        deliveries: &mut Deliveries,
+    ) {
+        handle_queue(db, &reqwest::Client::new(), &Arc::new(settings), deliveries)
+            .await
+            .unwrap();
+        deliveries.join_all().await;
+    }
+
+    fn delivery_settings() -> DeliverySettings {
+        DeliverySettings {
+            base_url: "http://localhost:8000".to_string(),
+            max_attempts: 10,
+            disable_after: None,
+        }
     }
 
     async fn get_db() -> Arc<dyn DbProvider> {
@@ -233,10 +772,9 @@ mod service_tests {
 
     fn sample_webhook(event: WebhookEvent, callback_port: u16) -> Webhook {
         Webhook {
-            id: None,
-            event,
+            events: vec![event],
             callback_url: format!("http://0.0.0.0:{callback_port}"),
-            name: None,
+            ..Webhook::default()
         }
     }
 }
@@ -0,0 +1,140 @@
+//! Signatures of webhook deliveries according to the Standard Webhooks spec:
+//! <https://github.com/standard-webhooks/standard-webhooks/blob/main/spec/standard-webhooks.md#verifying-webhook-authenticity>
+//!
+//! Every delivery carries its id, the unix timestamp of the attempt and an
+//! HMAC-SHA256 signature over `{id}.{timestamp}.{body}`. Receivers verify the
+//! signature and reject old timestamps and known ids to prevent replays.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::RequestBuilder;
+use reqwest::header::CONTENT_TYPE;
+use sha2::Sha256;
+
+use crate::types::WebhookError;
+
+pub const HEADER_ID: &str = "webhook-id";
+pub const HEADER_TIMESTAMP: &str = "webhook-timestamp";
+pub const HEADER_SIGNATURE: &str = "webhook-signature";
+
+/// Prefix of webhook secrets, followed by the base64 encoded key
+pub const SECRET_PREFIX: &str = "whsec_";
+
+const MIN_KEY_LENGTH: usize = 24;
+const MAX_KEY_LENGTH: usize = 64;
+
+/// Generate a secret with a random key of 32 bytes.
+pub fn generate_secret() -> String {
+    let key: [u8; 32] = rand::random();
+    format!("{SECRET_PREFIX}{}", STANDARD.encode(key))
+}
+
+/// Key of a secret, which has to be `whsec_` followed by a base64 encoded key
+/// of 24 to 64 bytes.
+pub fn secret_key(secret: &str) -> Result<Vec<u8>, WebhookError> {
+    let invalid = || {
+        WebhookError::InvalidSecret(format!(
+            "secret has to be \"{SECRET_PREFIX}\" followed by a base64 encoded key of {MIN_KEY_LENGTH} to {MAX_KEY_LENGTH} bytes"
+        ))
+    };
+    let key = secret
+        .strip_prefix(SECRET_PREFIX)
+        .and_then(|key| STANDARD.decode(key).ok())
+        .ok_or_else(invalid)?;
+    if (MIN_KEY_LENGTH..=MAX_KEY_LENGTH).contains(&key.len()) {
+        Ok(key)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Value of the signature header with one `v1,{signature}` per secret,
+/// separated by spaces.
+pub fn sign(secrets: &[String], id: &str, timestamp: i64, body: &[u8]) -> String {
+    secrets
+        .iter()
+        .filter_map(|secret| match secret_key(secret) {
+            Ok(key) => Some(key),
+            Err(err) => {
+                tracing::error!("Cannot sign webhook delivery {id}. Reason: {err}");
+                None
+            }
+        })
+        .map(|key| {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+            mac.update(format!("{id}.{timestamp}.").as_bytes());
+            mac.update(body);
+            format!("v1,{}", STANDARD.encode(mac.finalize().into_bytes()))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Add the JSON body and the delivery headers to a request. The signature is
+/// omitted if there are no secrets.
+pub fn signed_request(
+    request: RequestBuilder,
+    secrets: &[String],
+    id: &str,
+    body: Vec<u8>,
+) -> RequestBuilder {
+    let timestamp = Utc::now().timestamp();
+    let mut request = request
+        .header(CONTENT_TYPE, "application/json")
+        .header(HEADER_ID, id)
+        .header(HEADER_TIMESTAMP, timestamp);
+    if !secrets.is_empty() {
+        request = request.header(HEADER_SIGNATURE, sign(secrets, id, timestamp, &body));
+    }
+    request.body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example of the Standard Webhooks reference implementations
+    const SECRET: &str = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+
+    #[test]
+    fn signature_matches_reference_implementation() {
+        let signature = sign(
+            &[SECRET.to_string()],
+            "msg_p5jXN8AQM9LWM0D4loKWxJek",
+            1_614_265_330,
+            br#"{"test": 2432232314}"#,
+        );
+
+        assert_eq!("v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=", signature);
+    }
+
+    #[test]
+    fn every_secret_signs_during_rotation() {
+        let new = generate_secret();
+
+        let signature = sign(&[new.clone(), SECRET.to_string()], "id", 1, b"{}");
+
+        let signatures: Vec<_> = signature.split(' ').collect();
+        assert_eq!(2, signatures.len());
+        assert_eq!(sign(&[new], "id", 1, b"{}"), signatures[0]);
+        assert_eq!(sign(&[SECRET.to_string()], "id", 1, b"{}"), signatures[1]);
+    }
+
+    #[test]
+    fn generated_secret_is_valid() {
+        let secret = generate_secret();
+
+        assert!(secret.starts_with(SECRET_PREFIX));
+        assert_eq!(32, secret_key(&secret).unwrap().len());
+    }
+
+    #[test]
+    fn invalid_secrets_are_rejected() {
+        assert!(secret_key("MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw").is_err());
+        assert!(secret_key("whsec_not base64!").is_err());
+        assert!(secret_key("whsec_c2hvcnQ=").is_err());
+    }
+}
@@ -1,9 +1,12 @@
 use std::time::Duration;
 
-use kellnr_common::webhook::{Webhook, WebhookEvent};
+use chrono::{DateTime, Utc};
+use kellnr_common::webhook::{
+    Webhook, WebhookDeadLetter, WebhookDelivery, WebhookEvent, WebhookFormat,
+};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 /// Build the HTTP client used for delivering webhook callbacks.
 ///
@@ -20,31 +23,111 @@ pub fn build_client() -> reqwest::Client {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct RegisterWebhookRequest {
+    /// Single event to subscribe to, in addition to `events`
     // `type` alias included for webhook standards compatibility
-    #[serde(alias = "type")]
-    pub event: WebhookEvent,
+    #[serde(default, alias = "type")]
+    pub event: Option<WebhookEvent>,
+    /// Events to subscribe to
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
     pub callback_url: String,
     pub name: Option<String>,
+    /// Only deliver events about crates whose name matches this glob, e.g. `acme-*`
+    pub crate_filter: Option<String>,
+    /// Only deliver events about crates that belong to this group
+    pub group_filter: Option<String>,
+    /// Format of the delivered payload, native JSON by default
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// JSON template of the payload for the `template` format. The
+    /// placeholders `{{event}}`, `{{timestamp}}`, `{{crate_name}}`,
+    /// `{{version}}`, `{{actor}}`, `{{user}}`, `{{url}}` and `{{summary}}` are
+    /// replaced by the JSON escaped fields of the event, `{{data}}` by the
+    /// event data as JSON.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Secret the deliveries are signed with, `whsec_` followed by a base64
+    /// encoded key of 24 to 64 bytes. Generated if not set.
+    #[serde(default)]
+    pub secret: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct RegisterWebhookResponse {
     pub id: String,
+    /// Secret to verify the signatures of deliveries with
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RotateWebhookSecretRequest {
+    /// New secret, generated if not set
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Seconds deliveries are still signed with the previous secret as well
+    #[serde(default = "default_grace_period")]
+    pub grace_period_seconds: u32,
+}
+
+fn default_grace_period() -> u32 {
+    24 * 60 * 60
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RotateWebhookSecretResponse {
+    pub secret: String,
+    /// Time until deliveries are signed with the previous secret as well
+    pub previous_secret_expires: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct GetWebhookResponse {
     pub id: String,
-    pub event: WebhookEvent,
+    pub events: Vec<WebhookEvent>,
     pub callback_url: String,
     pub name: Option<String>,
+    pub crate_filter: Option<String>,
+    pub group_filter: Option<String>,
+    pub format: WebhookFormat,
+    pub template: Option<String>,
+    /// Start of the current series of failed deliveries
+    pub failing_since: Option<DateTime<Utc>>,
+    /// Time the webhook was disabled after failing for too long
+    pub disabled_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct GetAllWebhooksResponse(pub Vec<Webhook>);
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GetWebhookEventsResponse(pub Vec<WebhookEvent>);
+
+/// Number of deliveries listed if no limit is given
+pub const DEFAULT_DELIVERIES_LIMIT: u64 = 100;
+
+/// Max. number of deliveries listed at once
+pub const MAX_DELIVERIES_LIMIT: u64 = 1000;
+
+#[derive(Default, Debug, Clone, Deserialize, IntoParams)]
+pub struct GetWebhookDeliveriesParams {
+    /// Max. number of deliveries, 100 by default and at most 1000
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GetWebhookDeliveriesResponse(pub Vec<WebhookDelivery>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GetWebhookDeadLettersResponse(pub Vec<WebhookDeadLetter>);
+
 #[derive(Error, Debug)]
 pub enum WebhookError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] kellnr_db::error::DbError),
+    #[error("Invalid webhook secret: {0}")]
+    InvalidSecret(String),
+    #[error("Invalid webhook template: {0}")]
+    InvalidTemplate(String),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
 }
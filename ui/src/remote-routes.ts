@@ -46,6 +46,16 @@ export const STATISTICS = "./api/v1/ui/statistics";
 export const SEARCH = "./api/v1/ui/search";
 
 export const DOCS_BUILDS = "./api/v1/docs/builds";
+export const DOCS_BUILD = (crate_name: string, version: string) => `./api/v1/docs/builds/${encodeURIComponent(crate_name)}/${encodeURIComponent(version)}`;
+export const DOCS_BUILD_RETRY = (crate_name: string, version: string) => `./api/v1/docs/builds/${encodeURIComponent(crate_name)}/${encodeURIComponent(version)}/retry`;
+
+// Crate file browser, `registry` is "crates" or "cratesio"
+export const SOURCE_FILES = (registry: string, crate_name: string, version: string) =>
+  `./api/v1/source/${registry}/${encodeURIComponent(crate_name)}/${encodeURIComponent(version)}/files`;
+export const SOURCE_FILE = (registry: string, crate_name: string, version: string) =>
+  `./api/v1/source/${registry}/${encodeURIComponent(crate_name)}/${encodeURIComponent(version)}/file`;
+export const SOURCE_DIFF = (registry: string, crate_name: string, from: string, to: string) =>
+  `./api/v1/source/${registry}/${encodeURIComponent(crate_name)}/${encodeURIComponent(from)}/diff/${encodeURIComponent(to)}`;
 
 // OAuth2/OIDC
 export const OAUTH2_CONFIG = "./api/v1/oauth2/config";
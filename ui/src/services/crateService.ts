@@ -30,8 +30,19 @@ import {
   CRATE_OWNERS,
   CRATE_OWNER,
   DOCS_BUILDS,
+  DOCS_BUILD,
+  DOCS_BUILD_RETRY,
+  SOURCE_FILES,
+  SOURCE_FILE,
+  SOURCE_DIFF,
 } from '../remote-routes'
-import type { DocQueueItem } from '../types/doc_queue_item'
+import type { DocBuild, DocQueueItem } from '../types/doc_queue_item'
+import type {
+  CrateDiff,
+  CrateFiles,
+  CrateSourceRegistry,
+  FileContent,
+} from '../types/crate_source'
 
 /**
  * Get paginated list of crates
@@ -45,7 +56,8 @@ export async function getCrates(
 }
 
 /**
- * Search for crates by name
+ * Search for crates by name, keywords, categories, description and readme.
+ * Supports the filters `keyword:`, `category:`, `owner:` and `license:`.
  */
 export async function searchCrates(
   name: string,
@@ -246,7 +258,27 @@ export async function buildDocs(
 }
 
 /**
- * Get documentation build queue
+ * Retry a failed documentation build
+ */
+export async function retryDocsBuild(
+  crateName: string,
+  version: string
+): Promise<ApiResult<void>> {
+  return apiPost<void>(DOCS_BUILD_RETRY(crateName, version))
+}
+
+/**
+ * Get the latest documentation build of a crate version with its log
+ */
+export async function getDocsBuild(
+  crateName: string,
+  version: string
+): Promise<ApiResult<DocBuild>> {
+  return apiGet<DocBuild>(DOCS_BUILD(crateName, version), undefined, { noCache: true })
+}
+
+/**
+ * Get documentation builds, newest first
  */
 export async function getDocsQueue(): Promise<ApiResult<DocQueueItem[]>> {
   // Backend returns { queue: [...] }, we need to extract the array
@@ -257,6 +289,41 @@ export async function getDocsQueue(): Promise<ApiResult<DocQueueItem[]>> {
   return { data: result.data?.queue ?? [], error: null }
 }
 
+/**
+ * List the files of a stored crate version
+ */
+export async function getCrateFiles(
+  registry: CrateSourceRegistry,
+  crateName: string,
+  version: string
+): Promise<ApiResult<CrateFiles>> {
+  return apiGet<CrateFiles>(SOURCE_FILES(registry, crateName, version))
+}
+
+/**
+ * Get the content of a file of a stored crate version
+ */
+export async function getCrateFile(
+  registry: CrateSourceRegistry,
+  crateName: string,
+  version: string,
+  path: string
+): Promise<ApiResult<FileContent>> {
+  return apiGet<FileContent>(SOURCE_FILE(registry, crateName, version), { path })
+}
+
+/**
+ * Compare two stored versions of a crate
+ */
+export async function getCrateDiff(
+  registry: CrateSourceRegistry,
+  crateName: string,
+  from: string,
+  to: string
+): Promise<ApiResult<CrateDiff>> {
+  return apiGet<CrateDiff>(SOURCE_DIFF(registry, crateName, from, to))
+}
+
 // --- Helper for getting all groups (used in crate settings) ---
 
 /**
@@ -3,7 +3,7 @@
  */
 import { apiGet, apiPost, apiDelete } from './api'
 import type { ApiResult } from '../types/api'
-import type { Token, TokenCreateRequest, TokenCreateResponse } from '../types/token'
+import type { Token, TokenCreateRequest, TokenCreateResponse, TokenScopes } from '../types/token'
 import { ADD_TOKEN, DELETE_TOKEN, LIST_TOKENS } from '../remote-routes'
 
 /**
@@ -14,13 +14,16 @@ export async function getTokens(): Promise<ApiResult<Token[]>> {
 }
 
 /**
- * Create a new authentication token
+ * Create a new authentication token, optionally limited by scopes
  */
-export async function createToken(name: string): Promise<ApiResult<TokenCreateResponse>> {
-  const data: TokenCreateRequest = { name }
+export async function createToken(
+  name: string,
+  scopes?: TokenScopes,
+): Promise<ApiResult<TokenCreateResponse>> {
+  const data: TokenCreateRequest = { name, scopes }
   return apiPost<TokenCreateResponse>(ADD_TOKEN, data, undefined, {
     customErrors: {
-      400: 'Invalid token name.',
+      400: 'Invalid token name, crate pattern or expiry date.',
       409: 'A token with this name already exists.',
     },
   })
@@ -1,8 +1,11 @@
+import type { DocQueueItem } from './doc_queue_item'
+
 export type CrateData = {
     name: string,
     owners: Array<string>,
     max_version: string,
     total_downloads: number,
+    recent_downloads: number,
     last_updated: string,
     homepage?: string,
     description?: string,
@@ -11,6 +14,22 @@ export type CrateData = {
     keywords: Array<string>,
     authors: Array<string>,
     versions: Array<CrateVersionData>,
+    advisories: Array<CrateAdvisory>,
+    doc_builds: Array<DocQueueItem>,
+}
+
+export type CrateAdvisory = {
+    id: string,
+    package: string,
+    title: string,
+    description: string,
+    date: string,
+    url?: string,
+    aliases: Array<string>,
+    informational?: string,
+    patched: Array<string>,
+    unaffected: Array<string>,
+    affected_versions: Array<string>,
 }
 
 export const defaultCrateData : CrateData = {
@@ -18,6 +37,7 @@ export const defaultCrateData : CrateData = {
     owners: [],
     max_version: "",
     total_downloads: 0,
+    recent_downloads: 0,
     last_updated: "",
     homepage: "",
     description: "",
@@ -26,12 +46,15 @@ export const defaultCrateData : CrateData = {
     keywords: [],
     authors: [],
     versions: [],
+    advisories: [],
+    doc_builds: [],
 }
 
 export type CrateVersionData = {
     version: string,
     created: string,
     downloads: number,
+    recent_downloads: number,
     readme?: string,
     license?: string,
     license_file?: string,
@@ -49,6 +72,7 @@ export const defaultCrateVersionData : CrateVersionData = {
     version: "",
     created: "",
     downloads: 0,
+    recent_downloads: 0,
     readme: "",
     license: "",
     license_file: "",
@@ -0,0 +1,35 @@
+export type CrateSourceRegistry = "crates" | "cratesio"
+
+export type CrateFile = {
+    path: string
+    size: number
+}
+
+export type CrateFiles = {
+    name: string
+    version: string
+    files: CrateFile[]
+}
+
+export type FileContent = {
+    path: string
+    size: number
+    binary: boolean
+    truncated: boolean
+    content: string
+}
+
+export type FileChange = "added" | "removed" | "modified"
+
+export type FileDiff = {
+    path: string
+    change: FileChange
+    diff?: string
+}
+
+export type CrateDiff = {
+    name: string
+    from: string
+    to: string
+    files: FileDiff[]
+}
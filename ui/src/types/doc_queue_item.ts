@@ -1,4 +1,15 @@
+export type DocBuildState = "queued" | "building" | "succeeded" | "failed"
+
 export type DocQueueItem = {
     name: string
     version: string
-}
\ No newline at end of file
+    state: DocBuildState
+    queued: string
+    started?: string
+    finished?: string
+    duration_ms?: number
+}
+
+export type DocBuild = DocQueueItem & {
+    log?: string
+}
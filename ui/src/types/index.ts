@@ -40,6 +40,7 @@ export type {
 // Crate types (re-export existing)
 export type {
   CrateData,
+  CrateAdvisory,
   CrateVersionData,
   CrateRegistryDep,
   CrateAccessData,
@@ -51,7 +52,16 @@ export { defaultCrateData, defaultCrateVersionData, defaultCrateAccessData } fro
 export type { CrateOverview } from './crate_overview'
 export type { Statistics } from './statistics'
 export type { Settings } from './settings'
-export type { DocQueueItem } from './doc_queue_item'
+export type { DocBuild, DocBuildState, DocQueueItem } from './doc_queue_item'
+export type {
+  CrateSourceRegistry,
+  CrateFile,
+  CrateFiles,
+  FileContent,
+  FileChange,
+  FileDiff,
+  CrateDiff,
+} from './crate_source'
 export type { VersionInfo } from './version_info'
 export type { Owner } from './owner'
 export type { OAuth2Config } from './oauth2'
@@ -21,6 +21,8 @@ export type SettingsDefaults = {
   registry: Registry
   s3: S3
   toolchain: Toolchain
+  advisories: Advisories
+  publish: Publish
 }
 
 export type LeafKind = 'boolean' | 'number' | 'string' | 'array';
@@ -48,6 +50,8 @@ export type Settings = {
   registry: Registry
   s3: S3
   toolchain: Toolchain
+  advisories: Advisories
+  publish: Publish
   sources: SourceMap
   defaults?: SettingsDefaults
   leaves?: LeafMeta[]
@@ -58,9 +62,30 @@ export type Toolchain = {
   max_size: number
 }
 
+export type Advisories = {
+  enabled: boolean
+  path: string | null
+  import_interval_seconds: number
+}
+
+export type Publish = {
+  allowed_licenses: string[]
+  deny_git_dependencies: boolean
+  deny_path_dependencies: boolean
+  repository_pattern: string
+  name_prefixes: string[]
+  max_files: number
+  max_unpacked_size: number
+  validator_url: string | null
+  validator_timeout_seconds: number
+}
+
 export type Docs = {
   enabled: boolean
   max_size: number
+  build_timeout_seconds: number
+  build_max_memory: number
+  require_network_isolation: boolean
 }
 
 export type Local = {
@@ -98,6 +123,17 @@ export type Proxy = {
   api: string
   connect_timeout_seconds: number
   request_timeout_seconds: number
+  deny: string[]
+  allowed_licenses: string[]
+  min_age_days: number
+  upstreams: Upstream[]
+}
+
+export type Upstream = {
+  name: string
+  index: string
+  token?: string
+  bucket?: string
 }
 
 export type Registry = {
@@ -119,6 +155,12 @@ export type Registry = {
   download_timeout_seconds: number
   download_max_concurrent: number
   download_counter_flush_seconds: number
+  download_history_days: number
+  webhook_delivery_days: number
+  webhook_max_attempts: number
+  webhook_disable_after_hours: number
+  trust_proxy_headers: boolean
+  git_index: boolean
 }
 
 export type S3 = {
@@ -138,7 +180,10 @@ export type S3 = {
 export const emptySettings: Settings = {
   docs: {
     enabled: true,
-    max_size: 0
+    max_size: 0,
+    build_timeout_seconds: 600,
+    build_max_memory: 4096,
+    require_network_isolation: true
   },
   local: {
     ip: "",
@@ -170,7 +215,11 @@ export const emptySettings: Settings = {
     index: "",
     api: "",
     connect_timeout_seconds: 5,
-    request_timeout_seconds: 30
+    request_timeout_seconds: 30,
+    deny: [],
+    allowed_licenses: [],
+    min_age_days: 0,
+    upstreams: []
   },
   registry: {
     data_dir: "",
@@ -190,7 +239,13 @@ export const emptySettings: Settings = {
     token_db_retry_delay_ms: 100,
     download_timeout_seconds: 60,
     download_max_concurrent: 20,
-    download_counter_flush_seconds: 30
+    download_counter_flush_seconds: 30,
+    download_history_days: 90,
+    webhook_delivery_days: 30,
+    webhook_max_attempts: 10,
+    webhook_disable_after_hours: 72,
+    trust_proxy_headers: false,
+    git_index: false
   },
   s3: {
     enabled: false,
@@ -209,6 +264,22 @@ export const emptySettings: Settings = {
     enabled: false,
     max_size: 500
   },
+  advisories: {
+    enabled: false,
+    path: null,
+    import_interval_seconds: 3600
+  },
+  publish: {
+    allowed_licenses: [],
+    deny_git_dependencies: false,
+    deny_path_dependencies: false,
+    repository_pattern: "",
+    name_prefixes: [],
+    max_files: 0,
+    max_unpacked_size: 0,
+    validator_url: null,
+    validator_timeout_seconds: 10
+  },
   sources: {},
   defaults: undefined,
   leaves: []
@@ -2,13 +2,26 @@
  * Authentication token type definitions
  */
 
+export type TokenOperation = 'publish' | 'yank' | 'change-owners' | 'download'
+
+export interface TokenScopes {
+  /** Operations the token is limited to. Empty means all operations. */
+  operations: TokenOperation[]
+  /** Crate name patterns (e.g. "my-org-*"). Empty means all crates. */
+  crates: string[]
+  /** RFC 3339 timestamp after which the token is rejected */
+  expires_at: string | null
+}
+
 export interface Token {
   id: number
   name: string
+  scopes: TokenScopes
 }
 
 export interface TokenCreateRequest {
   name: string
+  scopes?: TokenScopes
 }
 
 export interface TokenCreateResponse {